@@ -0,0 +1,42 @@
+// With the ffi feature, generates the C header for the functions src/ffi.rs
+// exports into OUT_DIR, where a test checks include/tactician.h against it.
+// Building with TACTICIAN_UPDATE_HEADER=1 copies it to include/tactician.h,
+// so the source tree only changes when asked to.
+
+#[cfg(feature = "ffi")]
+extern crate cbindgen;
+
+#[cfg(feature = "ffi")]
+fn main() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-env-changed=TACTICIAN_UPDATE_HEADER");
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let mut config = cbindgen::Config::default();
+    config.language = cbindgen::Language::C;
+    config.header = Some(
+        "// Generated from src/ffi.rs, which describes each function, by building\n\
+         // with the ffi feature and TACTICIAN_UPDATE_HEADER=1. Don't edit it by hand."
+            .to_string(),
+    );
+    config.include_guard = Some("TACTICIAN_H".to_string());
+    config.cpp_compat = true;
+    // Only ffi.rs is read, since the exports are all there and the rest of
+    // the crate is older Rust than cbindgen parses.
+    let header = format!("{}/tactician.h", out_dir);
+    cbindgen::Builder::new()
+        .with_src(format!("{}/src/ffi.rs", dir))
+        .with_config(config)
+        .generate()
+        .expect("Unable to generate tactician.h")
+        .write_to_file(&header);
+    println!("cargo:rustc-env=TACTICIAN_HEADER={}", header);
+
+    if std::env::var_os("TACTICIAN_UPDATE_HEADER").is_some() {
+        std::fs::copy(&header, format!("{}/include/tactician.h", dir))
+            .expect("Unable to write include/tactician.h");
+    }
+}
+
+#[cfg(not(feature = "ffi"))]
+fn main() {}
@@ -0,0 +1,8 @@
+// Generates the service and its messages from proto/tactician.proto, with a
+// protoc bundled for the build so none needs to be installed.
+
+fn main() {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+    println!("cargo:rerun-if-changed=proto/tactician.proto");
+    tonic_build::compile_protos("proto/tactician.proto").expect("Unable to compile the protos");
+}
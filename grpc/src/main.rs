@@ -0,0 +1,390 @@
+// Serves Tactician's games over gRPC, as proto/tactician.proto describes,
+// for clients that want typed messages and streamed events rather than
+// the JSON of serve --stdio and serve --http.
+
+// Errors are tonic's Status, which the service's methods return.
+#![allow(clippy::result_large_err)]
+
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+use tactician::{
+    check_choice, fresh_game_with_kingdom, game_context, CardIdentifier, CardRegistry,
+    DecisionType, EvalContext, Game, GameEvent, Rules, Verbosity,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{Request, Response, Status};
+
+mod proto {
+    tonic::include_proto!("tactician.v1");
+}
+
+use proto::tactician_server::{Tactician, TacticianServer};
+use proto::{
+    CreateGameReply, CreateGameRequest, Decision, DecisionKind, Event, EventKind, GetViewRequest,
+    PlayerView, StreamEventsRequest, SubmitDecisionRequest, View,
+};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:50051";
+const DEFAULT_PLAYERS: [&str; 2] = ["Player 1", "Player 2"];
+
+type EventSender = mpsc::UnboundedSender<Result<Event, Status>>;
+
+// A game being played through the server, with the random draws for its
+// shuffles, the events so far and the clients streaming them.
+struct ServedGame {
+    game: Game,
+    ctx: EvalContext,
+    events: Vec<Event>,
+    listeners: Vec<EventSender>,
+}
+
+impl ServedGame {
+    // Plays on until someone has a decision to make or the game is over,
+    // sending what happened to the listeners. They're let go once the game
+    // is over, which ends their streams.
+    fn advance(&mut self) {
+        while !self.game.is_game_over() && self.game.pending_decision.is_none() {
+            self.game.advance_game(&mut self.ctx);
+        }
+        let events = self.ctx.events.replace(vec![]).unwrap_or_default();
+        let mut happened = events.into_iter().map(event).collect::<Vec<_>>();
+        if self.game.is_game_over() {
+            happened.push(Event {
+                kind: EventKind::GameOver.into(),
+                scores: scores(&self.game),
+                ..Default::default()
+            });
+        }
+        for e in happened {
+            self.listeners.retain(|l| l.send(Ok(e.clone())).is_ok());
+            self.events.push(e);
+        }
+        if self.game.is_game_over() {
+            self.listeners.clear();
+        }
+    }
+}
+
+fn card_names(cards: &[CardIdentifier]) -> Vec<String> {
+    cards
+        .iter()
+        .map(|c| CardRegistry::lookup(c).name.to_string())
+        .collect()
+}
+
+fn scores(g: &Game) -> Vec<f32> {
+    match g.is_game_over() {
+        true => g.player_scores().iter().map(|&(_, s)| s).collect(),
+        false => vec![],
+    }
+}
+
+fn event(e: GameEvent) -> Event {
+    let (kind, player, cards, count) = match e {
+        GameEvent::TurnStarted(p, turn) => (EventKind::TurnStarted, p, vec![], turn as u32),
+        GameEvent::Shuffled(p) => (EventKind::Shuffled, p, vec![], 0),
+        GameEvent::Drew(p, n) => (EventKind::Drew, p, vec![], n as u32),
+        GameEvent::Played(p, c) => (EventKind::Played, p, c, 0),
+        GameEvent::Bought(p, c) => (EventKind::Bought, p, vec![c], 0),
+        GameEvent::Gained(p, c) => (EventKind::Gained, p, vec![c], 0),
+        GameEvent::Discarded(p, c) => (EventKind::Discarded, p, c, 0),
+        GameEvent::CleanedUp(p) => (EventKind::CleanedUp, p, vec![], 0),
+        GameEvent::Trashed(p, c) => (EventKind::Trashed, p, c, 0),
+        GameEvent::Revealed(p, c) => (EventKind::Revealed, p, vec![c], 0),
+    };
+    Event {
+        kind: kind.into(),
+        player: player.0 as u32,
+        cards: card_names(&cards),
+        count,
+        scores: vec![],
+    }
+}
+
+fn decision_kind(decision_type: &DecisionType) -> DecisionKind {
+    match *decision_type {
+        DecisionType::PlayAction => DecisionKind::PlayAction,
+        DecisionType::PlayTreasures => DecisionKind::PlayTreasures,
+        DecisionType::BuyCard => DecisionKind::BuyCard,
+        DecisionType::GainCard(_) => DecisionKind::GainCard,
+        DecisionType::DiscardCards(_) => DecisionKind::DiscardCards,
+        DecisionType::TrashCards(_) => DecisionKind::TrashCards,
+        DecisionType::RevealReaction(_) => DecisionKind::RevealReaction,
+    }
+}
+
+// The game as everyone sees it, or, for a seat, without the other players'
+// hands.
+fn view(id: usize, g: &Game, seat: Option<usize>) -> View {
+    let players = g
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, p)| PlayerView {
+            name: p.name.clone(),
+            hand: match seat {
+                Some(seat) if seat != i => vec![],
+                _ => card_names(&p.hand),
+            },
+            hand_size: p.hand.len() as u32,
+            deck_size: p.deck.len() as u32,
+            discard: card_names(&p.discard),
+        })
+        .collect();
+    let pending_decision = g.pending_decision.as_ref().map(|d| Decision {
+        player: d.player.0 as u32,
+        kind: decision_kind(&d.decision_type).into(),
+        choices: card_names(&d.choices),
+        min: d.range.0 as u32,
+        max: d.range.1 as u32,
+    });
+    View {
+        game: id as u32,
+        turn: g.turn as u32,
+        active_player: g.active_player.0 as u32,
+        over: g.is_game_over(),
+        players,
+        supply: g
+            .piles
+            .iter()
+            .map(|(c, &n)| (CardRegistry::lookup(c).name.to_string(), n as u32))
+            .collect(),
+        play_area: card_names(&g.play_area),
+        trash: card_names(&g.trash_pile),
+        pending_decision,
+        scores: scores(g),
+    }
+}
+
+fn parse_cards(names: &[String]) -> Result<Vec<CardIdentifier>, Status> {
+    names
+        .iter()
+        .map(|n| match CardRegistry::named(n) {
+            Some(c) => Ok(c.identifier),
+            None => Err(Status::invalid_argument(format!("Unknown card {}", n))),
+        })
+        .collect()
+}
+
+// Runs games for gRPC clients. Games are numbered from 1.
+#[derive(Default)]
+struct Server {
+    games: Mutex<Vec<ServedGame>>,
+}
+
+impl Server {
+    // Calls f with a game, or fails if there's no such game.
+    fn with_game<T, F>(&self, id: u32, f: F) -> Result<T, Status>
+    where
+        F: FnOnce(&mut ServedGame) -> Result<T, Status>,
+    {
+        let mut games = self.games.lock().unwrap();
+        match games.get_mut((id as usize).wrapping_sub(1)) {
+            Some(served) => f(served),
+            None => Err(Status::not_found(format!("There's no game {}", id))),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Tactician for Server {
+    async fn create_game(
+        &self,
+        request: Request<CreateGameRequest>,
+    ) -> Result<Response<CreateGameReply>, Status> {
+        let request = request.into_inner();
+        let players = match request.players.is_empty() {
+            true => DEFAULT_PLAYERS.iter().map(|&n| n.to_string()).collect(),
+            false => request.players,
+        };
+        if players.len() < 2 || players.len() > 4 {
+            return Err(Status::invalid_argument("Games need 2 to 4 players"));
+        }
+        let kingdom = match request.kingdom.is_empty() {
+            true => Rules::default().kingdom,
+            false => CardRegistry::kingdom(&request.kingdom).map_err(Status::invalid_argument)?,
+        };
+        let mut ctx = game_context(request.seed, Verbosity::Quiet);
+        ctx.events = Some(vec![]);
+        let mut served = ServedGame {
+            game: fresh_game_with_kingdom(&players, &kingdom),
+            ctx,
+            events: vec![],
+            listeners: vec![],
+        };
+        served.game.initialize_game(&mut served.ctx);
+        served.advance();
+        let mut games = self.games.lock().unwrap();
+        games.push(served);
+        Ok(Response::new(CreateGameReply {
+            game: games.len() as u32,
+        }))
+    }
+
+    async fn get_view(&self, request: Request<GetViewRequest>) -> Result<Response<View>, Status> {
+        let request = request.into_inner();
+        self.with_game(request.game, |served| {
+            let seat = request.player.map(|p| p as usize);
+            if seat.is_some_and(|p| p >= served.game.players.len()) {
+                return Err(Status::invalid_argument("player should be a seat number"));
+            }
+            Ok(Response::new(view(
+                request.game as usize,
+                &served.game,
+                seat,
+            )))
+        })
+    }
+
+    async fn submit_decision(
+        &self,
+        request: Request<SubmitDecisionRequest>,
+    ) -> Result<Response<View>, Status> {
+        let request = request.into_inner();
+        let choice = parse_cards(&request.choice)?;
+        self.with_game(request.game, |served| {
+            match served.game.pending_decision {
+                Some(ref d) => check_choice(&choice, d).map_err(Status::failed_precondition)?,
+                None => return Err(Status::failed_precondition("The game is over")),
+            }
+            served.game.resolve_decision(choice, &mut served.ctx);
+            served.advance();
+            Ok(Response::new(view(
+                request.game as usize,
+                &served.game,
+                None,
+            )))
+        })
+    }
+
+    type StreamEventsStream = UnboundedReceiverStream<Result<Event, Status>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let request = request.into_inner();
+        self.with_game(request.game, |served| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            for e in served.events.iter() {
+                let _ = sender.send(Ok(e.clone()));
+            }
+            if !served.game.is_game_over() {
+                served.listeners.push(sender);
+            }
+            Ok(Response::new(UnboundedReceiverStream::new(receiver)))
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() > 2 || args.iter().any(|a| a == "-h" || a == "--help") {
+        eprintln!(
+            "Usage: tactician-grpc [ADDR]\n\nServes games over gRPC on ADDR, {} by default.",
+            DEFAULT_ADDR
+        );
+        std::process::exit(2);
+    }
+    let addr = args.get(1).map_or(DEFAULT_ADDR, |a| a.as_str());
+    let addr: SocketAddr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("Invalid address {}: {}", addr, e);
+            std::process::exit(2);
+        }
+    };
+    let served = tonic::transport::Server::builder()
+        .add_service(TacticianServer::new(Server::default()))
+        .serve(addr);
+    if let Err(e) = served.await {
+        panic!("Unable to serve: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tactician::Registry;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_server() {
+        let server = Server::default();
+        let request = CreateGameRequest {
+            seed: Some(1),
+            ..Default::default()
+        };
+        let game = server
+            .create_game(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner()
+            .game;
+        assert_eq!(game, 1);
+
+        let request = GetViewRequest {
+            game,
+            player: Some(1),
+        };
+        let v = server
+            .get_view(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(v.players[0].hand.is_empty());
+        assert_eq!(v.players[1].hand_size, 5);
+        let missing = GetViewRequest {
+            game: 2,
+            player: None,
+        };
+        assert!(server.get_view(Request::new(missing)).await.is_err());
+
+        let illegal = SubmitDecisionRequest {
+            game,
+            choice: vec!["Province".into()],
+        };
+        assert!(server.submit_decision(Request::new(illegal)).await.is_err());
+
+        // Big Money plays the game out.
+        let request = StreamEventsRequest { game };
+        let mut events = server
+            .stream_events(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut bigmoney = Registry::standard().create("bigmoney", true).unwrap();
+        let mut v = View::default();
+        loop {
+            let choice = {
+                let games = server.games.lock().unwrap();
+                match games[0].game.pending_decision {
+                    Some(_) => bigmoney.make_decision(&games[0].game),
+                    None => break,
+                }
+            };
+            let request = SubmitDecisionRequest {
+                game,
+                choice: card_names(&choice),
+            };
+            v = server
+                .submit_decision(Request::new(request))
+                .await
+                .unwrap()
+                .into_inner();
+        }
+        assert!(v.over);
+        assert_eq!(v.scores.len(), 2);
+
+        let mut kinds = vec![];
+        while let Some(e) = events.next().await {
+            kinds.push(e.unwrap().kind());
+        }
+        assert!(kinds.contains(&EventKind::TurnStarted));
+        assert_eq!(kinds.last(), Some(&EventKind::GameOver));
+    }
+}
@@ -0,0 +1,44 @@
+// Generated from src/ffi.rs, which describes each function, by building
+// with the ffi feature and TACTICIAN_UPDATE_HEADER=1. Don't edit it by hand.
+
+#ifndef TACTICIAN_H
+#define TACTICIAN_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct TacticianGame TacticianGame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+struct TacticianGame *tactician_game_new(const char *players, const char *kingdom, uint32_t seed);
+
+struct TacticianGame *tactician_game_load(const char *state, uint32_t seed);
+
+void tactician_game_free(struct TacticianGame *game);
+
+char *tactician_game_state(struct TacticianGame *game, int32_t seat);
+
+char *tactician_game_status(struct TacticianGame *game);
+
+char *tactician_game_legal_moves(struct TacticianGame *game);
+
+int32_t tactician_game_apply_move(struct TacticianGame *game, const char *choice);
+
+char *tactician_game_ai_choose(struct TacticianGame *game, uint32_t iterations);
+
+char *tactician_game_analyze(struct TacticianGame *game, uint32_t iterations);
+
+void tactician_string_free(char *s);
+
+const char *tactician_last_error(void);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* TACTICIAN_H */
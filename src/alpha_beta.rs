@@ -0,0 +1,235 @@
+use rand::XorShiftRng;
+use std::collections::HashMap;
+use std::f32;
+
+use tree_search::{Evaluator, Rewards, RolloutPolicy, SearchableState};
+
+// How far alpha-beta searches.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlphaBetaOptions {
+    // Moves to look ahead. Positions beyond them are scored by the
+    // evaluator, or as even without one. None searches to the end of the
+    // game, which is only feasible for small games.
+    pub max_depth: Option<u32>,
+    // Remembers the values of states by their transposition keys, so states
+    // reached by different move orders are only searched once.
+    pub use_transpositions: bool,
+}
+
+// A position's value for the player to move, from 0.0 for a certain loss to
+// 1.0 for a certain win, and the move that achieves it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlphaBetaResult<M> {
+    pub value: f32,
+    pub best_move: Option<M>,
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bound {
+    Exact,
+    // The value is at least this much.
+    Lower,
+    // The value is at most this much.
+    Upper,
+}
+
+struct Entry<M> {
+    depth: u32,
+    value: f32,
+    bound: Bound,
+    best_move: Option<M>,
+}
+
+struct Searcher<'a, T: SearchableState + 'a> {
+    // Values are for this player, who the opponent tries to minimize.
+    player: T::P,
+    evaluator: Option<&'a Evaluator<T>>,
+    use_transpositions: bool,
+    table: HashMap<u64, Entry<T::M>>,
+    nodes: u64,
+}
+
+impl<'a, T: SearchableState> Searcher<'a, T> {
+    fn evaluate(&self, state: &T) -> f32 {
+        match self.evaluator {
+            Some(e) => e.evaluate(state).for_player(&self.player),
+            None => 0.5,
+        }
+    }
+
+    // Minimax with alpha-beta pruning. Values outside (alpha, beta) are only
+    // bounds, since the search stops looking once a line can't matter.
+    // Players may move several times in a row, so each node maximizes or
+    // minimizes by who is to move there rather than alternating.
+    fn value(
+        &mut self,
+        state: &T,
+        depth: u32,
+        mut alpha: f32,
+        mut beta: f32,
+        ctx: &mut T::C,
+    ) -> (f32, Option<T::M>) {
+        self.nodes += 1;
+        if let Some(winners) = state.game_result() {
+            let rewards = Rewards::from_winners(&winners, &state.all_players());
+            return (rewards.for_player(&self.player), None);
+        }
+        let mover = match state.active_player() {
+            Some(p) => p,
+            None => return (self.evaluate(state), None),
+        };
+        if depth == 0 {
+            return (self.evaluate(state), None);
+        }
+
+        let key = match self.use_transpositions {
+            true => state.transposition_key(),
+            false => None,
+        };
+        let mut first_move = None;
+        if let Some(entry) = key.and_then(|k| self.table.get(&k)) {
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return (entry.value, entry.best_move.clone()),
+                    Bound::Lower => alpha = alpha.max(entry.value),
+                    Bound::Upper => beta = beta.min(entry.value),
+                }
+                if alpha >= beta {
+                    return (entry.value, entry.best_move.clone());
+                }
+            }
+            first_move = entry.best_move.clone();
+        }
+
+        // The best move last time is searched first, for the most cutoffs.
+        let mut moves = state.all_moves();
+        if let Some(i) = first_move.and_then(|m| moves.iter().position(|n| *n == m)) {
+            let m = moves.remove(i);
+            moves.insert(0, m);
+        }
+        if moves.is_empty() {
+            return (self.evaluate(state), None);
+        }
+
+        let maximizing = mover == self.player;
+        let (alpha0, beta0) = (alpha, beta);
+        let mut best = match maximizing {
+            true => (f32::NEG_INFINITY, None),
+            false => (f32::INFINITY, None),
+        };
+        for m in moves {
+            let mut next = state.clone();
+            next.make_move_mut(m.clone(), ctx);
+            let (v, _) = self.value(&next, depth - 1, alpha, beta, ctx);
+            if maximizing {
+                if v > best.0 {
+                    best = (v, Some(m));
+                }
+                alpha = alpha.max(v);
+            } else {
+                if v < best.0 {
+                    best = (v, Some(m));
+                }
+                beta = beta.min(v);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if let Some(k) = key {
+            let bound = if best.0 <= alpha0 {
+                Bound::Upper
+            } else if best.0 >= beta0 {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            self.table.insert(
+                k,
+                Entry {
+                    depth: depth,
+                    value: best.0,
+                    bound: bound,
+                    best_move: best.1.clone(),
+                },
+            );
+        }
+        best
+    }
+}
+
+fn searcher<'a, T: SearchableState>(
+    root: &T,
+    options: &AlphaBetaOptions,
+    evaluator: Option<&'a Evaluator<T>>,
+) -> Searcher<'a, T> {
+    assert_eq!(root.all_players().len(), 2, "Alpha-beta needs a two-player game");
+    Searcher {
+        player: root.active_player().expect("Alpha-beta needs a player to move"),
+        evaluator: evaluator,
+        use_transpositions: options.use_transpositions,
+        table: HashMap::new(),
+        nodes: 0,
+    }
+}
+
+// Searches a two-player game of perfect information for the best move of
+// the player to move. Without a depth limit the result is exact, which
+// makes it a reference to check Monte Carlo searches of small games by.
+#[allow(dead_code)]
+pub fn alpha_beta<T: SearchableState>(
+    root: &T,
+    options: &AlphaBetaOptions,
+    evaluator: Option<&Evaluator<T>>,
+    ctx: &mut T::C,
+) -> AlphaBetaResult<T::M> {
+    let mut s = searcher(root, options, evaluator);
+    let depth = options.max_depth.unwrap_or(u32::max_value());
+    let (value, best_move) = s.value(root, depth, 0.0, 1.0, ctx);
+    AlphaBetaResult {
+        value: value,
+        best_move: best_move,
+        nodes: s.nodes,
+    }
+}
+
+// The value of each move for the player to move, searched with a full
+// window so every value is exact rather than a bound.
+#[allow(dead_code)]
+pub fn move_values<T: SearchableState>(
+    root: &T,
+    options: &AlphaBetaOptions,
+    evaluator: Option<&Evaluator<T>>,
+    ctx: &mut T::C,
+) -> Vec<(T::M, f32)> {
+    let mut s = searcher(root, options, evaluator);
+    let depth = options.max_depth.unwrap_or(u32::max_value());
+    root.all_moves()
+        .into_iter()
+        .map(|m| {
+            let mut next = root.clone();
+            next.make_move_mut(m.clone(), ctx);
+            let (v, _) = s.value(&next, depth.saturating_sub(1), 0.0, 1.0, ctx);
+            (m, v)
+        })
+        .collect()
+}
+
+// Plays the moves alpha-beta finds, e.g. as a perfect opponent to test a
+// search against.
+#[allow(dead_code)]
+pub struct AlphaBetaPlayer<T: SearchableState> {
+    pub options: AlphaBetaOptions,
+    pub ctx: T::C,
+}
+
+impl<T: SearchableState> RolloutPolicy<T> for AlphaBetaPlayer<T> {
+    fn choose_move(&mut self, state: &T, _rng: &mut XorShiftRng) -> Option<T::M> {
+        if state.game_result().is_some() {
+            return None;
+        }
+        alpha_beta(state, &self.options, None, &mut self.ctx).best_move
+    }
+}
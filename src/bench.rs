@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+use deciders::BigMoney;
+use game;
+use game::{Decider, DecisionType, EvalContext, Game, Verbosity};
+use search_decider::SearchDecider;
+use util;
+
+// Seeds for the benchmark's games and positions. Changing them changes the
+// workload, so results are only comparable between runs with the same set.
+pub const BENCH_SEEDS: u32 = 5;
+const BENCH_GAMES_PER_SEED: u32 = 1000;
+
+// Turns whose first buy is searched in each seeded game.
+const BENCH_TURNS: [i32; 4] = [1, 5, 10, 15];
+
+pub struct BenchReport {
+    pub games: u32,
+    pub game_time: Duration,
+    pub decisions: u32,
+    pub iterations: u64,
+    pub search_time: Duration,
+}
+
+pub fn seconds(d: Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9
+}
+
+impl BenchReport {
+    pub fn games_per_second(&self) -> f64 {
+        self.games as f64 / seconds(self.game_time)
+    }
+
+    pub fn iterations_per_second(&self) -> f64 {
+        self.iterations as f64 / seconds(self.search_time)
+    }
+
+    pub fn millis_per_decision(&self) -> f64 {
+        1000.0 * seconds(self.search_time) / self.decisions as f64
+    }
+}
+
+fn bench_context(seed: u32) -> EvalContext {
+    EvalContext {
+        rng: util::seeded_weak_rng(seed),
+        verbosity: Verbosity::Quiet,
+        events: None,
+    }
+}
+
+// Positions to search: the first buy of each benchmark turn, in Big Money
+// games played from each seed.
+pub fn bench_positions() -> Vec<Game> {
+    let mut positions = vec![];
+    for seed in 1..(BENCH_SEEDS + 1) {
+        let mut ctx = bench_context(seed);
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = game::fresh_game(&names);
+        g.initialize_game(&mut ctx);
+
+        let mut turns = BENCH_TURNS.iter().peekable();
+        while !g.is_game_over() && turns.peek().is_some() {
+            if g.pending_decision.is_none() {
+                g.advance_game(&mut ctx);
+                continue;
+            }
+
+            let is_buy = g.pending_decision.as_ref().unwrap().decision_type
+                == DecisionType::BuyCard;
+            if is_buy && **turns.peek().unwrap() <= g.turn {
+                positions.push(g.clone());
+                turns.next();
+            }
+            let choice = BigMoney.make_decision(&g);
+            g.resolve_decision(choice, &mut ctx);
+        }
+    }
+    positions
+}
+
+// Times full Big Money games, then searches each benchmark position with
+// a decider built by new_decider.
+pub fn run_bench(new_decider: &Fn() -> SearchDecider) -> BenchReport {
+    let start = Instant::now();
+    let mut games = 0;
+    for seed in 1..(BENCH_SEEDS + 1) {
+        let mut ctx = bench_context(seed);
+        for _ in 0..BENCH_GAMES_PER_SEED {
+            let mut players: Vec<Box<Decider>> = vec![Box::new(BigMoney), Box::new(BigMoney)];
+            game::run_game_with_context(&mut players, &mut ctx);
+            games += 1;
+        }
+    }
+    let game_time = start.elapsed();
+
+    let positions = bench_positions();
+    let mut iterations = 0;
+    let start = Instant::now();
+    for g in positions.iter() {
+        let mut decider = new_decider();
+        iterations += decider
+            .analyze(g)
+            .iter()
+            .map(|a| a.visits as u64)
+            .sum::<u64>();
+    }
+
+    BenchReport {
+        games: games,
+        game_time: game_time,
+        decisions: positions.len() as u32,
+        iterations: iterations,
+        search_time: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use bench::*;
+    use game::*;
+
+    #[test]
+    fn test_bench_positions() {
+        let positions = bench_positions();
+        assert!(positions.len() >= BENCH_SEEDS as usize);
+        for g in positions.iter() {
+            let d = g.pending_decision.as_ref().unwrap();
+            assert!(d.decision_type == DecisionType::BuyCard);
+        }
+
+        let turns = |ps: &Vec<Game>| ps.iter().map(|g| g.turn).collect::<Vec<_>>();
+        assert_eq!(turns(&positions), turns(&bench_positions()));
+    }
+}
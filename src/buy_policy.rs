@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fs;
+
+use cards;
+use cards::CardIdentifier;
+use game::DecisionType;
+use game_features::{feature_index, FEATURE_NAMES};
+use game_serialization::{cards_from_json, decision_from_json};
+use json::Json;
+
+// A buy option: a card, or None for buying nothing.
+pub type BuyOption = Option<CardIdentifier>;
+
+// Picks a card to buy with a softmax over the options offered, scoring each
+// option as a bias plus a weighted sum of the buyer's features. Each option
+// has its own weights, which are learned from self-play data by train.
+//
+// Weights are saved as text with one "<option> <feature> <weight>" triple
+// per line, where the option is a card name or "none" and the feature is one
+// of FEATURE_NAMES or "bias". Options without weights score zero.
+pub struct BuyPolicy {
+    // Feature weights for each option, followed by its bias.
+    pub weights: HashMap<BuyOption, Vec<f32>>,
+}
+
+fn option_name(o: &BuyOption) -> &'static str {
+    match *o {
+        Some(ref c) => cards::lookup_card(c).name,
+        None => "none",
+    }
+}
+
+fn option_named(name: &str) -> Option<BuyOption> {
+    match name {
+        "none" => Some(None),
+        _ => cards::card_named(name).map(|c| Some(c.identifier)),
+    }
+}
+
+fn softmax(scores: &[f32]) -> Vec<f32> {
+    let max = scores.iter().cloned().fold(std::f32::MIN, f32::max);
+    let exps = scores.iter().map(|s| (s - max).exp()).collect::<Vec<_>>();
+    let total: f32 = exps.iter().sum();
+    exps.iter().map(|e| e / total).collect()
+}
+
+impl BuyPolicy {
+    pub fn parse(contents: &str) -> Result<BuyPolicy, String> {
+        let bias = FEATURE_NAMES.len();
+        let mut weights = HashMap::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            if parts.len() != 3 {
+                return Err(format!(
+                    "Line {}: expected an option, a feature and a weight",
+                    i + 1
+                ));
+            }
+            let option = option_named(parts[0])
+                .ok_or(format!("Line {}: unknown option {}", i + 1, parts[0]))?;
+            let index = match parts[1] {
+                "bias" => bias,
+                name => feature_index(name)
+                    .ok_or(format!("Line {}: unknown feature {}", i + 1, name))?,
+            };
+            let weight = parts[2]
+                .parse::<f32>()
+                .map_err(|_| format!("Line {}: invalid weight {}", i + 1, parts[2]))?;
+            weights.entry(option).or_insert(vec![0.0; bias + 1])[index] = weight;
+        }
+        Ok(BuyPolicy { weights: weights })
+    }
+
+    pub fn load(path: &str) -> Result<BuyPolicy, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        BuyPolicy::parse(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Writes the weights in the format parse reads, skipping zeros.
+    pub fn to_text(&self) -> String {
+        let mut options = self.weights.keys().cloned().collect::<Vec<_>>();
+        options.sort_by_key(|o| option_name(o));
+
+        let mut out = String::new();
+        for o in options.iter() {
+            for (i, w) in self.weights[o].iter().enumerate() {
+                if *w == 0.0 {
+                    continue;
+                }
+                let feature = FEATURE_NAMES.get(i).map_or("bias", |n| n.as_str());
+                out.push_str(&format!("{} {} {}\n", option_name(o), feature, w));
+            }
+        }
+        out
+    }
+
+    fn score(&self, option: &BuyOption, features: &[f32]) -> f32 {
+        match self.weights.get(option) {
+            Some(ws) => {
+                let bias = ws[features.len()];
+                bias + features.iter().zip(ws.iter()).map(|(f, w)| f * w).sum::<f32>()
+            }
+            None => 0.0,
+        }
+    }
+
+    // The probability of choosing each option, in order.
+    pub fn probabilities(&self, features: &[f32], options: &[BuyOption]) -> Vec<f32> {
+        let scores = options
+            .iter()
+            .map(|o| self.score(o, features))
+            .collect::<Vec<_>>();
+        softmax(&scores)
+    }
+
+    // The most likely option, with ties going to the earliest.
+    pub fn choose(&self, features: &[f32], options: &[BuyOption]) -> BuyOption {
+        let probabilities = self.probabilities(features, options);
+        let mut best = 0;
+        for (i, p) in probabilities.iter().enumerate() {
+            if *p > probabilities[best] {
+                best = i;
+            }
+        }
+        options[best]
+    }
+}
+
+// One buy decision from self-play, with the share of the search's visits
+// each option received as the target distribution.
+pub struct BuyExample {
+    pub features: Vec<f32>,
+    pub options: Vec<BuyOption>,
+    pub target: Vec<f32>,
+}
+
+// Reads a self-play record, returning None for decisions other than buys.
+pub fn example_from_json(j: &Json) -> Result<Option<BuyExample>, String> {
+    let decision = decision_from_json(j.get("decision").ok_or("Missing field decision")?)?;
+    if decision.decision_type != DecisionType::BuyCard {
+        return Ok(None);
+    }
+
+    let features = j.get("features")
+        .and_then(|fs| fs.as_array())
+        .and_then(|fs| fs.get(decision.player.0 as usize))
+        .and_then(|fs| fs.as_array())
+        .ok_or("Field features should have a list of numbers per player")?
+        .iter()
+        .map(|f| match *f {
+            Json::Number(n) => Ok(n as f32),
+            _ => Err("Features should be numbers".to_string()),
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+    if features.len() != FEATURE_NAMES.len() {
+        return Err(format!(
+            "Expected {} features, found {}",
+            FEATURE_NAMES.len(),
+            features.len()
+        ));
+    }
+
+    let mut options = decision.choices.iter().map(|&c| Some(c)).collect::<Vec<_>>();
+    if decision.range.0 == 0 {
+        options.push(None);
+    }
+
+    let mut target = vec![0.0; options.len()];
+    let visits = j.get("visits")
+        .and_then(|v| v.as_array())
+        .ok_or("Field visits should be a list")?;
+    for v in visits.iter() {
+        let m = cards_from_json(v.get("move").ok_or("Missing field move")?)?;
+        let n = v.get("visits")
+            .and_then(|n| n.as_i64())
+            .ok_or("Field visits should be an integer")?;
+        if let Some(i) = options.iter().position(|o| *o == m.first().cloned()) {
+            target[i] += n as f32;
+        }
+    }
+
+    // Book moves and forced choices aren't searched, so learn the move made.
+    let total: f32 = target.iter().sum();
+    if total == 0.0 {
+        let m = cards_from_json(j.get("move").ok_or("Missing field move")?)?;
+        match options.iter().position(|o| *o == m.first().cloned()) {
+            Some(i) => target[i] = 1.0,
+            None => return Err("The move made wasn't one of the choices".into()),
+        }
+    } else {
+        for t in target.iter_mut() {
+            *t /= total;
+        }
+    }
+
+    Ok(Some(BuyExample {
+        features: features,
+        options: options,
+        target: target,
+    }))
+}
+
+pub fn load_examples(path: &str) -> Result<Vec<BuyExample>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    let mut examples = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let j = Json::parse(line).map_err(|e| format!("{} line {}: {}", path, i + 1, e))?;
+        if let Some(e) =
+            example_from_json(&j).map_err(|e| format!("{} line {}: {}", path, i + 1, e))?
+        {
+            examples.push(e);
+        }
+    }
+    Ok(examples)
+}
+
+// Fits a policy to the examples by full-batch gradient descent on the
+// cross-entropy between the policy and each target distribution.
+//
+// Features range from fractions to dozens, so they're scaled to at most 1
+// in magnitude while training, and the scale is folded back into the
+// weights afterwards.
+pub fn train(examples: &[BuyExample], epochs: usize, learning_rate: f32) -> BuyPolicy {
+    let num_features = FEATURE_NAMES.len();
+    let mut scale = vec![1.0f32; num_features];
+    for e in examples.iter() {
+        for (s, f) in scale.iter_mut().zip(e.features.iter()) {
+            *s = s.max(f.abs());
+        }
+    }
+    let scaled = examples
+        .iter()
+        .map(|e| {
+            e.features
+                .iter()
+                .zip(scale.iter())
+                .map(|(f, s)| f / s)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut policy = BuyPolicy {
+        weights: HashMap::new(),
+    };
+    for e in examples.iter() {
+        for o in e.options.iter() {
+            policy.weights.entry(*o).or_insert(vec![0.0; num_features + 1]);
+        }
+    }
+
+    for _ in 0..epochs {
+        let mut gradients: HashMap<BuyOption, Vec<f32>> = policy
+            .weights
+            .keys()
+            .map(|o| (*o, vec![0.0; num_features + 1]))
+            .collect();
+        for (e, features) in examples.iter().zip(scaled.iter()) {
+            let probabilities = policy.probabilities(features, &e.options);
+            for (i, o) in e.options.iter().enumerate() {
+                let error = probabilities[i] - e.target[i];
+                let g = gradients.get_mut(o).unwrap();
+                for (gw, f) in g.iter_mut().zip(features.iter()) {
+                    *gw += error * f;
+                }
+                g[num_features] += error;
+            }
+        }
+
+        let step = learning_rate / examples.len().max(1) as f32;
+        for (o, g) in gradients.iter() {
+            let ws = policy.weights.get_mut(o).unwrap();
+            for (w, gw) in ws.iter_mut().zip(g.iter()) {
+                *w -= step * gw;
+            }
+        }
+    }
+
+    for ws in policy.weights.values_mut() {
+        for (w, s) in ws.iter_mut().zip(scale.iter()) {
+            *w /= s;
+        }
+    }
+    policy
+}
+
+#[cfg(test)]
+mod tests {
+
+    use buy_policy::*;
+    use cards::*;
+    use game::*;
+    use game_features::*;
+    use game_serialization::*;
+    use json::Json;
+
+    #[test]
+    fn test_train_buy_policy() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let g = fresh_game(&names);
+        let features = player_features(&g, PlayerIdentifier(0));
+        let decision = Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::BuyCard,
+            choices: vec![COPPER.identifier, SILVER.identifier],
+            range: (0, 1),
+        };
+        let record = Json::object(vec![
+            ("decision", decision_to_json(&decision)),
+            (
+                "features",
+                Json::Array(vec![
+                    Json::Array(features.iter().map(|&f| f.into()).collect()),
+                ]),
+            ),
+            ("move", cards_to_json(&vec![SILVER.identifier])),
+            (
+                "visits",
+                Json::Array(vec![
+                    Json::object(vec![
+                        ("move", cards_to_json(&vec![SILVER.identifier])),
+                        ("visits", 90.into()),
+                    ]),
+                    Json::object(vec![("move", cards_to_json(&vec![])), ("visits", 10.into())]),
+                ]),
+            ),
+        ]);
+
+        let example = example_from_json(&record).unwrap().unwrap();
+        assert_eq!(example.target, vec![0.0, 0.9, 0.1]);
+
+        let policy = train(&[example], 200, 1.0);
+        let options = vec![Some(COPPER.identifier), Some(SILVER.identifier), None];
+        assert_eq!(policy.choose(&features, &options), Some(SILVER.identifier));
+
+        let reloaded = BuyPolicy::parse(&policy.to_text()).unwrap();
+        assert_eq!(reloaded.choose(&features, &options), Some(SILVER.identifier));
+        assert!(BuyPolicy::parse("Silver vp").is_err());
+        assert!(BuyPolicy::parse("Platinum bias 1.0").is_err());
+    }
+}
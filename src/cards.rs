@@ -81,7 +81,6 @@ impl std::fmt::Display for CardIdentifier {
 }
 
 impl Card {
-    #[allow(dead_code)]
     pub fn is_action(&self) -> bool {
         self.action_effects.len() > 0
     }
@@ -112,6 +111,65 @@ impl Card {
     }
 }
 
+fn describe_action(action: &CardAction) -> String {
+    let plural = |n: i32, name: &str| format!("+{} {}", n, pluralize_name(name, n));
+    match action {
+        &CardAction::DiscardForEffect(DiscardEffect::DrawPerDiscard) => {
+            "Discard any number of cards, then draw that many".into()
+        }
+        &CardAction::DrawCards(n) => plural(n, "Card"),
+        &CardAction::GainCardCostingUpto(n) => format!("Gain a card costing up to ${}", n),
+        &CardAction::OpponentsDiscardTo(n) => {
+            format!("Each other player discards down to {} cards", n)
+        }
+        &CardAction::PlusActions(n) => plural(n, "Action"),
+        &CardAction::PlusBuys(n) => plural(n, "Buy"),
+        &CardAction::PlusCoins(n) => format!("+${}", n),
+        &CardAction::TrashCards(ref card_type, ref followup) => {
+            let kind = match *card_type {
+                Some(ref t) => format!("{:?}", t),
+                None => "card".into(),
+            };
+            match *followup {
+                Some(TrashFollowup::ReplaceByCost(_, plus_cost, ref dest)) => format!(
+                    "Trash a {} from your hand, and gain a {} costing up to ${} more{}",
+                    kind,
+                    kind,
+                    plus_cost,
+                    match *dest {
+                        GainDestination::GainToHand => " to your hand",
+                        GainDestination::GainToDiscard => "",
+                    }
+                ),
+                None => format!("Trash a {} from your hand", kind),
+            }
+        }
+    }
+}
+
+impl Card {
+    // What the card does, in the words of its card text, such as
+    // "+1 Card, +2 Actions" for Village.
+    pub fn effect_summary(&self) -> String {
+        let mut effects = self.action_effects
+            .iter()
+            .map(describe_action)
+            .collect::<Vec<_>>();
+        if let Some(coins) = self.coin_value {
+            effects.push(format!("${}", coins));
+        }
+        if let Some(vp) = self.vp_value {
+            effects.push(format!("{} VP", vp));
+        }
+        if let Some(CardReaction::AttackImmunity) = self.reaction_effect {
+            effects.push(
+                "When another player plays an Attack, reveal this to be unaffected".into(),
+            );
+        }
+        effects.join(", ")
+    }
+}
+
 pub fn is_of_type(c: &CardIdentifier, card_type: &CardType) -> bool {
     let card = lookup_card(&c);
     match card_type {
@@ -302,6 +360,38 @@ pub fn lookup_card(ci: &CardIdentifier) -> &Card {
     return &CARDS[(ci.0 - 1) as usize];
 }
 
+pub fn card_named(name: &str) -> Option<&'static Card> {
+    CARDS
+        .iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+// The cards games can use, looked up by identifier or name, for programs
+// using Tactician as a library. Identifiers are only stable within a run,
+// so anything saved should name cards instead.
+pub struct CardRegistry;
+
+impl CardRegistry {
+    pub fn all() -> &'static [&'static Card] {
+        &CARDS
+    }
+
+    pub fn lookup(ci: &CardIdentifier) -> &'static Card {
+        CARDS[(ci.0 - 1) as usize]
+    }
+
+    // The card with an English name, in any case.
+    pub fn named(name: &str) -> Option<&'static Card> {
+        card_named(name)
+    }
+
+    // A kingdom of card names separated by commas, or preset:NAME.
+    pub fn kingdom(s: &str) -> Result<Vec<CardIdentifier>, String> {
+        parse_kingdom(s)
+    }
+}
+
 pub fn card_names(identifiers: &Vec<CardIdentifier>) -> String {
     return identifiers
         .iter()
@@ -310,6 +400,16 @@ pub fn card_names(identifiers: &Vec<CardIdentifier>) -> String {
         .join(", ");
 }
 
+pub fn pluralize_name(name: &str, count: i32) -> String {
+    if count == 1 {
+        name.to_string()
+    } else if name.ends_with("y") {
+        format!("{}ies", &name[..name.len() - 1])
+    } else {
+        format!("{}s", name)
+    }
+}
+
 pub fn score_cards(identifiers: &Vec<CardIdentifier>) -> i32 {
     return identifiers
         .iter()
@@ -321,7 +421,123 @@ const VP_PILE_COUNT_2P: i32 = 8;
 const VP_PILE_COUNT_MP: i32 = 12;
 const KINGDOM_PILE_COUNT: i32 = 10;
 
+// The kingdom suggested for a first game.
+pub fn first_game_kingdom() -> Vec<CardIdentifier> {
+    vec![
+        VILLAGE.identifier,
+        SMITHY.identifier,
+        MARKET.identifier,
+        WOODCUTTER.identifier,
+        MILITIA.identifier,
+        WORKSHOP.identifier,
+        MINE.identifier,
+        REMODEL.identifier,
+        CELLAR.identifier,
+        MOAT.identifier,
+    ]
+}
+
+// Named kingdoms, for benchmarking on familiar boards and listing which
+// include each card.
+pub fn kingdom_presets() -> Vec<(&'static str, Vec<CardIdentifier>)> {
+    vec![
+        ("first-game", first_game_kingdom()),
+        // Terminal draw and money, with nothing to build an engine from.
+        (
+            "big-money",
+            vec![
+                SMITHY.identifier,
+                WOODCUTTER.identifier,
+                MOAT.identifier,
+                MINE.identifier,
+                WORKSHOP.identifier,
+            ],
+        ),
+        // Villages, draw and cheap cantrips, rewarding long turns.
+        (
+            "engine-heavy",
+            vec![
+                VILLAGE.identifier,
+                SMITHY.identifier,
+                MARKET.identifier,
+                CELLAR.identifier,
+                WORKSHOP.identifier,
+                REMODEL.identifier,
+            ],
+        ),
+        // Militia and the Moats to block it.
+        (
+            "attack",
+            vec![
+                MILITIA.identifier,
+                MOAT.identifier,
+                SMITHY.identifier,
+                MARKET.identifier,
+                CELLAR.identifier,
+            ],
+        ),
+        // Cards that turn cards into better ones.
+        (
+            "remodel",
+            vec![
+                REMODEL.identifier,
+                MINE.identifier,
+                WORKSHOP.identifier,
+                VILLAGE.identifier,
+                WOODCUTTER.identifier,
+            ],
+        ),
+    ]
+}
+
+// Parses a kingdom given as preset:NAME, or as card names separated by
+// commas.
+pub fn parse_kingdom(s: &str) -> Result<Vec<CardIdentifier>, String> {
+    if s.starts_with("preset:") {
+        let name = &s["preset:".len()..];
+        let presets = kingdom_presets();
+        return match presets.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, ref kingdom)) => Ok(kingdom.clone()),
+            None => {
+                let names = presets.iter().map(|&(n, _)| n).collect::<Vec<_>>();
+                Err(format!(
+                    "Unknown kingdom preset {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        };
+    }
+    kingdom_named(&s.split(',').map(|n| n.trim()).collect::<Vec<_>>())
+}
+
+// Looks up a kingdom by card names, which must be distinct action cards.
+pub fn kingdom_named(names: &[&str]) -> Result<Vec<CardIdentifier>, String> {
+    let mut kingdom = vec![];
+    for name in names.iter() {
+        let c = match card_named(name) {
+            Some(c) if c.is_action() => c.identifier,
+            Some(_) => return Err(format!("{} isn't a kingdom card", name)),
+            None => return Err(format!("Unknown card {}", name)),
+        };
+        if kingdom.contains(&c) {
+            return Err(format!("{} is in the kingdom twice", name));
+        }
+        kingdom.push(c);
+    }
+    if kingdom.is_empty() {
+        return Err("A kingdom needs at least one card".into());
+    }
+    Ok(kingdom)
+}
+
 pub fn standard_piles(num_players: i32) -> HashMap<CardIdentifier, i32> {
+    supply_piles(num_players, &first_game_kingdom())
+}
+
+// The base treasure, victory and curse piles, plus a pile of each kingdom
+// card.
+pub fn supply_piles(num_players: i32, kingdom: &[CardIdentifier]) -> HashMap<CardIdentifier, i32> {
     let vp_count = if num_players == 2 {
         VP_PILE_COUNT_2P
     } else {
@@ -339,21 +555,8 @@ pub fn standard_piles(num_players: i32) -> HashMap<CardIdentifier, i32> {
         (CURSE.identifier, curses),
     ];
 
-    let kingdom_cards = vec![
-        VILLAGE.identifier,
-        SMITHY.identifier,
-        MARKET.identifier,
-        WOODCUTTER.identifier,
-        MILITIA.identifier,
-        WORKSHOP.identifier,
-        MINE.identifier,
-        REMODEL.identifier,
-        CELLAR.identifier,
-        MOAT.identifier,
-    ];
-
-    for c in kingdom_cards {
-        cards.push((c, KINGDOM_PILE_COUNT));
+    for c in kingdom.iter() {
+        cards.push((*c, KINGDOM_PILE_COUNT));
     }
 
     cards.into_iter().collect::<HashMap<CardIdentifier, i32>>()
@@ -367,3 +570,47 @@ fn test_card_identifiers() {
         assert_eq!(*c1, c2);
     }
 }
+
+#[test]
+fn test_kingdom_named() {
+    assert_eq!(
+        kingdom_named(&["smithy", "Village"]),
+        Ok(vec![SMITHY.identifier, VILLAGE.identifier])
+    );
+    assert!(kingdom_named(&["Smithy", "smithy"]).is_err());
+    assert!(kingdom_named(&["Gold"]).is_err());
+    assert!(kingdom_named(&["Chapel"]).is_err());
+    assert!(kingdom_named(&[]).is_err());
+}
+
+#[test]
+fn test_parse_kingdom() {
+    assert_eq!(parse_kingdom("preset:first-game"), Ok(first_game_kingdom()));
+    assert_eq!(
+        parse_kingdom("Smithy, village"),
+        Ok(vec![SMITHY.identifier, VILLAGE.identifier])
+    );
+    assert!(parse_kingdom("preset:chapel-deck").is_err());
+    for &(_, ref kingdom) in kingdom_presets().iter() {
+        assert!(kingdom.iter().all(|c| lookup_card(c).is_action()));
+    }
+}
+
+#[test]
+fn test_pluralize_name() {
+    assert_eq!(pluralize_name(PROVINCE.name, 6), "Provinces");
+    assert_eq!(pluralize_name(DUCHY.name, 2), "Duchies");
+    assert_eq!(pluralize_name(CURSE.name, 1), "Curse");
+}
+
+#[test]
+fn test_effect_summary() {
+    assert_eq!(VILLAGE.effect_summary(), "+1 Card, +2 Actions");
+    assert_eq!(MILITIA.effect_summary(), "+$2, Each other player discards down to 3 cards");
+    assert_eq!(
+        MINE.effect_summary(),
+        "Trash a Treasure from your hand, and gain a Treasure costing up to $3 more to your hand"
+    );
+    assert_eq!(GOLD.effect_summary(), "$3");
+    assert_eq!(CURSE.effect_summary(), "-1 VP");
+}
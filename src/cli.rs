@@ -0,0 +1,2020 @@
+// The tactician command line. The binary only hands it its arguments, so
+// it can be built from the library's internals without them being public.
+
+use getopts;
+use rand;
+use std;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use bench;
+use buy_policy;
+use cards;
+use color;
+use fuzz;
+use game;
+use game_log;
+use game_report;
+use genetic;
+use interrupt;
+use json;
+use locale;
+use log_import;
+use match_config;
+use match_runner;
+use match_stats;
+use progress;
+use registry;
+use remote_play;
+use repl;
+use replay;
+use rest_server;
+use results_db;
+use results_output;
+use rpc_server;
+use search_decider;
+use self_play;
+use spectator;
+use sprt;
+use text_protocol;
+use time_control;
+use tournament;
+use tree_search;
+use tuning;
+use util;
+#[cfg(feature = "websocket")]
+use websocket_server;
+
+// A finished game of a match: its number, from 1, its seed, whether the
+// players' seats were swapped and how it ended, with the scores in the
+// players' order, and its turns if the match has a results database.
+struct FinishedGame {
+    number: u32,
+    seed: Option<u32>,
+    swapped: bool,
+    outcome: game::GameOutcome,
+    snapshots: Option<results_db::GameSnapshots>,
+}
+
+// Plays the config's games one after another, passing each to on_game,
+// which returns whether to play on.
+fn play_games_serially(
+    config: &match_config::MatchConfig,
+    schedule: match_runner::Schedule,
+    rules: &game::Rules,
+    players: &mut Vec<Box<game::Decider>>,
+    on_game: &mut FnMut(FinishedGame) -> bool,
+) {
+    let snapshots = config.db_path.as_ref().map(|_| results_db::watch(players));
+    for i in 0..config.games {
+        if interrupt::stop_requested() {
+            break;
+        }
+        if config.games > 1 && config.verbosity >= game::Verbosity::Verbose {
+            let title = format!("Game {}", i + 1);
+            println!("");
+            println!("========================================");
+            println!("|{: ^38}|", title);
+            println!("========================================");
+            println!("");
+        }
+        let (seed, swapped) = schedule.game(i);
+        let mut ctx = match_runner::game_context(seed, config.verbosity);
+        let finished = FinishedGame {
+            number: i + 1,
+            seed: seed,
+            swapped: swapped,
+            outcome: match_runner::play_seated(players, &mut ctx, rules, swapped),
+            snapshots: snapshots.as_ref().map(|s| s.replace(Default::default())),
+        };
+        if !on_game(finished) {
+            break;
+        }
+    }
+}
+
+// Plays the config's games on its number of threads, each with its own
+// players built from the config's specs, so nothing a player learns is
+// shared between threads. Games are seeded as they are when played one
+// after another, so seeded matches play the same games, but they finish
+// out of order.
+fn play_games_in_parallel(
+    config: &match_config::MatchConfig,
+    schedule: match_runner::Schedule,
+    rules: &game::Rules,
+    on_game: &mut FnMut(FinishedGame) -> bool,
+) {
+    let next = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+    let workers = (0..config.jobs)
+        .map(|_| {
+            let next = next.clone();
+            let sender = sender.clone();
+            let specs = config.seeded_players();
+            let rules = rules.clone();
+            let verbosity = config.verbosity;
+            let snapshot = config.db_path.is_some();
+            thread::spawn(move || {
+                let mut players = specs
+                    .iter()
+                    .map(|s| player_for_string(s.clone(), true))
+                    .collect::<Vec<_>>();
+                let snapshots = match snapshot {
+                    true => Some(results_db::watch(&mut players)),
+                    false => None,
+                };
+                while !interrupt::stop_requested() {
+                    let i = next.fetch_add(1, Ordering::SeqCst) as u32;
+                    if i >= schedule.games {
+                        return;
+                    }
+                    let (seed, swapped) = schedule.game(i);
+                    let mut ctx = match_runner::game_context(seed, verbosity);
+                    let finished = FinishedGame {
+                        number: i + 1,
+                        seed: seed,
+                        swapped: swapped,
+                        outcome: match_runner::play_seated(&mut players, &mut ctx, &rules, swapped),
+                        snapshots: snapshots.as_ref().map(|s| s.replace(Default::default())),
+                    };
+                    // The match stopped early, so the game isn't wanted.
+                    if sender.send(finished).is_err() {
+                        return;
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    for finished in receiver.iter() {
+        if !on_game(finished) {
+            // Workers stop once they've finished the games they're playing.
+            next.store(config.games as usize, Ordering::SeqCst);
+            break;
+        }
+    }
+    drop(receiver);
+    for w in workers {
+        if w.join().is_err() {
+            panic!("A game thread failed");
+        }
+    }
+}
+
+// Plays up to the config's number of games, on several threads if it has
+// more than one job. With an SPRT, stops as soon as the test decides whether
+// the first player is stronger. With a seed, each game's shuffles are seeded
+// from it, so seeded players replay the same games. With a results file,
+// each game's result is written to it as it finishes, followed by the
+// match's totals, and with a record file or log directory, every decision
+// of each game, for replays. With a results database, each game, its
+// scores and its turns are added to a run of their own in it. Quiet runs
+// of several games show their progress instead of each game's result, and
+// optionally a chart of the first player's win rate, unless progress is
+// turned off. Every run ends
+// with a table of each player's wins, draws and losses. Ctrl-C during a match of
+// several games stops it once the games being played are over, or a second
+// one aborts them, and the results so far are printed and saved.
+fn run_games(config: &match_config::MatchConfig, players: &mut Vec<Box<game::Decider>>) {
+    let num_games = config.games;
+    let verbosity = config.verbosity;
+    if num_games > 1 && verbosity > game::Verbosity::Quiet {
+        println!("Running {} game(s)", num_games);
+    }
+    let names = registry::match_names(
+        &config.players,
+        players.iter().map(|p| p.description()).collect(),
+    );
+    let command = config.command_line();
+    if verbosity > game::Verbosity::Quiet || config.progress {
+        println!(
+            "Seed {}, kingdom {}",
+            config.seed.map_or("none".to_string(), |s| s.to_string()),
+            locale::card_names(&config.rules.kingdom)
+        );
+        println!("Reproduce with: {}", command);
+    }
+    let show_progress = verbosity == game::Verbosity::Quiet && num_games > 1 && config.progress;
+    let mut progress = match show_progress {
+        true => Some(progress::Progress::new(num_games, names.clone(), config.chart)),
+        false => None,
+    };
+
+    let mut results_file = config.results_path.as_ref().map(|path| {
+        let created =
+            results_output::ResultsFile::create(path, config.results_format, names.clone())
+                .and_then(|mut f| {
+                    f.record_run(&command, config.seed, &config.rules.kingdom)
+                        .map(|_| f)
+                });
+        match created {
+            Ok(f) => f,
+            Err(e) => fail(EXIT_BAD_FILE, &format!("Unable to create {}: {}", path, e)),
+        }
+    });
+    let mut results_db = config.db_path.as_ref().map(|path| {
+        results_db::ResultsDb::open(
+            path,
+            names.clone(),
+            &command,
+            config.seed,
+            &config.rules.kingdom,
+        ).unwrap_or_else(|e| fail(EXIT_BAD_FILE, &e))
+    });
+    let log = Rc::new(RefCell::new(game_log::GameLog::default()));
+    if config.record_path.is_some() || config.log_dir.is_some() {
+        let logged = players
+            .drain(..)
+            .map(|p| Box::new(game_log::Logged::new(p, log.clone())) as Box<game::Decider>)
+            .collect::<Vec<_>>();
+        players.extend(logged);
+    }
+    let mut record_file = config.record_path.as_ref().map(|path| match File::create(path) {
+        Ok(f) => f,
+        Err(e) => fail(EXIT_BAD_FILE, &format!("Unable to create {}: {}", path, e)),
+    });
+    if let Some(ref dir) = config.log_dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            fail(EXIT_BAD_FILE, &format!("Unable to create {}: {}", dir, e));
+        }
+    }
+    let mut rules = config.rules.clone();
+    if num_games > 1 {
+        interrupt::begin_match();
+        rules.abort = Some(&interrupt::ABORT_GAME);
+    }
+    let mut sprt = config.sprt.clone();
+    let mut stats = match_stats::MatchStats::new(players.len());
+    {
+        let mut on_game = |finished: FinishedGame| {
+            // Aborted games are left out of the results.
+            if rules.abort.map_or(false, |a| a.load(Ordering::SeqCst)) {
+                return false;
+            }
+            let r = &finished.outcome.scores;
+            stats.record(&finished.outcome);
+            if config.paired {
+                stats.record_pair((finished.number - 1) / 2, r[0]);
+            }
+            if let Some(ref mut p) = progress {
+                p.update(stats.games, &stats.scores);
+            }
+            if verbosity > game::Verbosity::Quiet {
+                let scores = names
+                    .iter()
+                    .zip(r.iter())
+                    .enumerate()
+                    .map(|(i, (name, score))| format!("{} {}", color::player(i, name), score))
+                    .collect::<Vec<_>>();
+                println!("Game {}: {}", finished.number, scores.join(", "));
+            }
+
+            // Who sat where, and the scores by seat, for the files written.
+            let seats = match finished.swapped {
+                true => vec![1, 0],
+                false => (0..names.len()).collect::<Vec<_>>(),
+            };
+            let seated = game::GameOutcome {
+                scores: seats.iter().map(|&p| r[p]).collect(),
+                turns: finished.outcome.turns,
+            };
+            if let Some(ref mut f) = results_file {
+                let result = results_output::GameResult {
+                    game: finished.number,
+                    seed: finished.seed,
+                    seats: seats.clone(),
+                    outcome: &seated,
+                    kingdom: &config.rules.kingdom,
+                };
+                if let Err(e) = f.record(&result) {
+                    fail(EXIT_BAD_FILE, &format!("Unable to write results: {}", e));
+                }
+            }
+            if let Some(ref mut db) = results_db {
+                let result = results_output::GameResult {
+                    game: finished.number,
+                    seed: finished.seed,
+                    seats: seats.clone(),
+                    outcome: &seated,
+                    kingdom: &config.rules.kingdom,
+                };
+                let snapshots = finished.snapshots.unwrap_or_default();
+                if let Err(e) = db.record(&result, &snapshots) {
+                    fail(
+                        EXIT_BAD_FILE,
+                        &format!("Unable to write to the results database: {}", e),
+                    );
+                }
+            }
+
+            if record_file.is_some() || config.log_dir.is_some() {
+                let game_log = game_log::GameLog {
+                    game: finished.number,
+                    seed: finished.seed,
+                    command: Some(command.clone()),
+                    players: seats.iter().map(|&p| names[p].clone()).collect(),
+                    scores: seated.scores.clone(),
+                    ..log.replace(game_log::GameLog::default())
+                };
+                if let Some(ref mut f) = record_file {
+                    if let Err(e) = writeln!(f, "{}", game_log.to_json()) {
+                        fail(EXIT_BAD_FILE, &format!("Unable to write game log: {}", e));
+                    }
+                }
+                if let Some(ref dir) = config.log_dir {
+                    let path = Path::new(dir).join(game_log.file_name());
+                    let written = File::create(&path)
+                        .and_then(|mut f| writeln!(f, "{}", game_log.to_json()));
+                    if let Err(e) = written {
+                        fail(EXIT_BAD_FILE, &format!("Unable to write {}: {}", path.display(), e));
+                    }
+                }
+            }
+
+            match sprt {
+                Some(ref mut sprt) => {
+                    sprt.record(r[0]);
+                    sprt.result() == sprt::SprtResult::Continue
+                }
+                None => true,
+            }
+        };
+        let schedule = match_runner::Schedule::new(config.games, config.seed, config.paired);
+        match config.jobs {
+            1 => play_games_serially(config, schedule, &rules, players, &mut on_game),
+            _ => play_games_in_parallel(config, schedule, &rules, &mut on_game),
+        }
+    }
+    if let Some(ref p) = progress {
+        p.finish();
+    }
+    if num_games > 1 {
+        if interrupt::stop_requested() {
+            println!("");
+            println!("Stopped after {} of {} game(s)", stats.games, num_games);
+        }
+        interrupt::end_match();
+    }
+    if let Some(ref mut f) = results_file {
+        if let Err(e) = f.finish() {
+            fail(EXIT_BAD_FILE, &format!("Unable to write results: {}", e));
+        }
+    }
+
+    println!("");
+    println!("{}", stats.table(&names));
+
+    if let Some(ref sprt) = sprt {
+        let (lower, upper) = sprt.bounds();
+        let conclusion = match sprt.result() {
+            sprt::SprtResult::AcceptH0 => format!("at most {} Elo stronger", sprt.elo0),
+            sprt::SprtResult::AcceptH1 => format!("at least {} Elo stronger", sprt.elo1),
+            sprt::SprtResult::Continue => "undecided".into(),
+        };
+        println!(
+            "SPRT after {} game(s): LLR {:.2} ({:.2}, {:.2}), {} is {}",
+            stats.games,
+            sprt.llr(),
+            lower,
+            upper,
+            names[0],
+            conclusion
+        );
+    }
+}
+
+// Exit codes, so scripts can tell why a run failed. Failures found while
+// running, such as a broken invariant in fuzzing, exit with 1.
+const EXIT_USAGE: i32 = 2;
+const EXIT_BAD_PLAYER: i32 = 3;
+const EXIT_BAD_FILE: i32 = 4;
+
+// Reports a mistake in how tactician was run, and exits with its code.
+fn fail(code: i32, message: &str) -> ! {
+    eprintln!("tactician: {}", message);
+    std::process::exit(code)
+}
+
+// Reports a spec's player that can't be made: an unknown player or option
+// is a bad player, and a bad value for a known option a bad argument.
+fn fail_player(spec: &str, message: &str) -> ! {
+    match registry::Registry::standard().knows(spec) {
+        true => fail(EXIT_USAGE, message),
+        false => fail(EXIT_BAD_PLAYER, message),
+    }
+}
+
+// The spec for Tactician with options, e.g. "tactician:iters=5000".
+fn tactician_spec(options: &[(&str, &str)]) -> String {
+    let options = options.iter().map(|&(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+    format!("tactician:{}", options.join(","))
+}
+
+// Players are made for the command line here, where Ctrl-C cuts the
+// current search short, and a second one quits.
+fn player_for_string(s: String, silent: bool) -> Box<game::Decider> {
+    interrupt::install_handler();
+    registry::Registry::standard()
+        .create(&s, silent)
+        .unwrap_or_else(|e| fail_player(&s, &e))
+}
+
+// Tactician with options from a spec, failing with a message if they don't
+// make sense.
+fn tactician_with_options(options: Vec<(&str, &str)>) -> search_decider::SearchDecider {
+    interrupt::install_handler();
+    let spec = tactician_spec(&options);
+    search_decider::SearchDecider::with_options(options, true)
+        .unwrap_or_else(|e| fail_player(&spec, &e))
+}
+
+// Checks every player spec before any games start, so a typo fails fast
+// with a suggestion instead of partway through a run.
+fn check_player_specs(specs: &[String]) {
+    let registry = registry::Registry::standard();
+    for spec in specs.iter() {
+        if let Err(e) = registry.check(spec) {
+            fail_player(spec, &e);
+        }
+    }
+}
+
+// Reads an option's value, which must parse as a T.
+fn option_value<T: std::str::FromStr>(matches: &getopts::Matches, name: &str) -> Option<T> {
+    matches.opt_str(name).map(|s| match s.parse::<T>() {
+        Ok(v) => v,
+        Err(_) => fail(EXIT_USAGE, &format!("Invalid value {} for --{}", s, name)),
+    })
+}
+
+// Options from a spec for commands that only work with Tactician, such as
+// "tactician:iters=5000".
+fn tactician_spec_options(spec: Option<&String>) -> Vec<(&str, &str)> {
+    match spec.map(|s| s.splitn(2, ':').collect::<Vec<_>>()) {
+        Some(ref parts) if parts[0].to_lowercase() == "tactician" => {
+            registry::parse_player_options(parts.get(1).cloned())
+                .unwrap_or_else(|e| fail(EXIT_BAD_PLAYER, &e))
+        }
+        Some(_) => fail(EXIT_BAD_PLAYER, "Only tactician can be used here"),
+        None => vec![],
+    }
+}
+
+// Loads a saved game state and prints how the search rates each legal move
+// of its pending decision.
+fn analyze_position(path: &str, spec: Option<&String>) {
+    let mut g = repl::load_position(path).unwrap_or_else(|e| fail(EXIT_BAD_FILE, &e));
+
+    let mut ctx = game::EvalContext {
+        rng: util::randomly_seeded_weak_rng(),
+        verbosity: game::Verbosity::Quiet,
+        events: None,
+    };
+    while !g.is_game_over() && g.pending_decision.is_none() {
+        g.advance_game(&mut ctx);
+    }
+    if g.pending_decision.is_none() {
+        println!("The game is over, so there's nothing to analyze.");
+        return;
+    }
+
+    let mut decider = tactician_with_options(tactician_spec_options(spec));
+    let analysis = decider.analyze(&g);
+
+    {
+        let d = g.pending_decision.as_ref().unwrap();
+        println!(
+            "{} to decide {:?} on turn {}, choosing from {}",
+            g.players[d.player.0 as usize].name,
+            d.decision_type,
+            g.turn,
+            locale::card_names(&d.choices)
+        );
+    }
+    println!("");
+    println!("{:<40} {:>8} {:>9}", "Move", "Visits", "Win rate");
+    for a in analysis.iter() {
+        println!(
+            "{:<40} {:>8} {:>8.1}%",
+            tree_search::SearchableState::printable_move(&g, &a.choice),
+            a.visits,
+            100.0 * a.win_rate
+        );
+    }
+}
+
+// Plays Tactician against itself, saving each searched decision as
+// training data.
+fn self_play(
+    num_games: u32,
+    out_path: &str,
+    format: self_play::DatasetFormat,
+    batch: Option<usize>,
+    spec: Option<&String>,
+    silent: bool,
+) {
+    let options = tactician_spec_options(spec);
+    let new_decider = || tactician_with_options(options.clone());
+    match self_play::run_self_play(num_games, &new_decider, out_path, format, batch, silent) {
+        Ok((n, ref files)) if files.len() == 1 => println!("Wrote {} records to {}", n, files[0]),
+        Ok((n, files)) => println!(
+            "Wrote {} records to {} files, {} to {}",
+            n,
+            files.len(),
+            files[0],
+            files[files.len() - 1]
+        ),
+        Err(e) => fail(EXIT_BAD_FILE, &e),
+    }
+}
+
+// Fits a buy policy to self-play data and saves its weights.
+fn train_policy(data_path: &str, out_path: &str, epochs: usize) {
+    let examples = match buy_policy::load_examples(data_path) {
+        Ok(e) => e,
+        Err(e) => fail(EXIT_BAD_FILE, &e),
+    };
+    if examples.is_empty() {
+        fail(EXIT_BAD_FILE, &format!("No buy decisions found in {}", data_path));
+    }
+
+    let policy = buy_policy::train(&examples, epochs, 1.0);
+    let agreed = examples
+        .iter()
+        .filter(|e| {
+            let choice = policy.choose(&e.features, &e.options);
+            let i = e.options.iter().position(|o| *o == choice).unwrap();
+            e.target.iter().all(|t| *t <= e.target[i])
+        })
+        .count();
+    if let Err(e) = std::fs::write(out_path, policy.to_text()) {
+        fail(EXIT_BAD_FILE, &format!("Unable to write {}: {}", out_path, e));
+    }
+    println!(
+        "Trained on {} buy decisions, agreeing with the search on {:.1}%. Wrote {}",
+        examples.len(),
+        100.0 * agreed as f32 / examples.len() as f32,
+        out_path
+    );
+}
+
+// Evolves a priority strategy against a reference player and prints the
+// best one found, as a spec for the priority player.
+fn evolve_strategy(generations: usize, reference: Option<&String>, silent: bool) {
+    let options = genetic::GeneticOptions {
+        generations: generations,
+        ..Default::default()
+    };
+    let reference = reference.cloned().unwrap_or(String::from("bigmoney"));
+    check_player_specs(&[reference.clone()]);
+    let new_reference = || player_for_string(reference.clone(), true);
+    let mut rng = util::randomly_seeded_weak_rng();
+
+    let (best, win_rate) = genetic::evolve(&options, &new_reference, &mut rng, silent);
+    println!("");
+    println!(
+        "Best strategy won {:.1}% against {}:",
+        100.0 * win_rate,
+        reference
+    );
+    println!("priority:{}", best);
+}
+
+// Games played between the two perturbed configurations in each tuning
+// iteration, alternating seats.
+const TUNING_GAMES_PER_ITERATION: usize = 2;
+
+// Tunes Tactician's search options by playing it against itself, and saves
+// the result as a config file.
+fn tune_search(iterations: usize, out_path: &str, spec: Option<&String>, silent: bool) {
+    let base = tactician_spec_options(spec);
+    let params = tuning::search_parameters();
+
+    let new_decider = |values: &[f32]| {
+        let tuned = params
+            .iter()
+            .zip(values.iter())
+            .map(|(p, v)| (p.name, v.to_string()))
+            .collect::<Vec<_>>();
+        let mut decider = tactician_with_options(base.clone());
+        for &(key, ref value) in tuned.iter() {
+            decider
+                .set_option(key, value)
+                .unwrap_or_else(|e| fail(EXIT_BAD_PLAYER, &e));
+        }
+        Box::new(decider) as Box<game::Decider>
+    };
+    let mut play_match = |a: &[f32], b: &[f32]| {
+        let mut score = 0.0;
+        for i in 0..TUNING_GAMES_PER_ITERATION {
+            let seat = i % 2;
+            let mut players = match seat {
+                0 => vec![new_decider(a), new_decider(b)],
+                _ => vec![new_decider(b), new_decider(a)],
+            };
+            score += game::run_game(&mut players, game::Verbosity::Quiet)[seat];
+        }
+        score / TUNING_GAMES_PER_ITERATION as f32
+    };
+
+    let mut rng = util::randomly_seeded_weak_rng();
+    let values = tuning::spsa(&params, iterations, &mut rng, &mut play_match, silent);
+    if let Err(e) = std::fs::write(out_path, tuning::config_file_contents(&params, &values)) {
+        fail(EXIT_BAD_FILE, &format!("Unable to write {}: {}", out_path, e));
+    }
+    println!(
+        "Wrote tuned options to {}. Use them with tactician:config={}",
+        out_path, out_path
+    );
+}
+
+// Players for a tournament, where "@file" stands for the players listed in
+// a file, one per line.
+fn tournament_player_specs(args: &[String]) -> Vec<String> {
+    let mut specs = vec![];
+    for arg in args.iter() {
+        if arg.starts_with('@') {
+            let path = &arg[1..];
+            match std::fs::read_to_string(path) {
+                Ok(contents) => specs.extend(
+                    contents
+                        .lines()
+                        .map(|l| l.trim())
+                        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                        .map(|l| l.to_string()),
+                ),
+                Err(e) => fail(EXIT_BAD_FILE, &format!("Unable to read {}: {}", path, e)),
+            }
+        } else {
+            specs.push(arg.clone());
+        }
+    }
+    if specs.len() < 2 {
+        fail(EXIT_USAGE, "A tournament needs at least two players");
+    }
+    check_player_specs(&specs);
+    specs
+}
+
+// Reads an option that counts something, which must be positive.
+fn count_option(matches: &getopts::Matches, name: &str, default: u32) -> u32 {
+    match matches.opt_str(name) {
+        Some(s) => match s.parse::<u32>() {
+            Ok(n) if n > 0 => n,
+            _ => fail(
+                EXIT_USAGE,
+                &format!("Invalid number of {} {}, expected a positive whole number", name, s),
+            ),
+        },
+        None => default,
+    }
+}
+
+fn player_factories(specs: &[String]) -> Vec<Box<Fn() -> Box<game::Decider>>> {
+    specs
+        .iter()
+        .map(|s| {
+            let spec = s.clone();
+            let factory = move || player_for_string(spec.clone(), true);
+            Box::new(factory) as Box<Fn() -> Box<game::Decider>>
+        })
+        .collect()
+}
+
+fn print_match_result(specs: &[String], i: usize, j: usize, result: &tournament::MatchResult) {
+    println!(
+        "{} vs {}: +{} ={} -{}",
+        specs[i], specs[j], result.wins, result.ties, result.losses
+    );
+}
+
+// Writes each game of a tournament to a results file, if there is one,
+// numbering them in the order they finish.
+fn tournament_results(
+    output: Option<&(results_output::ResultsFormat, String)>,
+    specs: &[String],
+) -> Option<results_output::ResultsFile> {
+    output.map(|&(format, ref path)| {
+        match results_output::ResultsFile::create(path, format, specs.to_vec()) {
+            Ok(f) => f,
+            Err(e) => fail(EXIT_BAD_FILE, &format!("Unable to create {}: {}", path, e)),
+        }
+    })
+}
+
+fn record_tournament_game(
+    results: &mut Option<results_output::ResultsFile>,
+    played: &mut u32,
+    kingdom: &Vec<cards::CardIdentifier>,
+    seats: &[usize],
+    seed: Option<u32>,
+    outcome: &game::GameOutcome,
+) {
+    *played += 1;
+    if let Some(ref mut f) = *results {
+        let result = results_output::GameResult {
+            game: *played,
+            seed: seed,
+            seats: seats.to_vec(),
+            outcome: outcome,
+            kingdom: kingdom,
+        };
+        if let Err(e) = f.record(&result) {
+            fail(EXIT_BAD_FILE, &format!("Unable to write results: {}", e));
+        }
+    }
+}
+
+fn finish_tournament_results(results: &mut Option<results_output::ResultsFile>) {
+    if let Some(ref mut f) = *results {
+        if let Err(e) = f.finish() {
+            fail(EXIT_BAD_FILE, &format!("Unable to write results: {}", e));
+        }
+    }
+}
+
+// Plays every pairing of the given players and prints a cross-table of
+// their scores with Elo ratings, and each pairing's win rates.
+fn run_tournament(
+    games: u32,
+    args: &[String],
+    settings: &tournament::Settings,
+    silent: bool,
+    output: Option<&(results_output::ResultsFormat, String)>,
+) {
+    let specs = tournament_player_specs(args);
+    let factories = player_factories(&specs);
+    let players = factories.iter().map(|f| &**f).collect::<Vec<_>>();
+    let mut results = tournament_results(output, &specs);
+    let mut played = 0;
+    let kingdom = &settings.rules.kingdom;
+    let table = tournament::round_robin(
+        &players,
+        games,
+        settings,
+        &mut |i, j, result| {
+            if !silent {
+                print_match_result(&specs, i, j, result);
+            }
+        },
+        &mut |seats, seed, outcome| {
+            record_tournament_game(&mut results, &mut played, kingdom, seats, seed, outcome)
+        },
+    );
+    finish_tournament_results(&mut results);
+    let ratings = tournament::elo_ratings(&table);
+
+    let mut order = (0..specs.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| ratings[b].0.partial_cmp(&ratings[a].0).unwrap());
+    let width = specs.iter().map(|s| s.chars().count()).max().unwrap().max(6);
+
+    println!("");
+    print!("{:>3} {:<w$} {:>13}", "#", "Player", "Elo", w = width);
+    for rank in 0..order.len() {
+        print!(" {:>6}", rank + 1);
+    }
+    println!("");
+    for (rank, &i) in order.iter().enumerate() {
+        print!(
+            "{:>3} {:<w$} {:>6.0} ± {:<4.0}",
+            rank + 1,
+            specs[i],
+            ratings[i].0,
+            ratings[i].1,
+            w = width
+        );
+        for &j in order.iter() {
+            if i == j {
+                print!(" {:>6}", "-");
+            } else {
+                print!(" {:>6}", table[i][j].score());
+            }
+        }
+        println!("");
+    }
+
+    println!("");
+    println!("Win rates, row against column, with 95% intervals and games played:");
+    println!("{}", tournament::matchup_matrix(&table, &specs, &order));
+}
+
+// Plays a Swiss tournament and prints the standings, by points and then
+// Elo rating.
+fn run_swiss(
+    rounds: u32,
+    games: u32,
+    args: &[String],
+    settings: &tournament::Settings,
+    silent: bool,
+    output: Option<&(results_output::ResultsFormat, String)>,
+) {
+    let specs = tournament_player_specs(args);
+    let factories = player_factories(&specs);
+    let players = factories.iter().map(|f| &**f).collect::<Vec<_>>();
+    let mut results = tournament_results(output, &specs);
+    let mut played = 0;
+    let kingdom = &settings.rules.kingdom;
+    let (table, points) = tournament::swiss(
+        &players,
+        rounds,
+        games,
+        settings,
+        &mut |i, j, result| {
+            if !silent {
+                print_match_result(&specs, i, j, result);
+            }
+        },
+        &mut |seats, seed, outcome| {
+            record_tournament_game(&mut results, &mut played, kingdom, seats, seed, outcome)
+        },
+    );
+    finish_tournament_results(&mut results);
+    let ratings = tournament::elo_ratings(&table);
+
+    let mut order = (0..specs.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        (points[b], ratings[b].0)
+            .partial_cmp(&(points[a], ratings[a].0))
+            .unwrap()
+    });
+    let width = specs.iter().map(|s| s.chars().count()).max().unwrap().max(6);
+
+    println!("");
+    println!(
+        "{:>3} {:<w$} {:>6} {:>6} {:>6}",
+        "#",
+        "Player",
+        "Points",
+        "Games",
+        "Elo",
+        w = width
+    );
+    for (rank, &i) in order.iter().enumerate() {
+        let played: u32 = table[i].iter().map(|r| r.games()).sum();
+        println!(
+            "{:>3} {:<w$} {:>6} {:>6} {:>6.0} ± {:.0}",
+            rank + 1,
+            specs[i],
+            points[i],
+            played,
+            ratings[i].0,
+            ratings[i].1,
+            w = width
+        );
+    }
+}
+
+const MAX_PLAYERS: usize = 4;
+
+// Iterations per search when benchmarking, unless the spec sets a budget.
+const BENCH_ITERATIONS: i32 = 1000;
+
+// Measures game simulation and search throughput on a fixed workload.
+fn run_bench(spec: Option<&String>) {
+    let options = tactician_spec_options(spec);
+    let new_decider = || {
+        let mut decider = tactician_with_options(vec![]);
+        decider.options.budget = tree_search::SearchBudget::Iterations(BENCH_ITERATIONS);
+        for &(key, value) in options.iter() {
+            decider
+                .set_option(key, value)
+                .unwrap_or_else(|e| fail_player(&tactician_spec(&options), &e));
+        }
+        decider
+    };
+
+    let report = bench::run_bench(&new_decider);
+    println!(
+        "Games:      {} in {:.2}s, {:.0} games/s",
+        report.games,
+        bench::seconds(report.game_time),
+        report.games_per_second()
+    );
+    println!(
+        "Search:     {} iterations over {} decisions, {:.0} iterations/s",
+        report.iterations,
+        report.decisions,
+        report.iterations_per_second()
+    );
+    println!("Decisions:  {:.1}ms each", report.millis_per_decision());
+}
+
+// Plays random games checking the rules engine's invariants, and reports
+// the seeds of any that failed.
+fn run_fuzz(games: u32, seed: u32) {
+    let report = fuzz::fuzz(games, seed);
+    println!(
+        "Played {} game(s) from seed {}, {} failed",
+        report.games,
+        seed,
+        report.failures.len()
+    );
+    for s in report.failures.iter() {
+        println!("  Replay with: tactician fuzz --games 1 --seed {}", s);
+    }
+    if !report.failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+// Lists the cards games are played with, what each does and the named
+// kingdoms that include it.
+fn print_cards() {
+    let presets = cards::kingdom_presets();
+    println!("{:<12} {:>4}  {:<18} {}", "Card", "Cost", "Types", "Kingdoms");
+    for c in cards::CARDS.iter() {
+        let mut types = vec![];
+        if c.is_treasure() {
+            types.push("Treasure");
+        }
+        if c.is_action() {
+            types.push("Action");
+        }
+        if c.is_attack {
+            types.push("Attack");
+        }
+        if c.is_reaction() {
+            types.push("Reaction");
+        }
+        if c.is_curse() {
+            types.push("Curse");
+        } else if c.is_victory() {
+            types.push("Victory");
+        }
+        let kingdoms = presets
+            .iter()
+            .filter(|&&(_, ref kingdom)| kingdom.contains(&c.identifier))
+            .map(|&(name, _)| name)
+            .collect::<Vec<_>>();
+        println!(
+            "{:<12} {:>4}  {:<18} {}",
+            c.name,
+            format!("${}", c.cost),
+            types.join(", "),
+            kingdoms.join(", ")
+        );
+        println!("{:<12} {}", "", c.effect_summary());
+    }
+}
+
+// A subcommand, with the options it takes besides --help.
+struct Command {
+    name: &'static str,
+    // Its arguments, for its usage line.
+    args: &'static str,
+    summary: &'static str,
+    options: fn(&mut getopts::Options),
+    run: fn(&getopts::Matches),
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "play",
+        args: "[player]...",
+        summary: "Plays games between two to four players, Tactician and Big Money by default.",
+        options: play_options,
+        run: play,
+    },
+    Command {
+        name: "tournament",
+        args: "<player|@file> <player|@file>...",
+        summary: "Plays every pairing of the players and prints a cross-table with Elo ratings.",
+        options: tournament_options,
+        run: tournament,
+    },
+    Command {
+        name: "swiss",
+        args: "<player|@file> <player|@file>...",
+        summary: "Plays a Swiss tournament and prints the standings.",
+        options: swiss_options,
+        run: swiss,
+    },
+    Command {
+        name: "analyze",
+        args: "<state.json> [tactician:options]",
+        summary: "Prints how Tactician rates each move of a saved position's decision.",
+        options: no_options,
+        run: analyze,
+    },
+    Command {
+        name: "repl",
+        args: "[state.json]",
+        summary: "Explores a position: lists, plays and takes back moves, and searches it.",
+        options: no_options,
+        run: repl,
+    },
+    Command {
+        name: "replay",
+        args: "<games.jsonl>",
+        summary: "Steps through a game recorded with play --record.",
+        options: replay_options,
+        run: replay,
+    },
+    Command {
+        name: "report",
+        args: "<games.jsonl>",
+        summary: "Writes up a game recorded with play --record in Markdown or HTML, for sharing.",
+        options: report_options,
+        run: report,
+    },
+    Command {
+        name: "import",
+        args: "<log.txt>",
+        summary: "Plays a Dominion Online game log through the engine, flagging illegal moves.",
+        options: import_options,
+        run: import,
+    },
+    Command {
+        name: "serve",
+        args: "[players...]",
+        summary: "Runs games for other programs, over JSON-RPC, HTTP or WebSocket.",
+        options: serve_options,
+        run: serve,
+    },
+    Command {
+        name: "host",
+        args: "<addr> [players...]",
+        summary: "Hosts games over TCP, with remote seats for players who join.",
+        options: host_options,
+        run: host,
+    },
+    Command {
+        name: "join",
+        args: "<addr> [player]",
+        summary: "Joins games hosted over TCP, playing at the terminal or as a bot.",
+        options: no_options,
+        run: join,
+    },
+    Command {
+        name: "engine",
+        args: "[tactician:options]",
+        summary: "Plays a game as an engine for match managers, over a text protocol on stdin.",
+        options: engine_options,
+        run: engine,
+    },
+    Command {
+        name: "bench",
+        args: "[tactician:options]",
+        summary: "Times games and searches of a fixed workload.",
+        options: no_options,
+        run: bench,
+    },
+    Command {
+        name: "fuzz",
+        args: "",
+        summary: "Plays random games, checking the rules engine's invariants at every decision.",
+        options: fuzz_options,
+        run: fuzz,
+    },
+    Command {
+        name: "cards",
+        args: "",
+        summary: "Lists the cards games are played with, what they do and their kingdoms.",
+        options: no_options,
+        run: cards,
+    },
+    Command {
+        name: "players",
+        args: "",
+        summary: "Lists the players, their options with defaults, and example specs.",
+        options: no_options,
+        run: players,
+    },
+    Command {
+        name: "selfplay",
+        args: "<out.jsonl|out.csv> [tactician:options]",
+        summary: "Plays Tactician against itself, saving its searches as training data.",
+        options: selfplay_options,
+        run: selfplay,
+    },
+    Command {
+        name: "train",
+        args: "<selfplay.jsonl> <out.txt>",
+        summary: "Fits a buy policy to self-play data.",
+        options: train_options,
+        run: train,
+    },
+    Command {
+        name: "evolve",
+        args: "[reference player]",
+        summary: "Evolves a priority strategy against a reference player, Big Money by default.",
+        options: evolve_options,
+        run: evolve,
+    },
+    Command {
+        name: "tune",
+        args: "<out.cfg> [tactician:options]",
+        summary: "Tunes Tactician's search options by self-play.",
+        options: tune_options,
+        run: tune,
+    },
+];
+
+fn no_options(_opts: &mut getopts::Options) {}
+
+fn quiet_option(opts: &mut getopts::Options) {
+    opts.optflag("q", "quiet", "print only the final results");
+}
+
+fn play_options(opts: &mut getopts::Options) {
+    quiet_option(opts);
+    opts.optflag("", "no-progress", "with -q, print nothing but the final results");
+    opts.optflag(
+        "",
+        "chart",
+        "with -q, chart the first player's win rate and its confidence band as games finish",
+    );
+    output_option(opts);
+    opts.optopt(
+        "",
+        "record",
+        "log every decision of each game to FILE, for replay",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "log-dir",
+        "log each game to a file of its own in DIR, named by its number and seed",
+        "DIR",
+    );
+    opts.optopt(
+        "",
+        "db",
+        "add each game, its scores and its turns to the SQLite database FILE",
+        "FILE",
+    );
+    opts.optflag("", "no-color", "don't color game logs, even on a terminal");
+    opts.optflag(
+        "",
+        "tui",
+        "watch each game on a dashboard of the supply, the players and each decision",
+    );
+    opts.optflag(
+        "",
+        "spectate",
+        "follow each game as it's played, with what happened and the score after each decision",
+    );
+    opts.optopt(
+        "",
+        "delay",
+        "pause for TIME after each decision with --tui or --spectate (default 400ms)",
+        "TIME",
+    );
+    opts.optflagmulti(
+        "v",
+        "verbose",
+        "print a summary of each turn, or with -vv, every card moved and the players' thinking",
+    );
+    opts.optopt(
+        "",
+        "config",
+        "read the match from a TOML file, which other options override",
+        "FILE",
+    );
+    opts.optopt("n", "games", "games to play (default 1)", "N");
+    opts.optflag(
+        "",
+        "matchups",
+        "play each pair of players head to head for N games, and print their win rates",
+    );
+    opts.optflag(
+        "",
+        "paired",
+        "play each seed twice, swapping the seats, to cancel out luck",
+    );
+    opts.optopt("j", "jobs", "play N games at once, on separate threads (default 1)", "N");
+    opts.optopt(
+        "",
+        "sprt",
+        "stop once a sequential test decides if the first player is stronger",
+        "ELO0,ELO1",
+    );
+    opts.optopt("", "seed", "seed each game's shuffles, for reproducible matches", "SEED");
+    opts.optopt(
+        "",
+        "kingdom",
+        "play with these cards, separated by commas, or a named kingdom, e.g. preset:big-money",
+        "CARDS",
+    );
+    opts.optopt(
+        "",
+        "clock",
+        "give each player a chess clock, e.g. 60s+1s for a minute plus a second a move",
+        "BASE+INC",
+    );
+    opts.optflag(
+        "",
+        "timeout-move",
+        "play the least legal move for a player out of time, instead of forfeiting",
+    );
+}
+
+fn play(matches: &getopts::Matches) {
+    color::enable_for_terminal(!matches.opt_present("no-color"));
+    // Options on the command line override the config file's.
+    let mut config = match matches.opt_str("config") {
+        Some(path) => match match_config::load(&path) {
+            Ok(config) => config,
+            Err(e) => fail(EXIT_BAD_FILE, &e),
+        },
+        None => match_config::MatchConfig::default(),
+    };
+    if let Some(games) = option_value(matches, "games") {
+        config.games = games;
+    }
+    if config.games == 0 {
+        fail(EXIT_USAGE, "I can't play zero games. That’s silly!");
+    }
+    if matches.opt_present("paired") {
+        config.paired = true;
+    }
+    if matches.opt_present("no-progress") {
+        config.progress = false;
+    }
+    if matches.opt_present("chart") {
+        config.chart = true;
+    }
+    if matches.opt_present("jobs") {
+        config.jobs = count_option(matches, "jobs", 1);
+    }
+    if matches.opt_present("quiet") || matches.opt_present("verbose") {
+        config.verbosity = game::Verbosity::from_flags(
+            matches.opt_present("quiet"),
+            matches.opt_count("verbose"),
+        );
+    }
+
+    // Two to four players, defaulting to Tactician against Big Money.
+    if !matches.free.is_empty() {
+        config.players = matches.free.clone();
+    }
+    // Or any number of players, each pair playing head to head.
+    if matches.opt_present("matchups") {
+        let quiet = config.verbosity == game::Verbosity::Quiet;
+        let settings = tournament_settings(matches, config.rules.clone(), config.seed);
+        return run_tournament(
+            config.games,
+            &config.players,
+            &settings,
+            quiet,
+            parse_output_option(matches).as_ref(),
+        );
+    }
+    if config.players.len() > MAX_PLAYERS {
+        fail(EXIT_USAGE, &format!("At most {} players can play", MAX_PLAYERS));
+    }
+    let defaults = ["tactician", "bigmoney"];
+    for i in config.players.len()..2 {
+        config.players.push(defaults[i].into());
+    }
+    check_player_specs(&config.players);
+    if let Some(seed) = option_value(matches, "seed") {
+        config.seed = Some(seed);
+    }
+    // Every match is seeded, players included, so any run can be reproduced.
+    if config.seed.is_none() {
+        config.seed = Some(rand::random());
+    }
+
+    // Players only share their thinking when tracing.
+    let silent = config.verbosity < game::Verbosity::Trace;
+    let mut players = config
+        .seeded_players()
+        .iter()
+        .map(|s| player_for_string(s.clone(), silent))
+        .collect::<Vec<_>>();
+    let watched = matches.opt_present("tui") || matches.opt_present("spectate");
+    if watched {
+        let view = match (matches.opt_present("tui"), matches.opt_present("spectate")) {
+            (true, true) => fail(EXIT_USAGE, "Only one of --tui and --spectate can be used"),
+            (true, false) => spectator::View::Dashboard,
+            _ => spectator::View::Scrolling,
+        };
+        if config.verbosity != game::Verbosity::Normal {
+            fail(EXIT_USAGE, "Watched games are shown as they're played, so can't use -q or -v");
+        }
+        let delay = match matches.opt_str("delay") {
+            Some(s) => util::parse_duration(&s)
+                .unwrap_or_else(|| fail(EXIT_USAGE, &format!("Invalid --delay {}", s))),
+            None => spectator::FRAME_DELAY,
+        };
+        // Every player sees every decision, so watching one sees them all.
+        let first = players.remove(0);
+        players.insert(0, Box::new(spectator::Spectator::new(first, view, delay)));
+    } else if matches.opt_present("delay") {
+        fail(EXIT_USAGE, "--delay is only used with --tui or --spectate");
+    }
+    if let Some(s) = matches.opt_str("sprt") {
+        config.sprt = Some(sprt::Sprt::parse(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e)));
+    }
+    if let Some((format, path)) = parse_output_option(matches) {
+        config.results_format = format;
+        config.results_path = Some(path);
+    }
+    if let Some(path) = matches.opt_str("record") {
+        config.record_path = Some(path);
+    }
+    if let Some(dir) = matches.opt_str("log-dir") {
+        config.log_dir = Some(dir);
+    }
+    if let Some(path) = matches.opt_str("db") {
+        config.db_path = Some(path);
+    }
+    if let Some(s) = matches.opt_str("kingdom") {
+        config.rules.kingdom = cards::parse_kingdom(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e));
+    }
+    if let Some(s) = matches.opt_str("clock") {
+        let tc = time_control::TimeControl::parse(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e));
+        config.rules.time_control = Some(tc);
+    }
+    if matches.opt_present("timeout-move") {
+        if let Some(ref mut tc) = config.rules.time_control {
+            tc.on_timeout = time_control::OnTimeout::DefaultMove;
+        }
+    }
+    if config.paired && (config.players.len() != 2 || config.games % 2 == 1) {
+        fail(EXIT_USAGE, "Paired games need two players and an even number of games");
+    }
+    if config.jobs > 1 {
+        // Games on other threads can't be watched, recorded or followed in
+        // a log, and only bots can play them.
+        if watched || config.record_path.is_some() || config.log_dir.is_some() {
+            fail(EXIT_USAGE, "Games played with --jobs can't be watched or recorded");
+        }
+        if config.verbosity > game::Verbosity::Normal {
+            fail(EXIT_USAGE, "Games played with --jobs can't be logged with -v");
+        }
+        if config.players.iter().any(|s| s.to_lowercase().starts_with("human")) {
+            fail(EXIT_USAGE, "Games played with --jobs can't have human players");
+        }
+    }
+    run_games(&config, &mut players);
+}
+
+fn output_option(opts: &mut getopts::Options) {
+    opts.optopt(
+        "",
+        "output",
+        "write each game's result and the totals to FILE, as JSON lines or CSV",
+        "json|csv:FILE",
+    );
+}
+
+fn parse_output_option(
+    matches: &getopts::Matches,
+) -> Option<(results_output::ResultsFormat, String)> {
+    matches
+        .opt_str("output")
+        .map(|s| results_output::parse_output(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e)))
+}
+
+fn tournament_options(opts: &mut getopts::Options) {
+    quiet_option(opts);
+    output_option(opts);
+    opts.optopt("g", "games", "games per pairing (default 10)", "N");
+    opts.optopt("", "seed", "seed the i-th game of each pairing with SEED + i", "SEED");
+    opts.optopt(
+        "",
+        "kingdom",
+        "play with these cards, separated by commas, or a named kingdom, e.g. preset:big-money",
+        "CARDS",
+    );
+}
+
+// The rules and first seed of a tournament's games, with --kingdom and
+// --seed taking the place of any given.
+fn tournament_settings(
+    matches: &getopts::Matches,
+    rules: game::Rules,
+    seed: Option<u32>,
+) -> tournament::Settings {
+    let mut settings = tournament::Settings {
+        rules: rules,
+        seed: option_value(matches, "seed").or(seed),
+    };
+    if let Some(s) = matches.opt_str("kingdom") {
+        settings.rules.kingdom = cards::parse_kingdom(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e));
+    }
+    settings
+}
+
+fn tournament(matches: &getopts::Matches) {
+    run_tournament(
+        count_option(matches, "games", 10),
+        &matches.free,
+        &tournament_settings(matches, game::Rules::default(), None),
+        matches.opt_present("quiet"),
+        parse_output_option(matches).as_ref(),
+    );
+}
+
+fn swiss_options(opts: &mut getopts::Options) {
+    tournament_options(opts);
+    opts.optopt("r", "rounds", "rounds to play (default 5)", "N");
+}
+
+fn swiss(matches: &getopts::Matches) {
+    run_swiss(
+        count_option(matches, "rounds", 5),
+        count_option(matches, "games", 10),
+        &matches.free,
+        &tournament_settings(matches, game::Rules::default(), None),
+        matches.opt_present("quiet"),
+        parse_output_option(matches).as_ref(),
+    );
+}
+
+fn replay_options(opts: &mut getopts::Options) {
+    opts.optopt("g", "game", "the game to replay (default the file's first)", "N");
+}
+
+// Loads the game picked with --game from the recorded games named by the
+// command's first argument.
+fn load_game_log(matches: &getopts::Matches, command: &str) -> game_log::GameLog {
+    let path = match matches.free.first() {
+        Some(path) => path,
+        None => usage_error(command),
+    };
+    let game = match matches.opt_present("game") {
+        true => Some(count_option(matches, "game", 1)),
+        false => None,
+    };
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => fail(EXIT_BAD_FILE, &format!("Unable to read {}: {}", path, e)),
+    };
+    let log = contents
+        .lines()
+        .map(|l| json::Json::parse(l).and_then(|j| game_log::GameLog::from_json(&j)))
+        .find(|log| log.as_ref().map_or(true, |log| game.map_or(true, |n| log.game == n)));
+    match log {
+        Some(Ok(log)) => log,
+        Some(Err(e)) => fail(
+            EXIT_BAD_FILE,
+            &format!("Unable to load games from {}: {}", path, e),
+        ),
+        None => match game {
+            Some(n) => fail(EXIT_BAD_FILE, &format!("{} has no game {}", path, n)),
+            None => fail(EXIT_BAD_FILE, &format!("{} has no games", path)),
+        },
+    }
+}
+
+fn replay(matches: &getopts::Matches) {
+    let log = load_game_log(matches, "replay");
+    color::enable_for_terminal(true);
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = replay::replay(&log, &mut stdin.lock(), &mut stdout.lock()) {
+        panic!("Unable to replay: {}", e);
+    }
+}
+
+fn report_options(opts: &mut getopts::Options) {
+    opts.optopt("g", "game", "the game to report on (default the file's first)", "N");
+    opts.optopt("o", "output", "write the report to FILE (default stdout)", "FILE");
+    opts.optopt(
+        "",
+        "format",
+        "markdown or html (default html for .html files, otherwise markdown)",
+        "FORMAT",
+    );
+}
+
+fn report(matches: &getopts::Matches) {
+    let log = load_game_log(matches, "report");
+    let output = matches.opt_str("output");
+    let format = match (matches.opt_str("format"), output.as_ref()) {
+        (Some(f), _) => {
+            game_report::ReportFormat::parse(&f).unwrap_or_else(|e| fail(EXIT_USAGE, &e))
+        }
+        (None, Some(path)) => game_report::ReportFormat::for_path(path),
+        (None, None) => game_report::ReportFormat::Markdown,
+    };
+    let report = game_report::game_report(&log, format);
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, report) {
+                fail(EXIT_BAD_FILE, &format!("Unable to write {}: {}", path, e));
+            }
+        }
+        None => print!("{}", report),
+    }
+}
+
+fn import_options(opts: &mut getopts::Options) {
+    opts.optopt(
+        "",
+        "kingdom",
+        "the game's cards, separated by commas, or a named kingdom (default the first game's)",
+        "CARDS",
+    );
+    opts.optopt(
+        "",
+        "analyze",
+        "search each decision for ITERS iterations and list the blunders",
+        "ITERS",
+    );
+    opts.optopt(
+        "",
+        "margin",
+        "how many points of win rate a move must lose to be a blunder (default 10)",
+        "PCT",
+    );
+    opts.optopt("", "record", "save the game to FILE, for replay", "FILE");
+    opts.optopt("", "seed", "shuffle the cards the log doesn't show with SEED (default 1)", "SEED");
+}
+
+// The cards of a move, as blunders list them.
+fn move_names(choice: &[cards::CardIdentifier]) -> String {
+    match choice.is_empty() {
+        true => "nothing".to_string(),
+        false => locale::card_names(choice),
+    }
+}
+
+fn import(matches: &getopts::Matches) {
+    let path = match matches.free.first() {
+        Some(path) => path,
+        None => usage_error("import"),
+    };
+    let kingdom = match matches.opt_str("kingdom") {
+        Some(s) => cards::parse_kingdom(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e)),
+        None => cards::first_game_kingdom(),
+    };
+    let margin: f32 = option_value(matches, "margin").unwrap_or(10.0);
+    let seed = option_value(matches, "seed").unwrap_or(1);
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => fail(EXIT_BAD_FILE, &format!("Unable to read {}: {}", path, e)),
+    };
+    let mut imported = match log_import::import_log(&contents, &kingdom, seed) {
+        Ok(imported) => imported,
+        Err(e) => fail(EXIT_BAD_FILE, &format!("Unable to import {}: {}", path, e)),
+    };
+
+    println!(
+        "{} decisions over {} turns between {}",
+        imported.log.decisions.len(),
+        imported.log.decisions.last().map_or(0, |d| d.state.turn),
+        imported.log.players.join(" and ")
+    );
+    match imported.log.scores.is_empty() {
+        true => println!("The log ends before the game is over"),
+        false => {
+            let scores = imported
+                .log
+                .players
+                .iter()
+                .zip(imported.log.scores.iter())
+                .map(|(name, score)| format!("{} {}", name, score))
+                .collect::<Vec<_>>();
+            println!("Final scores: {}", scores.join(", "));
+        }
+    }
+    println!("");
+    for f in imported.flags.iter() {
+        println!("Line {}: {}", f.line, f.message);
+    }
+    if imported.flags.is_empty() {
+        println!("No illegal moves");
+    }
+
+    if matches.opt_present("analyze") {
+        let iterations = count_option(matches, "analyze", 1);
+        let blunders = log_import::find_blunders(&mut imported, iterations, margin / 100.0, seed);
+        println!("");
+        for b in blunders.iter() {
+            println!(
+                "Line {}, turn {}: {} chose {} ({:.1}%), Tactician prefers {} ({:.1}%)",
+                b.line,
+                b.turn,
+                b.player,
+                move_names(&b.played),
+                100.0 * b.played_win_rate,
+                move_names(&b.best),
+                100.0 * b.best_win_rate
+            );
+        }
+        if blunders.is_empty() {
+            println!("No blunders");
+        }
+    }
+
+    if let Some(path) = matches.opt_str("record") {
+        let written =
+            File::create(&path).and_then(|mut f| writeln!(f, "{}", imported.log.to_json()));
+        if let Err(e) = written {
+            fail(EXIT_BAD_FILE, &format!("Unable to write {}: {}", path, e));
+        }
+    }
+    if !imported.flags.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn repl(matches: &getopts::Matches) {
+    let mut ctx = game::EvalContext {
+        rng: util::randomly_seeded_weak_rng(),
+        verbosity: game::Verbosity::Quiet,
+        events: None,
+    };
+    let start = match matches.free.first() {
+        Some(path) => repl::load_position(path).unwrap_or_else(|e| fail(EXIT_BAD_FILE, &e)),
+        None => repl::new_game(&mut ctx),
+    };
+    color::enable_for_terminal(true);
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = repl::repl(start, ctx, &mut stdin.lock(), &mut stdout.lock()) {
+        panic!("Unable to run the console: {}", e);
+    }
+}
+
+fn serve_options(opts: &mut getopts::Options) {
+    opts.optflag(
+        "",
+        "stdio",
+        "read requests from stdin and write responses to stdout, a line of JSON each",
+    );
+    opts.optopt(
+        "",
+        "http",
+        "answer REST requests to create games, view them and make decisions",
+        "ADDR",
+    );
+    opts.optopt(
+        "",
+        "websocket",
+        "host games between the players given, with remote seats for clients to join",
+        "ADDR",
+    );
+    opts.optopt("n", "games", "games to play with --websocket (default 1)", "N");
+    opts.optopt(
+        "",
+        "kingdom",
+        "play with these cards, separated by commas, or a named kingdom, e.g. preset:big-money",
+        "CARDS",
+    );
+}
+
+fn serve(matches: &getopts::Matches) {
+    let modes = ["stdio", "http", "websocket"];
+    match modes.iter().filter(|m| matches.opt_present(m)).count() {
+        0 => fail(EXIT_USAGE, "Expected --stdio, --http ADDR or --websocket ADDR"),
+        1 => {}
+        _ => fail(EXIT_USAGE, "Expected only one of --stdio, --http and --websocket"),
+    }
+    if let Some(addr) = matches.opt_str("websocket") {
+        return serve_websocket(matches, &addr);
+    }
+    if !matches.free.is_empty() || matches.opt_present("games") || matches.opt_present("kingdom") {
+        fail(EXIT_USAGE, "Players, --games and --kingdom are for --websocket");
+    }
+    if let Some(addr) = matches.opt_str("http") {
+        if let Err(e) = rest_server::serve(listen(&addr)) {
+            panic!("Unable to serve: {}", e);
+        }
+        return;
+    }
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = rpc_server::serve(&mut stdin.lock(), &mut stdout.lock()) {
+        panic!("Unable to serve: {}", e);
+    }
+}
+
+fn listen(addr: &str) -> std::net::TcpListener {
+    std::net::TcpListener::bind(addr)
+        .unwrap_or_else(|e| fail(EXIT_USAGE, &format!("Unable to listen on {}: {}", addr, e)))
+}
+
+// The players of games with remote seats, those given or the defaults,
+// and the rules they play by.
+fn remote_game(
+    players: &[String],
+    defaults: &[&str],
+    matches: &getopts::Matches,
+) -> (Vec<String>, game::Rules) {
+    let specs = match players.is_empty() {
+        true => defaults.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        false => players.to_vec(),
+    };
+    if specs.len() < 2 || specs.len() > 4 {
+        fail(EXIT_USAGE, "Games need 2 to 4 players");
+    }
+    if !specs.iter().any(|s| s == remote_play::REMOTE) {
+        fail(EXIT_USAGE, "Expected at least one remote player for clients to join");
+    }
+    let others = specs
+        .iter()
+        .filter(|s| *s != remote_play::REMOTE)
+        .cloned()
+        .collect::<Vec<_>>();
+    check_player_specs(&others);
+    let mut rules = game::Rules::default();
+    if let Some(s) = matches.opt_str("kingdom") {
+        rules.kingdom = cards::parse_kingdom(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e));
+    }
+    (specs, rules)
+}
+
+// Hosts games for clients such as browsers, which fill the remote seats.
+#[cfg(feature = "websocket")]
+fn serve_websocket(matches: &getopts::Matches, addr: &str) {
+    let (specs, rules) = remote_game(&matches.free, &[remote_play::REMOTE, "tactician"], matches);
+    let listener = listen(addr);
+    let games = count_option(matches, "games", 1);
+    let registry = registry::Registry::standard();
+    if let Err(e) = websocket_server::host(listener, &specs, rules, games, &registry) {
+        panic!("Unable to serve: {}", e);
+    }
+}
+
+#[cfg(not(feature = "websocket"))]
+fn serve_websocket(_matches: &getopts::Matches, _addr: &str) {
+    fail(EXIT_USAGE, "Tactician was built without the websocket feature, which --websocket needs");
+}
+
+fn host_options(opts: &mut getopts::Options) {
+    opts.optopt("n", "games", "games to play (default 1)", "N");
+    opts.optopt(
+        "",
+        "kingdom",
+        "play with these cards, separated by commas, or a named kingdom, e.g. preset:big-money",
+        "CARDS",
+    );
+}
+
+// Hosts games over TCP, running the rules for every seat, while players
+// elsewhere fill the remote seats with join.
+fn host(matches: &getopts::Matches) {
+    let addr = match matches.free.first() {
+        Some(addr) => addr,
+        None => usage_error("host"),
+    };
+    let (specs, rules) = remote_game(&matches.free[1..], &["human", remote_play::REMOTE], matches);
+    let listener = listen(addr);
+    let games = count_option(matches, "games", 1);
+    let registry = registry::Registry::standard();
+    if let Err(e) = remote_play::host_tcp(listener, &specs, rules, games, &registry) {
+        panic!("Unable to host: {}", e);
+    }
+}
+
+// Plays a remote seat of games hosted with host, as a person at the
+// terminal unless another player is given.
+fn join(matches: &getopts::Matches) {
+    let addr = match matches.free.first() {
+        Some(addr) => addr,
+        None => usage_error("join"),
+    };
+    let spec = matches.free.get(1).cloned().unwrap_or_else(|| "human".to_string());
+    if spec == remote_play::REMOTE {
+        fail(EXIT_USAGE, "Remote players can only be hosted");
+    }
+    check_player_specs(&[spec.clone()]);
+    let mut decider = player_for_string(spec, true);
+    let connection = std::net::TcpStream::connect(addr.as_str()).and_then(remote_play::Lines::new);
+    let mut connection = connection
+        .unwrap_or_else(|e| fail(EXIT_USAGE, &format!("Unable to connect to {}: {}", addr, e)));
+    match remote_play::join(&mut connection, &mut decider) {
+        Ok(games) => println!("The host closed the connection after {} game(s)", games),
+        Err(e) => {
+            eprintln!("tactician: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn analyze(matches: &getopts::Matches) {
+    match matches.free.first() {
+        Some(path) => analyze_position(path, matches.free.get(1)),
+        None => usage_error("analyze"),
+    }
+}
+
+fn engine_options(opts: &mut getopts::Options) {
+    opts.optopt(
+        "",
+        "kingdom",
+        "play with these cards, separated by commas, or a named kingdom, e.g. preset:big-money",
+        "CARDS",
+    );
+    opts.optopt("", "seed", "seed the games' shuffles (default random)", "SEED");
+}
+
+fn engine(matches: &getopts::Matches) {
+    let kingdom = match matches.opt_str("kingdom") {
+        Some(s) => cards::parse_kingdom(&s).unwrap_or_else(|e| fail(EXIT_USAGE, &e)),
+        None => cards::first_game_kingdom(),
+    };
+    let ctx = game::EvalContext {
+        rng: match option_value(matches, "seed") {
+            Some(seed) => util::seeded_weak_rng(seed),
+            None => util::randomly_seeded_weak_rng(),
+        },
+        verbosity: game::Verbosity::Quiet,
+        events: None,
+    };
+    check_player_specs(&matches.free);
+    let options = tactician_spec_options(matches.free.first());
+    let decider = tactician_with_options(options);
+    let names = vec!["Player 1".to_string(), "Player 2".to_string()];
+    let mut engine = text_protocol::Engine::new(names, kingdom, decider, ctx);
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = text_protocol::serve(&mut engine, &mut stdin.lock(), &mut stdout.lock()) {
+        panic!("Unable to run the engine: {}", e);
+    }
+}
+
+fn bench(matches: &getopts::Matches) {
+    run_bench(matches.free.first());
+}
+
+fn fuzz_options(opts: &mut getopts::Options) {
+    opts.optopt("n", "games", "games to play (default 1000)", "N");
+    opts.optopt("", "seed", "the first game's seed (default random)", "SEED");
+}
+
+fn fuzz(matches: &getopts::Matches) {
+    let seed = option_value(matches, "seed").unwrap_or_else(rand::random);
+    run_fuzz(count_option(matches, "games", 1000), seed);
+}
+
+fn cards(_matches: &getopts::Matches) {
+    print_cards();
+}
+
+fn print_players() {
+    print!("{}", registry::Registry::standard().describe());
+}
+
+fn players(_matches: &getopts::Matches) {
+    print_players();
+}
+
+fn selfplay_options(opts: &mut getopts::Options) {
+    quiet_option(opts);
+    opts.optopt("n", "games", "games to play (default 100)", "N");
+    opts.optopt("", "format", "json or csv (default json)", "FORMAT");
+    opts.optopt("", "batch", "records per file, numbering the files", "N");
+}
+
+fn selfplay(matches: &getopts::Matches) {
+    let format = match matches.opt_str("format") {
+        Some(f) => self_play::DatasetFormat::parse(&f).unwrap_or_else(|e| fail(EXIT_USAGE, &e)),
+        None => self_play::DatasetFormat::Json,
+    };
+    let batch = match matches.opt_present("batch") {
+        true => Some(count_option(matches, "batch", 1) as usize),
+        false => None,
+    };
+    match matches.free.first() {
+        Some(path) => self_play(
+            count_option(matches, "games", 100),
+            path,
+            format,
+            batch,
+            matches.free.get(1),
+            matches.opt_present("quiet"),
+        ),
+        None => usage_error("selfplay"),
+    }
+}
+
+fn train_options(opts: &mut getopts::Options) {
+    opts.optopt("", "epochs", "passes over the data (default 500)", "N");
+}
+
+fn train(matches: &getopts::Matches) {
+    match (matches.free.get(0), matches.free.get(1)) {
+        (Some(data), Some(path)) => {
+            train_policy(data, path, count_option(matches, "epochs", 500) as usize)
+        }
+        _ => usage_error("train"),
+    }
+}
+
+fn evolve_options(opts: &mut getopts::Options) {
+    quiet_option(opts);
+    opts.optopt("", "generations", "generations to breed (default 30)", "N");
+}
+
+fn evolve(matches: &getopts::Matches) {
+    evolve_strategy(
+        count_option(matches, "generations", 30) as usize,
+        matches.free.first(),
+        matches.opt_present("quiet"),
+    );
+}
+
+fn tune_options(opts: &mut getopts::Options) {
+    quiet_option(opts);
+    opts.optopt("", "iterations", "tuning iterations (default 200)", "N");
+}
+
+fn tune(matches: &getopts::Matches) {
+    match matches.free.first() {
+        Some(path) => tune_search(
+            count_option(matches, "iterations", 200) as usize,
+            path,
+            matches.free.get(1),
+            matches.opt_present("quiet"),
+        ),
+        None => usage_error("tune"),
+    }
+}
+
+fn command_named(name: &str) -> &'static Command {
+    match COMMANDS.iter().find(|c| c.name == name) {
+        Some(c) => c,
+        None => {
+            let names = COMMANDS.iter().map(|c| c.name).collect::<Vec<_>>();
+            let suggestion = util::closest_match(name, names.iter().cloned())
+                .map_or(String::new(), |n| format!(" Did you mean {}?", n));
+            fail(
+                EXIT_USAGE,
+                &format!("Unknown command {}.{} Commands are {}", name, suggestion, names.join(", ")),
+            )
+        }
+    }
+}
+
+fn command_usage(command: &Command) -> String {
+    format!("Usage: tactician {} [options] {}", command.name, command.args)
+}
+
+// Exits with a command's usage, when its arguments are missing.
+fn usage_error(name: &str) -> ! {
+    fail(EXIT_USAGE, &command_usage(command_named(name)))
+}
+
+fn all_options(command: &Command) -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    opts.optopt(
+        "",
+        "locale",
+        "show card names and prompts in a language: de, or a locale file (default en)",
+        "LANG",
+    );
+    (command.options)(&mut opts);
+    opts
+}
+
+fn print_command_help(command: &Command) {
+    let brief = format!("{}\n\n{}", command_usage(command), command.summary);
+    print!("{}", all_options(command).usage(&brief));
+}
+
+fn print_usage() {
+    println!("Usage: tactician <command> [options] [arguments]");
+    println!("");
+    println!("Commands:");
+    for c in COMMANDS.iter() {
+        println!("  {:<12}{}", c.name, c.summary);
+    }
+    println!("");
+    println!("Run tactician help <command> for a command's options.");
+    println!("Run tactician players for the players and their options.");
+}
+
+// Runs a command from the arguments tactician was started with, the first
+// being the program's name.
+pub fn run(args: Vec<String>) {
+    let command = match args.get(1).map(|s| s.as_str()) {
+        None | Some("-h") | Some("--help") => return print_usage(),
+        Some("--list-deciders") => return print_players(),
+        Some("help") => {
+            return match args.get(2) {
+                Some(name) => print_command_help(command_named(name)),
+                None => print_usage(),
+            }
+        }
+        Some(name) => command_named(name),
+    };
+
+    let matches = match all_options(command).parse(&args[2..]) {
+        Ok(m) => m,
+        Err(f) => fail(
+            EXIT_USAGE,
+            &format!("{}. Run tactician help {} for its options.", f, command.name),
+        ),
+    };
+    if matches.opt_present("help") {
+        return print_command_help(command);
+    }
+    if let Some(name) = matches.opt_str("locale") {
+        locale::set(locale::Locale::load(&name).unwrap_or_else(|e| fail(EXIT_USAGE, &e)));
+    }
+    (command.run)(&matches);
+}
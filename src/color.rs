@@ -0,0 +1,96 @@
+#[cfg(not(target_arch = "wasm32"))]
+use libc;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use cards;
+use cards::CardIdentifier;
+use locale;
+
+// Whether game logs are colored. Off until turned on, so tests and output
+// sent to files stay plain.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Colors output when it goes to a terminal, unless NO_COLOR is set or
+// colors are turned off.
+pub fn enable_for_terminal(allowed: bool) {
+    let on = allowed && stdout_is_terminal() && env::var_os("NO_COLOR").is_none();
+    ENABLED.store(on, Ordering::SeqCst);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn stdout_is_terminal() -> bool {
+    unsafe { libc::isatty(1) == 1 }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn stdout_is_terminal() -> bool {
+    false
+}
+
+fn paint(code: &str, text: &str) -> String {
+    match ENABLED.load(Ordering::SeqCst) {
+        true => format!("\x1b[{}m{}\x1b[0m", code, text),
+        false => text.to_string(),
+    }
+}
+
+// Bold, with a different bright color for each seat.
+const SEAT_COLORS: [&str; 4] = ["1;94", "1;95", "1;96", "1;92"];
+
+pub fn player(seat: usize, name: &str) -> String {
+    paint(SEAT_COLORS[seat % SEAT_COLORS.len()], name)
+}
+
+fn card_color(ci: &CardIdentifier) -> &'static str {
+    let c = cards::lookup_card(ci);
+    if c.is_attack {
+        "31"
+    } else if c.is_reaction() {
+        "34"
+    } else if c.is_action() {
+        "36"
+    } else if c.is_curse() {
+        "35"
+    } else if c.is_victory() {
+        "32"
+    } else {
+        "33"
+    }
+}
+
+// A card's name in the current language, colored by its type: yellow for treasures, green for
+// victory cards, cyan for actions, red for attacks, blue for reactions and
+// magenta for curses.
+pub fn card(ci: &CardIdentifier) -> String {
+    paint_as(ci, &locale::card_name(ci))
+}
+
+// Colors text, such as a card's plural name, as the card is colored.
+pub fn paint_as(ci: &CardIdentifier, text: &str) -> String {
+    paint(card_color(ci), text)
+}
+
+// Colored card names, separated by commas, as card_names writes them.
+pub fn card_names(identifiers: &Vec<CardIdentifier>) -> String {
+    identifiers.iter().map(card).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::{COPPER, CURSE, DUCHY, MILITIA, MOAT, SMITHY};
+    use color::*;
+
+    #[test]
+    fn test_card_colors() {
+        assert_eq!(card_color(&COPPER.identifier), "33");
+        assert_eq!(card_color(&DUCHY.identifier), "32");
+        assert_eq!(card_color(&CURSE.identifier), "35");
+        assert_eq!(card_color(&SMITHY.identifier), "36");
+        assert_eq!(card_color(&MILITIA.identifier), "31");
+        assert_eq!(card_color(&MOAT.identifier), "34");
+        // Tests don't run on a terminal of their own, so nothing's colored.
+        assert_eq!(card_names(&vec![COPPER.identifier, MOAT.identifier]), "Copper, Moat");
+    }
+}
@@ -1,8 +1,11 @@
 use rand::{seq, Rng, XorShiftRng};
 
+use buy_policy::BuyPolicy;
 use cards;
-use cards::CardIdentifier;
-use game::{Decider, DecisionType, Game};
+use cards::{Card, CardAction, CardIdentifier};
+use game::{Decider, Decision, DecisionType, Game, PlayerIdentifier};
+use game_features::player_features;
+use registry::{parse_option_flag, parse_option_value, OptionSpec, PlayerKind, PlayerSpec, Registry};
 use util;
 
 pub struct BigMoney;
@@ -17,20 +20,15 @@ impl Decider for BigMoney {
             .as_ref()
             .expect("BigMoney::make_decision called without pending decision");
         match d.decision_type {
-            DecisionType::PlayAction => panic!("BigMoney should not buy actions"),
+            // BigMoney never buys actions, but can be handed a deck with some
+            // when it plays out games for a search.
+            DecisionType::PlayAction => vec![],
             DecisionType::PlayTreasures => return d.choices.clone(),
-            DecisionType::BuyCard => {
-                let cs = g.coins;
-                if cs >= cards::PROVINCE.cost {
-                    vec![cards::PROVINCE.identifier]
-                } else if cs >= cards::GOLD.cost {
-                    vec![cards::GOLD.identifier]
-                } else if cs >= cards::SILVER.cost {
-                    vec![cards::SILVER.identifier]
-                } else {
-                    vec![]
-                }
-            }
+            // Piles can run out in long playouts, so only buy what's offered.
+            DecisionType::BuyCard => [&*cards::PROVINCE, &*cards::GOLD, &*cards::SILVER]
+                .iter()
+                .find(|c| g.coins >= c.cost && d.choices.contains(&c.identifier))
+                .map_or(vec![], |c| vec![c.identifier]),
             DecisionType::DiscardCards(_) => {
                 let mut cards = d.choices.clone();
                 cards.sort_by_key(|c| cards::lookup_card(c).coin_value.unwrap_or(0));
@@ -49,8 +47,222 @@ impl Decider for BigMoney {
     }
 }
 
+// The usual refinements to Big Money's buys as the game ends. None of them
+// are on by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EndgameRules {
+    // Buy Duchy over Gold once this many Provinces or fewer are left.
+    pub duchy_at: Option<i32>,
+    // Buy Estate over Silver once this many Provinces or fewer are left.
+    pub estate_at: Option<i32>,
+    // The penultimate Province rule: don't buy the second to last Province
+    // while behind, since the opponent could then win by buying the last.
+    pub penultimate: bool,
+    // Don't buy the last Province when it would end the game in a loss.
+    pub no_losing_last: bool,
+}
+
+impl EndgameRules {
+    fn allows_province(&self, g: &Game, player: PlayerIdentifier) -> bool {
+        let vps = g.player_vp_and_turns();
+        let ours = vps[player.0 as usize].0;
+        let best_opponent = vps.iter()
+            .enumerate()
+            .filter(|&(i, _)| i != player.0 as usize)
+            .map(|(_, &(vp, _))| vp)
+            .max()
+            .unwrap_or(0);
+        match g.piles[&cards::PROVINCE.identifier] {
+            1 if self.no_losing_last => ours + cards::PROVINCE.vp_value.unwrap() >= best_opponent,
+            2 if self.penultimate => ours >= best_opponent,
+            _ => true,
+        }
+    }
+}
+
+// Big Money's buy, with an action bought over Silver if given, adjusted by
+// the endgame rules.
+fn big_money_buy(
+    g: &Game,
+    d: &Decision,
+    rules: &EndgameRules,
+    action: Option<&Card>,
+) -> Vec<CardIdentifier> {
+    let provinces = g.piles[&cards::PROVINCE.identifier];
+    let mut wanted: Vec<&Card> = vec![];
+    if rules.allows_province(g, d.player) {
+        wanted.push(&cards::PROVINCE);
+    }
+    if rules.duchy_at.map_or(false, |n| provinces <= n) {
+        wanted.push(&cards::DUCHY);
+    }
+    wanted.push(&cards::GOLD);
+    wanted.extend(action);
+    if rules.estate_at.map_or(false, |n| provinces <= n) {
+        wanted.push(&cards::ESTATE);
+    }
+    wanted.push(&cards::SILVER);
+    wanted
+        .iter()
+        .find(|c| g.coins >= c.cost && d.choices.contains(&c.identifier))
+        .map_or(vec![], |c| vec![c.identifier])
+}
+
+// Big Money with endgame rules, a more credible benchmark than plain Big
+// Money, which buys Provinces and nothing else to the end.
+pub struct BigMoneyUltimate {
+    pub endgame: EndgameRules,
+}
+
+impl Decider for BigMoneyUltimate {
+    fn description(&self) -> String {
+        return "Big Money Ultimate".into();
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("BigMoneyUltimate::make_decision called without pending decision");
+        match d.decision_type {
+            DecisionType::BuyCard => big_money_buy(g, d, &self.endgame, None),
+            _ => BigMoney.make_decision(g),
+        }
+    }
+}
+
+// The usual baseline for evaluating strategies: Big Money that also buys
+// up to limit copies of an action, in preference to Silver, and plays it
+// when drawn.
+pub struct BigMoneyX {
+    pub card: CardIdentifier,
+    pub limit: usize,
+    pub endgame: EndgameRules,
+}
+
+impl Decider for BigMoneyX {
+    fn description(&self) -> String {
+        format!("Big Money + {}", self.card)
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("BigMoneyX::make_decision called without pending decision");
+        match d.decision_type {
+            DecisionType::PlayAction => {
+                let mut actions = d.choices.clone();
+                actions.sort_by_key(|c| -plus_actions(c));
+                actions.into_iter().take(1).collect()
+            }
+            DecisionType::BuyCard => {
+                let mut owned = g.players[d.player.0 as usize].all_cards();
+                owned.extend(&g.play_area);
+                let wants_x = owned.iter().filter(|&&c| c == self.card).count() < self.limit;
+                let x = match wants_x {
+                    true => Some(cards::lookup_card(&self.card)),
+                    false => None,
+                };
+                big_money_buy(g, d, &self.endgame, x)
+            }
+            _ => BigMoney.make_decision(g),
+        }
+    }
+}
+
+// Always takes the first cards offered in order of name, as few as the
+// decision allows but at least one where it may take none, and plays every
+// treasure. It's fully deterministic, so games between FirstChoice players
+// with a seeded shuffle always replay the same, and it always buys
+// something, so they end.
+pub struct FirstChoice;
+
+impl Decider for FirstChoice {
+    fn description(&self) -> String {
+        return "First Choice".into();
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("FirstChoice::make_decision called without pending decision");
+        if d.decision_type == DecisionType::PlayTreasures {
+            return d.choices.clone();
+        }
+        let mut choices = d.choices.clone();
+        choices.sort_by_key(|c| cards::lookup_card(c).name);
+        choices.truncate(d.range.0.max(1).min(d.range.1));
+        choices
+    }
+}
+
+// Names a kind of decision for options that differ between them.
+pub fn decision_kind(t: &DecisionType) -> &'static str {
+    match *t {
+        DecisionType::PlayAction => "action",
+        DecisionType::PlayTreasures => "treasures",
+        DecisionType::BuyCard => "buy",
+        DecisionType::GainCard(_) => "gain",
+        DecisionType::DiscardCards(_) => "discard",
+        DecisionType::TrashCards(_) => "trash",
+        DecisionType::RevealReaction(_) => "reveal",
+    }
+}
+
+// Skews a RandomDecider's choices, which are otherwise uniform.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RandomBehavior {
+    // Chance of choosing nothing, where that's allowed, by decision kind.
+    // Kinds without one pick how many cards to choose uniformly.
+    pub pass: Vec<(&'static str, f32)>,
+    // Cards to buy or gain are weighted by (cost + 1) to this power, and
+    // cards to discard or trash by its inverse, so with a positive bias
+    // better cards are bought and worse ones let go more often.
+    pub cost_bias: f32,
+}
+
+impl RandomBehavior {
+    // Parses pass chances such as "buy:0.05/action:0.2", or a single chance
+    // such as "0.1" for every kind.
+    pub fn parse_pass(list: &str) -> Result<Vec<(&'static str, f32)>, String> {
+        let kinds = ["action", "buy", "gain", "discard", "trash", "reveal"];
+        let mut pass = vec![];
+        for part in list.split('/').filter(|s| !s.is_empty()) {
+            let mut kv = part.rsplitn(2, ':');
+            let chance = kv.next().unwrap();
+            let chance = chance
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid pass chance {}", chance))?;
+            match kv.next() {
+                Some(kind) => match kinds.iter().find(|&&k| k == kind) {
+                    Some(&k) => pass.push((k, chance)),
+                    None => return Err(format!("Unknown decision kind {}", kind)),
+                },
+                None => pass.extend(kinds.iter().map(|&k| (k, chance))),
+            }
+        }
+        Ok(pass)
+    }
+
+    fn pass_chance(&self, t: &DecisionType) -> Option<f32> {
+        let kind = decision_kind(t);
+        self.pass.iter().rev().find(|p| p.0 == kind).map(|p| p.1)
+    }
+
+    fn card_weight(&self, t: &DecisionType, c: &CardIdentifier) -> f32 {
+        let base = (cards::lookup_card(c).cost + 1) as f32;
+        match *t {
+            DecisionType::BuyCard | DecisionType::GainCard(_) => base.powf(self.cost_bias),
+            DecisionType::DiscardCards(_) | DecisionType::TrashCards(_) => {
+                base.powf(-self.cost_bias)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
 pub struct RandomDecider {
     rng: XorShiftRng,
+    pub behavior: RandomBehavior,
 }
 
 impl RandomDecider {
@@ -58,7 +270,36 @@ impl RandomDecider {
     pub fn new() -> RandomDecider {
         RandomDecider {
             rng: util::randomly_seeded_weak_rng(),
+            behavior: RandomBehavior::default(),
+        }
+    }
+
+    pub fn with_seed(seed: u32) -> RandomDecider {
+        RandomDecider {
+            rng: util::seeded_weak_rng(seed),
+            behavior: RandomBehavior::default(),
+        }
+    }
+
+    // Picks n of the choices without replacement, each in proportion to its
+    // weight among those left.
+    fn weighted_sample(&mut self, t: &DecisionType, choices: &Vec<CardIdentifier>, n: usize) -> Vec<CardIdentifier> {
+        let mut remaining = choices
+            .iter()
+            .map(|c| (*c, self.behavior.card_weight(t, c)))
+            .collect::<Vec<_>>();
+        let mut picked = vec![];
+        while picked.len() < n && !remaining.is_empty() {
+            let total: f32 = remaining.iter().map(|r| r.1).sum();
+            let mut x = self.rng.gen::<f32>() * total;
+            let mut i = 0;
+            while i + 1 < remaining.len() && x >= remaining[i].1 {
+                x -= remaining[i].1;
+                i += 1;
+            }
+            picked.push(remaining.remove(i).0);
         }
+        picked
     }
 }
 
@@ -75,11 +316,279 @@ impl Decider for RandomDecider {
             return d.choices.clone();
         }
 
-        let n = match d.range.0 == d.range.1 {
-            true => d.range.0,
-            false => self.rng.gen_range(d.range.0, d.range.1 + 1) as usize,
+        let mut min = d.range.0;
+        if let Some(chance) = self.behavior.pass_chance(&d.decision_type) {
+            if min == 0 && d.range.1 > 0 {
+                if self.rng.gen::<f32>() < chance {
+                    return vec![];
+                }
+                min = 1;
+            }
+        }
+        let n = match min == d.range.1 {
+            true => min,
+            false => self.rng.gen_range(min, d.range.1 + 1) as usize,
         };
 
-        return seq::sample_iter(&mut self.rng, d.choices.clone(), n).unwrap();
+        if self.behavior.cost_bias == 0.0 {
+            return seq::sample_iter(&mut self.rng, d.choices.clone(), n).unwrap();
+        }
+        self.weighted_sample(&d.decision_type, &d.choices, n)
+    }
+}
+
+// Buys with a policy trained on self-play data, and otherwise plays like
+// BigMoney, except that it plays the actions it buys, villages first.
+pub struct PolicyDecider {
+    policy: BuyPolicy,
+}
+
+impl PolicyDecider {
+    pub fn new(policy: BuyPolicy) -> PolicyDecider {
+        PolicyDecider { policy: policy }
+    }
+}
+
+pub fn plus_actions(c: &CardIdentifier) -> i32 {
+    cards::lookup_card(c)
+        .action_effects
+        .iter()
+        .map(|e| match *e {
+            CardAction::PlusActions(n) => n,
+            _ => 0,
+        })
+        .sum()
+}
+
+impl Decider for PolicyDecider {
+    fn description(&self) -> String {
+        return "Policy".into();
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("PolicyDecider::make_decision called without pending decision");
+        match d.decision_type {
+            DecisionType::PlayAction => {
+                let mut actions = d.choices.clone();
+                actions.sort_by_key(|c| -plus_actions(c));
+                actions.into_iter().take(1).collect()
+            }
+            DecisionType::BuyCard => {
+                let mut options = d.choices.iter().map(|&c| Some(c)).collect::<Vec<_>>();
+                if d.range.0 == 0 {
+                    options.push(None);
+                }
+                let features = player_features(g, d.player);
+                self.policy.choose(&features, &options).into_iter().collect()
+            }
+            _ => BigMoney.make_decision(g),
+        }
+    }
+}
+
+const ENDGAME_OPTIONS: [OptionSpec; 4] = [
+    OptionSpec { key: "duchy", value: "N", help: "buy Duchy over Gold with N or fewer Provinces left", default: "" },
+    OptionSpec { key: "estate", value: "N", help: "buy Estate over Silver with N or fewer Provinces left", default: "" },
+    OptionSpec { key: "ppr", value: "on/off", help: "the penultimate Province rule", default: "off" },
+    OptionSpec { key: "lastprovince", value: "on/off", help: "never buy the last Province to lose", default: "off" },
+];
+
+const BIG_MONEY_X_OPTIONS: [OptionSpec; 5] = [
+    OptionSpec { key: "copies", value: "N", help: "how many of the action to buy", default: "1" },
+    ENDGAME_OPTIONS[0],
+    ENDGAME_OPTIONS[1],
+    ENDGAME_OPTIONS[2],
+    ENDGAME_OPTIONS[3],
+];
+
+impl EndgameRules {
+    fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "duchy" => self.duchy_at = Some(parse_option_value(key, value)?),
+            "estate" => self.estate_at = Some(parse_option_value(key, value)?),
+            "ppr" => self.penultimate = parse_option_flag(key, value)?,
+            "lastprovince" => self.no_losing_last = parse_option_flag(key, value)?,
+            _ => return Err(format!("Unknown endgame rule {}", key)),
+        }
+        Ok(())
+    }
+}
+
+fn big_money(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    if spec.options.is_empty() {
+        return Ok(Box::new(BigMoney));
+    }
+    let mut endgame = EndgameRules::default();
+    for &(key, value) in spec.options.iter() {
+        endgame.set_option(key, value)?;
+    }
+    Ok(Box::new(BigMoneyUltimate { endgame: endgame }))
+}
+
+fn big_money_x(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    let action = spec.argument.as_ref().unwrap();
+    let card = match cards::card_named(action) {
+        Some(c) if c.is_action() => c.identifier,
+        _ => return Err(format!("Unknown action for player {}", spec.name)),
+    };
+    let mut limit = 1;
+    let mut endgame = EndgameRules::default();
+    for &(key, value) in spec.options.iter() {
+        match key {
+            "copies" => limit = parse_option_value(key, value)?,
+            _ => endgame.set_option(key, value)?,
+        }
+    }
+    Ok(Box::new(BigMoneyX {
+        card: card,
+        limit: limit,
+        endgame: endgame,
+    }))
+}
+
+fn random(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    let mut seed = None;
+    let mut behavior = RandomBehavior::default();
+    for &(key, value) in spec.options.iter() {
+        match key {
+            "seed" => seed = Some(parse_option_value(key, value)?),
+            "pass" => behavior.pass.extend(RandomBehavior::parse_pass(value)?),
+            "cost" => behavior.cost_bias = parse_option_value(key, value)?,
+            _ => unreachable!(),
+        }
+    }
+    let mut decider = match seed {
+        Some(seed) => RandomDecider::with_seed(seed),
+        None => RandomDecider::new(),
+    };
+    decider.behavior = behavior;
+    Ok(Box::new(decider))
+}
+
+fn policy(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    match spec.options.first().map(|&(_, path)| BuyPolicy::load(path)) {
+        Some(Ok(p)) => Ok(Box::new(PolicyDecider::new(p))),
+        Some(Err(e)) => Err(e),
+        None => Err("Player policy needs weights, e.g. policy:weights=buys.txt".into()),
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "bigmoney",
+        summary: "buys money and Provinces, or with options, Big Money Ultimate",
+        example: "bigmoney:duchy=4,estate=2,ppr=on",
+        argument: None,
+        options: &ENDGAME_OPTIONS,
+        factory: big_money,
+    });
+    registry.register(PlayerKind {
+        name: "bigmoney+",
+        summary: "Big Money with an action, bought over Silver and always played",
+        example: "bigmoney+smithy:copies=2",
+        argument: Some("action"),
+        options: &BIG_MONEY_X_OPTIONS,
+        factory: big_money_x,
+    });
+    registry.register(PlayerKind {
+        name: "random",
+        summary: "chooses at random, optionally passing and favoring expensive cards",
+        example: "random:pass=buy:0.05,cost=1",
+        argument: None,
+        options: &[
+            OptionSpec { key: "seed", value: "N", help: "random seed", default: "" },
+            OptionSpec { key: "pass", value: "kind:chance/...", help: "chance of choosing nothing", default: "" },
+            OptionSpec { key: "cost", value: "number", help: "bias towards expensive cards", default: "0" },
+        ],
+        factory: random,
+    });
+    registry.register(PlayerKind {
+        name: "first",
+        summary: "always takes the first cards offered, for reproducible games",
+        example: "first",
+        argument: None,
+        options: &[],
+        factory: |_, _| Ok(Box::new(FirstChoice)),
+    });
+    registry.register(PlayerKind {
+        name: "policy",
+        summary: "buys with trained weights, otherwise playing like Big Money",
+        example: "policy:weights=buys.txt",
+        argument: None,
+        options: &[OptionSpec { key: "weights", value: "file", help: "trained weights", default: "" }],
+        factory: policy,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use deciders::*;
+    use game::*;
+
+    fn buy(g: &mut Game, decider: &mut Decider) -> Vec<CardIdentifier> {
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::BuyCard,
+            choices: vec![
+                PROVINCE.identifier,
+                DUCHY.identifier,
+                GOLD.identifier,
+                SMITHY.identifier,
+                ESTATE.identifier,
+                SILVER.identifier,
+            ],
+            range: (0, 1),
+        });
+        decider.make_decision(g)
+    }
+
+    #[test]
+    fn test_big_money_endgame_rules() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        let mut decider = BigMoneyUltimate {
+            endgame: EndgameRules {
+                duchy_at: Some(4),
+                estate_at: Some(2),
+                penultimate: true,
+                no_losing_last: true,
+            },
+        };
+
+        g.coins = 6;
+        assert_eq!(buy(&mut g, &mut decider), vec![GOLD.identifier]);
+        g.coins = 8;
+        *g.piles.get_mut(&PROVINCE.identifier).unwrap() = 4;
+        assert_eq!(buy(&mut g, &mut decider), vec![PROVINCE.identifier]);
+        g.coins = 5;
+        assert_eq!(buy(&mut g, &mut decider), vec![DUCHY.identifier]);
+        g.coins = 3;
+        assert_eq!(buy(&mut g, &mut decider), vec![SILVER.identifier]);
+        *g.piles.get_mut(&PROVINCE.identifier).unwrap() = 2;
+        assert_eq!(buy(&mut g, &mut decider), vec![ESTATE.identifier]);
+
+        // Behind with two Provinces left, it takes a Duchy instead.
+        g.coins = 8;
+        g.players[1].discard.push(DUCHY.identifier);
+        assert_eq!(buy(&mut g, &mut decider), vec![DUCHY.identifier]);
+
+        // The last Province loses if still behind by more than six.
+        *g.piles.get_mut(&PROVINCE.identifier).unwrap() = 1;
+        g.players[1].discard.extend(vec![PROVINCE.identifier, PROVINCE.identifier]);
+        assert_eq!(buy(&mut g, &mut decider), vec![DUCHY.identifier]);
+
+        let mut x = BigMoneyX {
+            card: SMITHY.identifier,
+            limit: 1,
+            endgame: EndgameRules::default(),
+        };
+        g.coins = 4;
+        assert_eq!(buy(&mut g, &mut x), vec![SMITHY.identifier]);
+        g.coins = 8;
+        assert_eq!(buy(&mut g, &mut x), vec![PROVINCE.identifier]);
     }
 }
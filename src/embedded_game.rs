@@ -0,0 +1,206 @@
+use rand::{Rng, XorShiftRng};
+
+use cards;
+use cards::CardIdentifier;
+use game::{fresh_game_with_kingdom, Decider, EvalContext, Game, MoveStats, Verbosity};
+use game_log::analysis_to_json;
+use game_serialization::{cards_from_json, cards_to_json, status_to_json};
+use json::Json;
+use process_decider::check_choice;
+use search_decider::SearchDecider;
+use tree_search::{SearchBudget, SearchOptions, SearchableState};
+use util;
+
+const DEFAULT_PLAYERS: [&str; 2] = ["Player 1", "Player 2"];
+// Mixed into the game's seed to seed its searches, so they're reproducible
+// without drawing from the shuffles.
+const SEARCH_SEED_MIX: u32 = 0x9e37_79b9;
+
+// A game played a decision at a time by a program embedding Tactician, such
+// as a page running it as WebAssembly. The bindings for other languages wrap
+// this, passing players, moves and states as JSON, and games are always
+// left waiting on a decision or over. Everything is seeded, so a game and
+// the moves suggested for it can be replayed.
+pub struct EmbeddedGame {
+    game: Game,
+    ctx: EvalContext,
+    // Seeds each search, apart from ctx, so asking for a move doesn't change
+    // the shuffles that follow.
+    search_rng: XorShiftRng,
+}
+
+impl EmbeddedGame {
+    // Deals a game between players named by a JSON list, or two players if
+    // it's empty, with a kingdom as --kingdom takes it, or the first-game
+    // kingdom if it's empty.
+    pub fn new(players: &str, kingdom: &str, seed: u32) -> Result<EmbeddedGame, String> {
+        let players = match players.trim() {
+            "" => DEFAULT_PLAYERS.iter().map(|&n| n.to_string()).collect(),
+            players => Json::parse(players)?
+                .as_array()
+                .and_then(|names| {
+                    names
+                        .iter()
+                        .map(|n| n.as_str().map(String::from))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or("Players should be a list of names")?,
+        };
+        if players.len() < 2 || players.len() > 4 {
+            return Err("Games need 2 to 4 players".into());
+        }
+        let kingdom = match kingdom.trim() {
+            "" => cards::first_game_kingdom(),
+            kingdom => cards::parse_kingdom(kingdom)?,
+        };
+        let mut g = EmbeddedGame::with_game(fresh_game_with_kingdom(&players, &kingdom), seed);
+        g.game.initialize_game(&mut g.ctx);
+        g.advance();
+        Ok(g)
+    }
+
+    // Picks up a game saved as JSON by state.
+    pub fn load(state: &str, seed: u32) -> Result<EmbeddedGame, String> {
+        let game = Game::from_json(&Json::parse(state)?)?;
+        let mut g = EmbeddedGame::with_game(game, seed);
+        g.advance();
+        Ok(g)
+    }
+
+    fn with_game(game: Game, seed: u32) -> EmbeddedGame {
+        EmbeddedGame {
+            game: game,
+            ctx: EvalContext {
+                rng: util::seeded_weak_rng(seed),
+                verbosity: Verbosity::Quiet,
+                events: None,
+            },
+            search_rng: util::seeded_weak_rng(seed ^ SEARCH_SEED_MIX),
+        }
+    }
+
+    fn advance(&mut self) {
+        while !self.game.is_game_over() && self.game.pending_decision.is_none() {
+            self.game.advance_game(&mut self.ctx);
+        }
+    }
+
+    // The game as save files write it. With a seat, only what it can see:
+    // hidden hands and decks are counts instead.
+    pub fn state(&self, seat: Option<usize>) -> Result<String, String> {
+        match seat {
+            None => Ok(self.game.to_json().to_string()),
+            Some(seat) if seat < self.game.players.len() => {
+                let player = self.game.players[seat].identifier;
+                Ok(self.game.view_json(player).to_string())
+            }
+            Some(seat) => Err(format!("There's no seat {}", seat)),
+        }
+    }
+
+    // Its turn, whether it's over, the pending decision and the scores.
+    pub fn status(&self) -> String {
+        status_to_json(&self.game).to_string()
+    }
+
+    // The choices for the pending decision, each a list of card names, or
+    // none once the game is over.
+    pub fn legal_moves(&self) -> String {
+        let moves = match self.game.pending_decision {
+            Some(_) => self.game.all_moves(),
+            None => vec![],
+        };
+        Json::Array(moves.iter().map(cards_to_json).collect()).to_string()
+    }
+
+    // Makes the pending decision with a list of card names, and plays on to
+    // the next.
+    pub fn apply_move(&mut self, choice: &str) -> Result<(), String> {
+        let choice = cards_from_json(&Json::parse(choice)?)?;
+        match self.game.pending_decision {
+            Some(ref d) => check_choice(&choice, d)?,
+            None => return Err("There's no decision to make".into()),
+        }
+        self.game.resolve_decision(choice, &mut self.ctx);
+        self.advance();
+        Ok(())
+    }
+
+    // Searches the pending decision for a number of iterations, returning
+    // the move picked and how each move searched was rated.
+    fn search(&mut self, iterations: u32) -> Result<(Vec<CardIdentifier>, Vec<MoveStats>), String> {
+        if self.game.pending_decision.is_none() {
+            return Err("There's no decision to make".into());
+        }
+        // Budgets are counted in an i32, so larger ones are capped rather
+        // than wrapping around to a negative budget.
+        let iterations = iterations.max(1).min(i32::max_value() as u32) as i32;
+        let mut decider = SearchDecider::new(SearchOptions {
+            budget: SearchBudget::Iterations(iterations),
+            ..Default::default()
+        });
+        decider.seed(self.search_rng.gen());
+        let choice = decider.make_decision(&self.game);
+        Ok((choice, decider.analysis()))
+    }
+
+    // The move Tactician's search picks for the pending decision after
+    // a number of iterations, as a list of card names. It's not made.
+    pub fn ai_choose(&mut self, iterations: u32) -> Result<String, String> {
+        let (choice, _) = self.search(iterations)?;
+        Ok(cards_to_json(&choice).to_string())
+    }
+
+    // Each move the search weighed, with its visits and win rate, as game
+    // logs write them. Moves decided without searching, such as from the
+    // opening book, leave this empty. The wasm bindings don't offer it.
+    #[cfg_attr(not(any(feature = "python", feature = "ffi")), allow(dead_code))]
+    pub fn analyze(&mut self, iterations: u32) -> Result<String, String> {
+        let (_, analysis) = self.search(iterations)?;
+        Ok(analysis_to_json(&analysis).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use embedded_game::*;
+
+    #[test]
+    fn test_embedded_game() {
+        let mut g = EmbeddedGame::new(r#"["Alice", "Bob"]"#, "", 5).unwrap();
+        let status = Json::parse(&g.status()).unwrap();
+        assert!(!status.get("pending_decision").unwrap().is_null());
+        let choice = g.ai_choose(50).unwrap();
+        let moves = Json::parse(&g.legal_moves()).unwrap();
+        assert!(moves.as_array().unwrap().contains(&Json::parse(&choice).unwrap()));
+        g.apply_move(&choice).unwrap();
+        assert!(g.apply_move(r#"["Province"]"#).is_err());
+        assert!(g.state(Some(2)).is_err());
+        let analysis = Json::parse(&g.analyze(100).unwrap()).unwrap();
+        assert!(analysis.as_array().unwrap().iter().all(|m| m.get("visits").is_some()));
+
+        let mut loaded = EmbeddedGame::load(&g.state(None).unwrap(), 5).unwrap();
+        assert_eq!(loaded.state(None), g.state(None));
+        while loaded.legal_moves() != "[]" {
+            let choice = loaded.ai_choose(1).unwrap();
+            loaded.apply_move(&choice).unwrap();
+        }
+        assert!(loaded.ai_choose(1).is_err());
+        assert!(EmbeddedGame::new(r#"["Alone"]"#, "", 1).is_err());
+
+        // Asking for moves leaves the shuffles alone, so the same moves
+        // replay the same game.
+        let mut hinted = EmbeddedGame::new("", "", 9).unwrap();
+        let mut plain = EmbeddedGame::new("", "", 9).unwrap();
+        for _ in 0..20 {
+            hinted.ai_choose(5).unwrap();
+            let choice = plain.ai_choose(5).unwrap();
+            plain.ai_choose(5).unwrap();
+            hinted.apply_move(&choice).unwrap();
+            plain.apply_move(&choice).unwrap();
+            assert_eq!(hinted.state(None), plain.state(None));
+        }
+        assert!(EmbeddedGame::new("", "Bridge", 1).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use tree_search::{Evaluator, Rewards, SearchableState};
+
+// A depth-limited minimax search for the last turns of a game: every move
+// of every player is tried, but only for a few turns, without pruning, and
+// each sampled determinization is searched on its own with its own
+// shuffles. It's exact only for lines that end the game within its turns.
+
+// How far the search looks, and how much work it may do before giving up.
+#[derive(Clone, Copy, Debug)]
+pub struct EndgameLimits {
+    // Turns past the current one to search. Positions beyond them are scored
+    // by the evaluator, so only lines ending the game within them are exact.
+    pub turns: i32,
+    // Hidden information and shuffles are sampled this many times, and each
+    // move's value is averaged over them.
+    pub determinizations: u32,
+    // Positions visited across every determinization.
+    pub max_nodes: usize,
+}
+
+impl Default for EndgameLimits {
+    fn default() -> EndgameLimits {
+        EndgameLimits {
+            turns: 1,
+            determinizations: 8,
+            max_nodes: 200000,
+        }
+    }
+}
+
+struct Search<'a, T: SearchableState + 'a> {
+    evaluator: &'a Evaluator<T>,
+    last_turn: i32,
+    nodes: usize,
+    max_nodes: usize,
+}
+
+impl<'a, T: SearchableState> Search<'a, T> {
+    // Every player picks the move that's best for themselves, looking at
+    // every move to the end of the game or the last turn, past which the
+    // evaluator scores positions. Returns None once the node budget runs
+    // out.
+    fn value(&mut self, state: &T, ctx: &mut T::C) -> Option<Rewards<T::P>> {
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            return None;
+        }
+        if let Some(winners) = state.game_result() {
+            return Some(Rewards::from_winners(&winners, &state.all_players()));
+        }
+        let player = match state.active_player() {
+            Some(p) => p,
+            None => return Some(self.evaluator.evaluate(state)),
+        };
+        if state.turn() > self.last_turn {
+            return Some(self.evaluator.evaluate(state));
+        }
+
+        let mut best: Option<Rewards<T::P>> = None;
+        for m in state.all_moves() {
+            let mut next = state.clone();
+            next.make_move_mut(m, ctx);
+            let rewards = self.value(&next, ctx)?;
+            let is_better = best.as_ref().map_or(true, |b| {
+                rewards.for_player(&player) > b.for_player(&player)
+            });
+            if is_better {
+                best = Some(rewards);
+            }
+        }
+        best.or_else(|| Some(self.evaluator.evaluate(state)))
+    }
+}
+
+// Values each move of the player to move by searching every line of play
+// through limits.turns more turns, averaged over sampled determinizations.
+// Returns the moves with their expected reward, or None if the position is
+// too big to search within the limits.
+pub fn search<T: SearchableState>(
+    root: &T,
+    evaluator: &Evaluator<T>,
+    limits: EndgameLimits,
+    ctx: &mut T::C,
+) -> Option<Vec<(T::M, f32)>> {
+    let player = root.active_player()?;
+    let moves = root.all_moves();
+    let mut search = Search {
+        evaluator: evaluator,
+        last_turn: root.turn() + limits.turns,
+        nodes: 0,
+        max_nodes: limits.max_nodes,
+    };
+
+    let mut totals = vec![0.0; moves.len()];
+    for _ in 0..limits.determinizations {
+        let state = root.determinize(&player, ctx);
+        for (i, m) in moves.iter().enumerate() {
+            let mut next = state.clone();
+            next.make_move_mut(m.clone(), ctx);
+            totals[i] += search.value(&next, ctx)?.for_player(&player);
+        }
+    }
+
+    let n = limits.determinizations.max(1) as f32;
+    Some(
+        moves
+            .into_iter()
+            .zip(totals.into_iter())
+            .map(|(m, total)| (m, total / n))
+            .collect(),
+    )
+}
@@ -0,0 +1,239 @@
+use std::fs;
+
+use cards::CardIdentifier;
+use game::{Decider, DecisionError, Game, GameEvent};
+use registry::{OptionSpec, PlayerKind, PlayerSpec, Registry};
+
+// How the members' choices are counted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Vote {
+    // Each member's choice counts for its weight.
+    Majority,
+    // Each member's choice counts for its weight times how sure it was,
+    // for members that can tell.
+    Confidence,
+}
+
+impl Vote {
+    pub fn parse(s: &str) -> Result<Vote, String> {
+        match s {
+            "majority" => Ok(Vote::Majority),
+            "confidence" => Ok(Vote::Confidence),
+            _ => Err(format!("Unknown vote {}, expected majority or confidence", s)),
+        }
+    }
+}
+
+// Asks every member for each decision and plays the choice with the most
+// votes, the first member's on a tie. A fast heuristic and a short search,
+// say, can cover each other's blind spots.
+pub struct EnsembleDecider {
+    pub members: Vec<(Box<Decider>, f32)>,
+    pub vote: Vote,
+    // The winning choice's share of the votes at the last decision.
+    last_share: Option<f32>,
+}
+
+// Choices that pick the same cards in a different order are the same vote.
+fn sorted(choice: &Vec<CardIdentifier>) -> Vec<CardIdentifier> {
+    let mut sorted = choice.clone();
+    sorted.sort();
+    sorted
+}
+
+impl EnsembleDecider {
+    pub fn new(members: Vec<(Box<Decider>, f32)>, vote: Vote) -> EnsembleDecider {
+        assert!(!members.is_empty(), "An ensemble needs at least one member");
+        EnsembleDecider {
+            members: members,
+            vote: vote,
+            last_share: None,
+        }
+    }
+}
+
+impl Decider for EnsembleDecider {
+    fn description(&self) -> String {
+        let names = self.members
+            .iter()
+            .map(|&(ref d, _)| d.description())
+            .collect::<Vec<_>>();
+        format!("Ensemble of {}", names.join(", "))
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        match self.try_make_decision(g) {
+            Ok(choice) => choice,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    // Fails if any member does.
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        // Each distinct choice with its total vote, in the order first made.
+        let mut tallies: Vec<(Vec<CardIdentifier>, Vec<CardIdentifier>, f32)> = vec![];
+        let mut total = 0.0;
+        for &mut (ref mut d, weight) in self.members.iter_mut() {
+            let choice = d.try_make_decision(g)?;
+            let vote = match self.vote {
+                Vote::Majority => weight,
+                Vote::Confidence => weight * d.confidence().unwrap_or(1.0),
+            };
+            total += vote;
+
+            let key = sorted(&choice);
+            match tallies.iter().position(|t| t.0 == key) {
+                Some(i) => tallies[i].2 += vote,
+                None => tallies.push((key, choice, vote)),
+            }
+        }
+
+        let mut best = 0;
+        for i in 1..tallies.len() {
+            if tallies[i].2 > tallies[best].2 {
+                best = i;
+            }
+        }
+        self.last_share = if total > 0.0 {
+            Some(tallies[best].2 / total)
+        } else {
+            None
+        };
+        Ok(tallies.swap_remove(best).1)
+    }
+
+    fn observe_decision(&mut self, g: &Game, choice: &Vec<CardIdentifier>) {
+        for &mut (ref mut d, _) in self.members.iter_mut() {
+            d.observe_decision(g, choice);
+        }
+    }
+
+    fn ponder(&mut self, g: &Game) {
+        for &mut (ref mut d, _) in self.members.iter_mut() {
+            d.ponder(g);
+        }
+    }
+
+    fn on_event(&mut self, event: &GameEvent) {
+        for &mut (ref mut d, _) in self.members.iter_mut() {
+            d.on_event(event);
+        }
+    }
+
+    fn confidence(&self) -> Option<f32> {
+        self.last_share
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        for &mut (ref mut d, _) in self.members.iter_mut() {
+            d.game_over(g);
+        }
+    }
+}
+
+// Reads an ensemble's players from a file with one per line, each
+// optionally preceded by the weight of its vote, e.g. "2 bigmoney+smithy".
+fn ensemble_members(
+    path: &str,
+    registry: &Registry,
+    silent: bool,
+) -> Result<Vec<(Box<Decider>, f32)>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(format!("Unable to read {}: {}", path, e)),
+    };
+    contents
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            let mut parts = l.splitn(2, ' ');
+            let first = parts.next().unwrap();
+            match (first.parse::<f32>(), parts.next()) {
+                (Ok(weight), Some(spec)) => Ok((registry.create(spec.trim(), silent)?, weight)),
+                _ => Ok((registry.create(l, silent)?, 1.0)),
+            }
+        })
+        .collect()
+}
+
+fn ensemble(spec: &PlayerSpec, registry: &Registry) -> Result<Box<Decider>, String> {
+    let mut members = vec![];
+    let mut vote = Vote::Majority;
+    for &(key, value) in spec.options.iter() {
+        match key {
+            "file" => members = ensemble_members(value, registry, spec.silent)?,
+            "vote" => vote = Vote::parse(value)?,
+            _ => unreachable!(),
+        }
+    }
+    if members.is_empty() {
+        return Err("Player ensemble needs a file of players, e.g. ensemble:file=team.txt".into());
+    }
+    Ok(Box::new(EnsembleDecider::new(members, vote)))
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "ensemble",
+        summary: "plays the choice most of a file of players vote for",
+        example: "ensemble:file=team.txt,vote=confidence",
+        argument: None,
+        options: &[
+            OptionSpec { key: "file", value: "file", help: "the members, one per line", default: "" },
+            OptionSpec { key: "vote", value: "majority/confidence", help: "how votes count", default: "majority" },
+        ],
+        factory: ensemble,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use ensemble::*;
+    use game::*;
+
+    // Always makes the same choice, as sure of it as it's told to be.
+    struct Fixed(Vec<CardIdentifier>, Option<f32>);
+
+    impl Decider for Fixed {
+        fn description(&self) -> String {
+            "Fixed".into()
+        }
+
+        fn make_decision(&mut self, _g: &Game) -> Vec<CardIdentifier> {
+            self.0.clone()
+        }
+
+        fn confidence(&self) -> Option<f32> {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_ensemble_vote() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let g = fresh_game(&names);
+        let silver = vec![SILVER.identifier];
+        let smithy = vec![SMITHY.identifier];
+        let members = || -> Vec<(Box<Decider>, f32)> {
+            vec![
+                (Box::new(Fixed(smithy.clone(), Some(0.9))), 1.0),
+                (Box::new(Fixed(silver.clone(), Some(0.2))), 1.0),
+                (Box::new(Fixed(silver.clone(), Some(0.3))), 1.0),
+            ]
+        };
+
+        let mut majority = EnsembleDecider::new(members(), Vote::Majority);
+        assert_eq!(majority.make_decision(&g), silver);
+        assert_eq!(majority.confidence(), Some(2.0 / 3.0));
+
+        let mut confident = EnsembleDecider::new(members(), Vote::Confidence);
+        assert_eq!(confident.make_decision(&g), smithy);
+
+        let mut weighted = members();
+        weighted[0].1 = 3.0;
+        assert_eq!(EnsembleDecider::new(weighted, Vote::Majority).make_decision(&g), smithy);
+    }
+}
@@ -0,0 +1,297 @@
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::ptr;
+
+use embedded_game::EmbeddedGame;
+
+// A C ABI, with the ffi feature, for embedding the engine in C, C++, C# or
+// game engines such as Unity. include/tactician.h declares it, and is
+// regenerated by building with the feature and TACTICIAN_UPDATE_HEADER=1.
+// Games are opaque handles, and players, moves and states are JSON strings,
+// as the JSON-RPC server writes them:
+//
+//     TacticianGame *game = tactician_game_new("[\"You\", \"Bot\"]", "", 7);
+//     char *choice = tactician_game_ai_choose(game, 5000);
+//     tactician_game_apply_move(game, choice);
+//     tactician_string_free(choice);
+//     tactician_game_free(game);
+//
+// Strings returned are owned by the caller, who frees them with
+// tactician_string_free. A call that fails returns NULL or -1, and
+// tactician_last_error says why. A panic inside the engine is caught and
+// fails the call the same way, after which its game should be freed rather
+// than played on. Handles may be used from any thread, but only from one at
+// a time.
+
+// A game, always waiting on a decision or over.
+pub struct TacticianGame {
+    inner: EmbeddedGame,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+// Runs the body of an exported function, returning `failed` instead if it
+// panics, since unwinding into C is undefined behavior.
+fn guard<T, F: FnOnce() -> T>(failed: T, body: F) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(result) => result,
+        Err(cause) => {
+            let message = match cause.downcast_ref::<&str>() {
+                Some(s) => s.to_string(),
+                None => cause.downcast_ref::<String>().cloned().unwrap_or_default(),
+            };
+            set_error(&format!("Tactician panicked: {}", message));
+            failed
+        }
+    }
+}
+
+// A string from C, or None after recording why it's unusable.
+unsafe fn string_arg<'a>(s: *const c_char, name: &str) -> Option<&'a str> {
+    if s.is_null() {
+        set_error(&format!("{} is NULL", name));
+        return None;
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_error(&format!("{} isn't UTF-8", name));
+            None
+        }
+    }
+}
+
+unsafe fn game_arg<'a>(game: *mut TacticianGame) -> Option<&'a mut TacticianGame> {
+    if game.is_null() {
+        set_error("game is NULL");
+    }
+    game.as_mut()
+}
+
+// A string for C to free, or NULL after recording the error.
+fn string_result(result: Result<String, String>) -> *mut c_char {
+    match result {
+        Ok(s) => CString::new(s).unwrap().into_raw(),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+fn game_result(result: Result<EmbeddedGame, String>) -> *mut TacticianGame {
+    match result {
+        Ok(inner) => Box::into_raw(Box::new(TacticianGame { inner: inner })),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+// Deals a game between players named by a JSON list, with a kingdom as
+// --kingdom takes it. Empty strings give two players and the first-game
+// kingdom. Returns NULL on error.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_new(
+    players: *const c_char,
+    kingdom: *const c_char,
+    seed: u32,
+) -> *mut TacticianGame {
+    guard(ptr::null_mut(), || {
+        match (
+            string_arg(players, "players"),
+            string_arg(kingdom, "kingdom"),
+        ) {
+            (Some(players), Some(kingdom)) => {
+                game_result(EmbeddedGame::new(players, kingdom, seed))
+            }
+            _ => ptr::null_mut(),
+        }
+    })
+}
+
+// Picks up a game saved by tactician_game_state. Returns NULL on error.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_load(
+    state: *const c_char,
+    seed: u32,
+) -> *mut TacticianGame {
+    guard(ptr::null_mut(), || match string_arg(state, "state") {
+        Some(state) => game_result(EmbeddedGame::load(state, seed)),
+        None => ptr::null_mut(),
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_free(game: *mut TacticianGame) {
+    guard((), || {
+        if !game.is_null() {
+            drop(Box::from_raw(game));
+        }
+    })
+}
+
+// The whole game as JSON, or with a seat from 0, only what it can see. A
+// negative seat gives the whole game.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_state(game: *mut TacticianGame, seat: i32) -> *mut c_char {
+    guard(ptr::null_mut(), || match game_arg(game) {
+        Some(game) => {
+            let seat = match seat {
+                s if s < 0 => None,
+                s => Some(s as usize),
+            };
+            string_result(game.inner.state(seat))
+        }
+        None => ptr::null_mut(),
+    })
+}
+
+// The turn, whether the game is over, the pending decision and the scores.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_status(game: *mut TacticianGame) -> *mut c_char {
+    guard(ptr::null_mut(), || match game_arg(game) {
+        Some(game) => string_result(Ok(game.inner.status())),
+        None => ptr::null_mut(),
+    })
+}
+
+// The moves for the pending decision, each a list of card names.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_legal_moves(game: *mut TacticianGame) -> *mut c_char {
+    guard(ptr::null_mut(), || match game_arg(game) {
+        Some(game) => string_result(Ok(game.inner.legal_moves())),
+        None => ptr::null_mut(),
+    })
+}
+
+// Makes the pending decision, a JSON list of card names, and plays on to
+// the next. Returns 0, or -1 on error.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_apply_move(
+    game: *mut TacticianGame,
+    choice: *const c_char,
+) -> i32 {
+    guard(-1, || {
+        let applied = match (game_arg(game), string_arg(choice, "choice")) {
+            (Some(game), Some(choice)) => game.inner.apply_move(choice),
+            _ => return -1,
+        };
+        match applied {
+            Ok(()) => 0,
+            Err(e) => {
+                set_error(&e);
+                -1
+            }
+        }
+    })
+}
+
+// The move Tactician picks with a search of so many iterations.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_ai_choose(
+    game: *mut TacticianGame,
+    iterations: u32,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || match game_arg(game) {
+        Some(game) => string_result(game.inner.ai_choose(iterations)),
+        None => ptr::null_mut(),
+    })
+}
+
+// Each move a search of so many iterations weighed, with its visits and
+// win rate.
+#[no_mangle]
+pub unsafe extern "C" fn tactician_game_analyze(
+    game: *mut TacticianGame,
+    iterations: u32,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || match game_arg(game) {
+        Some(game) => string_result(game.inner.analyze(iterations)),
+        None => ptr::null_mut(),
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tactician_string_free(s: *mut c_char) {
+    guard((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+// Why the calling thread's last failed call failed, or NULL. The string
+// lasts until its next failed call.
+#[no_mangle]
+pub extern "C" fn tactician_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use ffi::*;
+    use json::Json;
+
+    fn take(s: *mut c_char) -> String {
+        assert!(!s.is_null());
+        let text = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { tactician_string_free(s) };
+        text
+    }
+
+    #[test]
+    fn test_ffi() {
+        let players = CString::new(r#"["Alice", "Bob"]"#).unwrap();
+        let kingdom = CString::new("").unwrap();
+        unsafe {
+            let game = tactician_game_new(players.as_ptr(), kingdom.as_ptr(), 3);
+            let choice = tactician_game_ai_choose(game, 50);
+            assert_eq!(tactician_game_apply_move(game, choice), 0);
+            tactician_string_free(choice);
+            let status = Json::parse(&take(tactician_game_status(game))).unwrap();
+            assert_eq!(status.get("over"), Some(&Json::Bool(false)));
+            assert!(Json::parse(&take(tactician_game_state(game, 1))).is_ok());
+
+            let illegal = CString::new(r#"["Province"]"#).unwrap();
+            assert_eq!(tactician_game_apply_move(game, illegal.as_ptr()), -1);
+            assert!(!tactician_last_error().is_null());
+            assert!(tactician_game_state(game, 5).is_null());
+            tactician_game_free(game);
+
+            assert!(tactician_game_new(ptr::null(), kingdom.as_ptr(), 3).is_null());
+            let error = CStr::from_ptr(tactician_last_error()).to_str().unwrap();
+            assert_eq!(error, "players is NULL");
+        }
+    }
+
+    #[test]
+    fn test_header_is_current() {
+        let generated = include_str!(env!("TACTICIAN_HEADER"));
+        let committed = include_str!("../include/tactician.h");
+        assert!(
+            generated == committed,
+            "include/tactician.h is out of date; build with TACTICIAN_UPDATE_HEADER=1"
+        );
+    }
+
+    #[test]
+    fn test_ffi_catches_panics() {
+        assert_eq!(guard(-1, || -> i32 { panic!("out of cards") }), -1);
+        let error = unsafe { CStr::from_ptr(tactician_last_error()) };
+        assert_eq!(error.to_str(), Ok("Tactician panicked: out of cards"));
+        assert_eq!(guard(-1, || 0), 0);
+    }
+}
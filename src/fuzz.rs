@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::panic;
+
+use cards::CardIdentifier;
+use deciders::{RandomBehavior, RandomDecider};
+use game;
+use game::{Decider, EvalContext, Game, Verbosity};
+use util;
+
+// Counts every card in the game, wherever it is.
+fn card_totals(g: &Game) -> HashMap<CardIdentifier, i32> {
+    let mut totals = g.piles.clone();
+    let mut count = |cards: &Vec<CardIdentifier>| {
+        for c in cards.iter() {
+            *totals.entry(*c).or_insert(0) += 1;
+        }
+    };
+    for p in g.players.iter() {
+        count(&p.all_cards());
+    }
+    count(&g.play_area);
+    count(&g.trash_pile);
+    totals
+}
+
+// Panics if a game has got into an impossible state.
+pub fn check_invariants(g: &Game, expected_totals: &HashMap<CardIdentifier, i32>) {
+    for (c, &n) in g.piles.iter() {
+        assert!(n >= 0, "{} pile has {} cards", c, n);
+    }
+    let totals = card_totals(g);
+    for (c, &n) in expected_totals.iter() {
+        let found = totals.get(c).cloned().unwrap_or(0);
+        assert_eq!(found, n, "{} {} in the game, expected {}", found, c, n);
+    }
+    if let Some(ref d) = g.pending_decision {
+        assert!(d.range.0 <= d.range.1, "Decision range {:?} is empty", d.range);
+        assert!(
+            d.range.0 <= d.choices.len(),
+            "Decision needs {} of only {} choices",
+            d.range.0,
+            d.choices.len()
+        );
+    }
+}
+
+// Checks the game before passing each decision on to another decider.
+struct Checked {
+    decider: Box<Decider>,
+    // Card totals as of the first decision.
+    totals: Option<HashMap<CardIdentifier, i32>>,
+}
+
+impl Decider for Checked {
+    fn description(&self) -> String {
+        self.decider.description()
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let totals = self.totals.get_or_insert_with(|| card_totals(g));
+        check_invariants(g, totals);
+        self.decider.make_decision(g)
+    }
+}
+
+pub struct FuzzReport {
+    pub games: u32,
+    // Seeds of the games that panicked.
+    pub failures: Vec<u32>,
+}
+
+// The players for a fuzzed game: two to four random players, some biased
+// towards expensive cards or passing, so more kinds of game get played.
+fn fuzz_players(seed: u32) -> Vec<Box<Decider>> {
+    let count = 2 + seed as usize % 3;
+    (0..count)
+        .map(|i| {
+            let mut d = RandomDecider::with_seed(seed.wrapping_add(i as u32));
+            let pass = (seed % 4) as f32 * 0.05;
+            d.behavior = RandomBehavior {
+                pass: RandomBehavior::parse_pass(&pass.to_string()).unwrap(),
+                cost_bias: (i % 3) as f32,
+            };
+            Box::new(Checked {
+                decider: Box::new(d),
+                totals: None,
+            }) as Box<Decider>
+        })
+        .collect()
+}
+
+// Plays games between random players with seeded shuffles, checking the
+// game's invariants at every decision. Games that panic are reported by
+// seed, and fuzzing one game from that seed replays it.
+pub fn fuzz(games: u32, first_seed: u32) -> FuzzReport {
+    let mut failures = vec![];
+    for i in 0..games {
+        let seed = first_seed.wrapping_add(i);
+        let result = panic::catch_unwind(|| {
+            let mut players = fuzz_players(seed);
+            let mut ctx = EvalContext {
+                rng: util::seeded_weak_rng(seed),
+                verbosity: Verbosity::Quiet,
+                events: None,
+            };
+            game::run_game_with_context(&mut players, &mut ctx);
+        });
+        if result.is_err() {
+            failures.push(seed);
+        }
+    }
+    FuzzReport {
+        games: games,
+        failures: failures,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fuzz::*;
+
+    #[test]
+    fn test_fuzz() {
+        let report = fuzz(20, 1);
+        assert_eq!(report.games, 20);
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+    }
+}
@@ -1,11 +1,15 @@
 use rand::{Rng, XorShiftRng};
 use std;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use cards;
+use color;
 use cards::{Card, CardAction, CardIdentifier, CardReaction, CardType, DiscardEffect, EffectTarget,
             GainDestination, TrashFollowup};
-use util::{randomly_seeded_weak_rng, subtract_vector};
+use time_control::{OnTimeout, TimeControl};
+use util::{randomly_seeded_weak_rng, subtract_vector, Stopwatch};
 
 pub const EMPTY_PILES_FOR_GAME_END: i32 = 3;
 pub const PLAYER_HAND_SIZE: usize = 5;
@@ -68,9 +72,97 @@ pub struct Decision {
     pub range: (usize, usize),
 }
 
+// Something that happened in the game which every player saw, such as an
+// opponent gaining a card or revealing a Moat. Cards drawn and discarded
+// in cleanup are private, so only how many were drawn is public.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    TurnStarted(PlayerIdentifier, i32),
+    Shuffled(PlayerIdentifier),
+    Drew(PlayerIdentifier, usize),
+    Played(PlayerIdentifier, Vec<CardIdentifier>),
+    Bought(PlayerIdentifier, CardIdentifier),
+    Gained(PlayerIdentifier, CardIdentifier),
+    Discarded(PlayerIdentifier, Vec<CardIdentifier>),
+    CleanedUp(PlayerIdentifier),
+    Trashed(PlayerIdentifier, Vec<CardIdentifier>),
+    Revealed(PlayerIdentifier, CardIdentifier),
+}
+
+// Why a decider couldn't make a decision.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecisionError {
+    // What the decider was given, such as a person's or a bot's reply, was
+    // unusable.
+    InvalidInput(String),
+    TimedOut,
+    // The decider's input went away, such as a bot exiting.
+    Disconnected(String),
+}
+
+impl std::fmt::Display for DecisionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            DecisionError::InvalidInput(ref e) => write!(f, "invalid input: {}", e),
+            DecisionError::TimedOut => write!(f, "timed out"),
+            DecisionError::Disconnected(ref e) => write!(f, "disconnected: {}", e),
+        }
+    }
+}
+
+// How a decider rated one of the moves it weighed, such as a search's visits
+// and win rate for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveStats {
+    pub choice: Vec<CardIdentifier>,
+    pub visits: i32,
+    pub win_rate: f32,
+}
+
 pub trait Decider {
     fn description(&self) -> String;
     fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier>;
+
+    // Makes a decision, or reports why it couldn't rather than panicking.
+    // Games call this, and a player who fails forfeits the game. Deciders
+    // that read from a person, a process or a connection should override it.
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        Ok(self.make_decision(g))
+    }
+
+    // Called on every decider with each decision made, including its own,
+    // before the choice is applied to the game.
+    fn observe_decision(&mut self, _g: &Game, _choice: &Vec<CardIdentifier>) {}
+
+    // Called while another player is about to decide, so a decider can think
+    // ahead on their time.
+    fn ponder(&mut self, _g: &Game) {}
+
+    // How sure the decider was of its last decision, from 0 to 1, if it can
+    // tell.
+    fn confidence(&self) -> Option<f32> {
+        None
+    }
+
+    // The moves the decider weighed for its last decision, if it searched,
+    // for game logs to save.
+    fn analysis(&self) -> Vec<MoveStats> {
+        vec![]
+    }
+
+    // Called before each of the decider's decisions in games played with a
+    // time control, with the time left on its clock and the increment it
+    // will get back after deciding.
+    fn clock(&mut self, _remaining: Duration, _increment: Duration) {}
+
+    // Called on every decider with each public event as it happens, so it
+    // can remember what it saw rather than only seeing the state when it
+    // decides.
+    fn on_event(&mut self, _event: &GameEvent) {}
+
+    // Called on every decider once the game has ended, which is before it's
+    // over if a player forfeited.
+    fn game_over(&mut self, _g: &Game) {}
 }
 
 impl Player {
@@ -86,9 +178,10 @@ impl Player {
             self.deck = self.discard.clone();
             self.discard.clear();
 
-            if ctx.debug {
-                println!("{} shuffles", self.name);
+            if ctx.logs(Verbosity::Trace) {
+                println!("{} shuffles", self.colored_name());
             }
+            ctx.record(GameEvent::Shuffled(self.identifier));
 
             let second_n = std::cmp::min(self.deck.len(), n - first_draw.len());
             let pivot = self.deck.len() - second_n;
@@ -97,17 +190,24 @@ impl Player {
             first_draw
         };
 
-        if ctx.debug {
-            println!("{} draws {} cards", self.name, drawn.len());
+        if ctx.logs(Verbosity::Trace) {
+            println!("{} draws {} cards", self.colored_name(), drawn.len());
         }
+        ctx.record(GameEvent::Drew(self.identifier, drawn.len()));
 
         self.hand.append(&mut drawn);
     }
 
+    // The player's name, colored by seat for game logs.
+    pub fn colored_name(&self) -> String {
+        color::player(self.identifier.0 as usize, &self.name)
+    }
+
     fn discard_hand(&mut self, ctx: &mut EvalContext) {
-        if ctx.debug {
-            println!("{} discards {}", self.name, cards::card_names(&self.hand));
+        if ctx.logs(Verbosity::Trace) {
+            println!("{} discards {}", self.colored_name(), color::card_names(&self.hand));
         }
+        ctx.record(GameEvent::CleanedUp(self.identifier));
 
         self.discard.extend(&self.hand);
         self.hand.clear();
@@ -143,16 +243,71 @@ pub struct Game {
     pub players: Vec<Player>,
     pub pending_decision: Option<Decision>,
     pub pending_effects: Vec<QueuedEffect>,
+    pub decisions_resolved: i32,
+}
+
+// How much of a game to print.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    // Nothing. Runs of games print only their final results.
+    Quiet,
+    // Each game's result.
+    Normal,
+    // A summary of each turn, and each game's final score.
+    Verbose,
+    // Every card played, drawn, gained and moved, and the players' thinking.
+    Trace,
+}
+
+impl Verbosity {
+    // The level for -q, or for each -v given.
+    pub fn from_flags(quiet: bool, verbose: usize) -> Verbosity {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            _ => Verbosity::Trace,
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Verbosity, String> {
+        match s {
+            "quiet" => Ok(Verbosity::Quiet),
+            "normal" => Ok(Verbosity::Normal),
+            "verbose" => Ok(Verbosity::Verbose),
+            "trace" => Ok(Verbosity::Trace),
+            _ => Err(format!(
+                "Unknown verbosity {}, expected quiet, normal, verbose or trace",
+                s
+            )),
+        }
+    }
 }
 
 pub struct EvalContext {
     pub rng: XorShiftRng,
-    pub debug: bool,
+    pub verbosity: Verbosity,
+    // Collects the game's public events when set. Only games played for
+    // real set it, so searches don't pay for events nobody reads.
+    pub events: Option<Vec<GameEvent>>,
+}
+
+impl EvalContext {
+    // Whether to print what happens at this level of detail.
+    pub fn logs(&self, level: Verbosity) -> bool {
+        self.verbosity >= level
+    }
+
+    fn record(&mut self, event: GameEvent) {
+        if let Some(ref mut events) = self.events {
+            events.push(event);
+        }
+    }
 }
 
 impl Game {
-    fn initialize_game(&mut self, ctx: &mut EvalContext) {
-        if ctx.debug {
+    pub fn initialize_game(&mut self, ctx: &mut EvalContext) {
+        if ctx.logs(Verbosity::Verbose) {
             println!("The game is afoot!");
         }
         for mut p in self.players.iter_mut() {
@@ -170,6 +325,9 @@ impl Game {
                 }
             }
         }
+        // Piles are hashed, so sort to keep choices in the same order from
+        // one run to the next.
+        gainable.sort();
         gainable
     }
 
@@ -202,10 +360,11 @@ impl Game {
             let ref mut player = self.players[pid.0 as usize];
             player.discard.extend(&cards);
             subtract_vector::<CardIdentifier>(&mut player.hand, &cards);
-            if ctx.debug {
-                println!("{} discards {}", player.name, cards::card_names(&cards));
+            if ctx.logs(Verbosity::Trace) {
+                println!("{} discards {}", player.colored_name(), color::card_names(&cards));
             }
         }
+        ctx.record(GameEvent::Discarded(pid, cards.clone()));
 
         if let Some(maybe_effect) = maybe_effect {
             match maybe_effect {
@@ -338,6 +497,7 @@ impl Game {
         match self.phase {
             Phase::StartTurn => {
                 self.print_turn_start_summary(ctx);
+                ctx.record(GameEvent::TurnStarted(self.active_player, self.turn));
                 self.phase = Phase::Action;
             }
             Phase::Action => {
@@ -433,10 +593,11 @@ impl Game {
             }
         }
 
-        if ctx.debug {
-            let c = cards::lookup_card(ci);
-            println!("{} gains {}", self.players[player.0 as usize].name, c.name);
+        if ctx.logs(Verbosity::Trace) {
+            let name = self.players[player.0 as usize].colored_name();
+            println!("{} gains {}", name, color::card(ci));
         }
+        ctx.record(GameEvent::Gained(player, *ci));
     }
 
     fn buy_card(&mut self, player: PlayerIdentifier, ci: &CardIdentifier, ctx: &mut EvalContext) {
@@ -452,9 +613,11 @@ impl Game {
         }
         self.players[player.0 as usize].discard.push(*ci);
 
-        if ctx.debug {
-            println!("{} buys {}", self.players[player.0 as usize].name, c.name);
+        if ctx.logs(Verbosity::Trace) {
+            let name = self.players[player.0 as usize].colored_name();
+            println!("{} buys {}", name, color::card(ci));
         }
+        ctx.record(GameEvent::Bought(player, *ci));
     }
 
     fn replace_card_by_cost(
@@ -496,13 +659,14 @@ impl Game {
 
         self.trash_pile.extend(&cards);
 
-        if ctx.debug {
+        if ctx.logs(Verbosity::Trace) {
             println!(
                 "{} trashes {}",
-                self.players[pid.0 as usize].name,
-                cards::card_names(&cards)
+                self.players[pid.0 as usize].colored_name(),
+                color::card_names(&cards)
             );
         }
+        ctx.record(GameEvent::Trashed(pid, cards.clone()));
 
         if let Some(followup) = maybe_followup {
             match followup {
@@ -523,12 +687,14 @@ impl Game {
         ctx: &mut EvalContext,
     ) {
         let reaction = cards::lookup_card(c);
-        if ctx.debug {
+        if ctx.logs(Verbosity::Trace) {
             println!(
                 "{} reveals {}",
-                self.players[pid.0 as usize].name, reaction.name
+                self.players[pid.0 as usize].colored_name(),
+                color::card(c)
             );
         }
+        ctx.record(GameEvent::Revealed(pid, *c));
 
         if let Some(ref rx_effect) = reaction.reaction_effect {
             match rx_effect {
@@ -601,9 +767,10 @@ impl Game {
 
         {
             let ref mut player = self.players[pid.0 as usize];
-            if ctx.debug {
-                println!("{} plays {}", player.name, action);
+            if ctx.logs(Verbosity::Trace) {
+                println!("{} plays {}", player.colored_name(), color::card(action));
             }
+            ctx.record(GameEvent::Played(pid, vec![*action]));
 
             let hand_idx = player
                 .hand
@@ -644,9 +811,10 @@ impl Game {
 
         let ref mut player = self.players[pid.0 as usize];
 
-        if ctx.debug {
-            println!("{} plays {}", player.name, cards::card_names(result));
+        if ctx.logs(Verbosity::Trace) {
+            println!("{} plays {}", player.colored_name(), color::card_names(result));
         }
+        ctx.record(GameEvent::Played(pid, result.clone()));
 
         self.play_area.extend(result);
         subtract_vector::<CardIdentifier>(&mut player.hand, &result);
@@ -656,6 +824,7 @@ impl Game {
         let decision = self.pending_decision
             .take()
             .expect("Game::resolve_decision called without pending decision");
+        self.decisions_resolved += 1;
         match decision.decision_type {
             DecisionType::PlayAction => {
                 assert!(result.len() <= 1, "Can only play at most one action");
@@ -749,6 +918,10 @@ pub fn fresh_player(identifier: PlayerIdentifier, name: &String) -> Player {
 }
 
 pub fn fresh_game(player_names: &Vec<String>) -> Game {
+    fresh_game_with_kingdom(player_names, &cards::first_game_kingdom())
+}
+
+pub fn fresh_game_with_kingdom(player_names: &Vec<String>, kingdom: &[CardIdentifier]) -> Game {
     let players = player_names
         .iter()
         .enumerate()
@@ -765,49 +938,195 @@ pub fn fresh_game(player_names: &Vec<String>) -> Game {
         buys: 1,
         coins: 0,
         current_action_identifier: ActionIdentifier::new(),
-        piles: cards::standard_piles(players.len() as i32),
+        piles: cards::supply_piles(players.len() as i32, kingdom),
         play_area: Vec::new(),
         trash_pile: Vec::new(),
         players: players,
         pending_decision: None,
         pending_effects: vec![],
+        decisions_resolved: 0,
     };
 }
 
-pub fn run_game(players: &mut Vec<Box<Decider>>, debug: bool) -> Vec<f32> {
+// How a game is set up and played, besides who plays it.
+#[derive(Clone, Debug)]
+pub struct Rules {
+    pub kingdom: Vec<CardIdentifier>,
+    // Puts each player on a chess clock.
+    pub time_control: Option<TimeControl>,
+    // Ends the game unfinished once set, e.g. by Ctrl-C.
+    pub abort: Option<&'static AtomicBool>,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            kingdom: cards::first_game_kingdom(),
+            time_control: None,
+            abort: None,
+        }
+    }
+}
+
+pub fn run_game(players: &mut Vec<Box<Decider>>, verbosity: Verbosity) -> Vec<f32> {
     let mut ctx = EvalContext {
         rng: randomly_seeded_weak_rng(),
-        debug: debug,
+        verbosity: verbosity,
+        events: None,
     };
+    run_game_with_context(players, &mut ctx)
+}
+
+// Ends a game when a player can't decide. They lose, and the other players,
+// if there are any, share the win.
+fn forfeit_game(
+    players: &mut Vec<Box<Decider>>,
+    g: &Game,
+    ctx: &EvalContext,
+    forfeiter: usize,
+    e: DecisionError,
+) -> Vec<f32> {
+    if ctx.logs(Verbosity::Normal) {
+        println!("{} forfeits the game: {}", g.players[forfeiter].colored_name(), e);
+    }
+    for p in players.iter_mut() {
+        p.game_over(g);
+    }
+    let share = 1.0 / (players.len() - 1).max(1) as f32;
+    (0..players.len())
+        .map(|i| if i == forfeiter { 0.0 } else { share })
+        .collect()
+}
+
+// Passes the events collected since the last call on to every player.
+fn dispatch_events(players: &mut Vec<Box<Decider>>, ctx: &mut EvalContext) {
+    if let Some(ref mut events) = ctx.events {
+        for e in events.drain(..) {
+            for p in players.iter_mut() {
+                p.on_event(&e);
+            }
+        }
+    }
+}
+
+// The least a decider may choose, for a player out of time.
+fn default_choice(d: &Decision) -> Vec<CardIdentifier> {
+    match d.decision_type {
+        DecisionType::PlayTreasures => d.choices.clone(),
+        _ => d.choices.iter().take(d.range.0).cloned().collect(),
+    }
+}
+
+// Plays a game using the context's RNG for shuffles, so games played with
+// the same seed and deterministic players are identical.
+pub fn run_game_with_context(players: &mut Vec<Box<Decider>>, ctx: &mut EvalContext) -> Vec<f32> {
+    run_game_with_rules(players, ctx, &Rules::default())
+}
+
+// Plays a game with the rules' kingdom, with each player on a chess clock
+// if there's a time control.
+pub fn run_game_with_rules(
+    players: &mut Vec<Box<Decider>>,
+    ctx: &mut EvalContext,
+    rules: &Rules,
+) -> Vec<f32> {
+    play_game(players, ctx, rules).scores
+}
+
+// How a game ended: each player's score, and the turn it ended on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameOutcome {
+    pub scores: Vec<f32>,
+    pub turns: i32,
+}
 
+// Plays a game as run_game_with_rules does, also returning how long it
+// lasted. An aborted game ends where it was, with the scores so far.
+pub fn play_game(
+    players: &mut Vec<Box<Decider>>,
+    ctx: &mut EvalContext,
+    rules: &Rules,
+) -> GameOutcome {
     let player_names = players.iter().map(|d| d.description()).collect::<Vec<_>>();
-    let mut game = fresh_game(&player_names);
-    game.initialize_game(&mut ctx);
+    let mut game = fresh_game_with_kingdom(&player_names, &rules.kingdom);
+    let time_control = rules.time_control.as_ref();
+    let mut clocks = vec![time_control.map_or(Duration::from_millis(0), |tc| tc.base); players.len()];
+    ctx.events = Some(vec![]);
+    game.initialize_game(ctx);
+    dispatch_events(players, ctx);
 
     while !game.is_game_over() {
+        if rules.abort.map_or(false, |a| a.load(Ordering::SeqCst)) {
+            break;
+        }
         if game.pending_decision.is_some() {
             let player_idx = game.pending_decision.as_ref().unwrap().player.0 as usize;
-            let choice = players[player_idx].make_decision(&game);
-            game.resolve_decision(choice, &mut ctx);
+            for (i, p) in players.iter_mut().enumerate() {
+                if i != player_idx {
+                    p.ponder(&game);
+                }
+            }
+
+            if let Some(tc) = time_control {
+                players[player_idx].clock(clocks[player_idx], tc.increment);
+            }
+            let started = Stopwatch::start();
+            let mut choice = match players[player_idx].try_make_decision(&game) {
+                Ok(choice) => choice,
+                Err(e) => {
+                    ctx.events = None;
+                    return GameOutcome {
+                        scores: forfeit_game(players, &game, ctx, player_idx, e),
+                        turns: game.turn,
+                    };
+                }
+            };
+
+            if let Some(tc) = time_control {
+                let elapsed = started.elapsed();
+                if elapsed > clocks[player_idx] {
+                    if tc.on_timeout == OnTimeout::Forfeit {
+                        ctx.events = None;
+                        let timed_out = DecisionError::TimedOut;
+                        let scores = forfeit_game(players, &game, ctx, player_idx, timed_out);
+                        return GameOutcome {
+                            scores: scores,
+                            turns: game.turn,
+                        };
+                    }
+                    if ctx.logs(Verbosity::Verbose) {
+                        println!("{} is out of time", game.players[player_idx].colored_name());
+                    }
+                    choice = default_choice(game.pending_decision.as_ref().unwrap());
+                    clocks[player_idx] = Duration::from_millis(0);
+                } else {
+                    clocks[player_idx] -= elapsed;
+                }
+                clocks[player_idx] += tc.increment;
+            }
+            for p in players.iter_mut() {
+                p.observe_decision(&game, &choice);
+            }
+            game.resolve_decision(choice, ctx);
         } else {
-            game.advance_game(&mut ctx);
+            game.advance_game(ctx);
         }
+        dispatch_events(players, ctx);
     }
+    ctx.events = None;
 
-    if ctx.debug {
-        let points = game.player_vp_and_turns();
-        println!("The game is over.");
-        for (i, &(points, turns)) in points.iter().enumerate() {
-            let ref name = game.players[i].name;
-            println!("{}: {} VP in {} turns", name, points, turns);
-        }
-        println!();
+    for p in players.iter_mut() {
+        p.game_over(&game);
+    }
+    game.print_score_report(ctx);
+
+    GameOutcome {
+        scores: game.player_scores()
+            .iter()
+            .map(|&(_, score)| score)
+            .collect(),
+        turns: game.turn,
     }
-
-    return game.player_scores()
-        .iter()
-        .map(|&(_, score)| score)
-        .collect();
 }
 
 #[cfg(test)]
@@ -815,6 +1134,8 @@ mod tests {
 
     use game::*;
     use cards::*;
+    use deciders::FirstChoice;
+    use util::seeded_weak_rng;
 
     fn advance_until_decision(game: &mut Game, ctx: &mut EvalContext) {
         while game.pending_decision.is_none() {
@@ -863,8 +1184,9 @@ mod tests {
     #[test]
     fn test_draw() {
         let mut ctx = EvalContext {
-            debug: false,
+            verbosity: Verbosity::Quiet,
             rng: randomly_seeded_weak_rng(),
+            events: None,
         };
         let mut p = fresh_player(PlayerIdentifier(0), &"Test Player".to_string());
         p.draw_cards(5, &mut ctx);
@@ -892,8 +1214,9 @@ mod tests {
     fn test_militia_attack() {
         let names = vec!["Player 1".into(), "Player 2".into()];
         let mut ctx = EvalContext {
-            debug: true,
+            verbosity: Verbosity::Trace,
             rng: randomly_seeded_weak_rng(),
+            events: None,
         };
         let mut game = fresh_game(&names);
 
@@ -922,8 +1245,9 @@ mod tests {
     fn test_moat_reveal() {
         let names = vec!["Player 1".into(), "Player 2".into()];
         let mut ctx = EvalContext {
-            debug: true,
+            verbosity: Verbosity::Trace,
             rng: randomly_seeded_weak_rng(),
+            events: None,
         };
         let mut game = fresh_game(&names);
 
@@ -950,4 +1274,105 @@ mod tests {
         assert_eq!(game.players[1].hand.len(), 5);
     }
 
+    #[test]
+    fn test_public_events() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut ctx = EvalContext {
+            verbosity: Verbosity::Quiet,
+            rng: randomly_seeded_weak_rng(),
+            events: Some(vec![]),
+        };
+        let mut game = fresh_game(&names);
+        let p1 = PlayerIdentifier(0);
+        let p2 = PlayerIdentifier(1);
+        game.players[0].hand = vec![MILITIA.identifier, COPPER.identifier];
+        game.players[1].hand = vec![MOAT.identifier, ESTATE.identifier];
+
+        advance_until_decision(&mut game, &mut ctx);
+        game.resolve_decision(vec![MILITIA.identifier], &mut ctx);
+        advance_until_decision(&mut game, &mut ctx);
+        game.resolve_decision(vec![MOAT.identifier], &mut ctx);
+        advance_until_decision(&mut game, &mut ctx);
+        game.resolve_decision(vec![COPPER.identifier], &mut ctx);
+        advance_until_decision(&mut game, &mut ctx);
+        game.resolve_decision(vec![ESTATE.identifier], &mut ctx);
+
+        assert_eq!(
+            ctx.events.unwrap(),
+            vec![
+                GameEvent::TurnStarted(p1, 1),
+                GameEvent::Played(p1, vec![MILITIA.identifier]),
+                GameEvent::Revealed(p2, MOAT.identifier),
+                GameEvent::Played(p1, vec![COPPER.identifier]),
+                GameEvent::Bought(p1, ESTATE.identifier),
+            ]
+        );
+    }
+
+    // Can never make up its mind.
+    struct Unreachable;
+
+    impl Decider for Unreachable {
+        fn description(&self) -> String {
+            "Unreachable".into()
+        }
+
+        fn make_decision(&mut self, _g: &Game) -> Vec<CardIdentifier> {
+            unreachable!()
+        }
+
+        fn try_make_decision(&mut self, _g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+            Err(DecisionError::TimedOut)
+        }
+    }
+
+    #[test]
+    fn test_forfeit() {
+        let mut players: Vec<Box<Decider>> = vec![Box::new(FirstChoice), Box::new(Unreachable)];
+        let mut ctx = EvalContext {
+            verbosity: Verbosity::Quiet,
+            rng: randomly_seeded_weak_rng(),
+            events: None,
+        };
+        assert_eq!(run_game_with_context(&mut players, &mut ctx), vec![1.0, 0.0]);
+
+        let mut alone: Vec<Box<Decider>> = vec![Box::new(Unreachable)];
+        assert_eq!(run_game_with_context(&mut alone, &mut ctx), vec![0.0]);
+    }
+
+    // Plays FirstChoice against itself, returning every choice made and
+    // the final game.
+    fn play_first_choice_game(seed: u32) -> (Vec<(PlayerIdentifier, Vec<CardIdentifier>)>, Game) {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut ctx = EvalContext {
+            verbosity: Verbosity::Quiet,
+            rng: seeded_weak_rng(seed),
+            events: None,
+        };
+        let mut game = fresh_game(&names);
+        let mut log = vec![];
+        game.initialize_game(&mut ctx);
+        while !game.is_game_over() {
+            if game.pending_decision.is_some() {
+                let choice = FirstChoice.make_decision(&game);
+                log.push((game.pending_decision.as_ref().unwrap().player, choice.clone()));
+                game.resolve_decision(choice, &mut ctx);
+            } else {
+                game.advance_game(&mut ctx);
+            }
+        }
+        (log, game)
+    }
+
+    #[test]
+    fn test_first_choice_games_replay() {
+        let (log, game) = play_first_choice_game(1);
+        let (replayed_log, replayed_game) = play_first_choice_game(1);
+        assert!(log == replayed_log);
+        assert_eq!(game.turn, replayed_game.turn);
+        assert_eq!(game.player_vp_and_turns(), replayed_game.player_vp_and_turns());
+
+        // FirstChoice always buys, so some piles ran out.
+        assert!(game.piles.values().filter(|&&n| n == 0).count() >= 3);
+    }
 }
@@ -0,0 +1,68 @@
+use rand::Rng;
+
+use game::{EvalContext, Game, PlayerIdentifier};
+
+impl Game {
+    // Returns a copy of the game with everything hidden from the observer
+    // reshuffled: the observer's own deck order, and each opponent's hand
+    // and deck. Discard piles, the play area and the trash are public.
+    pub fn determinize(&self, observer: PlayerIdentifier, ctx: &mut EvalContext) -> Game {
+        let mut game = self.clone();
+        for player in game.players.iter_mut() {
+            if player.identifier == observer {
+                ctx.rng.shuffle(&mut player.deck);
+                continue;
+            }
+
+            let hand_size = player.hand.len();
+            let mut unseen = player.deck.clone();
+            unseen.extend(&player.hand);
+            ctx.rng.shuffle(&mut unseen);
+
+            let pivot = unseen.len() - hand_size;
+            player.hand = unseen.split_off(pivot);
+            player.deck = unseen;
+        }
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use util::randomly_seeded_weak_rng;
+
+    fn sorted(v: &Vec<CardIdentifier>) -> Vec<CardIdentifier> {
+        let mut v = v.clone();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_determinize_hides_opponent_cards() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut ctx = EvalContext {
+            verbosity: Verbosity::Quiet,
+            rng: randomly_seeded_weak_rng(),
+            events: None,
+        };
+        let mut game = fresh_game(&names);
+        game.players[0].hand = vec![MILITIA.identifier, COPPER.identifier];
+        game.players[1].hand = vec![MOAT.identifier, GOLD.identifier, GOLD.identifier];
+        game.players[1].deck = vec![ESTATE.identifier, ESTATE.identifier];
+
+        let d = game.determinize(PlayerIdentifier(0), &mut ctx);
+
+        assert_eq!(d.players[0].hand, game.players[0].hand);
+        assert_eq!(d.players[0].discard, game.players[0].discard);
+        assert_eq!(d.players[1].discard, game.players[1].discard);
+        assert_eq!(d.players[1].hand.len(), 3);
+        assert_eq!(d.players[1].deck.len(), 2);
+        assert_eq!(
+            sorted(&d.players[1].all_cards()),
+            sorted(&game.players[1].all_cards())
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use cards;
+use cards::{CardAction, CardIdentifier, CardType, DiscardEffect, GainDestination, TrashFollowup};
+use game::{
+    ActionIdentifier, Decision, DecisionType, Game, Phase, Player, PlayerIdentifier, QueuedEffect,
+};
+
+// Game states as compact bytes, for self-play pipelines and network play,
+// where JSON for every state is too heavy. Unlike save files, cards are
+// written as their identifiers, so a change to the card list needs a new
+// VERSION, and states are only read back by the version that wrote them.
+// Numbers are LEB128 varints, zigzagged where they can be negative, and
+// every list is written after its length.
+
+// Every encoding starts with these bytes, then the version.
+pub const MAGIC: &[u8] = b"TCTN";
+pub const VERSION: u8 = 1;
+
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn byte(&mut self, b: u8) {
+        self.bytes.push(b);
+    }
+
+    fn uint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push((n as u8 & 0x7f) | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    fn int(&mut self, n: i32) {
+        self.uint(((n << 1) ^ (n >> 31)) as u32 as u64);
+    }
+
+    fn card(&mut self, c: &CardIdentifier) {
+        self.uint(c.0 as u64);
+    }
+
+    fn cards(&mut self, cs: &[CardIdentifier]) {
+        self.uint(cs.len() as u64);
+        for c in cs {
+            self.card(c);
+        }
+    }
+
+    fn string(&mut self, s: &str) {
+        self.uint(s.len() as u64);
+        self.bytes.extend(s.as_bytes());
+    }
+
+    fn destination(&mut self, d: &GainDestination) {
+        self.byte(match *d {
+            GainDestination::GainToHand => 0,
+            GainDestination::GainToDiscard => 1,
+        });
+    }
+
+    fn card_type(&mut self, t: &Option<CardType>) {
+        self.byte(match *t {
+            None => 0,
+            Some(CardType::Treasure) => 1,
+            Some(CardType::Action) => 2,
+            Some(CardType::Victory) => 3,
+            Some(CardType::Reaction) => 4,
+            Some(CardType::Curse) => 5,
+        });
+    }
+
+    fn trash_followup(&mut self, f: &Option<TrashFollowup>) {
+        match *f {
+            None => self.byte(0),
+            Some(TrashFollowup::ReplaceByCost(ref t, n, ref d)) => {
+                self.byte(1);
+                self.card_type(t);
+                self.int(n);
+                self.destination(d);
+            }
+        }
+    }
+
+    fn decision_type(&mut self, t: &DecisionType) {
+        match *t {
+            DecisionType::PlayAction => self.byte(0),
+            DecisionType::PlayTreasures => self.byte(1),
+            DecisionType::BuyCard => self.byte(2),
+            DecisionType::GainCard(ref d) => {
+                self.byte(3);
+                self.destination(d);
+            }
+            DecisionType::DiscardCards(None) => self.byte(4),
+            DecisionType::DiscardCards(Some(DiscardEffect::DrawPerDiscard)) => self.byte(5),
+            DecisionType::TrashCards(ref f) => {
+                self.byte(6);
+                self.trash_followup(f);
+            }
+            DecisionType::RevealReaction(a) => {
+                self.byte(7);
+                self.uint(a.0 as u64);
+            }
+        }
+    }
+
+    fn card_action(&mut self, a: &CardAction) {
+        match *a {
+            CardAction::DiscardForEffect(DiscardEffect::DrawPerDiscard) => self.byte(0),
+            CardAction::DrawCards(n) => {
+                self.byte(1);
+                self.int(n);
+            }
+            CardAction::GainCardCostingUpto(n) => {
+                self.byte(2);
+                self.int(n);
+            }
+            CardAction::OpponentsDiscardTo(n) => {
+                self.byte(3);
+                self.int(n);
+            }
+            CardAction::PlusActions(n) => {
+                self.byte(4);
+                self.int(n);
+            }
+            CardAction::PlusBuys(n) => {
+                self.byte(5);
+                self.int(n);
+            }
+            CardAction::PlusCoins(n) => {
+                self.byte(6);
+                self.int(n);
+            }
+            CardAction::TrashCards(ref t, ref f) => {
+                self.byte(7);
+                self.card_type(t);
+                self.trash_followup(f);
+            }
+        }
+    }
+
+    fn effect(&mut self, e: &QueuedEffect) {
+        match *e {
+            QueuedEffect::ActionEffect(p, a, ref action) => {
+                self.byte(0);
+                self.byte(p.0);
+                self.uint(a.0 as u64);
+                self.card_action(action);
+            }
+            QueuedEffect::ReactOption(p, a) => {
+                self.byte(1);
+                self.byte(p.0);
+                self.uint(a.0 as u64);
+            }
+        }
+    }
+
+    fn decision(&mut self, d: &Decision) {
+        self.byte(d.player.0);
+        self.decision_type(&d.decision_type);
+        self.cards(&d.choices);
+        self.uint(d.range.0 as u64);
+        self.uint(d.range.1 as u64);
+    }
+
+    fn player(&mut self, p: &Player) {
+        self.string(&p.name);
+        self.cards(&p.hand);
+        self.cards(&p.discard);
+        self.cards(&p.deck);
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self
+            .bytes
+            .get(self.at)
+            .ok_or("The encoding ends too soon")?;
+        self.at += 1;
+        Ok(b)
+    }
+
+    fn uint(&mut self) -> Result<u64, String> {
+        let mut n = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err("A number is too long".into())
+    }
+
+    fn uint32(&mut self) -> Result<u32, String> {
+        let n = self.uint()?;
+        match n <= u32::MAX as u64 {
+            true => Ok(n as u32),
+            false => Err(format!("{} is out of range", n)),
+        }
+    }
+
+    fn int(&mut self) -> Result<i32, String> {
+        let n = self.uint32()?;
+        Ok((n >> 1) as i32 ^ -((n & 1) as i32))
+    }
+
+    // A length, checked against what's left so a bad one can't allocate
+    // more than the encoding could hold.
+    fn len(&mut self) -> Result<usize, String> {
+        let n = self.uint()?;
+        match n <= (self.bytes.len() - self.at) as u64 {
+            true => Ok(n as usize),
+            false => Err("The encoding ends too soon".into()),
+        }
+    }
+
+    fn card(&mut self) -> Result<CardIdentifier, String> {
+        let n = self.uint()?;
+        match n >= 1 && n <= cards::CARDS.len() as u64 {
+            true => Ok(CardIdentifier(n as u16)),
+            false => Err(format!("Unknown card {}", n)),
+        }
+    }
+
+    fn cards(&mut self) -> Result<Vec<CardIdentifier>, String> {
+        let n = self.len()?;
+        (0..n).map(|_| self.card()).collect()
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let n = self.len()?;
+        let s = &self.bytes[self.at..self.at + n];
+        self.at += n;
+        String::from_utf8(s.to_vec()).map_err(|_| "A name isn't UTF-8".into())
+    }
+
+    fn destination(&mut self) -> Result<GainDestination, String> {
+        match self.byte()? {
+            0 => Ok(GainDestination::GainToHand),
+            1 => Ok(GainDestination::GainToDiscard),
+            b => Err(format!("Unknown gain destination {}", b)),
+        }
+    }
+
+    fn card_type(&mut self) -> Result<Option<CardType>, String> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => Ok(Some(CardType::Treasure)),
+            2 => Ok(Some(CardType::Action)),
+            3 => Ok(Some(CardType::Victory)),
+            4 => Ok(Some(CardType::Reaction)),
+            5 => Ok(Some(CardType::Curse)),
+            b => Err(format!("Unknown card type {}", b)),
+        }
+    }
+
+    fn trash_followup(&mut self) -> Result<Option<TrashFollowup>, String> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => Ok(Some(TrashFollowup::ReplaceByCost(
+                self.card_type()?,
+                self.int()?,
+                self.destination()?,
+            ))),
+            b => Err(format!("Unknown trash followup {}", b)),
+        }
+    }
+
+    fn decision_type(&mut self) -> Result<DecisionType, String> {
+        match self.byte()? {
+            0 => Ok(DecisionType::PlayAction),
+            1 => Ok(DecisionType::PlayTreasures),
+            2 => Ok(DecisionType::BuyCard),
+            3 => Ok(DecisionType::GainCard(self.destination()?)),
+            4 => Ok(DecisionType::DiscardCards(None)),
+            5 => Ok(DecisionType::DiscardCards(Some(
+                DiscardEffect::DrawPerDiscard,
+            ))),
+            6 => Ok(DecisionType::TrashCards(self.trash_followup()?)),
+            7 => Ok(DecisionType::RevealReaction(ActionIdentifier(
+                self.uint32()?,
+            ))),
+            b => Err(format!("Unknown decision type {}", b)),
+        }
+    }
+
+    fn card_action(&mut self) -> Result<CardAction, String> {
+        match self.byte()? {
+            0 => Ok(CardAction::DiscardForEffect(DiscardEffect::DrawPerDiscard)),
+            1 => Ok(CardAction::DrawCards(self.int()?)),
+            2 => Ok(CardAction::GainCardCostingUpto(self.int()?)),
+            3 => Ok(CardAction::OpponentsDiscardTo(self.int()?)),
+            4 => Ok(CardAction::PlusActions(self.int()?)),
+            5 => Ok(CardAction::PlusBuys(self.int()?)),
+            6 => Ok(CardAction::PlusCoins(self.int()?)),
+            7 => Ok(CardAction::TrashCards(
+                self.card_type()?,
+                self.trash_followup()?,
+            )),
+            b => Err(format!("Unknown card action {}", b)),
+        }
+    }
+
+    fn effect(&mut self) -> Result<QueuedEffect, String> {
+        match self.byte()? {
+            0 => Ok(QueuedEffect::ActionEffect(
+                PlayerIdentifier(self.byte()?),
+                ActionIdentifier(self.uint32()?),
+                self.card_action()?,
+            )),
+            1 => Ok(QueuedEffect::ReactOption(
+                PlayerIdentifier(self.byte()?),
+                ActionIdentifier(self.uint32()?),
+            )),
+            b => Err(format!("Unknown effect {}", b)),
+        }
+    }
+
+    fn decision(&mut self) -> Result<Decision, String> {
+        Ok(Decision {
+            player: PlayerIdentifier(self.byte()?),
+            decision_type: self.decision_type()?,
+            choices: self.cards()?,
+            range: (self.uint()? as usize, self.uint()? as usize),
+        })
+    }
+
+    fn player(&mut self, i: usize) -> Result<Player, String> {
+        Ok(Player {
+            identifier: PlayerIdentifier(i as u8),
+            name: self.string()?,
+            hand: self.cards()?,
+            discard: self.cards()?,
+            deck: self.cards()?,
+        })
+    }
+}
+
+fn phase_to_byte(p: &Phase) -> u8 {
+    match *p {
+        Phase::StartTurn => 0,
+        Phase::Action => 1,
+        Phase::BuyPlayTreasure => 2,
+        Phase::BuyPurchaseCard => 3,
+        Phase::Cleanup => 4,
+        Phase::EndTurn => 5,
+    }
+}
+
+fn phase_from_byte(b: u8) -> Result<Phase, String> {
+    match b {
+        0 => Ok(Phase::StartTurn),
+        1 => Ok(Phase::Action),
+        2 => Ok(Phase::BuyPlayTreasure),
+        3 => Ok(Phase::BuyPurchaseCard),
+        4 => Ok(Phase::Cleanup),
+        5 => Ok(Phase::EndTurn),
+        _ => Err(format!("Unknown phase {}", b)),
+    }
+}
+
+// Whether bytes look like an encoded game rather than a save file.
+pub fn is_encoded(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+impl Game {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut e = Encoder {
+            bytes: MAGIC.to_vec(),
+        };
+        e.byte(VERSION);
+
+        e.int(self.turn);
+        e.byte(self.active_player.0);
+        e.byte(phase_to_byte(&self.phase));
+        e.int(self.actions);
+        e.int(self.buys);
+        e.int(self.coins);
+        e.uint(self.current_action_identifier.0 as u64);
+        e.int(self.decisions_resolved);
+
+        let mut piles = self.piles.iter().collect::<Vec<_>>();
+        piles.sort();
+        e.uint(piles.len() as u64);
+        for (c, &n) in piles {
+            e.card(c);
+            e.int(n);
+        }
+        e.cards(&self.play_area);
+        e.cards(&self.trash_pile);
+
+        e.uint(self.players.len() as u64);
+        for p in &self.players {
+            e.player(p);
+        }
+        match self.pending_decision {
+            Some(ref d) => {
+                e.byte(1);
+                e.decision(d);
+            }
+            None => e.byte(0),
+        }
+        e.uint(self.pending_effects.len() as u64);
+        for effect in &self.pending_effects {
+            e.effect(effect);
+        }
+        e.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Game, String> {
+        if !is_encoded(bytes) {
+            return Err("Not an encoded game".into());
+        }
+        let mut d = Decoder {
+            bytes: bytes,
+            at: MAGIC.len(),
+        };
+        let version = d.byte()?;
+        if version != VERSION {
+            return Err(format!(
+                "Encoded with version {}, but only version {} can be read",
+                version, VERSION
+            ));
+        }
+
+        let turn = d.int()?;
+        let active_player = PlayerIdentifier(d.byte()?);
+        let phase = phase_from_byte(d.byte()?)?;
+        let actions = d.int()?;
+        let buys = d.int()?;
+        let coins = d.int()?;
+        let current_action_identifier = ActionIdentifier(d.uint32()?);
+        let decisions_resolved = d.int()?;
+
+        let pile_count = d.len()?;
+        let piles = (0..pile_count)
+            .map(|_| Ok((d.card()?, d.int()?)))
+            .collect::<Result<_, String>>()?;
+        let play_area = d.cards()?;
+        let trash_pile = d.cards()?;
+
+        let player_count = d.len()?;
+        let players = (0..player_count)
+            .map(|i| d.player(i))
+            .collect::<Result<Vec<_>, String>>()?;
+        let pending_decision = match d.byte()? {
+            0 => None,
+            1 => Some(d.decision()?),
+            b => return Err(format!("Unknown pending decision marker {}", b)),
+        };
+        let effect_count = d.len()?;
+        let pending_effects = (0..effect_count)
+            .map(|_| d.effect())
+            .collect::<Result<Vec<_>, String>>()?;
+        if d.at != bytes.len() {
+            return Err("The encoding has bytes left over".into());
+        }
+
+        Ok(Game {
+            turn: turn,
+            active_player: active_player,
+            phase: phase,
+            actions: actions,
+            buys: buys,
+            coins: coins,
+            current_action_identifier: current_action_identifier,
+            piles: piles,
+            play_area: play_area,
+            trash_pile: trash_pile,
+            players: players,
+            pending_decision: pending_decision,
+            pending_effects: pending_effects,
+            decisions_resolved: decisions_resolved,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use game_encoding::*;
+    use util;
+
+    #[test]
+    fn test_game_bytes_round_trip() {
+        let names = vec!["Player 1".into(), "Spieler 2".into()];
+        let mut g = fresh_game(&names);
+        let mut ctx = EvalContext {
+            rng: util::seeded_weak_rng(7),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        g.initialize_game(&mut ctx);
+        g.coins = -3;
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::TrashCards(Some(TrashFollowup::ReplaceByCost(
+                Some(CardType::Treasure),
+                3,
+                GainDestination::GainToHand,
+            ))),
+            choices: vec![SILVER.identifier],
+            range: (0, 1),
+        });
+        g.pending_effects = vec![
+            QueuedEffect::ReactOption(PlayerIdentifier(1), ActionIdentifier(2)),
+            QueuedEffect::ActionEffect(
+                PlayerIdentifier(0),
+                ActionIdentifier(300),
+                CardAction::TrashCards(None, None),
+            ),
+        ];
+
+        let bytes = g.to_bytes();
+        let loaded = Game::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.to_json().to_string(), g.to_json().to_string());
+        assert_eq!(loaded.to_bytes(), bytes);
+        assert!(bytes.len() * 4 < g.to_json().to_string().len());
+    }
+
+    #[test]
+    fn test_bad_encodings() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let bytes = fresh_game(&names).to_bytes();
+        for n in 0..bytes.len() {
+            assert!(Game::from_bytes(&bytes[..n]).is_err());
+        }
+        let mut newer = bytes.clone();
+        newer[MAGIC.len()] = VERSION + 1;
+        assert!(Game::from_bytes(&newer).is_err());
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(Game::from_bytes(&longer).is_err());
+    }
+}
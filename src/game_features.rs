@@ -0,0 +1,117 @@
+use cards;
+use game::{Game, PlayerIdentifier};
+
+// Card identifiers depend on the order cards are first used, so per-card
+// features are ordered by name to keep feature vectors comparable between
+// runs. Maps each card's identifier to its slot among the card features.
+lazy_static! {
+    static ref CARD_FEATURE_SLOTS : Vec<usize> = {
+        let mut by_name = cards::CARDS.iter().collect::<Vec<_>>();
+        by_name.sort_by_key(|c| c.name);
+        let mut slots = vec![0; by_name.len()];
+        for (slot, card) in by_name.iter().enumerate() {
+            slots[(card.identifier.0 - 1) as usize] = slot;
+        }
+        slots
+    };
+}
+
+// Features describing one player's position, for evaluators whose weights
+// are learned rather than hand-tuned. Names identify features in weight
+// files, so existing names shouldn't change meaning.
+lazy_static! {
+    pub static ref FEATURE_NAMES : Vec<String> = {
+        let mut names: Vec<String> = vec![
+            "vp".into(),
+            "vp_lead".into(),
+            "deck_size".into(),
+            "money_density".into(),
+            "money_density_x_provinces_left".into(),
+            "provinces_left".into(),
+            "turn".into(),
+        ];
+        let mut card_names = cards::CARDS.iter().map(|c| c.name).collect::<Vec<_>>();
+        card_names.sort();
+        for name in card_names {
+            names.push(format!("card:{}", name));
+        }
+        names
+    };
+}
+
+pub fn feature_index(name: &str) -> Option<usize> {
+    FEATURE_NAMES.iter().position(|n| n == name)
+}
+
+// Values for each of FEATURE_NAMES, in order.
+// Cards the active player has in play still count towards their deck.
+pub fn player_features(g: &Game, pid: PlayerIdentifier) -> Vec<f32> {
+    let mut all_cards = g.players[pid.0 as usize].all_cards();
+    if pid == g.active_player {
+        all_cards.extend(&g.play_area);
+    }
+    let vp = cards::score_cards(&all_cards) as f32;
+    let best_opponent_vp = g.players
+        .iter()
+        .filter(|p| p.identifier != pid)
+        .map(|p| cards::score_cards(&p.all_cards()) as f32)
+        .fold(std::f32::MIN, f32::max);
+    let money: i32 = all_cards
+        .iter()
+        .map(|c| cards::lookup_card(c).coin_value.unwrap_or(0))
+        .sum();
+    let money_density = money as f32 / all_cards.len().max(1) as f32;
+    let provinces_left = g.piles[&cards::PROVINCE.identifier] as f32;
+
+    let mut features = vec![
+        vp,
+        if best_opponent_vp == std::f32::MIN {
+            0.0
+        } else {
+            vp - best_opponent_vp
+        },
+        all_cards.len() as f32,
+        money_density,
+        money_density * provinces_left,
+        provinces_left,
+        g.turn as f32,
+    ];
+
+    let mut counts = vec![0.0; cards::CARDS.len()];
+    for c in all_cards.iter() {
+        counts[CARD_FEATURE_SLOTS[(c.0 - 1) as usize]] += 1.0;
+    }
+    features.extend(counts);
+    features
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use game_features::*;
+
+    #[test]
+    fn test_starting_features() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let g = fresh_game(&names);
+        let features = player_features(&g, PlayerIdentifier(0));
+        assert_eq!(features.len(), FEATURE_NAMES.len());
+
+        let value = |name: &str| features[feature_index(name).unwrap()];
+        assert_eq!(value("vp"), 3.0);
+        assert_eq!(value("vp_lead"), 0.0);
+        assert_eq!(value("deck_size"), 10.0);
+        assert_eq!(value(&format!("card:{}", COPPER.name)), 7.0);
+        assert_eq!(value("card:Province"), 0.0);
+
+        let mut card_features = FEATURE_NAMES
+            .iter()
+            .filter(|n| n.starts_with("card:"))
+            .collect::<Vec<_>>();
+        let in_order = card_features.clone();
+        card_features.sort();
+        assert_eq!(card_features, in_order);
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use cards::CardIdentifier;
+use game::{DecisionType, Game, Phase, PlayerIdentifier};
+
+// Feature groups hashed into a game's Zobrist key. Per-player zones are
+// offset by the player's index so each player's cards hash differently.
+const ZONE_SCALARS: u64 = 0;
+const ZONE_DECISION: u64 = 1;
+const ZONE_PILES: u64 = 2;
+const ZONE_PLAY_AREA: u64 = 3;
+const ZONE_TRASH: u64 = 4;
+const ZONE_HAND: u64 = 5;
+const ZONE_PLAYER_CARDS: u64 = 6;
+
+const ZOBRIST_SEED: u64 = 0x5eed_d011_a4c0_ffee;
+
+// Stands in for a table of random keys: every (zone, feature, value) triple
+// maps to a fixed pseudo-random 64-bit key via the splitmix64 finalizer.
+fn zobrist_key(zone: u64, feature: u64, value: u64) -> u64 {
+    let mut z = ZOBRIST_SEED ^ (zone << 48) ^ (feature << 24) ^ value;
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn hash_card_counts(zone: u64, counts: &BTreeMap<CardIdentifier, i32>) -> u64 {
+    counts.iter().fold(0, |h, (ci, &count)| {
+        h ^ zobrist_key(zone, ci.0 as u64, count as u64)
+    })
+}
+
+fn count_cards(identifiers: &Vec<CardIdentifier>) -> BTreeMap<CardIdentifier, i32> {
+    let mut counts = BTreeMap::new();
+    for ci in identifiers.iter() {
+        *counts.entry(*ci).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn phase_index(phase: &Phase) -> u64 {
+    match *phase {
+        Phase::StartTurn => 0,
+        Phase::Action => 1,
+        Phase::BuyPlayTreasure => 2,
+        Phase::BuyPurchaseCard => 3,
+        Phase::Cleanup => 4,
+        Phase::EndTurn => 5,
+    }
+}
+
+fn decision_type_index(decision_type: &DecisionType) -> u64 {
+    match *decision_type {
+        DecisionType::PlayAction => 0,
+        DecisionType::PlayTreasures => 1,
+        DecisionType::BuyCard => 2,
+        DecisionType::GainCard(_) => 3,
+        DecisionType::DiscardCards(_) => 4,
+        DecisionType::TrashCards(_) => 5,
+        DecisionType::RevealReaction(_) => 6,
+    }
+}
+
+impl Game {
+    // Zobrist hash of the information every player can see: the supply,
+    // which cards each player owns, the play area and trash, and the turn
+    // state. Hands and deck orders are left out, so every determinization of
+    // a position hashes the same.
+    pub fn public_zobrist_hash(&self) -> u64 {
+        let mut h = zobrist_key(ZONE_SCALARS, 0, self.turn as u64)
+            ^ zobrist_key(ZONE_SCALARS, 1, self.active_player.0 as u64)
+            ^ zobrist_key(ZONE_SCALARS, 2, phase_index(&self.phase))
+            ^ zobrist_key(ZONE_SCALARS, 3, self.actions as u64)
+            ^ zobrist_key(ZONE_SCALARS, 4, self.buys as u64)
+            ^ zobrist_key(ZONE_SCALARS, 5, self.coins as u64);
+
+        if let Some(ref d) = self.pending_decision {
+            h ^= zobrist_key(ZONE_DECISION, 0, d.player.0 as u64)
+                ^ zobrist_key(ZONE_DECISION, 1, decision_type_index(&d.decision_type))
+                ^ zobrist_key(ZONE_DECISION, 2, d.range.0 as u64)
+                ^ zobrist_key(ZONE_DECISION, 3, d.range.1 as u64);
+        }
+
+        let piles = self.piles
+            .iter()
+            .map(|(ci, &count)| (*ci, count))
+            .collect::<BTreeMap<_, _>>();
+        h ^= hash_card_counts(ZONE_PILES, &piles);
+        h ^= hash_card_counts(ZONE_PLAY_AREA, &count_cards(&self.play_area));
+        h ^= hash_card_counts(ZONE_TRASH, &count_cards(&self.trash_pile));
+
+        for (i, p) in self.players.iter().enumerate() {
+            let zone = ZONE_PLAYER_CARDS + i as u64;
+            h ^= hash_card_counts(zone, &count_cards(&p.all_cards()));
+        }
+        h
+    }
+
+    // Hash of the cards in a player's hand, ignoring their order.
+    pub fn hand_hash(&self, pid: PlayerIdentifier) -> u64 {
+        let hand = &self.players[pid.0 as usize].hand;
+        hash_card_counts(ZONE_HAND, &count_cards(hand))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+
+    #[test]
+    fn test_hash_ignores_hidden_order() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut a = fresh_game(&names);
+        let mut b = fresh_game(&names);
+        a.players[1].hand = vec![COPPER.identifier, ESTATE.identifier];
+        b.players[1].hand = vec![ESTATE.identifier, COPPER.identifier];
+        assert_eq!(a.public_zobrist_hash(), b.public_zobrist_hash());
+
+        b.players[1].discard.push(SILVER.identifier);
+        assert!(a.public_zobrist_hash() != b.public_zobrist_hash());
+    }
+}
@@ -0,0 +1,223 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use cards::CardIdentifier;
+use game::{Decider, DecisionError, Game, GameEvent, MoveStats};
+use game_serialization::{cards_from_json, cards_to_json};
+use json::Json;
+
+// A decision made in a logged game: the state it was made in, what was
+// chosen, and the moves the decider weighed, if it searched.
+#[derive(Clone)]
+pub struct LoggedDecision {
+    pub state: Game,
+    pub choice: Vec<CardIdentifier>,
+    pub analysis: Vec<MoveStats>,
+}
+
+// Everything needed to step through a game again. Logs are saved as one
+// line of JSON per game.
+#[derive(Clone, Default)]
+pub struct GameLog {
+    // The game's number in its match, from 1.
+    pub game: u32,
+    pub seed: Option<u32>,
+    // The command that plays the game's match again, if known.
+    pub command: Option<String>,
+    pub players: Vec<String>,
+    pub decisions: Vec<LoggedDecision>,
+    pub final_state: Option<Game>,
+    pub scores: Vec<f32>,
+}
+
+pub fn analysis_to_json(analysis: &Vec<MoveStats>) -> Json {
+    Json::Array(
+        analysis
+            .iter()
+            .map(|m| {
+                Json::object(vec![
+                    ("move", cards_to_json(&m.choice)),
+                    ("visits", m.visits.into()),
+                    ("win_rate", m.win_rate.into()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn analysis_from_json(j: &Json) -> Result<Vec<MoveStats>, String> {
+    j.as_array()
+        .ok_or("Analysis should be a list of moves")?
+        .iter()
+        .map(|m| {
+            let number = |key: &str| match m.get(key) {
+                Some(&Json::Number(n)) => Ok(n),
+                _ => Err(format!("Move field {} should be a number", key)),
+            };
+            Ok(MoveStats {
+                choice: cards_from_json(m.get("move").ok_or("Missing field move")?)?,
+                visits: number("visits")? as i32,
+                win_rate: number("win_rate")? as f32,
+            })
+        })
+        .collect()
+}
+
+fn field<'a>(j: &'a Json, key: &str) -> Result<&'a Json, String> {
+    j.get(key).ok_or(format!("Missing field {}", key))
+}
+
+impl GameLog {
+    // The name of the file the game is logged to on its own, by its number
+    // and seed, such as game-00042-seed-1234.json.
+    pub fn file_name(&self) -> String {
+        match self.seed {
+            Some(seed) => format!("game-{:05}-seed-{}.json", self.game, seed),
+            None => format!("game-{:05}.json", self.game),
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let decisions = self.decisions
+            .iter()
+            .map(|d| {
+                Json::object(vec![
+                    ("state", d.state.to_json()),
+                    ("choice", cards_to_json(&d.choice)),
+                    ("analysis", analysis_to_json(&d.analysis)),
+                ])
+            })
+            .collect();
+        Json::object(vec![
+            ("game", (self.game as i32).into()),
+            ("seed", self.seed.map_or(Json::Null, |s| (s as f64).into())),
+            (
+                "command",
+                self.command.as_ref().map_or(Json::Null, |c| c.as_str().into()),
+            ),
+            (
+                "players",
+                Json::Array(self.players.iter().map(|p| p.as_str().into()).collect()),
+            ),
+            ("decisions", Json::Array(decisions)),
+            (
+                "final_state",
+                self.final_state.as_ref().map_or(Json::Null, |g| g.to_json()),
+            ),
+            (
+                "scores",
+                Json::Array(self.scores.iter().map(|&s| s.into()).collect()),
+            ),
+        ])
+    }
+
+    pub fn from_json(j: &Json) -> Result<GameLog, String> {
+        let array = |key: &str| {
+            field(j, key)?
+                .as_array()
+                .ok_or(format!("Field {} should be a list", key))
+        };
+        let decisions = array("decisions")?
+            .iter()
+            .map(|d| {
+                Ok(LoggedDecision {
+                    state: Game::from_json(field(d, "state")?)?,
+                    choice: cards_from_json(field(d, "choice")?)?,
+                    analysis: analysis_from_json(field(d, "analysis")?)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let final_state = match *field(j, "final_state")? {
+            Json::Null => None,
+            ref g => Some(Game::from_json(g)?),
+        };
+        Ok(GameLog {
+            game: field(j, "game")?.as_i64().ok_or("Field game should be a number")? as u32,
+            seed: field(j, "seed")?.as_i64().map(|s| s as u32),
+            command: j.get("command").and_then(|c| c.as_str()).map(|c| c.to_string()),
+            players: array("players")?
+                .iter()
+                .map(|p| p.as_str().map(|s| s.to_string()).ok_or("Players should be names"))
+                .collect::<Result<Vec<_>, _>>()?,
+            decisions: decisions,
+            final_state: final_state,
+            scores: array("scores")?
+                .iter()
+                .map(|s| match *s {
+                    Json::Number(n) => Ok(n as f32),
+                    _ => Err("Scores should be numbers".to_string()),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+// Logs the decisions of the player it wraps, along with what they weighed.
+// Every player in a game is wrapped with the same log, which ends up
+// holding the whole game.
+pub struct Logged {
+    decider: Box<Decider>,
+    log: Rc<RefCell<GameLog>>,
+}
+
+impl Logged {
+    pub fn new(decider: Box<Decider>, log: Rc<RefCell<GameLog>>) -> Logged {
+        Logged {
+            decider: decider,
+            log: log,
+        }
+    }
+}
+
+impl Decider for Logged {
+    fn description(&self) -> String {
+        self.decider.description()
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        match self.try_make_decision(g) {
+            Ok(choice) => choice,
+            Err(e) => panic!("{} couldn't decide: {}", self.description(), e),
+        }
+    }
+
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        let choice = self.decider.try_make_decision(g)?;
+        self.log.borrow_mut().decisions.push(LoggedDecision {
+            state: g.clone(),
+            choice: choice.clone(),
+            analysis: self.decider.analysis(),
+        });
+        Ok(choice)
+    }
+
+    fn observe_decision(&mut self, g: &Game, choice: &Vec<CardIdentifier>) {
+        self.decider.observe_decision(g, choice);
+    }
+
+    fn ponder(&mut self, g: &Game) {
+        self.decider.ponder(g);
+    }
+
+    fn confidence(&self) -> Option<f32> {
+        self.decider.confidence()
+    }
+
+    fn analysis(&self) -> Vec<MoveStats> {
+        self.decider.analysis()
+    }
+
+    fn clock(&mut self, remaining: Duration, increment: Duration) {
+        self.decider.clock(remaining, increment);
+    }
+
+    fn on_event(&mut self, event: &GameEvent) {
+        self.decider.on_event(event);
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        self.log.borrow_mut().final_state = Some(g.clone());
+        self.decider.game_over(g);
+    }
+}
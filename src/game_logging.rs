@@ -1,21 +1,37 @@
 use itertools::Itertools;
 use cards;
-use game::{EvalContext, Game, EMPTY_PILES_FOR_GAME_END};
+use color;
+use game::{EvalContext, Game, Player, Verbosity, EMPTY_PILES_FOR_GAME_END};
+use game_scoring::CardTally;
+use locale;
 
 impl Game {
+    // The width of the longest player name, to line up tables of players.
+    fn name_width(&self) -> usize {
+        self.players.iter().map(|p| p.name.chars().count()).max().unwrap_or(0)
+    }
+
+    // Pads a player's name before coloring it, since escape codes take up no
+    // room on screen.
+    fn padded_name(&self, player: &Player, width: usize) -> String {
+        let padded = format!("{:<w$}", player.name, w = width);
+        color::player(player.identifier.0 as usize, &padded)
+    }
+
     pub fn print_turn_start_summary(&self, ctx: &mut EvalContext) {
-        if !ctx.debug {
+        if !ctx.logs(Verbosity::Verbose) {
             return;
         }
 
         let ref player = self.players[self.active_player.0 as usize];
-        println!("\n----- Turn {}, {} -----", self.turn, player.name);
+        println!("\n----- Turn {}, {} -----", self.turn, player.colored_name());
 
         let vp_and_turns = self.player_vp_and_turns();
         let player_vp_pairs = self.players.iter().zip(vp_and_turns);
 
+        let width = self.name_width();
         for (player, (vp, _)) in player_vp_pairs {
-            println!("- {}: {} VP", player.name, vp)
+            println!("- {} {:>3} VP", self.padded_name(player, width), vp)
         }
 
         let provinces_left = self.piles[&cards::PROVINCE.identifier];
@@ -28,17 +44,16 @@ impl Game {
         let non_province_pile_counts = self.piles
             .iter()
             .filter(|&(card, _)| *card != cards::PROVINCE.identifier)
-            .sorted_by_key(|&(_, count)| count);
+            .sorted_by_key(|&(card, count)| (count, card));
 
         let cards_to_empty_string = non_province_pile_counts
             .iter()
             .take(EMPTY_PILES_FOR_GAME_END as usize)
             .map(|&(card, count)| {
-                let card = cards::lookup_card(card);
                 if *count == 0 {
-                    format!("**{}**", card.name)
+                    format!("**{}**", color::card(card))
                 } else {
-                    format!("{} ({})", card.name, count)
+                    format!("{} ({})", color::card(card), count)
                 }
             })
             .join(", ");
@@ -53,4 +68,36 @@ impl Game {
         println!("  {}", cards_to_empty_string);
         println!();
     }
+
+    pub fn print_score_report(&self, ctx: &mut EvalContext) {
+        if !ctx.logs(Verbosity::Verbose) {
+            return;
+        }
+
+        fn describe_tally(t: &CardTally) -> String {
+            let name = locale::plural_card_name(&t.card, t.count);
+            format!("{} {}", t.count, color::paint_as(&t.card, &name))
+        }
+
+        println!("The game is over.");
+        let width = self.name_width();
+        for report in self.score_reports() {
+            let name = self.padded_name(&self.players[report.player.0 as usize], width);
+            println!("{} {:>3} VP in {} turns", name, report.total_vp, report.turns);
+
+            let vp_breakdown = report
+                .vp_cards
+                .iter()
+                .map(|t| format!("{} = {}", describe_tally(t), t.vp))
+                .join(", ");
+            if !vp_breakdown.is_empty() {
+                println!("  {}", vp_breakdown);
+            }
+
+            let deck_size: i32 = report.deck.iter().map(|t| t.count).sum();
+            let deck_composition = report.deck.iter().map(describe_tally).join(", ");
+            println!("  Deck ({} cards): {}", deck_size, deck_composition);
+        }
+        println!();
+    }
 }
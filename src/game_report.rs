@@ -0,0 +1,469 @@
+use itertools::Itertools;
+
+use cards;
+use cards::CardIdentifier;
+use game::{DecisionType, Game};
+use game_log::GameLog;
+use locale;
+
+// Buys costing this much or more are key buys, along with victory cards.
+const KEY_COST: i32 = 5;
+
+// How wide the longest bar of the VP graph is, in characters or ems.
+const BAR_WIDTH: i32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    // A page with its style inline, so it can be sent as one file.
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(s: &str) -> Result<ReportFormat, String> {
+        match s {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err(format!(
+                "Unknown report format {}, expected markdown or html",
+                s
+            )),
+        }
+    }
+
+    // The format a report written to path is in, by its extension.
+    pub fn for_path(path: &str) -> ReportFormat {
+        match path.ends_with(".html") || path.ends_with(".htm") {
+            true => ReportFormat::Html,
+            false => ReportFormat::Markdown,
+        }
+    }
+}
+
+// A player's turn: what they did, and what others did during it, such as
+// discarding to an attack.
+struct Turn {
+    turn: i32,
+    player: usize,
+    vp: Vec<i32>,
+    actions: Vec<String>,
+}
+
+struct KeyBuy {
+    turn: i32,
+    player: usize,
+    card: CardIdentifier,
+}
+
+// A logged game, gathered into what its report shows.
+struct Report {
+    title: String,
+    players: Vec<String>,
+    summary: Vec<String>,
+    turns: Vec<Turn>,
+    key_buys: Vec<KeyBuy>,
+    // Each player's VP at the start of each turn number, then at the end.
+    vp_graph: Vec<(String, Vec<i32>)>,
+    decks: Vec<(i32, String)>,
+}
+
+fn vp(g: &Game) -> Vec<i32> {
+    g.players
+        .iter()
+        .map(|p| cards::score_cards(&p.all_cards()))
+        .collect()
+}
+
+fn card_name(c: &CardIdentifier) -> String {
+    locale::card_names(&[*c])
+}
+
+// Cards with repeats counted, in the order they first appear, such as
+// "3 Copper, Silver".
+fn counted_cards(choice: &[CardIdentifier]) -> String {
+    choice
+        .iter()
+        .unique()
+        .map(|c| match choice.iter().filter(|d| *d == c).count() {
+            1 => card_name(c),
+            n => format!("{} {}", n, card_name(c)),
+        })
+        .join(", ")
+}
+
+// A player's cards, most numerous first.
+fn deck_list(all_cards: &[CardIdentifier]) -> String {
+    let mut sorted = all_cards.to_vec();
+    sorted.sort_by_key(|c| {
+        (
+            -(all_cards.iter().filter(|d| *d == c).count() as i32),
+            cards::lookup_card(c).name,
+        )
+    });
+    counted_cards(&sorted)
+}
+
+// What a decision did, as part of a turn's narrative, or None for choosing
+// not to play, discard or reveal anything.
+fn describe(decision_type: &DecisionType, choice: &[CardIdentifier]) -> Option<String> {
+    let verb = match *decision_type {
+        DecisionType::PlayAction | DecisionType::PlayTreasures => "played",
+        DecisionType::BuyCard if choice.is_empty() => return Some("bought nothing".into()),
+        DecisionType::BuyCard => "bought",
+        DecisionType::GainCard(_) => "gained",
+        DecisionType::DiscardCards(_) => "discarded",
+        DecisionType::TrashCards(_) => "trashed",
+        DecisionType::RevealReaction(_) => "revealed",
+    };
+    match choice.is_empty() {
+        true => None,
+        false => Some(format!("{} {}", verb, counted_cards(choice))),
+    }
+}
+
+fn is_key_buy(c: &CardIdentifier) -> bool {
+    let card = cards::lookup_card(c);
+    card.cost >= KEY_COST || card.vp_value.map_or(false, |v| v > 0)
+}
+
+impl Report {
+    fn new(log: &GameLog) -> Report {
+        let last = log
+            .final_state
+            .as_ref()
+            .or_else(|| log.decisions.last().map(|d| &d.state));
+        let players = match (log.players.is_empty(), last) {
+            (false, _) | (true, None) => log.players.clone(),
+            (true, Some(g)) => g.players.iter().map(|p| p.name.clone()).collect(),
+        };
+
+        let mut turns: Vec<Turn> = vec![];
+        let mut key_buys = vec![];
+        let mut vp_graph = vec![];
+        for d in &log.decisions {
+            let g = &d.state;
+            let active = g.active_player.0 as usize;
+            if turns.last().map_or(true, |t| t.turn != g.turn || t.player != active) {
+                if !vp_graph.iter().any(|&(ref turn, _)| *turn == g.turn.to_string()) {
+                    vp_graph.push((g.turn.to_string(), vp(g)));
+                }
+                turns.push(Turn {
+                    turn: g.turn,
+                    player: active,
+                    vp: vp(g),
+                    actions: vec![],
+                });
+            }
+            let decision = match g.pending_decision.as_ref() {
+                Some(decision) => decision,
+                None => continue,
+            };
+            let player = decision.player.0 as usize;
+            if let Some(action) = describe(&decision.decision_type, &d.choice) {
+                let turn = turns.last_mut().unwrap();
+                turn.actions.push(match player == active {
+                    true => action,
+                    false => format!("{} {}", players[player], action),
+                });
+            }
+            if decision.decision_type == DecisionType::BuyCard {
+                key_buys.extend(d.choice.iter().filter(|c| is_key_buy(c)).map(|c| KeyBuy {
+                    turn: g.turn,
+                    player: player,
+                    card: *c,
+                }));
+            }
+        }
+
+        let mut summary = vec![];
+        if !log.scores.is_empty() {
+            summary.push(format!(
+                "Final scores: {}",
+                players
+                    .iter()
+                    .zip(log.scores.iter())
+                    .map(|(p, s)| format!("{} {}", p, s))
+                    .join(", ")
+            ));
+        }
+        let mut decks = vec![];
+        if let Some(g) = last {
+            summary.push(format!(
+                "{} turns, over {} decisions",
+                g.turn,
+                log.decisions.len()
+            ));
+            if log.final_state.is_some() {
+                vp_graph.push(("End".into(), vp(g)));
+            } else {
+                summary.push("The log ends before the game is over".into());
+            }
+            decks = g
+                .players
+                .iter()
+                .map(|p| {
+                    let all_cards = p.all_cards();
+                    (cards::score_cards(&all_cards), deck_list(&all_cards))
+                })
+                .collect();
+        }
+        if let Some(seed) = log.seed {
+            summary.push(format!("Seed {}", seed));
+        }
+        if let Some(ref command) = log.command {
+            summary.push(format!("Played with `{}`", command));
+        }
+
+        Report {
+            title: format!("Game {}: {}", log.game, players.join(" vs ")),
+            players: players,
+            summary: summary,
+            turns: turns,
+            key_buys: key_buys,
+            vp_graph: vp_graph,
+            decks: decks,
+        }
+    }
+
+    // How long a bar for vp is, scaled so the most VP gets BAR_WIDTH.
+    fn bar(&self, vp: i32) -> i32 {
+        let most = self
+            .vp_graph
+            .iter()
+            .flat_map(|&(_, ref vps)| vps.iter().cloned())
+            .max()
+            .unwrap_or(0);
+        match most > 0 {
+            true => (vp.max(0) * BAR_WIDTH + most / 2) / most,
+            false => 0,
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        for line in &self.summary {
+            out += &format!("- {}\n", line);
+        }
+
+        out += "\n## Turns\n\n";
+        for t in &self.turns {
+            let actions = match t.actions.is_empty() {
+                true => "did nothing".to_string(),
+                false => t.actions.join("; "),
+            };
+            out += &format!(
+                "- **Turn {}, {}** ({} VP): {}.\n",
+                t.turn, self.players[t.player], t.vp[t.player], actions
+            );
+        }
+
+        out += "\n## Key buys\n\n";
+        match self.key_buys.is_empty() {
+            true => out += "None.\n",
+            false => {
+                out += "| Turn | Player | Card |\n| ---: | --- | --- |\n";
+                for b in &self.key_buys {
+                    out += &format!(
+                        "| {} | {} | {} |\n",
+                        b.turn,
+                        self.players[b.player],
+                        card_name(&b.card)
+                    );
+                }
+            }
+        }
+
+        out += "\n## Victory points\n\n";
+        out += &format!(
+            "| Turn | {} |\n| ---: |{}\n",
+            self.players.join(" | "),
+            " --- |".repeat(self.players.len())
+        );
+        for &(ref turn, ref vps) in &self.vp_graph {
+            let bars = vps
+                .iter()
+                .map(|&v| match self.bar(v) {
+                    0 => v.to_string(),
+                    n => format!("`{}` {}", "#".repeat(n as usize), v),
+                })
+                .join(" | ");
+            out += &format!("| {} | {} |\n", turn, bars);
+        }
+
+        out += "\n## Final decks\n\n";
+        for (p, &(vp, ref deck)) in self.decks.iter().enumerate() {
+            out += &format!("- **{}** ({} VP): {}\n", self.players[p], vp, deck);
+        }
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\n{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<ul>\n",
+            escape(&self.title),
+            STYLE,
+            escape(&self.title)
+        );
+        for line in &self.summary {
+            out += &format!("<li>{}</li>\n", code_spans(&escape(line)));
+        }
+
+        out += "</ul>\n<h2>Turns</h2>\n<ul>\n";
+        for t in &self.turns {
+            let actions = match t.actions.is_empty() {
+                true => "did nothing".to_string(),
+                false => t.actions.join("; "),
+            };
+            out += &format!(
+                "<li><b>Turn {}, {}</b> ({} VP): {}.</li>\n",
+                t.turn,
+                escape(&self.players[t.player]),
+                t.vp[t.player],
+                escape(&actions)
+            );
+        }
+
+        out += "</ul>\n<h2>Key buys</h2>\n";
+        match self.key_buys.is_empty() {
+            true => out += "<p>None.</p>\n",
+            false => {
+                out += "<table>\n<tr><th>Turn</th><th>Player</th><th>Card</th></tr>\n";
+                for b in &self.key_buys {
+                    out += &format!(
+                        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                        b.turn,
+                        escape(&self.players[b.player]),
+                        escape(&card_name(&b.card))
+                    );
+                }
+                out += "</table>\n";
+            }
+        }
+
+        out += "<h2>Victory points</h2>\n<table>\n<tr><th>Turn</th>";
+        for p in &self.players {
+            out += &format!("<th>{}</th>", escape(p));
+        }
+        out += "</tr>\n";
+        for &(ref turn, ref vps) in &self.vp_graph {
+            out += &format!("<tr><td>{}</td>", turn);
+            for (p, &v) in vps.iter().enumerate() {
+                out += &format!(
+                    "<td><span class=\"bar p{}\" style=\"width: {}em\"></span> {}</td>",
+                    p % 4,
+                    self.bar(v) as f32 / 2.0,
+                    v
+                );
+            }
+            out += "</tr>\n";
+        }
+
+        out += "</table>\n<h2>Final decks</h2>\n<ul>\n";
+        for (p, &(vp, ref deck)) in self.decks.iter().enumerate() {
+            out += &format!(
+                "<li><b>{}</b> ({} VP): {}</li>\n",
+                escape(&self.players[p]),
+                vp,
+                escape(deck)
+            );
+        }
+        out + "</ul>\n</body>\n</html>\n"
+    }
+}
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 50em; margin: 2em auto; }
+table { border-collapse: collapse; }
+th, td { padding: 0.2em 0.8em; text-align: left; }
+tr:nth-child(even) { background: #f2f2f2; }
+.bar { display: inline-block; height: 0.8em; }
+.p0 { background: #3b6fb6; }
+.p1 { background: #d0513a; }
+.p2 { background: #4a9a4a; }
+.p3 { background: #a05db0; }
+";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Text with its Markdown `code` spans as HTML.
+fn code_spans(s: &str) -> String {
+    s.split('`')
+        .enumerate()
+        .map(|(i, part)| match i % 2 {
+            1 => format!("<code>{}</code>", part),
+            _ => part.to_string(),
+        })
+        .join("")
+}
+
+// A report on a logged game for people to read: what happened each turn,
+// the key buys, a graph of each player's VP and the decks they ended with.
+pub fn game_report(log: &GameLog, format: ReportFormat) -> String {
+    let report = Report::new(log);
+    match format {
+        ReportFormat::Markdown => report.to_markdown(),
+        ReportFormat::Html => report.to_html(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use deciders::BigMoney;
+    use game::*;
+    use game_log::*;
+    use game_report::*;
+    use util;
+
+    fn logged_game() -> GameLog {
+        let log = Rc::new(RefCell::new(GameLog::default()));
+        let mut players: Vec<Box<Decider>> = (0..2)
+            .map(|_| Box::new(Logged::new(Box::new(BigMoney), log.clone())) as Box<Decider>)
+            .collect();
+        let mut ctx = EvalContext {
+            rng: util::seeded_weak_rng(1),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        let scores = run_game_with_context(&mut players, &mut ctx);
+        let mut log = log.borrow().clone();
+        log.game = 1;
+        log.players = vec!["Alice".into(), "Bob <2>".into()];
+        log.scores = scores;
+        log
+    }
+
+    #[test]
+    fn test_game_report() {
+        let log = logged_game();
+        let markdown = game_report(&log, ReportFormat::Markdown);
+        assert!(markdown.starts_with("# Game 1: Alice vs Bob <2>\n"));
+        assert!(markdown.contains("- **Turn 1, Alice** (3 VP): played "));
+        assert!(markdown.contains("| Turn | Alice | Bob <2> |"));
+        assert!(markdown.contains("| End | "));
+        assert!(markdown.contains("| Province |"));
+        assert!(markdown.contains("Copper"));
+
+        let html = game_report(&log, ReportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<th>Bob &lt;2&gt;</th>"));
+        assert!(!html.contains("Bob <2>"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_report_format() {
+        assert_eq!(ReportFormat::parse("md"), Ok(ReportFormat::Markdown));
+        assert!(ReportFormat::parse("pdf").is_err());
+        assert_eq!(ReportFormat::for_path("game.html"), ReportFormat::Html);
+        assert_eq!(ReportFormat::for_path("game.md"), ReportFormat::Markdown);
+    }
+}
@@ -1,6 +1,42 @@
+use std::collections::BTreeMap;
+
 use cards;
+use cards::CardIdentifier;
 use game::{Game, Phase, PlayerIdentifier, EMPTY_PILES_FOR_GAME_END};
 
+// Number of copies of a card a player owns, and the VP they are worth.
+#[derive(Clone, Debug)]
+pub struct CardTally {
+    pub card: CardIdentifier,
+    pub count: i32,
+    pub vp: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScoreReport {
+    pub player: PlayerIdentifier,
+    pub total_vp: i32,
+    pub turns: i32,
+    pub vp_cards: Vec<CardTally>,
+    pub deck: Vec<CardTally>,
+}
+
+fn tally_cards(identifiers: &Vec<CardIdentifier>) -> Vec<CardTally> {
+    let mut counts: BTreeMap<CardIdentifier, i32> = BTreeMap::new();
+    for ci in identifiers.iter() {
+        *counts.entry(*ci).or_insert(0) += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|(ci, count)| CardTally {
+            card: ci,
+            count: count,
+            vp: count * cards::lookup_card(&ci).vp_value.unwrap_or(0),
+        })
+        .collect()
+}
+
 impl Game {
     pub fn is_game_over(&self) -> bool {
         if self.phase != Phase::EndTurn {
@@ -62,4 +98,27 @@ impl Game {
             })
             .collect();
     }
+
+    pub fn score_reports(&self) -> Vec<ScoreReport> {
+        let vp_and_turns = self.player_vp_and_turns();
+        return self.players
+            .iter()
+            .zip(vp_and_turns)
+            .map(|(p, (vp, turns))| {
+                let deck = tally_cards(&p.all_cards());
+                let mut vp_cards = deck.iter()
+                    .filter(|t| cards::lookup_card(&t.card).is_victory())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                vp_cards.sort_by_key(|t| -t.vp);
+                ScoreReport {
+                    player: p.identifier,
+                    total_vp: vp,
+                    turns: turns,
+                    vp_cards: vp_cards,
+                    deck: deck,
+                }
+            })
+            .collect();
+    }
 }
@@ -0,0 +1,624 @@
+use cards;
+use cards::{CardAction, CardIdentifier, CardType, DiscardEffect, GainDestination, TrashFollowup};
+use game::{ActionIdentifier, Decision, DecisionType, Game, GameEvent, Phase, Player,
+           PlayerIdentifier, QueuedEffect};
+use json::Json;
+
+// Game states are saved as JSON, naming cards rather than using their
+// identifiers so files stay readable and survive changes to the card list.
+
+pub fn cards_to_json(cs: &Vec<CardIdentifier>) -> Json {
+    Json::Array(cs.iter().map(|c| cards::lookup_card(c).name.into()).collect())
+}
+
+fn field<'a>(j: &'a Json, key: &str) -> Result<&'a Json, String> {
+    j.get(key).ok_or(format!("Missing field {}", key))
+}
+
+fn int_field(j: &Json, key: &str) -> Result<i32, String> {
+    field(j, key)?
+        .as_i64()
+        .map(|n| n as i32)
+        .ok_or(format!("Field {} should be an integer", key))
+}
+
+fn str_field<'a>(j: &'a Json, key: &str) -> Result<&'a str, String> {
+    field(j, key)?
+        .as_str()
+        .ok_or(format!("Field {} should be a string", key))
+}
+
+pub fn card_to_json(c: &CardIdentifier) -> Json {
+    cards::lookup_card(c).name.into()
+}
+
+pub fn card_from_json(j: &Json) -> Result<CardIdentifier, String> {
+    let name = j.as_str().ok_or("Cards should be given by name")?;
+    cards::card_named(name)
+        .map(|c| c.identifier)
+        .ok_or(format!("Unknown card {}", name))
+}
+
+pub fn cards_from_json(j: &Json) -> Result<Vec<CardIdentifier>, String> {
+    j.as_array()
+        .ok_or("Expected a list of cards")?
+        .iter()
+        .map(card_from_json)
+        .collect()
+}
+
+fn cards_field(j: &Json, key: &str) -> Result<Vec<CardIdentifier>, String> {
+    field(j, key)?
+        .as_array()
+        .ok_or(format!("Field {} should be a list of cards", key))?
+        .iter()
+        .map(card_from_json)
+        .collect()
+}
+
+fn phase_from_str(s: &str) -> Result<Phase, String> {
+    match s {
+        "StartTurn" => Ok(Phase::StartTurn),
+        "Action" => Ok(Phase::Action),
+        "BuyPlayTreasure" => Ok(Phase::BuyPlayTreasure),
+        "BuyPurchaseCard" => Ok(Phase::BuyPurchaseCard),
+        "Cleanup" => Ok(Phase::Cleanup),
+        "EndTurn" => Ok(Phase::EndTurn),
+        _ => Err(format!("Unknown phase {}", s)),
+    }
+}
+
+fn destination_to_json(d: &GainDestination) -> Json {
+    match *d {
+        GainDestination::GainToHand => "hand".into(),
+        GainDestination::GainToDiscard => "discard".into(),
+    }
+}
+
+fn destination_from_json(j: &Json) -> Result<GainDestination, String> {
+    match j.as_str() {
+        Some("hand") => Ok(GainDestination::GainToHand),
+        Some("discard") => Ok(GainDestination::GainToDiscard),
+        _ => Err(format!("Unknown gain destination {}", j)),
+    }
+}
+
+fn discard_effect_to_json(e: &Option<DiscardEffect>) -> Json {
+    match *e {
+        Some(DiscardEffect::DrawPerDiscard) => "DrawPerDiscard".into(),
+        None => Json::Null,
+    }
+}
+
+fn discard_effect_from_json(j: &Json) -> Result<Option<DiscardEffect>, String> {
+    match *j {
+        Json::Null => Ok(None),
+        Json::String(ref s) if s == "DrawPerDiscard" => Ok(Some(DiscardEffect::DrawPerDiscard)),
+        _ => Err(format!("Unknown discard effect {}", j)),
+    }
+}
+
+fn card_type_to_json(t: &Option<CardType>) -> Json {
+    match *t {
+        Some(ref t) => format!("{:?}", t).into(),
+        None => Json::Null,
+    }
+}
+
+fn card_type_from_json(j: &Json) -> Result<Option<CardType>, String> {
+    match j.as_str() {
+        _ if j.is_null() => Ok(None),
+        Some("Treasure") => Ok(Some(CardType::Treasure)),
+        Some("Action") => Ok(Some(CardType::Action)),
+        Some("Victory") => Ok(Some(CardType::Victory)),
+        Some("Reaction") => Ok(Some(CardType::Reaction)),
+        Some("Curse") => Ok(Some(CardType::Curse)),
+        _ => Err(format!("Unknown card type {}", j)),
+    }
+}
+
+fn trash_followup_to_json(f: &Option<TrashFollowup>) -> Json {
+    match *f {
+        Some(TrashFollowup::ReplaceByCost(ref card_type, cost_increase, ref destination)) => {
+            Json::object(vec![
+                ("card_type", card_type_to_json(card_type)),
+                ("cost_increase", cost_increase.into()),
+                ("destination", destination_to_json(destination)),
+            ])
+        }
+        None => Json::Null,
+    }
+}
+
+fn trash_followup_from_json(j: &Json) -> Result<Option<TrashFollowup>, String> {
+    if j.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(TrashFollowup::ReplaceByCost(
+        card_type_from_json(field(j, "card_type")?)?,
+        int_field(j, "cost_increase")?,
+        destination_from_json(field(j, "destination")?)?,
+    )))
+}
+
+fn decision_type_to_json(t: &DecisionType) -> Json {
+    match *t {
+        DecisionType::PlayAction => Json::object(vec![("kind", "PlayAction".into())]),
+        DecisionType::PlayTreasures => Json::object(vec![("kind", "PlayTreasures".into())]),
+        DecisionType::BuyCard => Json::object(vec![("kind", "BuyCard".into())]),
+        DecisionType::GainCard(ref d) => Json::object(vec![
+            ("kind", "GainCard".into()),
+            ("destination", destination_to_json(d)),
+        ]),
+        DecisionType::DiscardCards(ref e) => Json::object(vec![
+            ("kind", "DiscardCards".into()),
+            ("effect", discard_effect_to_json(e)),
+        ]),
+        DecisionType::TrashCards(ref f) => Json::object(vec![
+            ("kind", "TrashCards".into()),
+            ("followup", trash_followup_to_json(f)),
+        ]),
+        DecisionType::RevealReaction(a) => Json::object(vec![
+            ("kind", "RevealReaction".into()),
+            ("action", (a.0 as i32).into()),
+        ]),
+    }
+}
+
+fn decision_type_from_json(j: &Json) -> Result<DecisionType, String> {
+    match str_field(j, "kind")? {
+        "PlayAction" => Ok(DecisionType::PlayAction),
+        "PlayTreasures" => Ok(DecisionType::PlayTreasures),
+        "BuyCard" => Ok(DecisionType::BuyCard),
+        "GainCard" => Ok(DecisionType::GainCard(destination_from_json(
+            field(j, "destination")?,
+        )?)),
+        "DiscardCards" => Ok(DecisionType::DiscardCards(discard_effect_from_json(
+            field(j, "effect")?,
+        )?)),
+        "TrashCards" => Ok(DecisionType::TrashCards(trash_followup_from_json(
+            field(j, "followup")?,
+        )?)),
+        "RevealReaction" => Ok(DecisionType::RevealReaction(ActionIdentifier(
+            int_field(j, "action")? as u32,
+        ))),
+        kind => Err(format!("Unknown decision type {}", kind)),
+    }
+}
+
+fn card_action_to_json(a: &CardAction) -> Json {
+    let (kind, amount) = match *a {
+        CardAction::DrawCards(n) => ("DrawCards", n),
+        CardAction::GainCardCostingUpto(n) => ("GainCardCostingUpto", n),
+        CardAction::OpponentsDiscardTo(n) => ("OpponentsDiscardTo", n),
+        CardAction::PlusActions(n) => ("PlusActions", n),
+        CardAction::PlusBuys(n) => ("PlusBuys", n),
+        CardAction::PlusCoins(n) => ("PlusCoins", n),
+        CardAction::DiscardForEffect(ref e) => {
+            return Json::object(vec![
+                ("kind", "DiscardForEffect".into()),
+                ("effect", discard_effect_to_json(&Some(e.clone()))),
+            ])
+        }
+        CardAction::TrashCards(ref t, ref f) => {
+            return Json::object(vec![
+                ("kind", "TrashCards".into()),
+                ("card_type", card_type_to_json(t)),
+                ("followup", trash_followup_to_json(f)),
+            ])
+        }
+    };
+    Json::object(vec![("kind", kind.into()), ("amount", amount.into())])
+}
+
+fn card_action_from_json(j: &Json) -> Result<CardAction, String> {
+    match str_field(j, "kind")? {
+        "DrawCards" => Ok(CardAction::DrawCards(int_field(j, "amount")?)),
+        "GainCardCostingUpto" => Ok(CardAction::GainCardCostingUpto(int_field(j, "amount")?)),
+        "OpponentsDiscardTo" => Ok(CardAction::OpponentsDiscardTo(int_field(j, "amount")?)),
+        "PlusActions" => Ok(CardAction::PlusActions(int_field(j, "amount")?)),
+        "PlusBuys" => Ok(CardAction::PlusBuys(int_field(j, "amount")?)),
+        "PlusCoins" => Ok(CardAction::PlusCoins(int_field(j, "amount")?)),
+        "DiscardForEffect" => match discard_effect_from_json(field(j, "effect")?)? {
+            Some(e) => Ok(CardAction::DiscardForEffect(e)),
+            None => Err("DiscardForEffect needs an effect".into()),
+        },
+        "TrashCards" => Ok(CardAction::TrashCards(
+            card_type_from_json(field(j, "card_type")?)?,
+            trash_followup_from_json(field(j, "followup")?)?,
+        )),
+        kind => Err(format!("Unknown card action {}", kind)),
+    }
+}
+
+fn effect_to_json(e: &QueuedEffect) -> Json {
+    match *e {
+        QueuedEffect::ActionEffect(p, a, ref action) => Json::object(vec![
+            ("kind", "ActionEffect".into()),
+            ("player", (p.0 as i32).into()),
+            ("action", (a.0 as i32).into()),
+            ("effect", card_action_to_json(action)),
+        ]),
+        QueuedEffect::ReactOption(p, a) => Json::object(vec![
+            ("kind", "ReactOption".into()),
+            ("player", (p.0 as i32).into()),
+            ("action", (a.0 as i32).into()),
+        ]),
+    }
+}
+
+fn effect_from_json(j: &Json) -> Result<QueuedEffect, String> {
+    let player = PlayerIdentifier(int_field(j, "player")? as u8);
+    let action = ActionIdentifier(int_field(j, "action")? as u32);
+    match str_field(j, "kind")? {
+        "ActionEffect" => Ok(QueuedEffect::ActionEffect(
+            player,
+            action,
+            card_action_from_json(field(j, "effect")?)?,
+        )),
+        "ReactOption" => Ok(QueuedEffect::ReactOption(player, action)),
+        kind => Err(format!("Unknown queued effect {}", kind)),
+    }
+}
+
+pub fn decision_to_json(d: &Decision) -> Json {
+    Json::object(vec![
+        ("player", (d.player.0 as i32).into()),
+        ("type", decision_type_to_json(&d.decision_type)),
+        ("choices", cards_to_json(&d.choices)),
+        (
+            "range",
+            Json::Array(vec![(d.range.0 as i32).into(), (d.range.1 as i32).into()]),
+        ),
+    ])
+}
+
+pub fn decision_from_json(j: &Json) -> Result<Decision, String> {
+    let range = field(j, "range")?
+        .as_array()
+        .and_then(|r| match (r.get(0), r.get(1)) {
+            (Some(min), Some(max)) => Some((min.as_i64()?, max.as_i64()?)),
+            _ => None,
+        })
+        .ok_or("Field range should be a [min, max] pair")?;
+    Ok(Decision {
+        player: PlayerIdentifier(int_field(j, "player")? as u8),
+        decision_type: decision_type_from_json(field(j, "type")?)?,
+        choices: cards_field(j, "choices")?,
+        range: (range.0 as usize, range.1 as usize),
+    })
+}
+
+pub fn player_to_json(p: &Player) -> Json {
+    Json::object(vec![
+        ("id", (p.identifier.0 as i32).into()),
+        ("name", p.name.clone().into()),
+        ("hand", cards_to_json(&p.hand)),
+        ("deck", cards_to_json(&p.deck)),
+        ("discard", cards_to_json(&p.discard)),
+    ])
+}
+
+// A player as the observer sees them. Their deck, and another player's
+// hand, are only counted, and the cards in them are listed under unseen in
+// sorted order, which is all the observer can tell about them.
+fn player_view_to_json(p: &Player, observer: PlayerIdentifier) -> Json {
+    let mut unseen = p.deck.clone();
+    let hand = match p.identifier == observer {
+        true => cards_to_json(&p.hand),
+        false => {
+            unseen.extend(&p.hand);
+            (p.hand.len() as i32).into()
+        }
+    };
+    unseen.sort();
+    Json::object(vec![
+        ("id", (p.identifier.0 as i32).into()),
+        ("name", p.name.clone().into()),
+        ("hand", hand),
+        ("deck", (p.deck.len() as i32).into()),
+        ("unseen", cards_to_json(&unseen)),
+        ("discard", cards_to_json(&p.discard)),
+    ])
+}
+
+// A hand or deck, either listed or, in a view, counted and dealt from the
+// unseen cards.
+fn hidden_cards_field(
+    j: &Json,
+    key: &str,
+    unseen: &mut Vec<CardIdentifier>,
+) -> Result<Vec<CardIdentifier>, String> {
+    match field(j, key)?.as_i64() {
+        None => cards_field(j, key),
+        Some(n) if n >= 0 && n as usize <= unseen.len() => {
+            let rest = unseen.len() - n as usize;
+            Ok(unseen.split_off(rest))
+        }
+        Some(n) => Err(format!("Field {} counts {} cards but too few are unseen", key, n)),
+    }
+}
+
+fn player_from_json(i: usize, j: &Json) -> Result<Player, String> {
+    let mut unseen = match j.get("unseen") {
+        Some(_) => cards_field(j, "unseen")?,
+        None => vec![],
+    };
+    Ok(Player {
+        identifier: PlayerIdentifier(i as u8),
+        name: str_field(j, "name")?.to_string(),
+        hand: hidden_cards_field(j, "hand", &mut unseen)?,
+        deck: hidden_cards_field(j, "deck", &mut unseen)?,
+        discard: cards_field(j, "discard")?,
+    })
+}
+
+// A player saved on their own rather than in a game, taking their seat
+// from the id field instead of their place in the game's list.
+#[cfg(feature = "serde")]
+pub fn seated_player_from_json(j: &Json) -> Result<Player, String> {
+    player_from_json(int_field(j, "id")? as usize, j)
+}
+
+// Events name their kind in a type field, with the player and, for
+// events with cards, which ones, e.g. {"type": "Bought", "player": 0,
+// "card": "Silver"}.
+pub fn event_to_json(e: &GameEvent) -> Json {
+    let (kind, p, detail) = match *e {
+        GameEvent::TurnStarted(p, turn) => ("TurnStarted", p, Some(("turn", turn.into()))),
+        GameEvent::Shuffled(p) => ("Shuffled", p, None),
+        GameEvent::Drew(p, n) => ("Drew", p, Some(("count", (n as i32).into()))),
+        GameEvent::Played(p, ref cs) => ("Played", p, Some(("cards", cards_to_json(cs)))),
+        GameEvent::Bought(p, ref c) => ("Bought", p, Some(("card", card_to_json(c)))),
+        GameEvent::Gained(p, ref c) => ("Gained", p, Some(("card", card_to_json(c)))),
+        GameEvent::Discarded(p, ref cs) => ("Discarded", p, Some(("cards", cards_to_json(cs)))),
+        GameEvent::CleanedUp(p) => ("CleanedUp", p, None),
+        GameEvent::Trashed(p, ref cs) => ("Trashed", p, Some(("cards", cards_to_json(cs)))),
+        GameEvent::Revealed(p, ref c) => ("Revealed", p, Some(("card", card_to_json(c)))),
+    };
+    let mut fields = vec![("type", kind.into()), ("player", (p.0 as i32).into())];
+    fields.extend(detail);
+    Json::object(fields)
+}
+
+pub fn event_from_json(j: &Json) -> Result<GameEvent, String> {
+    let p = PlayerIdentifier(int_field(j, "player")? as u8);
+    let card = || card_from_json(field(j, "card")?);
+    Ok(match str_field(j, "type")? {
+        "TurnStarted" => GameEvent::TurnStarted(p, int_field(j, "turn")?),
+        "Shuffled" => GameEvent::Shuffled(p),
+        "Drew" => GameEvent::Drew(p, int_field(j, "count")? as usize),
+        "Played" => GameEvent::Played(p, cards_field(j, "cards")?),
+        "Bought" => GameEvent::Bought(p, card()?),
+        "Gained" => GameEvent::Gained(p, card()?),
+        "Discarded" => GameEvent::Discarded(p, cards_field(j, "cards")?),
+        "CleanedUp" => GameEvent::CleanedUp(p),
+        "Trashed" => GameEvent::Trashed(p, cards_field(j, "cards")?),
+        "Revealed" => GameEvent::Revealed(p, card()?),
+        kind => return Err(format!("Unknown event {}", kind)),
+    })
+}
+
+// Where a game is up to: its turn, whether it's over, the pending decision
+// and, once it's over, the scores.
+pub fn status_to_json(g: &Game) -> Json {
+    let over = g.is_game_over();
+    let scores = match over {
+        true => Json::Array(g.player_scores().iter().map(|&(_, s)| s.into()).collect()),
+        false => Json::Null,
+    };
+    Json::object(vec![
+        ("turn", g.turn.into()),
+        ("over", over.into()),
+        (
+            "pending_decision",
+            g.pending_decision.as_ref().map_or(Json::Null, decision_to_json),
+        ),
+        ("scores", scores),
+    ])
+}
+
+impl Game {
+    pub fn to_json(&self) -> Json {
+        let players = self.players.iter().map(player_to_json).collect();
+        self.json_with(players, self.pending_decision.as_ref())
+    }
+
+    // The game as one seat sees it: hidden cards are only counted, and other
+    // players' pending decisions are left out. Game::from_json deals the
+    // counted cards out again, in no particular order.
+    pub fn view_json(&self, observer: PlayerIdentifier) -> Json {
+        let players = self.players
+            .iter()
+            .map(|p| player_view_to_json(p, observer))
+            .collect();
+        let pending = self.pending_decision
+            .as_ref()
+            .filter(|d| d.player == observer);
+        self.json_with(players, pending)
+    }
+
+    fn json_with(&self, players: Vec<Json>, pending_decision: Option<&Decision>) -> Json {
+        let mut piles = self.piles.iter().collect::<Vec<_>>();
+        piles.sort();
+
+        Json::object(vec![
+            ("turn", self.turn.into()),
+            ("active_player", (self.active_player.0 as i32).into()),
+            ("phase", format!("{:?}", self.phase).into()),
+            ("actions", self.actions.into()),
+            ("buys", self.buys.into()),
+            ("coins", self.coins.into()),
+            (
+                "current_action",
+                (self.current_action_identifier.0 as i32).into(),
+            ),
+            (
+                "piles",
+                Json::Object(
+                    piles
+                        .iter()
+                        .map(|&(c, &n)| (cards::lookup_card(c).name.to_string(), n.into()))
+                        .collect(),
+                ),
+            ),
+            ("play_area", cards_to_json(&self.play_area)),
+            ("trash", cards_to_json(&self.trash_pile)),
+            ("players", Json::Array(players)),
+            (
+                "pending_decision",
+                pending_decision.map_or(Json::Null, decision_to_json),
+            ),
+            (
+                "pending_effects",
+                Json::Array(self.pending_effects.iter().map(effect_to_json).collect()),
+            ),
+            ("decisions_resolved", self.decisions_resolved.into()),
+        ])
+    }
+
+    pub fn from_json(j: &Json) -> Result<Game, String> {
+        let piles = match *field(j, "piles")? {
+            Json::Object(ref fields) => fields
+                .iter()
+                .map(|&(ref name, ref count)| {
+                    let card = cards::card_named(name).ok_or(format!("Unknown card {}", name))?;
+                    let count = count
+                        .as_i64()
+                        .ok_or(format!("Pile count for {} should be an integer", name))?;
+                    Ok((card.identifier, count as i32))
+                })
+                .collect::<Result<_, String>>()?,
+            _ => return Err("Field piles should map card names to counts".into()),
+        };
+
+        let players = field(j, "players")?
+            .as_array()
+            .ok_or("Field players should be a list")?
+            .iter()
+            .enumerate()
+            .map(|(i, p)| player_from_json(i, p))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let pending_decision = match j.get("pending_decision") {
+            Some(d) if !d.is_null() => Some(decision_from_json(d)?),
+            _ => None,
+        };
+
+        let pending_effects = match j.get("pending_effects") {
+            Some(e) => e.as_array()
+                .ok_or("Field pending_effects should be a list")?
+                .iter()
+                .map(effect_from_json)
+                .collect::<Result<Vec<_>, String>>()?,
+            None => vec![],
+        };
+
+        Ok(Game {
+            turn: int_field(j, "turn")?,
+            active_player: PlayerIdentifier(int_field(j, "active_player")? as u8),
+            phase: phase_from_str(str_field(j, "phase")?)?,
+            actions: int_field(j, "actions")?,
+            buys: int_field(j, "buys")?,
+            coins: int_field(j, "coins")?,
+            current_action_identifier: ActionIdentifier(
+                j.get("current_action")
+                    .and_then(|a| a.as_i64())
+                    .unwrap_or(0) as u32,
+            ),
+            piles: piles,
+            play_area: cards_field(j, "play_area")?,
+            trash_pile: cards_field(j, "trash")?,
+            players: players,
+            pending_decision: pending_decision,
+            pending_effects: pending_effects,
+            decisions_resolved: j.get("decisions_resolved")
+                .and_then(|d| d.as_i64())
+                .unwrap_or(0) as i32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use game_serialization::*;
+    use json::Json;
+
+    #[test]
+    fn test_game_json_round_trip() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        g.players[0].hand = vec![SILVER.identifier, MINE.identifier];
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::TrashCards(Some(TrashFollowup::ReplaceByCost(
+                Some(CardType::Treasure),
+                3,
+                GainDestination::GainToHand,
+            ))),
+            choices: vec![SILVER.identifier],
+            range: (0, 1),
+        });
+        g.pending_effects = vec![
+            QueuedEffect::ReactOption(PlayerIdentifier(1), ActionIdentifier(2)),
+        ];
+
+        let text = g.to_json().to_string();
+        let loaded = Game::from_json(&Json::parse(&text).unwrap()).unwrap();
+        assert_eq!(loaded.to_json().to_string(), text);
+        assert_eq!(loaded.piles, g.piles);
+        assert_eq!(loaded.players[0].hand, g.players[0].hand);
+    }
+
+    #[test]
+    fn test_view_json_hides_cards() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        g.players[0].hand = vec![MILITIA.identifier, COPPER.identifier];
+        g.players[1].hand = vec![MOAT.identifier, GOLD.identifier];
+        g.players[1].deck = vec![ESTATE.identifier];
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(1),
+            decision_type: DecisionType::PlayAction,
+            choices: vec![MOAT.identifier],
+            range: (0, 1),
+        });
+
+        let view = g.view_json(PlayerIdentifier(0));
+        assert!(view.get("pending_decision").unwrap().is_null());
+        let opponent = &view.get("players").unwrap().as_array().unwrap()[1];
+        assert_eq!(opponent.get("hand").unwrap().as_i64(), Some(2));
+        assert_eq!(opponent.get("deck").unwrap().as_i64(), Some(1));
+        assert_eq!(opponent.get("unseen").unwrap().as_array().unwrap().len(), 3);
+
+        let loaded = Game::from_json(&view).unwrap();
+        assert_eq!(loaded.players[0].hand, g.players[0].hand);
+        assert_eq!(loaded.players[1].hand.len(), 2);
+        assert_eq!(loaded.players[1].deck.len(), 1);
+        assert!(loaded.pending_decision.is_none());
+    }
+
+    #[test]
+    fn test_event_json_round_trip() {
+        let events = vec![
+            GameEvent::TurnStarted(PlayerIdentifier(1), 4),
+            GameEvent::Drew(PlayerIdentifier(0), 5),
+            GameEvent::Bought(PlayerIdentifier(0), GOLD.identifier),
+            GameEvent::Trashed(PlayerIdentifier(1), vec![COPPER.identifier, ESTATE.identifier]),
+            GameEvent::CleanedUp(PlayerIdentifier(0)),
+        ];
+        for e in events {
+            assert_eq!(event_from_json(&event_to_json(&e)), Ok(e));
+        }
+        let bought = Json::parse("{\"type\": \"Bought\", \"player\": 0, \"card\": \"Silver\"}");
+        assert_eq!(
+            event_from_json(&bought.unwrap()),
+            Ok(GameEvent::Bought(PlayerIdentifier(0), SILVER.identifier))
+        );
+        let unknown = Json::parse("{\"type\": \"Won\", \"player\": 0}").unwrap();
+        assert!(event_from_json(&unknown).is_err());
+    }
+}
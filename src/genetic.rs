@@ -0,0 +1,281 @@
+use rand::{Rng, XorShiftRng};
+
+use cards;
+use cards::CardIdentifier;
+use game;
+use priority_strategy::{BuyRule, PriorityDecider, PriorityStrategy};
+
+pub struct GeneticOptions {
+    pub population: usize,
+    pub generations: usize,
+    // Games each candidate plays against the reference, alternating seats.
+    pub games: usize,
+    // The best candidates carried into the next generation unchanged.
+    pub elites: usize,
+}
+
+impl Default for GeneticOptions {
+    fn default() -> GeneticOptions {
+        GeneticOptions {
+            population: 16,
+            generations: 20,
+            games: 50,
+            elites: 2,
+        }
+    }
+}
+
+// Cards a strategy may buy. Nobody wants a Curse.
+fn candidate_cards() -> Vec<CardIdentifier> {
+    cards::CARDS
+        .iter()
+        .filter(|c| c.identifier != cards::CURSE.identifier)
+        .map(|c| c.identifier)
+        .collect()
+}
+
+fn random_rule<R: Rng>(rng: &mut R) -> BuyRule {
+    BuyRule {
+        card: *rng.choose(&candidate_cards()).unwrap(),
+        limit: match rng.gen_weighted_bool(2) {
+            true => Some(rng.gen_range(1, 5)),
+            false => None,
+        },
+    }
+}
+
+// Keeps the first rule or entry for each card, since later ones never apply.
+fn dedup_buys(rules: Vec<BuyRule>) -> Vec<BuyRule> {
+    let mut seen = vec![];
+    let mut out = vec![];
+    for r in rules {
+        if !seen.contains(&r.card) {
+            seen.push(r.card);
+            out.push(r);
+        }
+    }
+    out
+}
+
+fn dedup_cards(cs: Vec<CardIdentifier>) -> Vec<CardIdentifier> {
+    let mut out = vec![];
+    for c in cs {
+        if !out.contains(&c) {
+            out.push(c);
+        }
+    }
+    out
+}
+
+pub fn random_strategy<R: Rng>(rng: &mut R) -> PriorityStrategy {
+    let mut buys = vec![BuyRule {
+        card: cards::PROVINCE.identifier,
+        limit: None,
+    }];
+    for _ in 0..rng.gen_range(1, 6) {
+        buys.push(random_rule(rng));
+    }
+
+    let mut discards = candidate_cards();
+    rng.shuffle(&mut discards);
+    let n = rng.gen_range(0, 5);
+    discards.truncate(n);
+
+    PriorityStrategy {
+        buys: dedup_buys(buys),
+        discards: discards,
+    }
+}
+
+// Applies one random edit: adding, removing, moving or re-limiting a buy
+// rule, or adding, removing or moving a discard.
+pub fn mutate<R: Rng>(s: &PriorityStrategy, rng: &mut R) -> PriorityStrategy {
+    let mut s = s.clone();
+    let buys = s.buys.len();
+    let discards = s.discards.len();
+    match rng.gen_range(0, 7) {
+        0 => s.buys.insert(rng.gen_range(0, buys + 1), random_rule(rng)),
+        1 if buys > 1 => {
+            s.buys.remove(rng.gen_range(0, buys));
+        }
+        2 if buys > 1 => {
+            let i = rng.gen_range(0, buys - 1);
+            s.buys.swap(i, i + 1);
+        }
+        3 if buys > 0 => {
+            let i = rng.gen_range(0, buys);
+            s.buys[i].limit = match s.buys[i].limit {
+                Some(n) if n > 1 && rng.gen() => Some(n - 1),
+                Some(n) if rng.gen() => Some(n + 1),
+                Some(_) => None,
+                None => Some(rng.gen_range(1, 5)),
+            };
+        }
+        4 => {
+            let c = *rng.choose(&candidate_cards()).unwrap();
+            s.discards.insert(rng.gen_range(0, discards + 1), c);
+        }
+        5 if discards > 0 => {
+            s.discards.remove(rng.gen_range(0, discards));
+        }
+        6 if discards > 1 => {
+            let i = rng.gen_range(0, discards - 1);
+            s.discards.swap(i, i + 1);
+        }
+        _ => s.buys.push(random_rule(rng)),
+    }
+    s.buys = dedup_buys(s.buys);
+    s.discards = dedup_cards(s.discards);
+    s
+}
+
+// One-point crossover of each list: a prefix from a, then the rest of b.
+pub fn crossover<R: Rng>(
+    a: &PriorityStrategy,
+    b: &PriorityStrategy,
+    rng: &mut R,
+) -> PriorityStrategy {
+    let i = rng.gen_range(0, a.buys.len() + 1);
+    let j = rng.gen_range(0, b.buys.len() + 1);
+    let mut buys = a.buys[..i].to_vec();
+    buys.extend(b.buys[j..].iter().cloned());
+    if buys.is_empty() {
+        buys = a.buys.clone();
+    }
+
+    let i = rng.gen_range(0, a.discards.len() + 1);
+    let j = rng.gen_range(0, b.discards.len() + 1);
+    let mut discards = a.discards[..i].to_vec();
+    discards.extend(b.discards[j..].iter().cloned());
+
+    PriorityStrategy {
+        buys: dedup_buys(buys),
+        discards: dedup_cards(discards),
+    }
+}
+
+// The share of games a strategy wins against the reference, counting ties
+// as half.
+pub fn fitness(
+    s: &PriorityStrategy,
+    reference: &Fn() -> Box<game::Decider>,
+    games: usize,
+) -> f32 {
+    let mut score = 0.0;
+    for i in 0..games {
+        let candidate = Box::new(PriorityDecider {
+            strategy: s.clone(),
+        }) as Box<game::Decider>;
+        let seat = i % 2;
+        let mut players = match seat {
+            0 => vec![candidate, reference()],
+            _ => vec![reference(), candidate],
+        };
+        score += game::run_game(&mut players, game::Verbosity::Quiet)[seat];
+    }
+    score / games.max(1) as f32
+}
+
+// Picks the fitter of three random candidates.
+fn tournament_select<'a, R: Rng>(
+    scored: &'a [(PriorityStrategy, f32)],
+    rng: &mut R,
+) -> &'a PriorityStrategy {
+    let mut best = rng.choose(scored).unwrap();
+    for _ in 0..2 {
+        let other = rng.choose(scored).unwrap();
+        if other.1 > best.1 {
+            best = other;
+        }
+    }
+    &best.0
+}
+
+// Evolves priority strategies against the reference and returns the best
+// found with its win rate. Each generation keeps the elites, and fills the
+// rest of the population with mutated crossovers of fit parents.
+pub fn evolve(
+    options: &GeneticOptions,
+    reference: &Fn() -> Box<game::Decider>,
+    rng: &mut XorShiftRng,
+    silent: bool,
+) -> (PriorityStrategy, f32) {
+    let mut population = (0..options.population.max(1))
+        .map(|_| random_strategy(rng))
+        .collect::<Vec<_>>();
+    let mut best = (population[0].clone(), 0.0);
+
+    for generation in 0..options.generations {
+        let mut scored = population
+            .into_iter()
+            .map(|s| {
+                let f = fitness(&s, reference, options.games);
+                (s, f)
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        if generation == 0 || scored[0].1 > best.1 {
+            best = scored[0].clone();
+        }
+        if !silent {
+            println!(
+                "Generation {}: best {:.1}% {}",
+                generation + 1,
+                100.0 * scored[0].1,
+                scored[0].0
+            );
+        }
+
+        population = scored
+            .iter()
+            .take(options.elites)
+            .map(|&(ref s, _)| s.clone())
+            .collect();
+        while population.len() < scored.len() {
+            let child = crossover(
+                tournament_select(&scored, rng),
+                tournament_select(&scored, rng),
+                rng,
+            );
+            population.push(mutate(&child, rng));
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use deciders::*;
+    use game;
+    use genetic::*;
+    use util::seeded_weak_rng;
+
+    #[test]
+    fn test_evolve() {
+        let mut rng = seeded_weak_rng(7);
+        for _ in 0..100 {
+            let a = random_strategy(&mut rng);
+            let b = mutate(&crossover(&a, &random_strategy(&mut rng), &mut rng), &mut rng);
+            assert!(!b.buys.is_empty());
+            let mut cards = b.buys.iter().map(|r| r.card).collect::<Vec<_>>();
+            cards.sort();
+            cards.dedup();
+            assert_eq!(cards.len(), b.buys.len());
+            assert!(!b.buys.iter().any(|r| r.card == CURSE.identifier));
+        }
+
+        let options = GeneticOptions {
+            population: 4,
+            generations: 2,
+            games: 2,
+            elites: 1,
+        };
+        let reference = || Box::new(BigMoney) as Box<game::Decider>;
+        let (_, win_rate) = evolve(&options, &reference, &mut rng, true);
+        assert!(win_rate >= 0.0 && win_rate <= 1.0);
+    }
+}
@@ -0,0 +1,300 @@
+use cards;
+use cards::{Card, CardIdentifier, CardType, TrashFollowup};
+use deciders::plus_actions;
+use game::{Decider, Decision, DecisionType, Game, PlayerIdentifier};
+use registry::{PlayerKind, Registry};
+
+// Provinces left at which greening starts: Duchies over Gold, and then
+// Estates over Silver.
+const DUCHY_PROVINCES: i32 = 4;
+const ESTATE_PROVINCES: i32 = 2;
+
+// Cards in the deck per terminal action bought, so they rarely collide.
+const CARDS_PER_TERMINAL: usize = 11;
+
+const MAX_MARKETS: usize = 3;
+
+// A rule-based player stronger than Big Money and far cheaper than a
+// search: it plays villages and cantrips before terminals, and its best
+// terminal last, buys from a menu that greens as the Provinces run out,
+// discards and trashes its worst cards, and only plays Remodel and Mine
+// when they have something worth improving.
+pub struct HeuristicDecider;
+
+fn is_terminal(c: &CardIdentifier) -> bool {
+    cards::lookup_card(c).is_action() && plus_actions(c) == 0
+}
+
+// The player's whole deck, including cards in play on their turn.
+fn owned_cards(g: &Game, player: PlayerIdentifier) -> Vec<CardIdentifier> {
+    let mut owned = g.players[player.0 as usize].all_cards();
+    if player == g.active_player {
+        owned.extend(&g.play_area);
+    }
+    owned
+}
+
+fn count(cards: &Vec<CardIdentifier>, card: &Card) -> usize {
+    cards.iter().filter(|&&c| c == card.identifier).count()
+}
+
+fn provinces_left(g: &Game) -> i32 {
+    g.piles[&cards::PROVINCE.identifier]
+}
+
+// How much a card in hand is worth keeping. Cards below zero are junk.
+fn keep_value(c: &CardIdentifier) -> i32 {
+    let card = cards::lookup_card(c);
+    if card.is_curse() {
+        -2
+    } else if card.is_victory() {
+        -1
+    } else if let Some(coins) = card.coin_value {
+        coins
+    } else if plus_actions(c) > 0 {
+        3
+    } else {
+        2
+    }
+}
+
+// Cards to gain with the given coins, most wanted first.
+fn gain_priorities(g: &Game, player: PlayerIdentifier, coins: i32) -> Vec<&'static Card> {
+    let owned = owned_cards(g, player);
+    let provinces = provinces_left(g);
+    let terminals = owned.iter().filter(|c| is_terminal(c)).count();
+    let villages = count(&owned, &cards::VILLAGE);
+    let opponents_attack = g.players
+        .iter()
+        .filter(|p| p.identifier != player)
+        .any(|p| p.all_cards().contains(&cards::MILITIA.identifier));
+
+    let mut wanted: Vec<&'static Card> = vec![&cards::PROVINCE];
+    if provinces <= DUCHY_PROVINCES {
+        wanted.push(&cards::DUCHY);
+    }
+    if provinces <= ESTATE_PROVINCES {
+        wanted.push(&cards::ESTATE);
+    }
+    wanted.push(&cards::GOLD);
+    if count(&owned, &cards::MARKET) < MAX_MARKETS {
+        wanted.push(&cards::MARKET);
+    }
+    if terminals * CARDS_PER_TERMINAL < owned.len() {
+        if count(&owned, &cards::MILITIA) == 0 {
+            wanted.push(&cards::MILITIA);
+        }
+        wanted.push(&cards::SMITHY);
+    }
+    if terminals > villages + 1 {
+        wanted.push(&cards::VILLAGE);
+    }
+    wanted.push(&cards::SILVER);
+    if opponents_attack && count(&owned, &cards::MOAT) == 0 {
+        wanted.push(&cards::MOAT);
+    }
+    wanted.retain(|c| c.cost <= coins);
+    wanted
+}
+
+impl HeuristicDecider {
+    // How much it wants to play an action, or None if it shouldn't.
+    fn action_priority(g: &Game, d: &Decision, c: &CardIdentifier) -> Option<i32> {
+        let hand = &g.players[d.player.0 as usize].hand;
+        let card = cards::lookup_card(c);
+        let has_junk = hand.iter().filter(|h| keep_value(h) < 0).count() > 0;
+        match card.name {
+            "Village" => Some(100),
+            "Market" => Some(90),
+            "Cellar" if has_junk => Some(80),
+            "Cellar" => None,
+            "Smithy" => Some(50),
+            "Militia" => Some(45),
+            "Moat" => Some(40),
+            "Remodel" => HeuristicDecider::card_to_trash(g, hand, false).map(|_| 35),
+            "Mine" => HeuristicDecider::card_to_trash(g, hand, true).map(|_| 30),
+            "Woodcutter" => Some(25),
+            "Workshop" => Some(20),
+            _ => Some(10),
+        }
+    }
+
+    // A card worth trashing to Mine, or to Remodel, if there's one.
+    fn card_to_trash(g: &Game, hand: &Vec<CardIdentifier>, mining: bool) -> Option<CardIdentifier> {
+        let preferences: Vec<&'static Card> = if mining {
+            vec![&cards::SILVER, &cards::COPPER]
+        } else {
+            let mut p: Vec<&'static Card> = vec![&cards::CURSE];
+            if provinces_left(g) <= DUCHY_PROVINCES {
+                p.push(&cards::GOLD);
+            }
+            if provinces_left(g) > ESTATE_PROVINCES {
+                p.push(&cards::ESTATE);
+            }
+            p
+        };
+        preferences
+            .iter()
+            .find(|c| hand.contains(&c.identifier))
+            .map(|c| c.identifier)
+    }
+
+    fn card_to_gain(g: &Game, player: PlayerIdentifier, choices: &Vec<CardIdentifier>, coins: i32) -> Option<CardIdentifier> {
+        gain_priorities(g, player, coins)
+            .iter()
+            .find(|c| choices.contains(&c.identifier))
+            .map(|c| c.identifier)
+    }
+
+    fn cards_to_discard(d: &Decision) -> Vec<CardIdentifier> {
+        let mut hand = d.choices.clone();
+        hand.sort_by_key(|c| (keep_value(c), cards::lookup_card(c).cost));
+        let junk = hand.iter().filter(|c| keep_value(c) < 0).count();
+        let n = junk.max(d.range.0).min(d.range.1);
+        hand.into_iter().take(n).collect()
+    }
+}
+
+impl Decider for HeuristicDecider {
+    fn description(&self) -> String {
+        return "Heuristic".into();
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("HeuristicDecider::make_decision called without pending decision");
+        match d.decision_type {
+            DecisionType::PlayAction => d.choices
+                .iter()
+                .filter_map(|c| HeuristicDecider::action_priority(g, d, c).map(|p| (p, *c)))
+                .max_by_key(|&(p, _)| p)
+                .map_or(vec![], |(_, c)| vec![c]),
+            DecisionType::PlayTreasures => d.choices.clone(),
+            DecisionType::BuyCard => HeuristicDecider::card_to_gain(g, d.player, &d.choices, g.coins)
+                .into_iter()
+                .collect(),
+            DecisionType::GainCard(_) => {
+                let coins = d.choices
+                    .iter()
+                    .map(|c| cards::lookup_card(c).cost)
+                    .max()
+                    .unwrap_or(0);
+                match HeuristicDecider::card_to_gain(g, d.player, &d.choices, coins) {
+                    Some(c) => vec![c],
+                    // Gains can't be declined, so take the priciest card
+                    // that isn't a Curse.
+                    None => d.choices
+                        .iter()
+                        .filter(|&&c| c != cards::CURSE.identifier)
+                        .max_by_key(|c| cards::lookup_card(c).cost)
+                        .or(d.choices.first())
+                        .cloned()
+                        .into_iter()
+                        .collect(),
+                }
+            }
+            DecisionType::DiscardCards(_) => HeuristicDecider::cards_to_discard(d),
+            DecisionType::TrashCards(ref followup) => {
+                let mining = match *followup {
+                    Some(TrashFollowup::ReplaceByCost(Some(CardType::Treasure), _, _)) => true,
+                    _ => false,
+                };
+                match HeuristicDecider::card_to_trash(g, &d.choices, mining) {
+                    Some(c) => vec![c],
+                    None => {
+                        let mut choices = d.choices.clone();
+                        choices.sort_by_key(|c| (keep_value(c), cards::lookup_card(c).cost));
+                        choices.into_iter().take(d.range.0).collect()
+                    }
+                }
+            }
+            DecisionType::RevealReaction(_) => d.choices.iter().take(1).cloned().collect(),
+        }
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "heuristic",
+        summary: "rule-based, between Big Money and Tactician in strength",
+        example: "heuristic",
+        argument: None,
+        options: &[],
+        factory: |_, _| Ok(Box::new(HeuristicDecider)),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use heuristic_decider::*;
+
+    fn decide(g: &mut Game, decision_type: DecisionType, choices: Vec<CardIdentifier>, range: (usize, usize)) -> Vec<CardIdentifier> {
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: decision_type,
+            choices: choices,
+            range: range,
+        });
+        HeuristicDecider.make_decision(g)
+    }
+
+    #[test]
+    fn test_heuristic_decisions() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+
+        // Villages before terminals, and draw before Militia.
+        g.players[0].hand = vec![SMITHY.identifier, VILLAGE.identifier, MILITIA.identifier];
+        let actions = g.players[0].hand.clone();
+        assert_eq!(
+            decide(&mut g, DecisionType::PlayAction, actions, (0, 1)),
+            vec![VILLAGE.identifier]
+        );
+        let terminals = vec![MILITIA.identifier, SMITHY.identifier];
+        assert_eq!(
+            decide(&mut g, DecisionType::PlayAction, terminals, (0, 1)),
+            vec![SMITHY.identifier]
+        );
+
+        // Remodel is held back without anything to improve.
+        g.players[0].hand = vec![REMODEL.identifier, COPPER.identifier, SILVER.identifier];
+        assert!(decide(&mut g, DecisionType::PlayAction, vec![REMODEL.identifier], (0, 1)).is_empty());
+
+        let affordable = vec![
+            SILVER.identifier,
+            SMITHY.identifier,
+            DUCHY.identifier,
+            GOLD.identifier,
+        ];
+        g.coins = 6;
+        assert_eq!(
+            decide(&mut g, DecisionType::BuyCard, affordable.clone(), (0, 1)),
+            vec![GOLD.identifier]
+        );
+        *g.piles.get_mut(&PROVINCE.identifier).unwrap() = 3;
+        assert_eq!(
+            decide(&mut g, DecisionType::BuyCard, affordable, (0, 1)),
+            vec![DUCHY.identifier]
+        );
+
+        let hand = vec![
+            GOLD.identifier,
+            ESTATE.identifier,
+            COPPER.identifier,
+            SMITHY.identifier,
+            CURSE.identifier,
+        ];
+        assert_eq!(
+            decide(&mut g, DecisionType::DiscardCards(None), hand.clone(), (3, 3)),
+            vec![CURSE.identifier, ESTATE.identifier, COPPER.identifier]
+        );
+        assert_eq!(
+            decide(&mut g, DecisionType::DiscardCards(Some(DiscardEffect::DrawPerDiscard)), hand, (0, 5)),
+            vec![CURSE.identifier, ESTATE.identifier]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use itertools::Itertools;
+use std::io;
+use std::io::{BufRead, Write};
+
+use cards;
+use cards::CardIdentifier;
+use game::{Decider, Decision, DecisionError, DecisionType, Game, GameEvent, PlayerIdentifier};
+use locale;
+use registry::{PlayerKind, Registry};
+
+// Plays by asking a person at the terminal. Only their own hand is shown,
+// along with what every player can see.
+pub struct HumanDecider {
+    input: Box<BufRead>,
+    output: Box<Write>,
+    // Which player we are, learned at our first decision.
+    player: Option<PlayerIdentifier>,
+    // What's happened since we last decided.
+    events: Vec<GameEvent>,
+}
+
+fn card_list(cards: &Vec<CardIdentifier>) -> String {
+    if cards.is_empty() {
+        return locale::text("nothing", &[]);
+    }
+    let mut sorted = cards.clone();
+    sorted.sort();
+    locale::card_names(&sorted)
+}
+
+// Cards with how many of each, e.g. "7 Copper, 3 Estate".
+fn card_counts(cards: &Vec<CardIdentifier>) -> String {
+    let mut counts: Vec<(CardIdentifier, usize)> = vec![];
+    let mut sorted = cards.clone();
+    sorted.sort();
+    for c in sorted {
+        match counts.last_mut() {
+            Some(&mut (last, ref mut n)) if last == c => *n += 1,
+            _ => counts.push((c, 1)),
+        }
+    }
+    counts
+        .iter()
+        .map(|&(c, n)| format!("{} {}", n, locale::card_name(&c)))
+        .join(", ")
+}
+
+fn score_line(name: &str, vp: i32) -> String {
+    locale::text("score", &[("player", name), ("vp", &vp.to_string())])
+}
+
+pub fn describe_decision(g: &Game, d: &Decision) -> String {
+    let (min, max) = d.range;
+    let (min, max) = (min.to_string(), max.to_string());
+    let count = if min == max {
+        min
+    } else if min == "0" {
+        locale::text("up_to", &[("max", &max)])
+    } else {
+        locale::text("between", &[("min", &min), ("max", &max)])
+    };
+    let key = match d.decision_type {
+        DecisionType::PlayAction => "play_action",
+        DecisionType::PlayTreasures => "play_treasures",
+        DecisionType::BuyCard => "buy_card",
+        DecisionType::GainCard(_) => "gain_card",
+        DecisionType::DiscardCards(None) => "discard",
+        DecisionType::DiscardCards(Some(_)) => "discard_to_draw",
+        DecisionType::TrashCards(_) => "trash",
+        DecisionType::RevealReaction(_) => "reveal_reaction",
+    };
+    locale::text(key, &[("count", &count), ("coins", &g.coins.to_string())])
+}
+
+// Picks the cards for a decision from lines of choice numbers or card names
+// separated by spaces or commas. When more than one card may be picked,
+// picks build up over several lines until "done" or a blank line, so they
+// can be reviewed first: "undo" takes back the last pick and "reset" all of
+// them. "all" picks every choice and "none" picks none, and a blank line
+// with nothing picked plays every treasure.
+pub struct Selection<'a> {
+    d: &'a Decision,
+    // Indices of the picked choices, in the order picked.
+    picked: Vec<usize>,
+}
+
+impl<'a> Selection<'a> {
+    pub fn new(d: &'a Decision) -> Selection<'a> {
+        Selection {
+            d: d,
+            picked: vec![],
+        }
+    }
+
+    // Whether picks wait for "done". Single picks are made at once.
+    pub fn confirms(&self) -> bool {
+        self.d.range.1 > 1
+    }
+
+    pub fn cards(&self) -> Vec<CardIdentifier> {
+        self.picked.iter().map(|&i| self.d.choices[i]).collect()
+    }
+
+    fn finish(&mut self) -> Result<Option<Vec<CardIdentifier>>, String> {
+        let (min, max) = self.d.range;
+        let n = self.picked.len();
+        if n < min || n > max {
+            if !self.confirms() {
+                self.picked.clear();
+            }
+            return Err(match min == max {
+                true => format!("Pick exactly {}", min),
+                false => format!("Pick between {} and {}", min, max),
+            });
+        }
+        Ok(Some(self.cards()))
+    }
+
+    // Adds the choices named in a line to the picks, or to none of them if
+    // any are invalid.
+    fn pick(&mut self, line: &str) -> Result<(), String> {
+        let mut picked = self.picked.clone();
+        for word in line.split(|c: char| c == ',' || c.is_whitespace()) {
+            if word.is_empty() {
+                continue;
+            }
+            let i = match word.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= self.d.choices.len() => n - 1,
+                Ok(n) => return Err(format!("There's no choice {}", n)),
+                Err(_) => {
+                    let card = locale::card_named(word).map(|c| c.identifier);
+                    (0..self.d.choices.len())
+                        .find(|i| !picked.contains(i) && card == Some(self.d.choices[*i]))
+                        .ok_or_else(|| format!("No {} to choose", word))?
+                }
+            };
+            if picked.contains(&i) {
+                return Err(format!("Choice {} was already picked", i + 1));
+            }
+            picked.push(i);
+        }
+        if picked.len() > self.d.range.1 {
+            return Err(format!("Pick at most {}", self.d.range.1));
+        }
+        self.picked = picked;
+        Ok(())
+    }
+
+    // Takes a line of input, returning the cards once the selection is
+    // complete.
+    pub fn enter(&mut self, line: &str) -> Result<Option<Vec<CardIdentifier>>, String> {
+        let line = line.trim().to_lowercase();
+        match line.as_str() {
+            "undo" => match self.picked.pop() {
+                Some(_) => Ok(None),
+                None => Err("Nothing to undo".into()),
+            },
+            "reset" => {
+                self.picked.clear();
+                Ok(None)
+            }
+            "none" => {
+                let picked = self.picked.split_off(0);
+                self.finish().map_err(|e| {
+                    self.picked = picked;
+                    e
+                })
+            }
+            "" if self.picked.is_empty() && self.d.decision_type == DecisionType::PlayTreasures => {
+                self.picked = (0..self.d.choices.len()).collect();
+                self.finish()
+            }
+            "" | "done" => self.finish(),
+            "all" => {
+                self.picked.clear();
+                self.pick(&(1..self.d.choices.len() + 1).join(" "))?;
+                match self.confirms() {
+                    true => Ok(None),
+                    false => self.finish(),
+                }
+            }
+            _ => {
+                self.pick(&line)?;
+                match self.confirms() {
+                    true => Ok(None),
+                    false => self.finish(),
+                }
+            }
+        }
+    }
+}
+
+impl HumanDecider {
+    pub fn new() -> HumanDecider {
+        HumanDecider {
+            input: Box::new(io::BufReader::new(io::stdin())),
+            output: Box::new(io::stdout()),
+            player: None,
+            events: vec![],
+        }
+    }
+
+    // Shows what the other players did since we last decided, since game
+    // logs are usually off when playing.
+    fn print_events(&mut self, g: &Game) -> io::Result<()> {
+        for e in self.events.drain(..) {
+            let (player, key, cards) = match e {
+                GameEvent::Played(p, cs) => (p, "played", cs),
+                GameEvent::Bought(p, c) => (p, "bought", vec![c]),
+                GameEvent::Gained(p, c) => (p, "gained", vec![c]),
+                GameEvent::Discarded(p, cs) => (p, "discarded", cs),
+                GameEvent::Trashed(p, cs) => (p, "trashed", cs),
+                GameEvent::Revealed(p, c) => (p, "revealed", vec![c]),
+                _ => continue,
+            };
+            if Some(player) != self.player && !cards.is_empty() {
+                let name = &g.players[player.0 as usize].name;
+                let line = locale::text(key, &[("player", name), ("cards", &card_list(&cards))]);
+                writeln!(self.output, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_board(&mut self, g: &Game, d: &Decision) -> io::Result<()> {
+        let out = &mut self.output;
+        let me = &g.players[d.player.0 as usize];
+        let turn = g.turn.to_string();
+        let header = locale::text("to_decide", &[("turn", &turn), ("player", &me.name)]);
+        writeln!(out, "\n{}", header)?;
+        for (p, (vp, _)) in g.players.iter().zip(g.player_vp_and_turns()) {
+            writeln!(out, "{}", score_line(&p.name, vp))?;
+        }
+
+        let supply = g.piles
+            .iter()
+            .sorted_by_key(|&(c, _)| (cards::lookup_card(c).cost, *c))
+            .into_iter()
+            .map(|(c, n)| {
+                let cost = cards::lookup_card(c).cost;
+                format!("{} ${} ({})", locale::card_name(c), cost, n)
+            })
+            .join(", ");
+        writeln!(out, "{}", locale::text("supply", &[("cards", &supply)]))?;
+        if !g.play_area.is_empty() {
+            let play_area = card_list(&g.play_area);
+            writeln!(out, "{}", locale::text("in_play", &[("cards", &play_area)]))?;
+        }
+        if d.player == g.active_player {
+            let resources = locale::text(
+                "resources",
+                &[
+                    ("actions", &g.actions.to_string()),
+                    ("buys", &g.buys.to_string()),
+                    ("coins", &g.coins.to_string()),
+                ],
+            );
+            writeln!(out, "{}", resources)?;
+        }
+        writeln!(out, "{}", locale::text("hand", &[("cards", &card_list(&me.hand))]))?;
+        let mut all_cards = me.all_cards();
+        if d.player == g.active_player {
+            all_cards.extend(&g.play_area);
+        }
+        writeln!(out, "{}", locale::text("deck", &[("cards", &card_counts(&all_cards))]))?;
+        Ok(())
+    }
+
+    fn prompt(&mut self, g: &Game, d: &Decision) -> io::Result<Vec<CardIdentifier>> {
+        self.print_events(g)?;
+        self.print_board(g, d)?;
+        let hint = match d.decision_type {
+            DecisionType::PlayTreasures => locale::text("enter_plays_all", &[]),
+            _ => String::new(),
+        };
+        writeln!(self.output, "{}{}:", describe_decision(g, d), hint)?;
+        for (i, c) in d.choices.iter().enumerate() {
+            writeln!(self.output, "  {}) {}", i + 1, locale::card_name(c))?;
+        }
+
+        let mut selection = Selection::new(d);
+        if selection.confirms() {
+            writeln!(self.output, "{}", locale::text("pick_help", &[]))?;
+        }
+        loop {
+            write!(self.output, "> ")?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input closed"));
+            }
+            match selection.enter(&line) {
+                Ok(Some(cards)) => return Ok(cards),
+                Ok(None) => {
+                    let picked = card_list(&selection.cards());
+                    writeln!(self.output, "{}", locale::text("picked", &[("cards", &picked)]))?
+                }
+                Err(e) => writeln!(self.output, "{}", e)?,
+            }
+        }
+    }
+}
+
+impl Decider for HumanDecider {
+    fn description(&self) -> String {
+        return "Human".into();
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        match self.try_make_decision(g) {
+            Ok(selection) => selection,
+            Err(e) => panic!("Unable to read a decision: {}", e),
+        }
+    }
+
+    // Closing the input, e.g. with Ctrl-D, forfeits the game.
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("HumanDecider::make_decision called without pending decision");
+        self.player = Some(d.player);
+        self.prompt(g, d)
+            .map_err(|e| DecisionError::Disconnected(e.to_string()))
+    }
+
+    fn on_event(&mut self, event: &GameEvent) {
+        self.events.push(event.clone());
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        let _ = self.print_events(g);
+        let _ = writeln!(self.output, "\n{}", locale::text("game_over", &[]));
+        for (p, (vp, _)) in g.players.iter().zip(g.player_vp_and_turns()) {
+            let _ = writeln!(self.output, "{}", score_line(&p.name, vp));
+        }
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "human",
+        summary: "you, choosing at the terminal",
+        example: "human",
+        argument: None,
+        options: &[],
+        factory: |_, _| Ok(Box::new(HumanDecider::new())),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use human::*;
+
+    fn enter_all(d: &Decision, lines: &[&str]) -> Result<Option<Vec<CardIdentifier>>, String> {
+        let mut selection = Selection::new(d);
+        let mut result = Ok(None);
+        for line in lines {
+            result = selection.enter(line);
+        }
+        result
+    }
+
+    #[test]
+    fn test_selection() {
+        let d = Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::DiscardCards(None),
+            choices: vec![COPPER.identifier, ESTATE.identifier, COPPER.identifier],
+            range: (2, 2),
+        };
+        let coppers = Ok(Some(vec![COPPER.identifier, COPPER.identifier]));
+        assert!(enter_all(&d, &["1 3", "done"]) == coppers);
+        assert!(enter_all(&d, &["copper, Copper", ""]) == coppers);
+        assert!(enter_all(&d, &["1", "3", "done"]) == coppers);
+        assert!(enter_all(&d, &["1 3"]) == Ok(None));
+        assert!(enter_all(&d, &["1 2", "undo", "3", "done"]) == coppers);
+        assert!(enter_all(&d, &["2", "reset", "copper", "copper", "done"]) == coppers);
+        assert!(enter_all(&d, &["1", "1"]).is_err());
+        assert!(enter_all(&d, &["2", "done"]).is_err());
+        assert!(enter_all(&d, &["4 1"]).is_err());
+        assert!(enter_all(&d, &["gold estate"]).is_err());
+        assert!(enter_all(&d, &["all"]).is_err());
+        assert!(enter_all(&d, &["undo"]).is_err());
+
+        // Invalid lines leave the picks as they were.
+        assert!(enter_all(&d, &["1", "2 2", "3", "done"]) == coppers);
+
+        let treasures = Decision {
+            decision_type: DecisionType::PlayTreasures,
+            range: (0, 3),
+            ..d
+        };
+        assert_eq!(enter_all(&treasures, &["\n"]).unwrap().unwrap().len(), 3);
+        assert!(enter_all(&treasures, &["none"]) == Ok(Some(vec![])));
+        assert!(enter_all(&treasures, &["2", "\n"]) == Ok(Some(vec![ESTATE.identifier])));
+
+        // A single pick is made at once.
+        let buy = Decision {
+            decision_type: DecisionType::BuyCard,
+            range: (0, 1),
+            ..treasures
+        };
+        assert!(enter_all(&buy, &["estate"]) == Ok(Some(vec![ESTATE.identifier])));
+        assert!(enter_all(&buy, &[""]) == Ok(Some(vec![])));
+    }
+}
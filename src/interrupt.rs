@@ -0,0 +1,76 @@
+#[cfg(unix)]
+use libc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set by Ctrl-C, and cleared by whatever acts on it, such as a search
+// stopping early with its best move so far.
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// While a match of several games is running, the first Ctrl-C asks it to
+// stop once the games being played are over, and the second aborts them.
+static IN_MATCH: AtomicBool = AtomicBool::new(false);
+pub static STOP_MATCH: AtomicBool = AtomicBool::new(false);
+pub static ABORT_GAME: AtomicBool = AtomicBool::new(false);
+
+// Only async-signal-safe calls can be made from the handler, so messages
+// are written straight to stderr.
+#[cfg(unix)]
+fn write_stderr(message: &str) {
+    unsafe {
+        libc::write(2, message.as_ptr() as *const libc::c_void, message.len());
+    }
+}
+
+// A second Ctrl-C before the first was acted on quits, so the program can
+// always be stopped. During a match, it takes a third.
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    if IN_MATCH.load(Ordering::SeqCst) {
+        if !STOP_MATCH.swap(true, Ordering::SeqCst) {
+            write_stderr("\nStopping after the games being played. Ctrl-C again to abort them.\n");
+            return;
+        }
+        if !ABORT_GAME.swap(true, Ordering::SeqCst) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+            write_stderr("\nAborting the games being played.\n");
+            return;
+        }
+        unsafe {
+            libc::_exit(130);
+        }
+    }
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        unsafe {
+            libc::_exit(130);
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn install_handler() {
+    unsafe {
+        let handler: extern "C" fn(libc::c_int) = on_interrupt;
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_handler() {}
+
+// Treats Ctrl-C as a request to stop the match, until end_match.
+pub fn begin_match() {
+    install_handler();
+    STOP_MATCH.store(false, Ordering::SeqCst);
+    ABORT_GAME.store(false, Ordering::SeqCst);
+    IN_MATCH.store(true, Ordering::SeqCst);
+}
+
+pub fn end_match() {
+    IN_MATCH.store(false, Ordering::SeqCst);
+    ABORT_GAME.store(false, Ordering::SeqCst);
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+pub fn stop_requested() -> bool {
+    STOP_MATCH.load(Ordering::SeqCst)
+}
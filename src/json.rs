@@ -0,0 +1,395 @@
+use std;
+use std::fmt;
+
+// How deeply arrays and objects may nest. Documents come from clients over
+// the network, and a body of nothing but [ would otherwise overflow the
+// stack.
+pub const MAX_DEPTH: usize = 128;
+
+// A minimal JSON document model, enough to save and load game states without
+// pulling in a serialization framework. Object keys keep their order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(s: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref fields) => fields.iter().find(|&&(ref k, _)| k == key).map(|f| &f.1),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Json::Number(n) if n.fract() == 0.0 => Some(n as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match *self {
+            Json::Array(ref a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Json::Null
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<i32> for Json {
+    fn from(n: i32) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<f32> for Json {
+    fn from(n: f32) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Json {
+        Json::Number(n)
+    }
+}
+
+impl<'a> From<&'a str> for Json {
+    fn from(s: &'a str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            // JSON has no representation for NaN or infinities.
+            Json::Number(n) if !n.is_finite() => write!(f, "null"),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(ref s) => write_string(f, s),
+            Json::Array(ref values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (i, &(ref k, ref v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // How many arrays and objects enclose the value being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid JSON at character {}: {}", self.pos, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn consume_literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        for c in literal.chars() {
+            if self.peek() != Some(c) {
+                return Err(self.error(&format!("expected {}", literal)));
+            }
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.consume_literal("null", Json::Null),
+            Some('t') => self.consume_literal("true", Json::Bool(true)),
+            Some('f') => self.consume_literal("false", Json::Bool(false)),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('[') => self.nested(Parser::parse_array),
+            Some('{') => self.nested(Parser::parse_object),
+            Some(c) if c == '-' || c.is_digit(10) => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Parser) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_digit(10) || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| self.error(&format!("invalid number {}", text)))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some(e) => e,
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        '"' => s.push('"'),
+                        '\\' => s.push('\\'),
+                        '/' => s.push('/'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let mut code = self.parse_hex4()?;
+                            // Characters outside the Basic Multilingual Plane,
+                            // such as emoji, are escaped as surrogate pairs.
+                            let rest = &self.chars[self.pos..];
+                            if code >= 0xd800 && code < 0xdc00 && rest.starts_with(&['\\', 'u']) {
+                                let saved = self.pos;
+                                self.pos += 2;
+                                match self.parse_hex4()? {
+                                    low if low >= 0xdc00 && low < 0xe000 => {
+                                        code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                                    }
+                                    _ => self.pos = saved,
+                                }
+                            }
+                            s.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    // The four hex digits of a \u escape.
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        if self.pos + 4 > self.chars.len() {
+            return Err(self.error("truncated unicode escape"));
+        }
+        let hex: String = self.chars[self.pos..self.pos + 4].iter().collect();
+        self.pos += 4;
+        u32::from_str_radix(&hex, 16).map_err(|_| self.error("invalid unicode escape"))
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            fields.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use json::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let doc = Json::object(vec![
+            ("name", "Tactician \"TT\"".into()),
+            ("turn", 3.into()),
+            ("hand", Json::Array(vec!["Copper".into(), "Estate".into()])),
+            ("pending", Json::Null),
+            ("over", false.into()),
+        ]);
+        let text = doc.to_string();
+        assert_eq!(Json::parse(&text), Ok(doc.clone()));
+        assert_eq!(doc.get("turn").and_then(|t| t.as_i64()), Some(3));
+
+        let spaced = Json::parse(" { \"a\" : [ 1.5 , -2e1 ] } ").unwrap();
+        assert_eq!(
+            spaced.get("a"),
+            Some(&Json::Array(vec![Json::Number(1.5), Json::Number(-20.0)]))
+        );
+        assert!(Json::parse("{\"a\": 1,}").is_err());
+
+        let names = Json::parse("[\"Holzf\\u00e4ller\", \"\\ud83c\\udccf\", \"Schmiede\"]").unwrap();
+        let names = names.as_array().unwrap();
+        assert_eq!(names[0].as_str(), Some("Holzfäller"));
+        assert_eq!(names[1].as_str(), Some("\u{1f0cf}"));
+        let text = Json::Array(names.clone()).to_string();
+        assert_eq!(Json::parse(&text).unwrap().as_array(), Some(names));
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(Json::parse(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(Json::parse(&"[{\"a\":".repeat(1 << 20)).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+// Tactician as a library, for programs such as GUIs and research code that
+// play Dominion with its deciders. The types re-exported here are its
+// public API, which keeps working across releases with the same minor
+// version: games and their decisions, the Decider trait players implement,
+// the cards, the registry of standard players, Tactician's search and a
+// runner for matches.
+//
+// Everything else is internal, including the tactician command line, which
+// the binary runs through run_command_line.
+
+// pyo3's macros name ::core, which the 2015 edition only has if declared.
+#[cfg(feature = "python")]
+extern crate core;
+#[cfg(feature = "websocket")]
+extern crate base64;
+extern crate getopts;
+extern crate itertools;
+#[macro_use]
+extern crate lazy_static;
+extern crate libc;
+extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+#[cfg(feature = "wasm")]
+extern crate js_sys;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "sqlite")]
+extern crate rusqlite;
+#[cfg(feature = "websocket")]
+extern crate sha1;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+pub use cards::{Card, CardIdentifier, CardRegistry};
+pub use game::{fresh_game, fresh_game_with_kingdom, Decider, Decision, DecisionError,
+               DecisionType, EvalContext, Game, GameEvent, GameOutcome, MoveStats, Player,
+               PlayerIdentifier, Rules, Verbosity};
+pub use match_runner::{game_context, MatchRunner};
+pub use match_stats::MatchStats;
+pub use process_decider::check_choice;
+pub use registry::Registry;
+pub use search_decider::SearchDecider;
+pub use tree_search::SearchOptions;
+
+// Runs the tactician command line with the arguments it was started with.
+pub fn run_command_line(args: Vec<String>) {
+    cli::run(args)
+}
+
+mod alpha_beta;
+mod bench;
+mod buy_policy;
+mod cards;
+mod cli;
+mod color;
+mod deciders;
+#[cfg(any(feature = "wasm", feature = "python", feature = "ffi"))]
+mod embedded_game;
+mod endgame_search;
+mod ensemble;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod fuzz;
+mod game;
+mod game_determinization;
+mod game_encoding;
+mod game_features;
+mod game_hashing;
+mod game_log;
+mod game_logging;
+mod game_report;
+mod game_scoring;
+mod game_serialization;
+mod genetic;
+mod heuristic_decider;
+mod human;
+mod interrupt;
+mod json;
+mod linear_evaluator;
+mod log_import;
+mod locale;
+mod match_config;
+mod match_history;
+mod match_runner;
+mod match_stats;
+mod nim;
+mod opening_book;
+mod priority_strategy;
+mod process_decider;
+#[cfg(feature = "python")]
+mod python;
+mod progress;
+mod registry;
+mod remote_play;
+mod repl;
+mod replay;
+mod results_db;
+mod results_output;
+mod rest_server;
+mod rpc_server;
+mod search_decider;
+mod self_play;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod spectator;
+mod sprt;
+mod strategy_file;
+mod text_protocol;
+mod time_control;
+mod toml;
+mod tournament;
+mod tree_search;
+mod tree_search_logging;
+mod tuning;
+mod util;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "websocket")]
+mod websocket;
+#[cfg(feature = "websocket")]
+mod websocket_server;
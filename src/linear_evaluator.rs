@@ -0,0 +1,87 @@
+use std::fs;
+
+use game::{Game, PlayerIdentifier};
+use game_features::{feature_index, player_features, FEATURE_NAMES};
+use tree_search::{Evaluator, Rewards, SearchableState};
+
+// Scores each player as a weighted sum of their features, then converts the
+// scores to win probabilities with a softmax. Weights are trained outside
+// of Tactician and loaded from a text file with one "<feature> <weight>"
+// pair per line. Blank lines and lines starting with '#' are ignored, and
+// features without a weight count for nothing.
+pub struct LinearEvaluator {
+    pub weights: Vec<f32>,
+}
+
+impl LinearEvaluator {
+    pub fn parse(contents: &str) -> Result<LinearEvaluator, String> {
+        let mut weights = vec![0.0; FEATURE_NAMES.len()];
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            if parts.len() != 2 {
+                return Err(format!("Line {}: expected a feature and a weight", i + 1));
+            }
+            let index = feature_index(parts[0])
+                .ok_or(format!("Line {}: unknown feature {}", i + 1, parts[0]))?;
+            weights[index] = parts[1]
+                .parse::<f32>()
+                .map_err(|_| format!("Line {}: invalid weight {}", i + 1, parts[1]))?;
+        }
+        Ok(LinearEvaluator { weights: weights })
+    }
+
+    pub fn load(path: &str) -> Result<LinearEvaluator, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        LinearEvaluator::parse(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn player_score(&self, g: &Game, pid: PlayerIdentifier) -> f32 {
+        player_features(g, pid)
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(f, w)| f * w)
+            .sum()
+    }
+}
+
+impl Evaluator<Game> for LinearEvaluator {
+    fn evaluate(&self, g: &Game) -> Rewards<PlayerIdentifier> {
+        let scores = g.all_players()
+            .into_iter()
+            .map(|pid| (pid, self.player_score(g, pid)))
+            .collect::<Vec<_>>();
+        Rewards::from_scores(scores, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use linear_evaluator::*;
+
+    #[test]
+    fn test_linear_evaluator() {
+        let weights = "# VP matter most\nvp 0.5\n\ncard:Gold 0.25\n";
+        let evaluator = LinearEvaluator::parse(weights).unwrap();
+
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        let even = evaluator.evaluate(&g);
+        assert_eq!(even.for_player(&PlayerIdentifier(0)), 0.5);
+
+        g.players[0].deck.push(GOLD.identifier);
+        let ahead = evaluator.evaluate(&g);
+        assert!(ahead.for_player(&PlayerIdentifier(0)) > 0.5);
+
+        assert!(LinearEvaluator::parse("vp").is_err());
+        assert!(LinearEvaluator::parse("luck 1.0").is_err());
+    }
+}
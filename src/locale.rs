@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+use cards;
+use cards::{Card, CardIdentifier};
+use json::Json;
+use toml;
+
+// The locales built in, by their codes. Others can be loaded from files
+// laid out the same way.
+const BUILT_IN: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.toml")),
+    ("de", include_str!("../locales/de.toml")),
+];
+
+// The words shown to people for a language: card names, their plurals, and
+// the prompts and event lines, by key. Names are keyed by the cards' English
+// names, which files, specs and logs keep using whatever the language.
+#[derive(Clone, Debug, Default)]
+pub struct Locale {
+    pub code: String,
+    cards: HashMap<String, String>,
+    plurals: HashMap<String, String>,
+    messages: HashMap<String, String>,
+}
+
+lazy_static! {
+    static ref ENGLISH: Locale = Locale::load("en").unwrap();
+    static ref CURRENT: RwLock<Locale> = RwLock::new(ENGLISH.clone());
+}
+
+fn strings(doc: &Json, table: &str) -> Result<HashMap<String, String>, String> {
+    match doc.get(table) {
+        Some(&Json::Object(ref fields)) => fields
+            .iter()
+            .map(|&(ref key, ref value)| match value.as_str() {
+                Some(s) => Ok((key.clone(), s.to_string())),
+                None => Err(format!("{}.{} should be a string", table, key)),
+            })
+            .collect(),
+        Some(_) => Err(format!("{} should be a table", table)),
+        None => Ok(HashMap::new()),
+    }
+}
+
+impl Locale {
+    // Parses a locale file: [cards] and [plurals] tables of names keyed by
+    // English card names, and a [messages] table of prompts keyed by what
+    // they're for.
+    pub fn parse(code: &str, text: &str) -> Result<Locale, String> {
+        let doc = toml::parse(text)?;
+        let locale = Locale {
+            code: code.to_string(),
+            cards: strings(&doc, "cards")?,
+            plurals: strings(&doc, "plurals")?,
+            messages: strings(&doc, "messages")?,
+        };
+        for name in locale.cards.keys().chain(locale.plurals.keys()) {
+            if cards::card_named(name).is_none() {
+                return Err(format!("Unknown card {}", name));
+            }
+        }
+        Ok(locale)
+    }
+
+    // Loads a built-in locale by its code, such as de, or a locale file.
+    pub fn load(name: &str) -> Result<Locale, String> {
+        if let Some(&(code, text)) = BUILT_IN.iter().find(|&&(code, _)| code == name) {
+            return Locale::parse(code, text);
+        }
+        if !Path::new(name).is_file() {
+            let codes = BUILT_IN.iter().map(|&(code, _)| code).collect::<Vec<_>>();
+            return Err(format!(
+                "Unknown locale {}, expected one of {} or a locale file",
+                name,
+                codes.join(", ")
+            ));
+        }
+        let text = fs::read_to_string(name).map_err(|e| format!("Unable to read {}: {}", name, e))?;
+        Locale::parse(name, &text).map_err(|e| format!("{}: {}", name, e))
+    }
+
+    pub fn card_name(&self, ci: &CardIdentifier) -> String {
+        let name = cards::lookup_card(ci).name;
+        match self.cards.get(name) {
+            Some(local) => local.clone(),
+            None => name.to_string(),
+        }
+    }
+
+    // A card's name for count of them, e.g. "Duchies". Without a plural
+    // given, languages other than English use the name as it is.
+    pub fn plural_card_name(&self, ci: &CardIdentifier, count: i32) -> String {
+        let name = cards::lookup_card(ci).name;
+        match self.plurals.get(name) {
+            Some(plural) if count != 1 => plural.clone(),
+            _ if self.code == "en" => cards::pluralize_name(name, count),
+            _ => self.card_name(ci),
+        }
+    }
+
+    // The card with a name in this language or in English, in any case.
+    pub fn card_named(&self, name: &str) -> Option<&'static Card> {
+        let lower = name.to_lowercase();
+        cards::CARDS
+            .iter()
+            .find(|c| self.card_name(&c.identifier).to_lowercase() == lower)
+            .cloned()
+            .or_else(|| cards::card_named(name))
+    }
+
+    // The message for a key with the words in braces filled in, in English
+    // if this language doesn't have it.
+    pub fn text(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let template = self.messages
+            .get(key)
+            .or_else(|| ENGLISH.messages.get(key))
+            .map_or(key, |t| t.as_str());
+        args.iter().fold(template.to_string(), |s, &(name, value)| {
+            s.replace(&format!("{{{}}}", name), value)
+        })
+    }
+}
+
+// Shows games in a language from now on.
+pub fn set(locale: Locale) {
+    *CURRENT.write().unwrap() = locale;
+}
+
+pub fn card_name(ci: &CardIdentifier) -> String {
+    CURRENT.read().unwrap().card_name(ci)
+}
+
+// Card names separated by commas, as cards::card_names writes them.
+pub fn card_names(identifiers: &[CardIdentifier]) -> String {
+    let locale = CURRENT.read().unwrap();
+    identifiers
+        .iter()
+        .map(|c| locale.card_name(c))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn plural_card_name(ci: &CardIdentifier, count: i32) -> String {
+    CURRENT.read().unwrap().plural_card_name(ci, count)
+}
+
+pub fn card_named(name: &str) -> Option<&'static Card> {
+    CURRENT.read().unwrap().card_named(name)
+}
+
+pub fn text(key: &str, args: &[(&str, &str)]) -> String {
+    CURRENT.read().unwrap().text(key, args)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use locale::*;
+
+    #[test]
+    fn test_locale() {
+        let de = Locale::load("de").unwrap();
+        assert_eq!(de.card_name(&WOODCUTTER.identifier), "Holzfäller");
+        assert_eq!(de.plural_card_name(&DUCHY.identifier, 2), "Herzogtümer");
+        assert_eq!(de.plural_card_name(&COPPER.identifier, 7), "Kupfer");
+        assert_eq!(ENGLISH.plural_card_name(&DUCHY.identifier, 2), "Duchies");
+        assert_eq!(de.card_named("HOLZFÄLLER").unwrap().name, "Woodcutter");
+        assert_eq!(de.card_named("cellar").unwrap().name, "Cellar");
+        assert_eq!(
+            de.text("discarded", &[("player", "Alice"), ("cards", "Kupfer")]),
+            "Alice legt Kupfer ab"
+        );
+
+        // Every card and message has a German version.
+        for c in CARDS.iter() {
+            assert!(de.cards.contains_key(c.name), "{}", c.name);
+        }
+        for key in ENGLISH.messages.keys() {
+            assert!(de.messages.contains_key(key), "{}", key);
+        }
+        assert!(Locale::load("xx").is_err());
+    }
+}
@@ -0,0 +1,943 @@
+use cards;
+use cards::{CardIdentifier, GainDestination};
+use game::{
+    fresh_game_with_kingdom, DecisionType, EvalContext, Game, MoveStats, Player, PlayerIdentifier,
+    Verbosity,
+};
+use game_log::{GameLog, LoggedDecision};
+use process_decider::check_choice;
+use search_decider::SearchDecider;
+use tree_search::{SearchBudget, SearchOptions, SearchableState};
+use util;
+
+// What a line of a log says a player did with cards.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Verb {
+    Plays,
+    Buys,
+    Gains,
+    Discards,
+    Trashes,
+    Reveals,
+}
+
+// The verbs logs use, longest first where one starts another.
+const VERBS: &[(&str, Verb)] = &[
+    ("plays", Verb::Plays),
+    ("buys and gains", Verb::Buys),
+    ("buys", Verb::Buys),
+    ("gains", Verb::Gains),
+    ("discards", Verb::Discards),
+    ("trashes", Verb::Trashes),
+    ("reveals", Verb::Reveals),
+];
+
+// Words after the cards that say where they went, such as "into their hand".
+const PLACES: &[&str] = &[" into ", " onto ", " from ", " to ", " with ", " ("];
+
+impl Verb {
+    fn infinitive(&self) -> &'static str {
+        match *self {
+            Verb::Plays => "play",
+            Verb::Buys => "buy",
+            Verb::Gains => "gain",
+            Verb::Discards => "discard",
+            Verb::Trashes => "trash",
+            Verb::Reveals => "reveal",
+        }
+    }
+
+    // Whether the cards come from the player's hand, which the log may show
+    // holding cards the engine dealt elsewhere.
+    fn uses_hand(&self) -> bool {
+        match *self {
+            Verb::Buys | Verb::Gains => false,
+            _ => true,
+        }
+    }
+
+    fn for_decision(decision_type: &DecisionType) -> Verb {
+        match *decision_type {
+            DecisionType::PlayAction | DecisionType::PlayTreasures => Verb::Plays,
+            DecisionType::BuyCard => Verb::Buys,
+            DecisionType::GainCard(_) => Verb::Gains,
+            DecisionType::DiscardCards(_) => Verb::Discards,
+            DecisionType::TrashCards(_) => Verb::Trashes,
+            DecisionType::RevealReaction(_) => Verb::Reveals,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Entry {
+    // A player's turn starting, by seat.
+    Turn(usize),
+    Move(usize, Verb, Vec<CardIdentifier>),
+}
+
+// A line of a log that matters to the game, numbered from 1.
+#[derive(Clone, Debug)]
+struct Line {
+    number: usize,
+    entry: Entry,
+}
+
+// A line of a log that the engine wouldn't allow or that doesn't fit the
+// game the engine reconstructed.
+#[derive(Clone, Debug)]
+pub struct Flag {
+    pub line: usize,
+    pub message: String,
+}
+
+// A decision where Tactician's search rated another move well above the
+// one the log shows.
+#[derive(Clone, Debug)]
+pub struct Blunder {
+    pub line: usize,
+    pub turn: i32,
+    pub player: String,
+    pub played: Vec<CardIdentifier>,
+    pub played_win_rate: f32,
+    pub best: Vec<CardIdentifier>,
+    pub best_win_rate: f32,
+}
+
+// A game reconstructed from a log, as play --record saves games, with the
+// lines that didn't fit it.
+pub struct ImportedGame {
+    pub log: GameLog,
+    pub flags: Vec<Flag>,
+    // The line each of the log's decisions was read from.
+    pub lines: Vec<usize>,
+}
+
+fn turn_header(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if !line.starts_with("Turn ") {
+        return None;
+    }
+    let mut parts = line["Turn ".len()..].splitn(2, " - ");
+    if parts.next()?.trim().parse::<u32>().is_err() {
+        return None;
+    }
+    let name = parts.next()?;
+    let name = match name.find(" (") {
+        Some(i) => &name[..i],
+        None => name,
+    };
+    match name.trim() {
+        "" => None,
+        name => Some(name),
+    }
+}
+
+// The player a line is about, by their whole name or the start of it, as
+// some sites shorten names to initials.
+fn player_named(subject: &str, players: &[String]) -> Option<usize> {
+    if let Some(i) = players.iter().position(|p| p == subject) {
+        return Some(i);
+    }
+    let starting = players
+        .iter()
+        .enumerate()
+        .filter(|&(_, p)| p.starts_with(subject))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    match starting.len() {
+        1 => Some(starting[0]),
+        _ => None,
+    }
+}
+
+// The card a log names, in the singular or plural.
+fn card_in_log(name: &str) -> Option<CardIdentifier> {
+    cards::CARDS
+        .iter()
+        .find(|c| {
+            c.name.eq_ignore_ascii_case(name)
+                || cards::pluralize_name(c.name, 2).eq_ignore_ascii_case(name)
+        })
+        .map(|c| c.identifier)
+}
+
+// The cards in a list such as "3 Coppers, a Silver and a Gold.", or None if
+// the log hides them, as in "draws 5 cards".
+fn parse_cards(text: &str) -> Result<Option<Vec<CardIdentifier>>, String> {
+    let mut text = text.trim().trim_end_matches('.');
+    for place in PLACES.iter() {
+        if let Some(i) = text.find(place) {
+            text = &text[..i];
+        }
+    }
+    let mut found = vec![];
+    for item in text.replace(" and ", ", ").split(", ") {
+        let item = item.trim();
+        let (count, name) = match item.find(' ') {
+            Some(i) => match &item[..i] {
+                "a" | "an" => (1, &item[i + 1..]),
+                n => match n.parse::<usize>() {
+                    Ok(n) => (n, &item[i + 1..]),
+                    Err(_) => (1, item),
+                },
+            },
+            None => (1, item),
+        };
+        match name {
+            "" => continue,
+            "card" | "cards" => return Ok(None),
+            _ => {}
+        }
+        let c = card_in_log(name).ok_or(format!("Tactician doesn't know the card {}", name))?;
+        found.extend(::std::iter::repeat(c).take(count));
+    }
+    Ok(Some(found))
+}
+
+// The player, verb and cards of a line such as "Alice buys and gains a
+// Silver.", if it's one.
+fn parse_move(line: &str, players: &[String]) -> Result<Option<Entry>, String> {
+    let mut first: Option<(usize, &str, Verb)> = None;
+    for &(word, verb) in VERBS.iter() {
+        if let Some(i) = line.find(&format!(" {} ", word)) {
+            if first.map_or(true, |(j, _, _)| i < j) {
+                first = Some((i, word, verb));
+            }
+        }
+    }
+    let (i, word, verb) = match first {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let player = match player_named(line[..i].trim(), players) {
+        Some(player) => player,
+        None => return Ok(None),
+    };
+    Ok(match parse_cards(&line[i + word.len() + 2..])? {
+        Some(ref found) if found.is_empty() => None,
+        Some(found) => Some(Entry::Move(player, verb, found)),
+        None => None,
+    })
+}
+
+// The players, in turn order, and the lines of a log as Dominion Online
+// writes them: "Turn 3 - Alice" headers, with lines such as "Alice plays
+// 3 Coppers and a Silver." under them. Lines saying nothing the engine
+// decides, such as draws and shuffles, are left out.
+fn parse_log(text: &str) -> Result<(Vec<String>, Vec<Line>), String> {
+    let mut players: Vec<String> = vec![];
+    for name in text.lines().filter_map(turn_header) {
+        if !players.iter().any(|p| p == name) {
+            players.push(name.to_string());
+        }
+    }
+    if players.len() < 2 || players.len() > 4 {
+        return Err(format!(
+            "Expected turns for 2 to 4 players, found {}",
+            players.len()
+        ));
+    }
+    let mut lines = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let entry = match turn_header(line) {
+            Some(name) => Some(Entry::Turn(players.iter().position(|p| p == name).unwrap())),
+            // Anything before the first turn is setup.
+            None if lines.is_empty() => None,
+            None => parse_move(line, &players).map_err(|e| format!("Line {}: {}", i + 1, e))?,
+        };
+        if let Some(entry) = entry {
+            lines.push(Line {
+                number: i + 1,
+                entry: entry,
+            });
+        }
+    }
+    Ok((players, lines))
+}
+
+// The cards in wanted that have isn't holding, counting repeats.
+fn missing(wanted: &[CardIdentifier], have: &[CardIdentifier]) -> Vec<CardIdentifier> {
+    let mut have = have.to_vec();
+    let mut lacking = vec![];
+    for c in wanted.iter() {
+        match have.iter().position(|h| h == c) {
+            Some(i) => {
+                have.remove(i);
+            }
+            None => lacking.push(*c),
+        }
+    }
+    lacking
+}
+
+// Swaps cards from a player's deck or discard pile into their hand, as
+// though they had been drawn instead of cards in their hand that aren't
+// kept. Cards leaving or arriving in their hand with the decision being
+// made are counted as gone or held already. Without such cards to spare,
+// they take the place of the cards used last, which go on top of the deck
+// to be drawn later, or go there themselves. Returns false, changing
+// nothing, if the cards aren't in the deck or discard pile.
+fn draw_instead(
+    player: &mut Player,
+    wanted: &[CardIdentifier],
+    keep: &[CardIdentifier],
+    leaving: &[CardIdentifier],
+    arriving: &[CardIdentifier],
+) -> bool {
+    let mut p = player.clone();
+    let staying = missing(&p.hand, leaving);
+    let mut spare = missing(&staying, &missing(keep, arriving));
+    let mut later = missing(&missing(&staying, wanted), &spare);
+    later.sort_by_key(|c| keep.iter().rposition(|k| k == c));
+    let mut held = staying.clone();
+    held.extend(arriving);
+    // The cards put on top of the deck.
+    let mut top = 0;
+    for c in missing(wanted, &held) {
+        let below = p.deck.len() - top;
+        let (in_deck, i) = match p.deck[..below].iter().rposition(|&d| d == c) {
+            Some(i) => (true, i),
+            None => match p.discard.iter().position(|&d| d == c) {
+                Some(i) => (false, i),
+                None => return false,
+            },
+        };
+        match spare.pop() {
+            Some(out) => {
+                match in_deck {
+                    true => p.deck[i] = out,
+                    false => p.discard[i] = out,
+                }
+                let j = p.hand.iter().position(|&h| h == out).unwrap();
+                p.hand[j] = c;
+            }
+            None => {
+                match in_deck {
+                    true => p.deck.remove(i),
+                    false => p.discard.remove(i),
+                };
+                let drawn = match later.pop() {
+                    Some(out) => {
+                        let j = p.hand.iter().position(|&h| h == out).unwrap();
+                        p.hand[j] = c;
+                        out
+                    }
+                    None => c,
+                };
+                let i = p.deck.len() - top;
+                p.deck.insert(i, drawn);
+                top += 1;
+            }
+        }
+    }
+    *player = p;
+    true
+}
+
+// Swaps the cards in a player's hand that are unwanted, other than those
+// leaving it, with other cards from their deck, and puts them on top of
+// their deck, as though they'd be drawn later. Returns false, changing
+// nothing, if there aren't enough other cards.
+fn draw_other_than<F>(player: &mut Player, unwanted: F, leaving: &[CardIdentifier]) -> bool
+where
+    F: Fn(&CardIdentifier) -> bool,
+{
+    let mut p = player.clone();
+    for c in missing(&p.hand, leaving) {
+        if !unwanted(&c) {
+            continue;
+        }
+        let i = match p.deck.iter().position(|d| !unwanted(d)) {
+            Some(i) => i,
+            None => return false,
+        };
+        let j = p.hand.iter().position(|&h| h == c).unwrap();
+        p.hand[j] = p.deck.remove(i);
+        p.deck.push(c);
+    }
+    *player = p;
+    true
+}
+
+// Plays a log's moves through the engine. Logs don't show the cards
+// players draw, so the engine shuffles as usual, and when a line shows a
+// player using cards they weren't dealt, the game goes back to before the
+// last decision and deals them those cards instead.
+struct Reconstruction {
+    lines: Vec<Line>,
+    next: usize,
+    game: Game,
+    ctx: EvalContext,
+    // The game just before its last decision was made, with the choice,
+    // or as it was dealt.
+    before: (Game, Option<Vec<CardIdentifier>>),
+    // The last line whose cards were dealt again, so it's only tried once.
+    redrawn: Option<usize>,
+    // The line, if any, that cards were last dealt away for.
+    undrawn: Option<usize>,
+    // The player and turn of the last turn header read.
+    turn: Option<(PlayerIdentifier, i32)>,
+    imported: ImportedGame,
+}
+
+impl Reconstruction {
+    fn flag(&mut self, line: usize, message: String) {
+        self.imported.flags.push(Flag {
+            line: line,
+            message: message,
+        });
+    }
+
+    fn name(&self, player: usize) -> String {
+        self.game.players[player].name.clone()
+    }
+
+    fn advance(&mut self) {
+        while !self.game.is_game_over() && self.game.pending_decision.is_none() {
+            self.game.advance_game(&mut self.ctx);
+        }
+    }
+
+    fn front(&self) -> Option<&Entry> {
+        self.lines.get(self.next).map(|l| &l.entry)
+    }
+
+    // The line being read, or the last one once they're all read.
+    fn line_number(&self) -> usize {
+        match self.lines.get(self.next).or_else(|| self.lines.last()) {
+            Some(line) => line.number,
+            None => 0,
+        }
+    }
+
+    // The treasures of the lines from the next on that play only treasures
+    // for a player, and how many lines there are.
+    fn treasure_run(&self, player: usize) -> (Vec<CardIdentifier>, usize) {
+        let mut treasures = vec![];
+        let mut n = 0;
+        for line in self.lines[self.next..].iter() {
+            match line.entry {
+                Entry::Move(p, Verb::Plays, ref found)
+                    if p == player && found.iter().all(|c| cards::lookup_card(c).is_treasure()) =>
+                {
+                    treasures.extend(found);
+                    n += 1;
+                }
+                _ => break,
+            }
+        }
+        (treasures, n)
+    }
+
+    // Reads the next turn header once the engine starts a new turn, first
+    // flagging any moves left over from the last turn.
+    fn start_turn(&mut self) -> bool {
+        let active = self.game.active_player;
+        if self.turn == Some((active, self.game.turn)) {
+            return true;
+        }
+        while let Some(Entry::Move(p, verb, found)) = self.front().cloned() {
+            let line = self.line_number();
+            let message = format!(
+                "{} can't {} {} then",
+                self.name(p),
+                verb.infinitive(),
+                cards::card_names(&found)
+            );
+            self.flag(line, message);
+            self.next += 1;
+        }
+        match self.front().cloned() {
+            Some(Entry::Turn(p)) if p == active.0 as usize => {
+                self.next += 1;
+                self.turn = Some((active, self.game.turn));
+                true
+            }
+            Some(Entry::Turn(p)) => {
+                let line = self.line_number();
+                let message = format!(
+                    "The log has {}'s turn where the engine has {}'s",
+                    self.name(p),
+                    self.name(active.0 as usize)
+                );
+                self.flag(line, message);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    // Goes back to before the last decision, changes the cards a player
+    // was dealt with deal, and makes the decision again. deal is given the
+    // cards leaving and arriving in the player's hand with the decision,
+    // if it was theirs, and returns whether it changed anything.
+    fn deal_again<F>(&mut self, player: usize, deal: F) -> bool
+    where
+        F: FnOnce(&mut Player, &[CardIdentifier], &[CardIdentifier]) -> bool,
+    {
+        let (mut game, choice) = self.before.clone();
+        let (leaving, arriving) = match (game.pending_decision.as_ref(), choice.as_ref()) {
+            (Some(d), Some(choice)) if d.player.0 as usize == player => match d.decision_type {
+                DecisionType::GainCard(GainDestination::GainToHand) => (vec![], choice.clone()),
+                DecisionType::BuyCard
+                | DecisionType::GainCard(_)
+                | DecisionType::RevealReaction(_) => (vec![], vec![]),
+                _ => (choice.clone(), vec![]),
+            },
+            _ => (vec![], vec![]),
+        };
+        if !deal(&mut game.players[player], &leaving, &arriving) {
+            return false;
+        }
+        if choice.is_some() {
+            self.imported.log.decisions.last_mut().unwrap().state = game.clone();
+        }
+        self.before = (game.clone(), choice.clone());
+        self.game = game;
+        if let Some(choice) = choice {
+            self.game.resolve_decision(choice, &mut self.ctx);
+        }
+        self.advance();
+        true
+    }
+
+    // Deals the cards the next line shows a player using from their hand,
+    // if they weren't dealt them, and returns whether it did.
+    fn redraw(&mut self) -> bool {
+        let (player, wanted) = match self.front().cloned() {
+            Some(Entry::Move(p, verb, found)) if verb.uses_hand() => match self.treasure_run(p) {
+                (treasures, n) if n > 0 => (p, treasures),
+                _ => (p, found),
+            },
+            _ => return false,
+        };
+        if self.redrawn == Some(self.next)
+            || missing(&wanted, &self.game.players[player].hand).is_empty()
+        {
+            return false;
+        }
+        self.redrawn = Some(self.next);
+
+        // Cards the player goes on to use this turn stay in their hand.
+        let mut keep = vec![];
+        for line in self.lines[self.next..].iter() {
+            match line.entry {
+                Entry::Turn(_) => break,
+                Entry::Move(p, verb, ref found) if p == player && verb.uses_hand() => {
+                    keep.extend(found)
+                }
+                _ => {}
+            }
+        }
+        self.deal_again(player, |p, leaving, arriving| {
+            draw_instead(p, &wanted, &keep, leaving, arriving)
+        })
+    }
+
+    // When a player has to trash a card but the log doesn't show one, deals
+    // them other cards than those they could trash, and returns whether it
+    // did.
+    fn undraw(&mut self) -> bool {
+        let (player, choices) = match self.game.pending_decision {
+            Some(ref d) => match d.decision_type {
+                DecisionType::TrashCards(_) => (d.player.0 as usize, d.choices.clone()),
+                _ => return false,
+            },
+            None => return false,
+        };
+        let shown = match self.front() {
+            Some(&Entry::Move(p, Verb::Trashes, _)) => p == player,
+            _ => false,
+        };
+        if shown || self.undrawn == Some(self.next) {
+            return false;
+        }
+        self.undrawn = Some(self.next);
+        // Mine only trashes treasures; Remodel trashes anything.
+        let treasures = choices.iter().all(|c| cards::lookup_card(c).is_treasure());
+        self.deal_again(player, |p, leaving, _| {
+            draw_other_than(
+                p,
+                |c| !treasures || cards::lookup_card(c).is_treasure(),
+                leaving,
+            )
+        })
+    }
+
+    // Makes the pending decision as the next line says, or with nothing if
+    // the line is about something else.
+    fn decide(&mut self) {
+        let d = self.game.pending_decision.clone().unwrap();
+        let player = d.player.0 as usize;
+        let verb = Verb::for_decision(&d.decision_type);
+        let line = self.line_number();
+        let choice = match self.front().cloned() {
+            Some(Entry::Move(p, v, found)) if p == player && v == verb => match d.decision_type {
+                DecisionType::PlayAction => match found.len() {
+                    1 if cards::lookup_card(&found[0]).is_action() => {
+                        self.next += 1;
+                        Some(found)
+                    }
+                    // Playing treasures ends the actions.
+                    _ => Some(vec![]),
+                },
+                DecisionType::PlayTreasures => {
+                    let (treasures, n) = self.treasure_run(player);
+                    self.next += n;
+                    Some(treasures)
+                }
+                DecisionType::BuyCard | DecisionType::GainCard(_) => {
+                    let rest = found[1..].to_vec();
+                    match rest.is_empty() {
+                        true => {
+                            self.next += 1;
+                            // Some sites log a gain after each buy.
+                            let echo = Entry::Move(p, Verb::Gains, vec![found[0]]);
+                            if verb == Verb::Buys && self.front() == Some(&echo) {
+                                self.next += 1;
+                            }
+                        }
+                        false => self.lines[self.next].entry = Entry::Move(p, v, rest),
+                    }
+                    Some(vec![found[0]])
+                }
+                _ => {
+                    self.next += 1;
+                    Some(found)
+                }
+            },
+            _ => None,
+        };
+
+        let fallback = d.choices[..d.range.0].to_vec();
+        let choice = match choice {
+            Some(choice) => match check_choice(&choice, &d) {
+                Ok(()) => choice,
+                Err(e) => {
+                    let message = format!(
+                        "{} can't {} {}: {}",
+                        self.name(player),
+                        verb.infinitive(),
+                        cards::card_names(&choice),
+                        e
+                    );
+                    self.flag(line, message);
+                    fallback
+                }
+            },
+            None if d.range.0 > 0 => {
+                let message = format!(
+                    "{} has to {} but the log doesn't say what",
+                    self.name(player),
+                    verb.infinitive()
+                );
+                self.flag(line, message);
+                fallback
+            }
+            None => vec![],
+        };
+
+        self.imported.log.decisions.push(LoggedDecision {
+            state: self.game.clone(),
+            choice: choice.clone(),
+            analysis: vec![],
+        });
+        self.imported.lines.push(line);
+        self.before = (self.game.clone(), Some(choice.clone()));
+        self.game.resolve_decision(choice, &mut self.ctx);
+        self.advance();
+    }
+
+    fn run(&mut self) {
+        self.advance();
+        while self.next < self.lines.len() && !self.game.is_game_over() {
+            if !self.start_turn() {
+                break;
+            }
+            if !self.redraw() && !self.undraw() {
+                self.decide();
+            }
+        }
+        // The log's last turn ends with the decisions it doesn't mention.
+        while self.next == self.lines.len()
+            && !self.game.is_game_over()
+            && self.turn == Some((self.game.active_player, self.game.turn))
+        {
+            self.decide();
+        }
+        if self.game.is_game_over() {
+            let leftover = self.lines[self.next..].iter().find(|l| match l.entry {
+                Entry::Move(..) => true,
+                Entry::Turn(_) => false,
+            });
+            if let Some(line) = leftover {
+                let number = line.number;
+                self.flag(number, "The game was already over".into());
+            }
+        }
+    }
+}
+
+// Reconstructs the game a log records, as played with a kingdom, by making
+// each decision through the engine. Cards the log doesn't show are shuffled
+// from the seed. Lines the engine wouldn't allow are flagged and skipped,
+// and the reconstruction stops at the end of the log or of the game.
+pub fn import_log(
+    text: &str,
+    kingdom: &[CardIdentifier],
+    seed: u32,
+) -> Result<ImportedGame, String> {
+    let (players, lines) = parse_log(text)?;
+    let mut ctx = EvalContext {
+        rng: util::seeded_weak_rng(seed),
+        verbosity: Verbosity::Quiet,
+        events: None,
+    };
+    let mut game = fresh_game_with_kingdom(&players, kingdom);
+    game.initialize_game(&mut ctx);
+    let mut r = Reconstruction {
+        lines: lines,
+        next: 0,
+        game: game.clone(),
+        ctx: ctx,
+        before: (game, None),
+        redrawn: None,
+        undrawn: None,
+        turn: None,
+        imported: ImportedGame {
+            log: GameLog {
+                game: 1,
+                seed: Some(seed),
+                players: players,
+                ..Default::default()
+            },
+            flags: vec![],
+            lines: vec![],
+        },
+    };
+    r.run();
+    if r.game.is_game_over() {
+        r.imported.log.scores = r.game.player_scores().iter().map(|&(_, s)| s).collect();
+    }
+    r.imported.log.final_state = Some(r.game);
+    Ok(r.imported)
+}
+
+// Searches each decision with a choice of moves for a number of iterations,
+// saving the analysis in the game's log, and returns the decisions where
+// the best move's win rate beat the one played by more than a margin.
+// Moves the search didn't try aren't judged.
+pub fn find_blunders(
+    imported: &mut ImportedGame,
+    iterations: u32,
+    margin: f32,
+    seed: u32,
+) -> Vec<Blunder> {
+    let mut decider = SearchDecider::new(SearchOptions {
+        budget: SearchBudget::Iterations(iterations.max(1) as i32),
+        ..Default::default()
+    });
+    decider.seed(seed);
+    let mut blunders = vec![];
+    for (d, &line) in imported.log.decisions.iter_mut().zip(imported.lines.iter()) {
+        if d.state.all_moves().len() < 2 {
+            continue;
+        }
+        let analysis = decider.analyze(&d.state);
+        d.analysis = analysis
+            .iter()
+            .map(|a| MoveStats {
+                choice: a.choice.clone(),
+                visits: a.visits,
+                win_rate: a.win_rate,
+            })
+            .collect();
+        let mut played = d.choice.clone();
+        played.sort();
+        let played = analysis.iter().find(|a| {
+            let mut choice = a.choice.clone();
+            choice.sort();
+            choice == played
+        });
+        if let (Some(played), Some(best)) = (played, analysis.first()) {
+            if played.visits > 0 && best.win_rate - played.win_rate > margin {
+                let player = d.state.pending_decision.as_ref().unwrap().player;
+                blunders.push(Blunder {
+                    line: line,
+                    turn: d.state.turn,
+                    player: d.state.players[player.0 as usize].name.clone(),
+                    played: d.choice.clone(),
+                    played_win_rate: played.win_rate,
+                    best: best.choice.clone(),
+                    best_win_rate: best.win_rate,
+                });
+            }
+        }
+    }
+    blunders
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards;
+    use game::GameEvent;
+    use log_import::*;
+    use registry::Registry;
+
+    const LOG: &str = "Game #1, unrated.\n\
+                       Alice starts with 7 Coppers.\n\
+                       Turn 1 - Alice\n\
+                       Alice plays 3 Coppers.\n\
+                       Alice buys and gains a Silver.\n\
+                       Turn 1 - Bob\n\
+                       Bob plays 4 Coppers.\n\
+                       Bob buys a Smithy.\n\
+                       Bob gains a Smithy.\n\
+                       Turn 2 - Alice\n\
+                       Alice plays 4 Coppers.\n\
+                       Alice buys and gains a Militia.\n\
+                       Turn 2 - Bob\n\
+                       Bob plays 3 Coppers.\n\
+                       Bob buys and gains a Silver.\n";
+
+    #[test]
+    fn test_parse_log() {
+        let (players, lines) = parse_log(LOG).unwrap();
+        assert_eq!(players, vec!["Alice".to_string(), "Bob".to_string()]);
+        assert_eq!(lines[0].number, 3);
+        let copper = cards::COPPER.identifier;
+        let silver = cards::SILVER.identifier;
+        assert_eq!(lines[1].entry, Entry::Move(0, Verb::Plays, vec![copper; 3]));
+        assert_eq!(lines[2].entry, Entry::Move(0, Verb::Buys, vec![silver]));
+        let parsed = parse_move("B plays 2 Coppers, a Silver and a Gold.", &players);
+        let gold = cards::GOLD.identifier;
+        let found = vec![copper, copper, silver, gold];
+        assert_eq!(parsed, Ok(Some(Entry::Move(1, Verb::Plays, found))));
+        assert_eq!(parse_move("Alice draws 5 cards.", &players), Ok(None));
+        assert_eq!(parse_move("Alice discards 2 cards.", &players), Ok(None));
+        assert!(parse_move("Alice plays a Chapel.", &players).is_err());
+        assert!(parse_log("Turn 1 - Alice\n").is_err());
+    }
+
+    // A game's public events, written as Dominion Online logs them.
+    fn write_log(names: &[&str], events: &[GameEvent]) -> String {
+        let list = |found: &[CardIdentifier]| {
+            let items = found
+                .iter()
+                .map(|c| match "AEIOU".contains(&c.to_string()[..1]) {
+                    true => format!("an {}", c),
+                    false => format!("a {}", c),
+                })
+                .collect::<Vec<_>>();
+            match items.split_last() {
+                Some((last, rest)) if !rest.is_empty() => {
+                    format!("{} and {}", rest.join(", "), last)
+                }
+                _ => items.join(""),
+            }
+        };
+        let mut text = String::new();
+        for e in events.iter() {
+            let line = match *e {
+                GameEvent::TurnStarted(p, turn) => {
+                    format!("Turn {} - {}", turn, names[p.0 as usize])
+                }
+                GameEvent::Shuffled(p) => format!("{} shuffles their deck.", names[p.0 as usize]),
+                GameEvent::Drew(p, n) => format!("{} draws {} cards.", names[p.0 as usize], n),
+                GameEvent::Played(p, ref c) => {
+                    format!("{} plays {}.", names[p.0 as usize], list(c))
+                }
+                GameEvent::Bought(p, c) => {
+                    format!("{} buys and gains {}.", names[p.0 as usize], list(&[c]))
+                }
+                GameEvent::Gained(p, c) => format!("{} gains {}.", names[p.0 as usize], list(&[c])),
+                GameEvent::Discarded(p, ref c) => {
+                    format!("{} discards {}.", names[p.0 as usize], list(c))
+                }
+                GameEvent::Trashed(p, ref c) => {
+                    format!("{} trashes {}.", names[p.0 as usize], list(c))
+                }
+                GameEvent::Revealed(p, c) => {
+                    format!("{} reveals {}.", names[p.0 as usize], list(&[c]))
+                }
+                GameEvent::CleanedUp(_) => continue,
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    // Games the engine plays import without a flag, whatever the shuffles.
+    #[test]
+    fn test_import_played_game() {
+        let registry = Registry::standard();
+        for seed in 1..6 {
+            let mut players = [
+                registry.create("heuristic", true).unwrap(),
+                registry.create(&format!("random:seed={},cost=2", seed), true).unwrap(),
+            ];
+            let mut ctx = EvalContext {
+                rng: util::seeded_weak_rng(seed),
+                verbosity: Verbosity::Quiet,
+                events: Some(vec![]),
+            };
+            let names = vec!["Alice".to_string(), "Bob".to_string()];
+            let mut g = fresh_game_with_kingdom(&names, &cards::first_game_kingdom());
+            g.initialize_game(&mut ctx);
+            while !g.is_game_over() {
+                match g.pending_decision.as_ref().map(|d| d.player.0 as usize) {
+                    Some(p) => {
+                        let choice = players[p].make_decision(&g);
+                        g.resolve_decision(choice, &mut ctx);
+                    }
+                    None => g.advance_game(&mut ctx),
+                }
+            }
+            let scores = g
+                .player_scores()
+                .iter()
+                .map(|&(_, s)| s)
+                .collect::<Vec<_>>();
+            let text = write_log(&["Alice", "Bob"], ctx.events.as_ref().unwrap());
+            let imported = import_log(&text, &cards::first_game_kingdom(), seed + 100).unwrap();
+            assert!(imported.flags.is_empty(), "{:?}", imported.flags);
+            assert_eq!(imported.log.scores, scores);
+        }
+    }
+
+    #[test]
+    fn test_import_log() {
+        let imported = import_log(LOG, &cards::first_game_kingdom(), 1).unwrap();
+        assert!(imported.flags.is_empty(), "{:?}", imported.flags);
+        let g = imported.log.final_state.as_ref().unwrap();
+        let gained = |p: usize, c: &cards::Card| {
+            g.players[p]
+                .all_cards()
+                .iter()
+                .filter(|&&x| x == c.identifier)
+                .count()
+        };
+        assert_eq!(gained(0, &cards::SILVER), 1);
+        assert_eq!(gained(0, &cards::MILITIA), 1);
+        assert_eq!(gained(1, &cards::SMITHY), 1);
+        assert_eq!(gained(1, &cards::SILVER), 1);
+        assert_eq!(imported.log.decisions.len(), imported.lines.len());
+
+        // Three Coppers buy no Gold.
+        let illegal = LOG.replace("Bob buys and gains a Silver.", "Bob buys and gains a Gold.");
+        let imported = import_log(&illegal, &cards::first_game_kingdom(), 1).unwrap();
+        assert_eq!(imported.flags.len(), 1);
+        assert_eq!(imported.flags[0].line, 15);
+
+        let mut imported = import_log(LOG, &cards::first_game_kingdom(), 1).unwrap();
+        let blunders = find_blunders(&mut imported, 50, 2.0, 1);
+        assert!(blunders.is_empty());
+        assert!(imported
+            .log
+            .decisions
+            .iter()
+            .any(|d| !d.analysis.is_empty()));
+    }
+}
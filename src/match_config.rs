@@ -0,0 +1,379 @@
+use std::fs::File;
+use std::io::Read;
+use std::time::Duration;
+
+use cards;
+use game::{Rules, Verbosity};
+use json::Json;
+use rand::Rng;
+use registry::Registry;
+use results_output::ResultsFormat;
+use sprt::Sprt;
+use time_control::{OnTimeout, TimeControl};
+use toml;
+use util;
+
+// A match's settings besides the players themselves. They come from the
+// play command's options, or from a config file, so an experiment can be
+// shared and rerun as a single file.
+pub struct MatchConfig {
+    // Player specs, e.g. "tactician:iters=500".
+    pub players: Vec<String>,
+    pub games: u32,
+    // Threads to play games on at once.
+    pub jobs: u32,
+    pub sprt: Option<Sprt>,
+    // Seeds each game's shuffles, from this seed onwards.
+    pub seed: Option<u32>,
+    // Plays each seed twice, swapping the seats.
+    pub paired: bool,
+    pub rules: Rules,
+    // A file to write each game's result to, and the match's totals.
+    pub results_path: Option<String>,
+    pub results_format: ResultsFormat,
+    // A file to log every decision of each game to, for replays.
+    pub record_path: Option<String>,
+    // A directory to log each game to a file of its own.
+    pub log_dir: Option<String>,
+    // A SQLite database to add each game, its scores and its turns to.
+    pub db_path: Option<String>,
+    pub verbosity: Verbosity,
+    // Whether quiet runs show their progress.
+    pub progress: bool,
+    // Whether their progress includes a chart of the first player's win
+    // rate.
+    pub chart: bool,
+}
+
+impl Default for MatchConfig {
+    fn default() -> MatchConfig {
+        MatchConfig {
+            players: vec![],
+            games: 1,
+            jobs: 1,
+            sprt: None,
+            seed: None,
+            paired: false,
+            rules: Rules::default(),
+            results_path: None,
+            results_format: ResultsFormat::Json,
+            record_path: None,
+            log_dir: None,
+            db_path: None,
+            verbosity: Verbosity::Normal,
+            progress: true,
+            chart: false,
+        }
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + d.subsec_millis() as u64
+}
+
+impl MatchConfig {
+    // The players' specs, with a seed for each player that takes one and
+    // isn't given one, drawn from the match's seed, so a seeded match seeds
+    // its players too. Specs that can't be read are left for the players
+    // to reject.
+    pub fn seeded_players(&self) -> Vec<String> {
+        let registry = Registry::standard();
+        let mut rng = self.seed.map(util::seeded_weak_rng);
+        self.players
+            .iter()
+            .map(|spec| match rng {
+                Some(ref mut rng) => registry.seeded(spec, rng.gen()).unwrap_or(spec.clone()),
+                None => spec.clone(),
+            })
+            .collect()
+    }
+
+    // A play command that plays the match again, spelling out everything
+    // that decides its games, including the seeds given to its players.
+    // They only repeat exactly with a seed, and players that are seeded or
+    // play the same given the same shuffles.
+    pub fn command_line(&self) -> String {
+        let mut args = vec!["tactician".to_string(), "play".into()];
+        args.extend(vec!["-n".into(), self.games.to_string()]);
+        if let Some(seed) = self.seed {
+            args.extend(vec!["--seed".into(), seed.to_string()]);
+        }
+        if self.paired {
+            args.push("--paired".into());
+        }
+        if let Some(ref sprt) = self.sprt {
+            args.extend(vec!["--sprt".into(), format!("{},{}", sprt.elo0, sprt.elo1)]);
+        }
+        let kingdom = self.rules.kingdom.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        args.extend(vec!["--kingdom".into(), kingdom.join(",")]);
+        if let Some(tc) = self.rules.time_control {
+            let clock = format!("{}ms+{}ms", millis(tc.base), millis(tc.increment));
+            args.extend(vec!["--clock".into(), clock]);
+            if tc.on_timeout == OnTimeout::DefaultMove {
+                args.push("--timeout-move".into());
+            }
+        }
+        args.extend(self.seeded_players());
+        args.iter().map(|a| util::shell_quote(a)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+// Loads a match from a TOML file such as:
+//
+//   games = 100
+//   jobs = 4
+//   seed = 42
+//   paired = true
+//   verbosity = "quiet"
+//   progress = false
+//   chart = true
+//
+//   [[players]]
+//   name = "tactician"
+//   iters = 5000
+//
+//   [[players]]
+//   name = "bigmoney"
+//
+//   [rules]
+//   kingdom = ["Smithy", "Village", "Market", "Militia", "Cellar"]
+//   clock = "60s+1s"
+//   timeout_move = true
+//
+//   [output]
+//   results = "results.jsonl"
+//   format = "json"
+//   record = "games.jsonl"
+//   log_dir = "logs"
+//   db = "results.sqlite"
+//
+// Players are tables, whose keys besides the name are the player's options,
+// or can be listed as specs instead, e.g. players = ["tactician", "random"].
+// The kingdom can be a preset too, e.g. kingdom = "preset:engine-heavy".
+pub fn load(path: &str) -> Result<MatchConfig, String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    let doc = toml::parse(&text)?;
+    from_toml(&doc).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn fields<'a>(j: &'a Json, table: &str) -> Result<&'a Vec<(String, Json)>, String> {
+    match *j {
+        Json::Object(ref fields) => Ok(fields),
+        _ => Err(format!("{} should be a table", table)),
+    }
+}
+
+fn count(key: &str, value: &Json) -> Result<u32, String> {
+    match value.as_i64() {
+        Some(n) if n >= 0 && n <= u32::max_value() as i64 => Ok(n as u32),
+        _ => Err(format!("{} should be a whole number", key)),
+    }
+}
+
+fn string<'a>(key: &str, value: &'a Json) -> Result<&'a str, String> {
+    value.as_str().ok_or(format!("{} should be a string", key))
+}
+
+fn flag(key: &str, value: &Json) -> Result<bool, String> {
+    match *value {
+        Json::Bool(b) => Ok(b),
+        _ => Err(format!("{} should be true or false", key)),
+    }
+}
+
+// Writes a player's table as a spec, e.g. "tactician:iters=5000".
+fn player_spec(table: &Json) -> Result<String, String> {
+    let mut name = None;
+    let mut options = vec![];
+    for &(ref key, ref value) in fields(table, "players")?.iter() {
+        let value = match *value {
+            _ if key == "name" => {
+                name = Some(string(key, value)?);
+                continue;
+            }
+            Json::String(ref s) => s.clone(),
+            Json::Number(n) => n.to_string(),
+            Json::Bool(b) => if b { "on" } else { "off" }.to_string(),
+            _ => return Err(format!("Player option {} should be a string, number or flag", key)),
+        };
+        options.push(format!("{}={}", key, value));
+    }
+    match name {
+        Some(name) if options.is_empty() => Ok(name.to_string()),
+        Some(name) => Ok(format!("{}:{}", name, options.join(","))),
+        None => Err("Players need a name".into()),
+    }
+}
+
+fn players(value: &Json) -> Result<Vec<String>, String> {
+    let values = value.as_array().ok_or("players should be an array")?;
+    values
+        .iter()
+        .map(|v| match *v {
+            Json::String(ref s) => Ok(s.clone()),
+            Json::Object(_) => player_spec(v),
+            _ => Err("Players should be specs or tables".into()),
+        })
+        .collect()
+}
+
+fn load_rules(value: &Json, rules: &mut Rules) -> Result<(), String> {
+    let mut timeout_move = false;
+    for &(ref key, ref value) in fields(value, "rules")?.iter() {
+        match key.as_str() {
+            "kingdom" => {
+                rules.kingdom = match value.as_str() {
+                    Some(s) => cards::parse_kingdom(s)?,
+                    None => {
+                        let names = value
+                            .as_array()
+                            .and_then(|a| a.iter().map(|n| n.as_str()).collect::<Option<Vec<_>>>())
+                            .ok_or("kingdom should be an array of card names or a preset")?;
+                        cards::kingdom_named(&names)?
+                    }
+                }
+            }
+            "clock" => rules.time_control = Some(TimeControl::parse(string(key, value)?)?),
+            "timeout_move" => timeout_move = flag(key, value)?,
+            _ => return Err(format!("Unknown key {} in rules", key)),
+        }
+    }
+    if let Some(ref mut tc) = rules.time_control {
+        if timeout_move {
+            tc.on_timeout = OnTimeout::DefaultMove;
+        }
+    }
+    Ok(())
+}
+
+fn from_toml(doc: &Json) -> Result<MatchConfig, String> {
+    let mut config = MatchConfig::default();
+    for &(ref key, ref value) in fields(doc, "config")?.iter() {
+        match key.as_str() {
+            "players" => config.players = players(value)?,
+            "games" => config.games = count(key, value)?,
+            "jobs" => config.jobs = match count(key, value)? {
+                0 => return Err("jobs should be at least 1".into()),
+                n => n,
+            },
+            "seed" => config.seed = Some(count(key, value)?),
+            "paired" => config.paired = flag(key, value)?,
+            "sprt" => config.sprt = Some(Sprt::parse(string(key, value)?)?),
+            "verbosity" => config.verbosity = Verbosity::parse(string(key, value)?)?,
+            "progress" => config.progress = flag(key, value)?,
+            "chart" => config.chart = flag(key, value)?,
+            "rules" => load_rules(value, &mut config.rules)?,
+            "output" => for &(ref key, ref value) in fields(value, "output")?.iter() {
+                match key.as_str() {
+                    "results" => config.results_path = Some(string(key, value)?.to_string()),
+                    "format" => config.results_format = ResultsFormat::parse(string(key, value)?)?,
+                    "record" => config.record_path = Some(string(key, value)?.to_string()),
+                    "log_dir" => config.log_dir = Some(string(key, value)?.to_string()),
+                    "db" => config.db_path = Some(string(key, value)?.to_string()),
+                    _ => return Err(format!("Unknown key {} in output", key)),
+                }
+            },
+            _ => return Err(format!("Unknown key {}", key)),
+        }
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use match_config::*;
+    use results_output::ResultsFormat;
+    use time_control::OnTimeout;
+    use toml;
+
+    #[test]
+    fn test_match_config() {
+        let doc = toml::parse(
+            r#"
+games = 20
+jobs = 4
+seed = 7
+paired = true
+progress = false
+chart = true
+
+[[players]]
+name = "tactician"
+iters = 500
+c = 0.7
+verbose = false
+
+[[players]]
+name = "bigmoney"
+
+[rules]
+kingdom = ["Smithy", "Village"]
+clock = "5s+1s"
+timeout_move = true
+
+[output]
+results = "out.csv"
+format = "csv"
+log_dir = "logs"
+db = "results.sqlite"
+"#,
+        ).unwrap();
+        let config = from_toml(&doc).unwrap();
+        assert_eq!(
+            config.players,
+            vec!["tactician:iters=500,c=0.7,verbose=off", "bigmoney"]
+        );
+        assert_eq!(config.games, 20);
+        assert_eq!(config.jobs, 4);
+        assert!(!config.progress);
+        assert!(config.chart);
+        assert_eq!(config.seed, Some(7));
+        assert!(config.paired);
+        assert_eq!(config.rules.kingdom, vec![SMITHY.identifier, VILLAGE.identifier]);
+        assert_eq!(
+            config.rules.time_control.map(|tc| tc.on_timeout),
+            Some(OnTimeout::DefaultMove)
+        );
+        assert_eq!(config.results_path, Some("out.csv".to_string()));
+        assert_eq!(config.results_format, ResultsFormat::Csv);
+        assert_eq!(config.log_dir, Some("logs".to_string()));
+        assert_eq!(config.db_path, Some("results.sqlite".to_string()));
+        assert_eq!(
+            config.command_line(),
+            "tactician play -n 20 --seed 7 --paired --kingdom Smithy,Village \
+             --clock 5000ms+1000ms --timeout-move tactician:iters=500,c=0.7,verbose=off bigmoney"
+        );
+
+        // Players that take a seed get one from the match's, the same each time.
+        let seeded = MatchConfig {
+            seed: Some(7),
+            players: vec!["tactician:iters=500".into(), "bigmoney".into()],
+            ..Default::default()
+        };
+        let specs = seeded.seeded_players();
+        assert!(specs[0].starts_with("tactician:iters=500,seed="), "{}", specs[0]);
+        assert_eq!(specs[1], "bigmoney");
+        assert_eq!(seeded.seeded_players(), specs);
+        assert!(seeded.command_line().ends_with(&specs.join(" ")));
+
+        let specs = toml::parse("players = [\"random:seed=1\", \"bigmoney\"]").unwrap();
+        assert_eq!(
+            from_toml(&specs).unwrap().players,
+            vec!["random:seed=1", "bigmoney"]
+        );
+
+        let bad = |text: &str| from_toml(&toml::parse(text).unwrap()).is_err();
+        assert!(bad("rounds = 3"));
+        assert!(bad("games = -1"));
+        assert!(bad("jobs = 0"));
+        assert!(bad("[rules]\nkingdom = [\"Gold\"]"));
+        assert!(bad("[rules]\nkingdom = \"preset:none\""));
+        assert!(bad("[[players]]\niters = 5"));
+        assert!(bad("[output]\nformat = \"xml\""));
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use cards::CardIdentifier;
+use game::{DecisionType, Game};
+use opening_book::kingdom_cards;
+use tree_search::{Prior, PriorPolicy};
+
+// Games a buy needs to have been made in before it seeds searches.
+const MIN_GAMES: u32 = 2;
+
+// Pseudo-visits are capped, so what the search sees in a position always
+// outweighs the history of earlier games.
+const MAX_PRIOR_VISITS: u32 = 20;
+
+// A buy: the card bought, or None for buying nothing, the coins it was
+// bought with, and the kingdom it was bought from.
+type BuyKey = (Vec<CardIdentifier>, Option<CardIdentifier>, i32);
+
+#[derive(Clone, Copy, Debug, Default)]
+struct BuyOutcome {
+    games: u32,
+    score: f32,
+}
+
+// How a decider's buys have turned out over the games of a match, e.g.
+// that games where it bought Smithy with $4 won 62% of the time. Later
+// games search those buys starting from that win rate.
+#[derive(Debug, Default)]
+pub struct MatchHistory {
+    outcomes: HashMap<BuyKey, BuyOutcome>,
+    // Buys made in the current game, credited when it ends.
+    current_game: Vec<BuyKey>,
+}
+
+fn buy_key(g: &Game, choice: &Vec<CardIdentifier>) -> BuyKey {
+    (kingdom_cards(&g.piles), choice.first().cloned(), g.coins)
+}
+
+impl MatchHistory {
+    pub fn new() -> MatchHistory {
+        MatchHistory::default()
+    }
+
+    // Notes the choice if it's a buy. Each buy counts once per game, however
+    // many times it was made.
+    pub fn record_decision(&mut self, g: &Game, choice: &Vec<CardIdentifier>) {
+        match g.pending_decision {
+            Some(ref d) if d.decision_type == DecisionType::BuyCard => {}
+            _ => return,
+        }
+        let key = buy_key(g, choice);
+        if !self.current_game.contains(&key) {
+            self.current_game.push(key);
+        }
+    }
+
+    // Credits the current game's buys with its score: 1 for a win, a share
+    // of 1 for a tie, and 0 for a loss.
+    pub fn finish_game(&mut self, score: f32) {
+        for key in self.current_game.drain(..) {
+            let outcome = self.outcomes.entry(key).or_insert_with(BuyOutcome::default);
+            outcome.games += 1;
+            outcome.score += score;
+        }
+    }
+
+    // Forgets the current game's buys without crediting them.
+    pub fn abandon_game(&mut self) {
+        self.current_game.clear();
+    }
+
+    // The fraction of games won where this buy was made, and how many
+    // games that was.
+    pub fn win_rate(&self, g: &Game, choice: &Vec<CardIdentifier>) -> Option<(f32, u32)> {
+        self.outcomes
+            .get(&buy_key(g, choice))
+            .map(|o| (o.score / o.games as f32, o.games))
+    }
+}
+
+// Seeds buys with their win rate from earlier games of the match, and
+// anything else from the fallback policy.
+pub struct MatchPriorPolicy<'a> {
+    pub history: &'a MatchHistory,
+    pub fallback: Option<&'a PriorPolicy<Game>>,
+}
+
+impl<'a> PriorPolicy<Game> for MatchPriorPolicy<'a> {
+    fn prior(&self, g: &Game, m: &Vec<CardIdentifier>) -> Option<Prior> {
+        let is_buy = g.pending_decision
+            .as_ref()
+            .map_or(false, |d| d.decision_type == DecisionType::BuyCard);
+        match self.history.win_rate(g, m) {
+            Some((rate, games)) if is_buy && games >= MIN_GAMES => Some(Prior {
+                value: rate,
+                visits: games.min(MAX_PRIOR_VISITS) as i32,
+            }),
+            _ => self.fallback.and_then(|p| p.prior(g, m)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use match_history::*;
+
+    fn buy_position(coins: i32) -> Game {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        g.coins = coins;
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::BuyCard,
+            choices: vec![SMITHY.identifier, SILVER.identifier],
+            range: (0, 1),
+        });
+        g
+    }
+
+    #[test]
+    fn test_match_priors() {
+        let g = buy_position(4);
+        let smithy = vec![SMITHY.identifier];
+        let silver = vec![SILVER.identifier];
+        let mut history = MatchHistory::new();
+        let policy = |h: &MatchHistory, m: &Vec<CardIdentifier>| {
+            MatchPriorPolicy {
+                history: h,
+                fallback: None,
+            }.prior(&g, m)
+        };
+
+        for &score in [1.0, 1.0, 0.0].iter() {
+            history.record_decision(&g, &smithy);
+            history.record_decision(&g, &smithy);
+            history.finish_game(score);
+            assert!(policy(&history, &silver).is_none());
+        }
+
+        let prior = policy(&history, &smithy).unwrap();
+        assert!((prior.value - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(prior.visits, 3);
+
+        // Buys with other coins are a different position.
+        assert!(history.win_rate(&buy_position(5), &smithy).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use rand;
+
+use game;
+use game::{Decider, EvalContext, GameOutcome, Rules, Verbosity};
+use match_stats::MatchStats;
+use util;
+
+// Which games a match plays.
+#[derive(Clone, Copy)]
+pub struct Schedule {
+    pub games: u32,
+    first_seed: Option<u32>,
+    // Plays each seed twice, the second time with the seats swapped.
+    paired: bool,
+}
+
+impl Schedule {
+    // Paired games need a seed to share, so one is picked if there's none.
+    pub fn new(games: u32, seed: Option<u32>, paired: bool) -> Schedule {
+        Schedule {
+            games: games,
+            first_seed: match paired {
+                true => Some(seed.unwrap_or_else(rand::random)),
+                false => seed,
+            },
+            paired: paired,
+        }
+    }
+
+    // The seed of the i-th game, from 0, and whether its seats are swapped.
+    pub fn game(&self, i: u32) -> (Option<u32>, bool) {
+        match self.paired {
+            true => (self.first_seed.map(|s| s.wrapping_add(i / 2)), i % 2 == 1),
+            false => (self.first_seed.map(|s| s.wrapping_add(i)), false),
+        }
+    }
+}
+
+// Plays a game of two players with their seats swapped if asked, returning
+// the scores in the players' order.
+pub fn play_seated(
+    players: &mut Vec<Box<Decider>>,
+    ctx: &mut EvalContext,
+    rules: &Rules,
+    swapped: bool,
+) -> GameOutcome {
+    if swapped {
+        players.reverse();
+    }
+    let mut outcome = game::play_game(players, ctx, rules);
+    if swapped {
+        players.reverse();
+        outcome.scores.reverse();
+    }
+    outcome
+}
+
+pub fn game_context(seed: Option<u32>, verbosity: Verbosity) -> EvalContext {
+    EvalContext {
+        rng: match seed {
+            Some(seed) => util::seeded_weak_rng(seed),
+            None => util::randomly_seeded_weak_rng(),
+        },
+        verbosity: verbosity,
+        events: None,
+    }
+}
+
+// Plays a match between deciders one game after another, for programs
+// using Tactician as a library. The command line's matches add threads,
+// progress, results files and SPRTs on top of the same games.
+pub struct MatchRunner {
+    players: Vec<Box<Decider>>,
+    games: u32,
+    seed: Option<u32>,
+    paired: bool,
+    rules: Rules,
+    verbosity: Verbosity,
+}
+
+impl MatchRunner {
+    // A single quiet, unseeded game with the first-game kingdom.
+    pub fn new(players: Vec<Box<Decider>>) -> MatchRunner {
+        MatchRunner {
+            players: players,
+            games: 1,
+            seed: None,
+            paired: false,
+            rules: Rules::default(),
+            verbosity: Verbosity::Quiet,
+        }
+    }
+
+    pub fn players(&self) -> &[Box<Decider>] {
+        &self.players
+    }
+
+    pub fn games(&self) -> u32 {
+        self.games
+    }
+
+    pub fn set_games(&mut self, games: u32) {
+        self.games = games;
+    }
+
+    pub fn seed(&self) -> Option<u32> {
+        self.seed
+    }
+
+    // Seeds the first game's shuffles, and each game after with the next
+    // seed, so seeded players replay the same games.
+    pub fn set_seed(&mut self, seed: Option<u32>) {
+        self.seed = seed;
+    }
+
+    pub fn paired(&self) -> bool {
+        self.paired
+    }
+
+    // Plays each seed twice with the seats swapped, which takes two players.
+    pub fn set_paired(&mut self, paired: bool) -> Result<(), String> {
+        if paired && self.players.len() != 2 {
+            return Err("Paired games need two players".into());
+        }
+        self.paired = paired;
+        Ok(())
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    pub fn run(&mut self) -> MatchStats {
+        self.run_with(&mut |_, _| true)
+    }
+
+    // Plays the match, passing each game's number, from 1, and outcome to
+    // on_game, which returns whether to play on.
+    pub fn run_with(&mut self, on_game: &mut FnMut(u32, &GameOutcome) -> bool) -> MatchStats {
+        let schedule = Schedule::new(self.games, self.seed, self.paired);
+        let mut stats = MatchStats::new(self.players.len());
+        for i in 0..self.games {
+            let (seed, swapped) = schedule.game(i);
+            let mut ctx = game_context(seed, self.verbosity);
+            let outcome = play_seated(&mut self.players, &mut ctx, &self.rules, swapped);
+            stats.record(&outcome);
+            if self.paired {
+                stats.record_pair(i / 2, outcome.scores[0]);
+            }
+            if !on_game(i + 1, &outcome) {
+                break;
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use deciders::BigMoney;
+    use match_runner::*;
+
+    #[test]
+    fn test_match_runner() {
+        let players: Vec<Box<Decider>> = vec![Box::new(BigMoney), Box::new(BigMoney)];
+        let mut runner = MatchRunner::new(players);
+        runner.set_games(4);
+        runner.set_seed(Some(7));
+        runner.set_paired(true).unwrap();
+        let mut played = vec![];
+        let stats = runner.run_with(&mut |n, outcome| {
+            played.push((n, outcome.scores.clone()));
+            n < 3
+        });
+        assert_eq!((runner.games(), runner.seed(), runner.paired()), (4, Some(7), true));
+        assert_eq!(stats.games, 3);
+        assert_eq!(played.iter().map(|p| p.0).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(stats.pair_wins + stats.pair_draws + stats.pair_losses, 1);
+
+        let three: Vec<Box<Decider>> = (0..3).map(|_| Box::new(BigMoney) as Box<Decider>).collect();
+        let mut runner = MatchRunner::new(three);
+        assert!(runner.set_paired(true).is_err());
+        assert!(!runner.paired());
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use color;
+use game::GameOutcome;
+
+// Each seat's record over a match, and how long its games lasted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatchStats {
+    pub games: u32,
+    pub scores: Vec<f32>,
+    pub wins: Vec<u32>,
+    pub draws: Vec<u32>,
+    pub losses: Vec<u32>,
+    pub turns: i32,
+    // Pairs of games played with the same seed and the seats swapped, from
+    // the first player's side: pairs with more than half the points, half
+    // and less.
+    pub pair_wins: u32,
+    pub pair_draws: u32,
+    pub pair_losses: u32,
+    // The first player's score in each pair with only one game over yet.
+    unfinished_pairs: HashMap<u32, f32>,
+}
+
+impl MatchStats {
+    pub fn new(players: usize) -> MatchStats {
+        MatchStats {
+            scores: vec![0.0; players],
+            wins: vec![0; players],
+            draws: vec![0; players],
+            losses: vec![0; players],
+            ..Default::default()
+        }
+    }
+
+    // A whole point is a win and none a loss. Anything between, such as
+    // a shared win, is a draw.
+    pub fn record(&mut self, outcome: &GameOutcome) {
+        self.games += 1;
+        self.turns += outcome.turns;
+        for (i, &score) in outcome.scores.iter().enumerate() {
+            self.scores[i] += score;
+            if score >= 1.0 {
+                self.wins[i] += 1;
+            } else if score <= 0.0 {
+                self.losses[i] += 1;
+            } else {
+                self.draws[i] += 1;
+            }
+        }
+    }
+
+    // Records the first player's score in a game of a pair, which may
+    // finish in either order.
+    pub fn record_pair(&mut self, pair: u32, score: f32) {
+        let total = match self.unfinished_pairs.remove(&pair) {
+            Some(other) => other + score,
+            None => {
+                self.unfinished_pairs.insert(pair, score);
+                return;
+            }
+        };
+        if total > 1.0 {
+            self.pair_wins += 1;
+        } else if total < 1.0 {
+            self.pair_losses += 1;
+        } else {
+            self.pair_draws += 1;
+        }
+    }
+
+    pub fn win_rate(&self, i: usize) -> f32 {
+        self.scores[i] / self.games.max(1) as f32
+    }
+
+    // The 95% confidence interval of a seat's win rate.
+    pub fn interval(&self, i: usize) -> (f32, f32) {
+        wilson_interval(self.win_rate(i), self.games)
+    }
+
+    pub fn mean_turns(&self) -> f32 {
+        self.turns as f32 / self.games.max(1) as f32
+    }
+
+    // A table of each seat's wins, draws, losses, score and win rate, and
+    // the games' average length.
+    pub fn table(&self, names: &[String]) -> String {
+        let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0).max(6);
+        let mut lines = vec![format!(
+            "{:<w$} {:>6} {:>6} {:>6} {:>7} {:>21}",
+            "Player",
+            "Wins",
+            "Draws",
+            "Losses",
+            "Score",
+            "Win rate (95%)",
+            w = width
+        )];
+        for (i, name) in names.iter().enumerate() {
+            // Padded before coloring, since color codes take no room.
+            let padded = format!("{:<w$}", name, w = width);
+            let (low, high) = self.interval(i);
+            lines.push(format!(
+                "{} {:>6} {:>6} {:>6} {:>7} {:>5.1}% ({:>5.1}-{:>5.1}%)",
+                color::player(i, &padded),
+                self.wins[i],
+                self.draws[i],
+                self.losses[i],
+                self.scores[i],
+                100.0 * self.win_rate(i),
+                100.0 * low,
+                100.0 * high
+            ));
+        }
+        lines.push(format!(
+            "{} game(s), {:.1} turns on average",
+            self.games,
+            self.mean_turns()
+        ));
+        let pairs = self.pair_wins + self.pair_draws + self.pair_losses;
+        if pairs > 0 {
+            lines.push(format!(
+                "{} seat-swapped pair(s) of games, for {}: +{} ={} -{}",
+                pairs,
+                names[0],
+                self.pair_wins,
+                self.pair_draws,
+                self.pair_losses
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+// The 95% confidence interval of a win rate over a number of games, as a
+// Wilson score interval, which unlike p ± 1.96 standard errors stays within
+// 0% to 100% and doesn't shrink to nothing when every game is won.
+pub fn wilson_interval(rate: f32, games: u32) -> (f32, f32) {
+    if games == 0 {
+        return (0.0, 1.0);
+    }
+    let (p, n, z) = (rate, games as f32, 1.96);
+    let scale = 1.0 + z * z / n;
+    let center = (p + z * z / (2.0 * n)) / scale;
+    let spread = z / scale * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+    ((center - spread).max(0.0), (center + spread).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use game::GameOutcome;
+    use match_stats::*;
+
+    #[test]
+    fn test_match_stats() {
+        let mut stats = MatchStats::new(2);
+        for scores in [[1.0, 0.0], [0.5, 0.5], [1.0, 0.0], [0.0, 1.0]].iter() {
+            stats.record(&GameOutcome {
+                scores: scores.to_vec(),
+                turns: 15,
+            });
+        }
+        assert_eq!(stats.wins, vec![2, 1]);
+        assert_eq!(stats.draws, vec![1, 1]);
+        assert_eq!(stats.losses, vec![1, 2]);
+        assert_eq!(stats.win_rate(0), 0.625);
+        assert_eq!(stats.mean_turns(), 15.0);
+        let (low, high) = stats.interval(0);
+        assert!((low - 0.2194).abs() < 1e-3 && (high - 0.9081).abs() < 1e-3);
+
+        let table = stats.table(&["Big Money".to_string(), "Random".to_string()]);
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.ends_with("4 game(s), 15.0 turns on average"));
+    }
+
+    #[test]
+    fn test_interval() {
+        let mut stats = MatchStats::new(2);
+        assert_eq!(stats.interval(0), (0.0, 1.0));
+        for _ in 0..4 {
+            stats.record(&GameOutcome {
+                scores: vec![1.0, 0.0],
+                turns: 15,
+            });
+        }
+        let (low, high) = stats.interval(0);
+        assert!((low - 0.5101).abs() < 1e-3 && high == 1.0);
+        assert!(stats.interval(1).0 < 1e-6);
+    }
+
+    #[test]
+    fn test_record_pair() {
+        let mut stats = MatchStats::new(2);
+        stats.record_pair(0, 1.0);
+        stats.record_pair(1, 0.0);
+        stats.record_pair(1, 1.0);
+        stats.record_pair(0, 0.5);
+        stats.record_pair(2, 0.0);
+        assert_eq!((stats.pair_wins, stats.pair_draws, stats.pair_losses), (1, 1, 0));
+        stats.record_pair(2, 0.5);
+        assert_eq!(stats.pair_losses, 1);
+    }
+}
@@ -20,6 +20,16 @@ impl SearchableState for NimState {
         }
     }
 
+    // The player who took the last counter scores a point.
+    fn scores(&self) -> Option<Vec<(Self::P, f32)>> {
+        self.game_result().map(|Winners(winners)| {
+            self.all_players()
+                .into_iter()
+                .map(|p| (p, if winners.contains(&p) { 1.0 } else { 0.0 }))
+                .collect()
+        })
+    }
+
     fn all_players(&self) -> Vec<Self::P> {
         vec![0, 1]
     }
@@ -35,18 +45,15 @@ impl SearchableState for NimState {
             .collect::<Vec<_>>();
     }
 
-    fn make_move(&self, choice: Self::M, _: &mut Self::C) -> Self {
-        return NimState {
-            total: self.total - choice,
-            player_turn: (self.player_turn + 1) % 2,
-        };
-    }
-
     fn make_move_mut(&mut self, choice: Self::M, _: &mut Self::C) {
         self.total -= choice;
         self.player_turn = (self.player_turn + 1) % 2;
     }
 
+    fn transposition_key(&self) -> Option<u64> {
+        Some(((self.total as u64) << 1) | self.player_turn as u64)
+    }
+
     fn printable_player_identifier(&self, p: &Self::P) -> String {
         format!("Player {}", p + 1)
     }
@@ -55,8 +62,16 @@ impl SearchableState for NimState {
 #[cfg(test)]
 mod tests {
 
+    use alpha_beta::*;
     use tree_search;
+    use tree_search_logging::TreeFormat;
+    use endgame_search;
+    use endgame_search::EndgameLimits;
     use nim::*;
+    use rand::XorShiftRng;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use util::{randomly_seeded_weak_rng, seeded_weak_rng};
 
     #[test]
     fn test_nim_search() {
@@ -64,7 +79,577 @@ mod tests {
             total: 15,
             player_turn: 0,
         };
-        let best_move = tree_search::find_best_move(start_state, 10000, &mut (), false);
+        let options = tree_search::SearchOptions::default();
+        let mut rng = randomly_seeded_weak_rng();
+        let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
         assert_eq!(best_move, 3);
     }
+
+    #[test]
+    fn test_nim_search_multiple_rollouts() {
+        let start_state = NimState {
+            total: 15,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            budget: tree_search::SearchBudget::Iterations(5000),
+            rollouts_per_leaf: 4,
+            ..Default::default()
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
+        assert_eq!(best_move, 3);
+    }
+
+    #[test]
+    fn test_nim_search_selection_policies() {
+        let policies = [
+            tree_search::SelectionPolicy::Ucb1Tuned,
+            tree_search::SelectionPolicy::Thompson,
+        ];
+        for &selection in policies.iter() {
+            let start_state = NimState {
+                total: 15,
+                player_turn: 0,
+            };
+            let options = tree_search::SearchOptions {
+                selection: selection,
+                ..Default::default()
+            };
+            let mut rng = randomly_seeded_weak_rng();
+            let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
+            assert_eq!(best_move, 3, "{:?}", selection);
+        }
+    }
+
+    #[test]
+    fn test_nim_search_with_transpositions() {
+        let start_state = NimState {
+            total: 15,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            use_transpositions: true,
+            ..Default::default()
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
+        assert_eq!(best_move, 3);
+    }
+
+    #[test]
+    fn test_nim_search_with_first_play_urgency() {
+        let start_state = NimState {
+            total: 15,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            first_play_urgency: Some(1.1),
+            ..Default::default()
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
+        assert_eq!(best_move, 3);
+    }
+
+    #[test]
+    fn test_nim_solver_takes_winning_move() {
+        let start_state = NimState {
+            total: 2,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            budget: tree_search::SearchBudget::Iterations(10),
+            solver: true,
+            ..Default::default()
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
+        assert_eq!(best_move, 2);
+    }
+
+    #[test]
+    fn test_nim_solver_proves_forced_win() {
+        // Taking one leaves 4, where every reply leaves a position one move
+        // from the end, so the solver has to carry the proof up two levels.
+        let start_state = NimState {
+            total: 5,
+            player_turn: 0,
+        };
+        // Proofs are of wins and losses, however rewards are shaped.
+        let options = tree_search::SearchOptions {
+            solver: true,
+            reward_shaping: tree_search::RewardShaping::ScoreMargin {
+                weight: 0.5,
+                scale: 10.0,
+            },
+            turn_discount: Some(0.9),
+            ..Default::default()
+        };
+        let mut tree = tree_search::SearchTree::new(&start_state);
+        let mut policies = tree_search::SearchPolicies {
+            rollout: &mut tree_search::RandomRollout,
+            evaluator: None,
+            prior: None,
+            pruner: None,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        tree_search::grow_tree(
+            &mut tree,
+            &start_state,
+            &0,
+            tree_search::SearchBudget::Iterations(1000),
+            &options,
+            &mut policies,
+            &mut rng,
+            &mut (),
+        );
+        let take_one = tree.child_with_move(tree_search::ROOT, &1).unwrap();
+        let proven = tree.nodes[take_one].proven.as_ref().expect("should be proven");
+        assert_eq!(proven.for_player(&0), 1.0);
+        assert!(tree.root().proven.is_some());
+        assert_eq!(tree.best_child(tree_search::ROOT), take_one);
+    }
+
+    #[test]
+    fn test_nim_search_without_budget() {
+        let budgets = [
+            tree_search::SearchBudget::Iterations(0),
+            tree_search::SearchBudget::Time(Duration::from_secs(0)),
+        ];
+        for &budget in budgets.iter() {
+            let start_state = NimState {
+                total: 15,
+                player_turn: 0,
+            };
+            let options = tree_search::SearchOptions {
+                budget: budget,
+                ..Default::default()
+            };
+            let mut rng = randomly_seeded_weak_rng();
+            let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
+            assert!((1..=3).contains(&best_move), "{:?}", budget);
+        }
+    }
+
+    #[test]
+    fn test_nim_search_with_rave() {
+        let start_state = NimState {
+            total: 15,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            rave_equivalence: Some(500.0),
+            ..Default::default()
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let best_move = tree_search::find_best_move(start_state, &options, &mut rng, &mut ());
+        assert_eq!(best_move, 3);
+    }
+
+    #[test]
+    fn test_nim_search_with_node_cap() {
+        let start_state = NimState {
+            total: 15,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            max_nodes: Some(2000),
+            ..Default::default()
+        };
+        let mut tree = tree_search::SearchTree::new(&start_state);
+        let mut policies = tree_search::SearchPolicies {
+            rollout: &mut tree_search::RandomRollout,
+            evaluator: None,
+            prior: None,
+            pruner: None,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let best = tree_search::search_tree(
+            &mut tree,
+            start_state,
+            &options,
+            &mut policies,
+            &mut rng,
+            &mut (),
+        );
+        assert!(tree.nodes.len() <= 2000);
+        assert_eq!(tree.nodes[best].last_move, Some(3));
+    }
+
+    #[test]
+    fn test_nim_tree_export() {
+        let start_state = NimState {
+            total: 5,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            budget: tree_search::SearchBudget::Iterations(500),
+            ..Default::default()
+        };
+        let mut tree = tree_search::SearchTree::new(&start_state);
+        let mut policies = tree_search::SearchPolicies {
+            rollout: &mut tree_search::RandomRollout,
+            evaluator: None,
+            prior: None,
+            pruner: None,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        tree_search::search_tree(
+            &mut tree,
+            start_state,
+            &options,
+            &mut policies,
+            &mut rng,
+            &mut (),
+        );
+        assert!(!tree.principal_variation().is_empty());
+
+        let dot = tree.export_tree(TreeFormat::Dot, 2);
+        assert!(dot.starts_with("digraph"));
+        assert!(dot.contains("n0 -> n1 [penwidth=3, color=red];"));
+
+        let json = tree.export_tree(TreeFormat::Json, 1);
+        assert!(json.contains("\"principal_variation\": [\"1\""));
+        assert!(json.contains("\"player\": \"Player 1\""));
+    }
+
+    #[test]
+    fn test_nim_subtree() {
+        let start_state = NimState {
+            total: 6,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            budget: tree_search::SearchBudget::Iterations(500),
+            ..Default::default()
+        };
+        let mut tree = tree_search::SearchTree::new(&start_state);
+        let mut policies = tree_search::SearchPolicies {
+            rollout: &mut tree_search::RandomRollout,
+            evaluator: None,
+            prior: None,
+            pruner: None,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let best = tree_search::search_tree(
+            &mut tree,
+            start_state,
+            &options,
+            &mut policies,
+            &mut rng,
+            &mut (),
+        );
+        let visits = tree.nodes[best].visits;
+
+        let subtree = tree.into_subtree(best);
+        assert_eq!(subtree.root().visits, visits);
+        assert_eq!(subtree.root().parent, None);
+        for (id, node) in subtree.nodes.iter().enumerate().skip(1) {
+            let parent = node.parent.unwrap();
+            assert!(subtree.nodes[parent].children.contains(&id));
+        }
+    }
+
+    #[test]
+    fn test_nim_seeded_search() {
+        let start_state = NimState {
+            total: 9,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            budget: tree_search::SearchBudget::Iterations(300),
+            ..Default::default()
+        };
+        let search = |seed| {
+            let mut tree = tree_search::SearchTree::new(&start_state);
+            let mut policies = tree_search::SearchPolicies {
+                rollout: &mut tree_search::RandomRollout,
+                evaluator: None,
+                prior: None,
+                pruner: None,
+            };
+            let mut rng = seeded_weak_rng(seed);
+            tree_search::search_tree(
+                &mut tree,
+                start_state.clone(),
+                &options,
+                &mut policies,
+                &mut rng,
+                &mut (),
+            );
+            tree.nodes.iter().map(|n| n.visits).collect::<Vec<_>>()
+        };
+        assert_eq!(search(1), search(1));
+        assert!(search(1) != search(2));
+    }
+
+    #[test]
+    fn test_discounted_rewards() {
+        let rewards = tree_search::Rewards(vec![(0, 1.0), (1, 0.0)]);
+        let discounted = rewards.clone().discounted(0.9, 2);
+        assert!((discounted.for_player(&0) - 0.81).abs() < 1e-6);
+        assert_eq!(discounted.for_player(&1), 0.0);
+        assert_eq!(rewards.clone().discounted(0.9, 0), rewards);
+    }
+
+    #[test]
+    fn test_nim_search_stats() {
+        let start_state = NimState {
+            total: 10,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            rollouts_per_leaf: 2,
+            ..Default::default()
+        };
+        let mut tree = tree_search::SearchTree::new(&start_state);
+        let mut policies = tree_search::SearchPolicies {
+            rollout: &mut tree_search::RandomRollout,
+            evaluator: None,
+            prior: None,
+            pruner: None,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let stats = tree_search::grow_tree(
+            &mut tree,
+            &start_state,
+            &0,
+            tree_search::SearchBudget::Iterations(300),
+            &options,
+            &mut policies,
+            &mut rng,
+            &mut (),
+        );
+        assert_eq!(stats.iterations, 300);
+        assert_eq!(stats.rollouts, 600);
+        assert_eq!(stats.nodes_expanded as usize, tree.nodes.len() - 1);
+        assert!(stats.max_depth > 1 && stats.max_depth <= 10);
+        let branching = stats.effective_branching_factor();
+        assert!(branching > 1.0 && branching <= 3.0);
+
+        let mut total = stats;
+        total.add(&stats);
+        assert_eq!(total.searches, 2);
+        assert_eq!(total.max_depth, stats.max_depth);
+    }
+
+    // Takes as many as it can, up to a fixed amount.
+    struct TakeRollout(i32);
+
+    impl tree_search::RolloutPolicy<NimState> for TakeRollout {
+        fn choose_move(
+            &mut self,
+            state: &NimState,
+            _: &mut XorShiftRng,
+        ) -> Option<i32> {
+            match state.total {
+                0 => None,
+                total => Some(self.0.min(total)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_opponent_model_rollout() {
+        let mut own = TakeRollout(3);
+        let mut opponents = TakeRollout(1);
+        let mut policy = tree_search::OpponentModelRollout {
+            player: 0,
+            own: &mut own,
+            opponents: &mut opponents,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+
+        // Player 0 takes 3 and player 1 takes 1, so from 7 player 0 takes
+        // the last stones.
+        let mut state = NimState {
+            total: 7,
+            player_turn: 0,
+        };
+        while let Some(m) = policy.choose_move(&state, &mut rng) {
+            state.make_move_mut(m, &mut ());
+        }
+        assert_eq!(state.game_result(), Some(Winners(vec![0])));
+    }
+
+    #[test]
+    fn test_epsilon_greedy_rollout() {
+        let state = NimState {
+            total: 10,
+            player_turn: 0,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        let mut take = TakeRollout(2);
+        let mut moves = |epsilon: f32, rng: &mut XorShiftRng| {
+            let mut policy = tree_search::EpsilonGreedyRollout {
+                policy: &mut take,
+                epsilon: epsilon,
+            };
+            (0..100)
+                .map(|_| policy.choose_move(&state, rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(moves(0.0, &mut rng).iter().all(|&m| m == 2));
+        let random = moves(1.0, &mut rng);
+        assert!(random.iter().all(|&m| m >= 1 && m <= 3));
+        assert!(random.iter().any(|&m| m != 2));
+    }
+
+    struct EvenEvaluator;
+
+    impl tree_search::Evaluator<NimState> for EvenEvaluator {
+        fn evaluate(&self, _: &NimState) -> Rewards<i32> {
+            Rewards(vec![(0, 0.5), (1, 0.5)])
+        }
+    }
+
+    #[test]
+    fn test_nim_endgame_search() {
+        let state = NimState {
+            total: 6,
+            player_turn: 0,
+        };
+        let limits = EndgameLimits {
+            turns: 0,
+            determinizations: 1,
+            max_nodes: 1000,
+        };
+
+        // Taking 2 leaves a multiple of 4, which always wins.
+        let values = endgame_search::search(&state, &EvenEvaluator, limits, &mut ()).unwrap();
+        assert_eq!(values, vec![(1, 0.0), (2, 1.0), (3, 0.0)]);
+
+        let tiny = EndgameLimits {
+            max_nodes: 5,
+            ..limits
+        };
+        assert!(endgame_search::search(&state, &EvenEvaluator, tiny, &mut ()).is_none());
+    }
+
+    #[test]
+    fn test_nim_search_interrupt() {
+        static STOP: AtomicBool = AtomicBool::new(false);
+        let start_state = NimState {
+            total: 15,
+            player_turn: 0,
+        };
+        let options = tree_search::SearchOptions {
+            interrupt: Some(&STOP),
+            ..Default::default()
+        };
+        let mut tree = tree_search::SearchTree::new(&start_state);
+        let mut policies = tree_search::SearchPolicies {
+            rollout: &mut tree_search::RandomRollout,
+            evaluator: None,
+            prior: None,
+            pruner: None,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+
+        // The search always runs once, so it has a move to return, and
+        // clears the flag when it stops.
+        STOP.store(true, Ordering::SeqCst);
+        let stats = tree_search::grow_tree(
+            &mut tree,
+            &start_state,
+            &0,
+            tree_search::SearchBudget::Iterations(10000),
+            &options,
+            &mut policies,
+            &mut rng,
+            &mut (),
+        );
+        assert_eq!(stats.iterations, 1);
+        assert_eq!(stats.interrupted, 1);
+        assert!(!STOP.load(Ordering::SeqCst));
+        assert_eq!(tree.root().children.len(), 1);
+    }
+
+    #[test]
+    fn test_nim_alpha_beta() {
+        let mut nodes = vec![];
+        for &use_transpositions in [false, true].iter() {
+            let options = AlphaBetaOptions {
+                use_transpositions: use_transpositions,
+                ..Default::default()
+            };
+            let mut total_nodes = 0;
+            for total in 1..16 {
+                let state = NimState {
+                    total: total,
+                    player_turn: 0,
+                };
+                let result = alpha_beta(&state, &options, None, &mut ());
+                total_nodes += result.nodes;
+                // Leaving a multiple of four wins.
+                match total % 4 {
+                    0 => assert_eq!(result.value, 0.0),
+                    n => {
+                        assert_eq!(result.value, 1.0);
+                        assert_eq!(result.best_move, Some(n));
+                    }
+                }
+            }
+            nodes.push(total_nodes);
+        }
+        assert!(nodes[1] < nodes[0]);
+
+        // Too shallow to see the end, every move looks even.
+        let state = NimState {
+            total: 15,
+            player_turn: 0,
+        };
+        let shallow = AlphaBetaOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(alpha_beta(&state, &shallow, None, &mut ()).value, 0.5);
+    }
+
+    #[test]
+    fn test_nim_search_matches_alpha_beta() {
+        let options = tree_search::SearchOptions {
+            budget: tree_search::SearchBudget::Iterations(2000),
+            solver: true,
+            ..Default::default()
+        };
+        for total in (1..13).filter(|t| t % 4 != 0) {
+            let state = NimState {
+                total: total,
+                player_turn: 0,
+            };
+            let values = move_values(&state, &AlphaBetaOptions::default(), None, &mut ());
+            let mut rng = seeded_weak_rng(total as u32);
+            let best_move = tree_search::find_best_move(state, &options, &mut rng, &mut ());
+            let value = values.iter().find(|&&(m, _)| m == best_move).unwrap().1;
+            assert_eq!(value, 1.0, "{} stones, took {}", total, best_move);
+        }
+    }
+
+    #[test]
+    fn test_alpha_beta_player() {
+        let mut own = AlphaBetaPlayer {
+            options: AlphaBetaOptions::default(),
+            ctx: (),
+        };
+        let mut opponents = tree_search::RandomRollout;
+        let mut policy = tree_search::OpponentModelRollout {
+            player: 0,
+            own: &mut own,
+            opponents: &mut opponents,
+        };
+        let mut rng = randomly_seeded_weak_rng();
+        for _ in 0..20 {
+            let mut state = NimState {
+                total: 15,
+                player_turn: 0,
+            };
+            while let Some(m) = policy.choose_move(&state, &mut rng) {
+                state.make_move_mut(m, &mut ());
+            }
+            assert_eq!(state.game_result(), Some(Winners(vec![0])));
+        }
+    }
 }
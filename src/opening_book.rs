@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use cards;
+use cards::CardIdentifier;
+use game::{DecisionType, Game};
+
+// Buys for a player's first two turns, given how their starting Coppers
+// split between the two opening hands (e.g. 5/2 or 4/3). An empty buy means
+// buying nothing.
+pub struct OpeningBook {
+    lines: HashMap<(Vec<CardIdentifier>, (i32, i32)), (Vec<CardIdentifier>, Vec<CardIdentifier>)>,
+}
+
+// Kingdom cards are the supply piles beyond the base treasure, victory and
+// curse cards, sorted so a kingdom identifies the same way however it was
+// built.
+pub fn kingdom_cards(piles: &HashMap<CardIdentifier, i32>) -> Vec<CardIdentifier> {
+    let mut kingdom = piles
+        .keys()
+        .filter(|c| cards::lookup_card(c).is_action())
+        .cloned()
+        .collect::<Vec<_>>();
+    kingdom.sort();
+    kingdom
+}
+
+impl OpeningBook {
+    pub fn new() -> OpeningBook {
+        OpeningBook {
+            lines: HashMap::new(),
+        }
+    }
+
+    // Openings for the first game kingdom, built around Big Money with a
+    // single Smithy or Militia.
+    pub fn standard() -> OpeningBook {
+        let mut book = OpeningBook::new();
+        let first_game = kingdom_cards(&cards::standard_piles(2));
+
+        let smithy = vec![cards::SMITHY.identifier];
+        let militia = vec![cards::MILITIA.identifier];
+        let silver = vec![cards::SILVER.identifier];
+        book.add_line(&first_game, (4, 3), smithy.clone(), silver.clone());
+        book.add_line(&first_game, (3, 4), silver.clone(), smithy.clone());
+        book.add_line(&first_game, (5, 2), militia.clone(), vec![]);
+        book.add_line(&first_game, (2, 5), vec![], militia.clone());
+        book
+    }
+
+    pub fn add_line(
+        &mut self,
+        kingdom: &Vec<CardIdentifier>,
+        split: (i32, i32),
+        first_buy: Vec<CardIdentifier>,
+        second_buy: Vec<CardIdentifier>,
+    ) {
+        self.lines
+            .insert((kingdom.clone(), split), (first_buy, second_buy));
+    }
+
+    // Looks up the book buy for a pending buy decision in one of the first
+    // two turns, given what the player had in their first hand. Returns None
+    // when the book has no line or its buy isn't available.
+    pub fn book_buy(&self, g: &Game, first_turn_coins: i32) -> Option<Vec<CardIdentifier>> {
+        let d = match g.pending_decision {
+            Some(ref d) if d.decision_type == DecisionType::BuyCard => d,
+            _ => return None,
+        };
+        if g.turn > 2 {
+            return None;
+        }
+
+        let starting_coppers = 7;
+        let split = (first_turn_coins, starting_coppers - first_turn_coins);
+        let line = self.lines.get(&(kingdom_cards(&g.piles), split))?;
+        let buy = if g.turn == 1 { &line.0 } else { &line.1 };
+        if buy.iter().all(|c| d.choices.contains(c)) {
+            Some(buy.clone())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use opening_book::*;
+
+    fn buy_decision(g: &mut Game, coins: i32) {
+        g.coins = coins;
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::BuyCard,
+            choices: vec![SILVER.identifier, SMITHY.identifier, MILITIA.identifier],
+            range: (0, 1),
+        });
+    }
+
+    #[test]
+    fn test_book_buys() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let book = OpeningBook::standard();
+        let mut g = fresh_game(&names);
+
+        buy_decision(&mut g, 4);
+        assert_eq!(book.book_buy(&g, 4), Some(vec![SMITHY.identifier]));
+
+        g.turn = 2;
+        buy_decision(&mut g, 3);
+        assert_eq!(book.book_buy(&g, 4), Some(vec![SILVER.identifier]));
+
+        g.turn = 3;
+        assert_eq!(book.book_buy(&g, 4), None);
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+
+use cards;
+use cards::CardIdentifier;
+use deciders::{plus_actions, BigMoney};
+use game::{Decider, DecisionType, Game};
+use registry::{OptionSpec, PlayerKind, PlayerSpec, Registry};
+
+// Buy a card, unless the player already has limit copies of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuyRule {
+    pub card: CardIdentifier,
+    pub limit: Option<usize>,
+}
+
+// A strategy in the style of Dominion simulators: buy the first card in the
+// buy list that's offered and under its limit, and discard cards in the
+// order of the discard list. Cards missing from the discard list are only
+// discarded when an attack forces it, cheapest first.
+//
+// Strategies are written as "buy=Province/Gold/Smithy*1/Silver,
+// discard=Curse/Estate/Copper", where "*n" limits how many copies to buy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriorityStrategy {
+    pub buys: Vec<BuyRule>,
+    pub discards: Vec<CardIdentifier>,
+}
+
+fn parse_card(name: &str) -> Result<CardIdentifier, String> {
+    cards::card_named(name)
+        .map(|c| c.identifier)
+        .ok_or(format!("Unknown card {}", name))
+}
+
+impl PriorityStrategy {
+    pub fn parse_buys(list: &str) -> Result<Vec<BuyRule>, String> {
+        list.split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let mut parts = s.splitn(2, '*');
+                let card = parse_card(parts.next().unwrap())?;
+                let limit = match parts.next() {
+                    Some(n) => Some(n.parse::<usize>()
+                        .map_err(|_| format!("Invalid limit {} for {}", n, card))?),
+                    None => None,
+                };
+                Ok(BuyRule {
+                    card: card,
+                    limit: limit,
+                })
+            })
+            .collect()
+    }
+
+    pub fn parse_discards(list: &str) -> Result<Vec<CardIdentifier>, String> {
+        list.split('/')
+            .filter(|s| !s.is_empty())
+            .map(parse_card)
+            .collect()
+    }
+
+    fn card_to_buy(&self, g: &Game, choices: &Vec<CardIdentifier>) -> Option<CardIdentifier> {
+        let d = g.pending_decision.as_ref().unwrap();
+        let mut owned = g.players[d.player.0 as usize].all_cards();
+        if d.player == g.active_player {
+            owned.extend(&g.play_area);
+        }
+        self.buys
+            .iter()
+            .find(|r| {
+                choices.contains(&r.card)
+                    && r.limit
+                        .map_or(true, |n| owned.iter().filter(|&&c| c == r.card).count() < n)
+            })
+            .map(|r| r.card)
+    }
+
+    fn cards_to_discard(&self, choices: &Vec<CardIdentifier>, range: (usize, usize)) -> Vec<CardIdentifier> {
+        let mut remaining = choices.clone();
+        let mut discarded = vec![];
+        for c in self.discards.iter() {
+            while discarded.len() < range.1 {
+                match remaining.iter().position(|r| r == c) {
+                    Some(i) => discarded.push(remaining.remove(i)),
+                    None => break,
+                }
+            }
+        }
+
+        remaining.sort_by_key(|c| cards::lookup_card(c).cost);
+        let missing = range.0.saturating_sub(discarded.len());
+        discarded.extend(remaining.into_iter().take(missing));
+        discarded
+    }
+}
+
+impl fmt::Display for PriorityStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let buys = self.buys
+            .iter()
+            .map(|r| match r.limit {
+                Some(n) => format!("{}*{}", r.card, n),
+                None => format!("{}", r.card),
+            })
+            .collect::<Vec<_>>();
+        let discards = self.discards
+            .iter()
+            .map(|c| format!("{}", c))
+            .collect::<Vec<_>>();
+        write!(f, "buy={},discard={}", buys.join("/"), discards.join("/"))
+    }
+}
+
+pub struct PriorityDecider {
+    pub strategy: PriorityStrategy,
+}
+
+impl Decider for PriorityDecider {
+    fn description(&self) -> String {
+        return "Priority".into();
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("PriorityDecider::make_decision called without pending decision");
+        match d.decision_type {
+            DecisionType::PlayAction => {
+                let mut actions = d.choices.clone();
+                actions.sort_by_key(|c| -plus_actions(c));
+                actions.into_iter().take(1).collect()
+            }
+            DecisionType::BuyCard => self.strategy.card_to_buy(g, &d.choices).into_iter().collect(),
+            DecisionType::GainCard(_) => match self.strategy.card_to_buy(g, &d.choices) {
+                Some(c) => vec![c],
+                None => BigMoney.make_decision(g),
+            },
+            DecisionType::DiscardCards(_) => self.strategy.cards_to_discard(&d.choices, d.range),
+            _ => BigMoney.make_decision(g),
+        }
+    }
+}
+
+fn priority(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    let mut strategy = PriorityStrategy {
+        buys: vec![],
+        discards: vec![],
+    };
+    for &(key, value) in spec.options.iter() {
+        match key {
+            "buy" => strategy.buys = PriorityStrategy::parse_buys(value)?,
+            "discard" => strategy.discards = PriorityStrategy::parse_discards(value)?,
+            _ => unreachable!(),
+        }
+    }
+    Ok(Box::new(PriorityDecider { strategy: strategy }))
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "priority",
+        summary: "buys the first card of its list that is offered",
+        example: "priority:buy=Province/Gold/Smithy*1/Silver",
+        argument: None,
+        options: &[
+            OptionSpec { key: "buy", value: "Card*N/...", help: "cards to buy, best first", default: "" },
+            OptionSpec { key: "discard", value: "Card/...", help: "cards to discard, first first", default: "" },
+        ],
+        factory: priority,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use priority_strategy::*;
+
+    #[test]
+    fn test_priority_strategy() {
+        let strategy = PriorityStrategy {
+            buys: PriorityStrategy::parse_buys("Province/Gold/Smithy*1/Silver").unwrap(),
+            discards: PriorityStrategy::parse_discards("Estate/Copper").unwrap(),
+        };
+        assert_eq!(strategy.buys[2].limit, Some(1));
+        assert_eq!(
+            strategy.to_string(),
+            "buy=Province/Gold/Smithy*1/Silver,discard=Estate/Copper"
+        );
+
+        let hand = vec![
+            SILVER.identifier,
+            COPPER.identifier,
+            ESTATE.identifier,
+            SMITHY.identifier,
+            COPPER.identifier,
+        ];
+        assert_eq!(
+            strategy.cards_to_discard(&hand, (2, 2)),
+            vec![ESTATE.identifier, COPPER.identifier]
+        );
+        assert_eq!(
+            strategy.cards_to_discard(&hand, (4, 4)),
+            vec![
+                ESTATE.identifier,
+                COPPER.identifier,
+                COPPER.identifier,
+                SILVER.identifier,
+            ]
+        );
+        assert_eq!(strategy.cards_to_discard(&hand, (0, 5)).len(), 3);
+        assert!(PriorityStrategy::parse_buys("Gold/Platinum").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use cards::CardIdentifier;
+use game::{Decider, Decision, DecisionError, EvalContext, Game, Verbosity};
+use game_serialization::cards_from_json;
+use json::Json;
+use registry::{parse_option_duration, OptionSpec, PlayerKind, PlayerSpec, Registry};
+use util;
+
+// Plays by asking another program, so bots written in any language can play
+// through the same harness. Messages are single lines of JSON. For each
+// decision the program is sent
+//
+//     {"type": "decision", "state": {...}}
+//
+// where the state is the game as saved by to_json, with the cards hidden
+// from the player reshuffled and the pending decision included. It replies
+// with a list of the card names it chooses, e.g. ["Silver"] or []. After each
+// game it's sent
+//
+//     {"type": "game_over", "state": {...}, "scores": [1, 0]}
+//
+// and needn't reply. Scores are null if a player forfeited. The program's
+// stdin is closed when the match ends. A program that exits, replies with
+// an illegal choice, or runs past the timeout is stopped, and forfeits
+// this and every later game.
+pub struct ProcessDecider {
+    command: String,
+    child: Child,
+    // None once the program has been stopped.
+    stdin: Option<ChildStdin>,
+    // Lines of output, read on their own thread so replies can time out.
+    replies: Receiver<String>,
+    pub timeout: Option<Duration>,
+    ctx: EvalContext,
+}
+
+// Parses a reply, a list of card names, and checks it's a legal choice.
+pub fn parse_choice(line: &str, d: &Decision) -> Result<Vec<CardIdentifier>, String> {
+    let choice = Json::parse(line).and_then(|j| cards_from_json(&j))?;
+    check_choice(&choice, d)?;
+    Ok(choice)
+}
+
+// Checks that a choice picks from the cards offered, as many times as
+// they're offered, and picks an allowed number of them.
+pub fn check_choice(choice: &[CardIdentifier], d: &Decision) -> Result<(), String> {
+    let mut remaining = d.choices.clone();
+    for c in choice.iter() {
+        match remaining.iter().position(|r| r == c) {
+            Some(i) => {
+                remaining.remove(i);
+            }
+            None => return Err(format!("{} wasn't offered", c)),
+        }
+    }
+    if choice.len() < d.range.0 || choice.len() > d.range.1 {
+        return Err(format!(
+            "Expected between {} and {} cards, got {}",
+            d.range.0,
+            d.range.1,
+            choice.len()
+        ));
+    }
+    Ok(())
+}
+
+impl ProcessDecider {
+    // Starts the command with the shell, so it may include arguments.
+    pub fn spawn(command: &str) -> Result<ProcessDecider, String> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Unable to start {}: {}", command, e))?;
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender, replies) = channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() {
+                        break;
+                    },
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(ProcessDecider {
+            command: command.into(),
+            child: child,
+            stdin: stdin,
+            replies: replies,
+            timeout: None,
+            ctx: EvalContext {
+                rng: util::randomly_seeded_weak_rng(),
+                verbosity: Verbosity::Quiet,
+                events: None,
+            },
+        })
+    }
+
+    fn send(&mut self, message: Json) -> Result<(), DecisionError> {
+        let stdin = match self.stdin.as_mut() {
+            Some(stdin) => stdin,
+            None => return Err(DecisionError::Disconnected(format!("{} was stopped", self.command))),
+        };
+        writeln!(stdin, "{}", message)
+            .and_then(|_| stdin.flush())
+            .map_err(|e| DecisionError::Disconnected(format!("{}: {}", self.command, e)))
+    }
+
+    fn receive(&mut self) -> Result<String, DecisionError> {
+        let exited = || DecisionError::Disconnected(format!("{} exited", self.command));
+        match self.timeout {
+            Some(timeout) => self.replies.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => DecisionError::TimedOut,
+                RecvTimeoutError::Disconnected => exited(),
+            }),
+            None => self.replies.recv().map_err(|_| exited()),
+        }
+    }
+
+    fn request_choice(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("ProcessDecider::make_decision called without pending decision");
+        let state = g.determinize(d.player, &mut self.ctx);
+        self.send(Json::object(vec![
+            ("type", "decision".into()),
+            ("state", state.to_json()),
+        ]))?;
+        let line = self.receive()?;
+        parse_choice(&line, d).map_err(|e| DecisionError::InvalidInput(format!("{}: {}", self.command, e)))
+    }
+
+    // Stops the program, so a late reply can't be taken as the answer to a
+    // later decision.
+    fn stop(&mut self) {
+        self.stdin.take();
+        let _ = self.child.kill();
+    }
+}
+
+impl Decider for ProcessDecider {
+    fn description(&self) -> String {
+        self.command.clone()
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        match self.try_make_decision(g) {
+            Ok(choice) => choice,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        let choice = self.request_choice(g);
+        if choice.is_err() {
+            self.stop();
+        }
+        choice
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        let scores = match g.is_game_over() {
+            true => Json::Array(
+                g.player_scores()
+                    .iter()
+                    .map(|&(_, score)| score.into())
+                    .collect(),
+            ),
+            false => Json::Null,
+        };
+        let message = Json::object(vec![
+            ("type", "game_over".into()),
+            ("state", g.to_json()),
+            ("scores", scores),
+        ]);
+        // A stopped program has already forfeited, so there's no one to tell.
+        let _ = self.send(message);
+    }
+}
+
+impl Drop for ProcessDecider {
+    fn drop(&mut self) {
+        // Closing stdin tells the program the match is over.
+        self.stdin.take();
+        let _ = self.child.wait();
+    }
+}
+
+fn process(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    let mut command = None;
+    let mut timeout = None;
+    for &(key, value) in spec.options.iter() {
+        match key {
+            "cmd" => command = Some(value),
+            "timeout" => timeout = Some(parse_option_duration(key, value)?),
+            _ => unreachable!(),
+        }
+    }
+    match command.map(ProcessDecider::spawn) {
+        Some(Ok(mut d)) => {
+            d.timeout = timeout;
+            Ok(Box::new(d))
+        }
+        Some(Err(e)) => Err(e),
+        None => Err("Player process needs a command, e.g. \"process:cmd=python3 bot.py\"".into()),
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "process",
+        summary: "a bot in another program, talking in lines of JSON",
+        example: "process:cmd=python3 bot.py,timeout=2s",
+        argument: None,
+        options: &[
+            OptionSpec { key: "cmd", value: "command", help: "the command to run", default: "" },
+            OptionSpec { key: "timeout", value: "duration", help: "longest wait for a reply", default: "" },
+        ],
+        factory: process,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use process_decider::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_choice() {
+        let d = Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::DiscardCards(None),
+            choices: vec![COPPER.identifier, ESTATE.identifier, COPPER.identifier],
+            range: (0, 2),
+        };
+        assert!(parse_choice("[\"Copper\", \"Copper\"]\n", &d) == Ok(vec![COPPER.identifier; 2]));
+        assert!(parse_choice("[]", &d) == Ok(vec![]));
+        assert!(parse_choice("[\"Estate\", \"Estate\"]", &d).is_err());
+        assert!(parse_choice("[\"Gold\"]", &d).is_err());
+        assert!(parse_choice("[\"Copper\", \"Estate\", \"Copper\"]", &d).is_err());
+        assert!(parse_choice("Copper", &d).is_err());
+    }
+
+    #[test]
+    fn test_process_decider() {
+        // A bot that never plays or buys anything.
+        let mut bot = ProcessDecider::spawn("while read line; do echo '[]'; done").unwrap();
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        let mut ctx = EvalContext {
+            rng: util::randomly_seeded_weak_rng(),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        g.initialize_game(&mut ctx);
+        while g.pending_decision.is_none() {
+            g.advance_game(&mut ctx);
+        }
+        assert!(bot.make_decision(&g).is_empty());
+
+        let mut slow = ProcessDecider::spawn("read line; sleep 5; echo '[]'").unwrap();
+        slow.timeout = Some(Duration::from_millis(50));
+        assert!(slow.try_make_decision(&g) == Err(DecisionError::TimedOut));
+        match slow.try_make_decision(&g) {
+            Err(DecisionError::Disconnected(_)) => {}
+            _ => panic!("A stopped bot should stay disconnected"),
+        }
+    }
+}
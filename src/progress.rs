@@ -0,0 +1,208 @@
+use libc;
+use match_stats;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 20;
+const CHART_WIDTH: usize = 40;
+// Sparkline characters, from a win rate of none to all.
+const LEVELS: &[u8] = b"_.-~*^";
+
+// Reports a long run's progress on stderr after each game: games played,
+// time taken, games per second and each player's running win rate. On a
+// terminal the line is redrawn in place. Otherwise, e.g. when stderr goes to
+// a log file, a line is written every twentieth of the run. With a chart,
+// the first player's running win rate is drawn below, with its confidence
+// band, so it's clear early on whether a match is decided or needs more
+// games.
+pub struct Progress {
+    total: u32,
+    names: Vec<String>,
+    started: Instant,
+    terminal: bool,
+    chart: Option<WinRateChart>,
+}
+
+impl Progress {
+    pub fn new(total: u32, names: Vec<String>, chart: bool) -> Progress {
+        Progress {
+            total: total,
+            names: names,
+            started: Instant::now(),
+            terminal: unsafe { libc::isatty(2) == 1 },
+            chart: match chart {
+                true => Some(WinRateChart::default()),
+                false => None,
+            },
+        }
+    }
+
+    pub fn update(&mut self, played: u32, scores: &[f32]) {
+        let mut lines = vec![status_line(
+            played,
+            self.total,
+            self.started.elapsed(),
+            &self.names,
+            scores,
+        )];
+        if let Some(ref mut chart) = self.chart {
+            chart.record(scores[0] / played.max(1) as f32);
+            lines.extend(chart.lines(&self.names[0], played));
+        }
+        let stderr = io::stderr();
+        let mut out = stderr.lock();
+        if self.terminal {
+            // Redraws every line, then goes back up to the first.
+            let _ = write!(out, "\r\x1b[J{}", lines.join("\n"));
+            if lines.len() > 1 {
+                let _ = write!(out, "\x1b[{}A\r", lines.len() - 1);
+            }
+            let _ = out.flush();
+        } else if played % (self.total / 20).max(1) == 0 || played == self.total {
+            let _ = writeln!(out, "{}", lines.join("\n"));
+        }
+    }
+
+    // Ends the redrawn lines, so what's printed next starts on its own.
+    pub fn finish(&self) {
+        if self.terminal {
+            if let Some(ref chart) = self.chart {
+                if !chart.rates.is_empty() {
+                    eprint!("\x1b[2B");
+                }
+            }
+            eprintln!("");
+        }
+    }
+}
+
+// The first player's win rate after each game.
+#[derive(Default)]
+struct WinRateChart {
+    rates: Vec<f32>,
+}
+
+impl WinRateChart {
+    fn record(&mut self, rate: f32) {
+        self.rates.push(rate);
+    }
+
+    // A sparkline of the win rate over the match so far, each character
+    // the rate at the end of its share of the games, and the latest rate's
+    // 95% confidence band on a scale from 0% to 100%, with 50% marked.
+    fn lines(&self, name: &str, played: u32) -> Vec<String> {
+        let columns = self.rates.len().min(CHART_WIDTH);
+        let spark = (0..columns)
+            .map(|i| {
+                let rate = self.rates[(i + 1) * self.rates.len() / columns - 1];
+                let level = (rate * LEVELS.len() as f32) as usize;
+                LEVELS[level.min(LEVELS.len() - 1)] as char
+            })
+            .collect::<String>();
+
+        let rate = self.rates.last().cloned().unwrap_or(0.0);
+        let (low_rate, high_rate) = match_stats::wilson_interval(rate, played);
+        let column = |r: f32| ((r.max(0.0).min(1.0) * (CHART_WIDTH - 1) as f32).round()) as usize;
+        let (low, high) = (column(low_rate), column(high_rate));
+        let band = (0..CHART_WIDTH)
+            .map(|i| {
+                if i == column(rate) {
+                    '|'
+                } else if i == low {
+                    '['
+                } else if i == high {
+                    ']'
+                } else if i > low && i < high {
+                    '='
+                } else if i == column(0.5) {
+                    ':'
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        vec![
+            format!(
+                "{} win rate {:<w$} {:.1}% ({:.1}-{:.1}%)",
+                name,
+                spark,
+                100.0 * rate,
+                100.0 * low_rate,
+                100.0 * high_rate,
+                w = CHART_WIDTH
+            ),
+            format!("0% {} 100%", band),
+        ]
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// E.g. "[#####---------------] 25/100 0:50 eta 2:30 0.50 games/s | Tactician
+// 64.0% | Big Money 36.0%".
+fn status_line(
+    played: u32,
+    total: u32,
+    elapsed: Duration,
+    names: &[String],
+    scores: &[f32],
+) -> String {
+    let filled = BAR_WIDTH * played as usize / total.max(1) as usize;
+    let bar = format!("{}{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled));
+    let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+    let mut line = format!("[{}] {}/{} {}", bar, played, total, format_duration(elapsed));
+    if played > 0 && played < total {
+        let remaining = secs / played as f64 * (total - played) as f64;
+        line += &format!(" eta {}", format_duration(Duration::from_secs(remaining as u64)));
+    }
+    if secs > 0.0 {
+        line += &format!(" {:.2} games/s", played as f64 / secs);
+    }
+    for (name, score) in names.iter().zip(scores.iter()) {
+        let rate = match played {
+            0 => 0.0,
+            _ => 100.0 * *score as f64 / played as f64,
+        };
+        line += &format!(" | {} {:.1}%", name, rate);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+
+    use progress::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_status_line() {
+        let names = vec!["Tactician".to_string(), "Big Money".to_string()];
+        assert_eq!(
+            status_line(25, 100, Duration::from_secs(50), &names, &[16.0, 9.0]),
+            "[#####---------------] 25/100 0:50 eta 2:30 0.50 games/s | Tactician 64.0% | Big Money 36.0%"
+        );
+        assert_eq!(
+            status_line(0, 10, Duration::from_secs(0), &names, &[0.0, 0.0]),
+            "[--------------------] 0/10 0:00 | Tactician 0.0% | Big Money 0.0%"
+        );
+    }
+
+    #[test]
+    fn test_win_rate_chart() {
+        let mut chart = WinRateChart::default();
+        for &rate in [1.0, 0.5, 0.0, 0.25].iter() {
+            chart.record(rate);
+        }
+        let lines = chart.lines("Tactician", 100);
+        assert!(lines[0].starts_with("Tactician win rate ^~_. "));
+        assert!(lines[0].ends_with(" 25.0% (17.5-34.3%)"));
+        assert_eq!(lines[1], "0% .......[==|==]......:................... 100%");
+    }
+}
@@ -0,0 +1,142 @@
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString};
+
+use embedded_game::EmbeddedGame;
+use json::Json;
+
+// The tactician module for Python, with the python feature, so the
+// simulator and search can be driven from reinforcement learning code:
+//
+//     import tactician
+//     game = tactician.Game(["Alice", "Bob"], seed=7)
+//     while not game.status()["over"]:
+//         game.apply_move(game.ai_choose(1000))
+//
+// States, statuses and moves are the same dicts and lists the JSON-RPC
+// server sends, with cards named. Mistakes, such as an illegal move, raise
+// ValueError.
+
+fn to_python(py: Python, j: &Json) -> PyResult<PyObject> {
+    Ok(match *j {
+        Json::Null => py.None(),
+        Json::Bool(b) => PyBool::new(py, b).to_owned().into_any().unbind(),
+        // Whole numbers, such as counts and turns, come back as ints.
+        Json::Number(n) if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 => {
+            (n as i64).into_pyobject(py)?.into_any().unbind()
+        }
+        Json::Number(n) => PyFloat::new(py, n).into_any().unbind(),
+        Json::String(ref s) => PyString::new(py, s).into_any().unbind(),
+        Json::Array(ref values) => {
+            let values = values.iter().map(|v| to_python(py, v)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, values)?.into_any().unbind()
+        }
+        Json::Object(ref fields) => {
+            let dict = PyDict::new(py);
+            for &(ref key, ref value) in fields {
+                dict.set_item(key, to_python(py, value)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+fn from_python(value: &Bound<PyAny>) -> PyResult<Json> {
+    if value.is_none() {
+        Ok(Json::Null)
+    } else if let Ok(b) = value.downcast::<PyBool>() {
+        Ok(Json::Bool(b.is_true()))
+    } else if value.is_instance_of::<PyInt>() || value.is_instance_of::<PyFloat>() {
+        Ok(Json::Number(value.extract::<f64>()?))
+    } else if let Ok(s) = value.downcast::<PyString>() {
+        Ok(Json::String(s.to_str()?.to_string()))
+    } else if let Ok(dict) = value.downcast::<PyDict>() {
+        let mut fields = vec![];
+        for (key, value) in dict.iter() {
+            fields.push((key.extract::<String>()?, from_python(&value)?));
+        }
+        Ok(Json::Object(fields))
+    } else if let Ok(values) = value.try_iter() {
+        let values = values.map(|v| from_python(&v?)).collect::<PyResult<Vec<_>>>()?;
+        Ok(Json::Array(values))
+    } else {
+        Err(PyTypeError::new_err(format!("Unable to convert {} to JSON", value)))
+    }
+}
+
+fn parsed(py: Python, result: Result<String, String>) -> PyResult<PyObject> {
+    let text = result.map_err(PyValueError::new_err)?;
+    let j = Json::parse(&text).map_err(PyValueError::new_err)?;
+    to_python(py, &j)
+}
+
+// A game, always waiting on a decision or over.
+#[pyclass(name = "Game", unsendable)]
+struct PyGame {
+    inner: EmbeddedGame,
+}
+
+#[pymethods]
+impl PyGame {
+    // Deals a game between players, by name, with a kingdom as --kingdom
+    // takes it. By default there are two players and the first-game kingdom.
+    #[new]
+    #[pyo3(signature = (players=None, kingdom="", seed=0))]
+    fn new(players: Option<Vec<String>>, kingdom: &str, seed: u32) -> PyResult<PyGame> {
+        let players = match players {
+            Some(names) => Json::Array(names.into_iter().map(Json::from).collect()).to_string(),
+            None => String::new(),
+        };
+        let inner = EmbeddedGame::new(&players, kingdom, seed).map_err(PyValueError::new_err)?;
+        Ok(PyGame { inner: inner })
+    }
+
+    // Picks up a game from a state() dict.
+    #[staticmethod]
+    #[pyo3(signature = (state, seed=0))]
+    fn load(state: &Bound<PyAny>, seed: u32) -> PyResult<PyGame> {
+        let state = from_python(state)?.to_string();
+        let inner = EmbeddedGame::load(&state, seed).map_err(PyValueError::new_err)?;
+        Ok(PyGame { inner: inner })
+    }
+
+    // The whole game, or with a seat, only what that seat can see.
+    #[pyo3(signature = (seat=None))]
+    fn state(&mut self, py: Python, seat: Option<usize>) -> PyResult<PyObject> {
+        parsed(py, self.inner.state(seat))
+    }
+
+    // The turn, whether the game is over, the pending decision and scores.
+    fn status(&self, py: Python) -> PyResult<PyObject> {
+        parsed(py, Ok(self.inner.status()))
+    }
+
+    // The moves for the pending decision, each a list of card names.
+    fn legal_moves(&self, py: Python) -> PyResult<PyObject> {
+        parsed(py, Ok(self.inner.legal_moves()))
+    }
+
+    // Makes the pending decision, a list of card names, and plays on.
+    fn apply_move(&mut self, choice: &Bound<PyAny>) -> PyResult<()> {
+        let choice = from_python(choice)?.to_string();
+        self.inner.apply_move(&choice).map_err(PyValueError::new_err)
+    }
+
+    // The move Tactician picks with a search of so many iterations.
+    #[pyo3(signature = (iterations=10000))]
+    fn ai_choose(&mut self, py: Python, iterations: u32) -> PyResult<PyObject> {
+        parsed(py, self.inner.ai_choose(iterations))
+    }
+
+    // Each move a search weighed, with its visits and win rate, which make
+    // targets for training policies.
+    #[pyo3(signature = (iterations=10000))]
+    fn analyze(&mut self, py: Python, iterations: u32) -> PyResult<PyObject> {
+        parsed(py, self.inner.analyze(iterations))
+    }
+}
+
+#[pymodule]
+fn tactician(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_class::<PyGame>()
+}
@@ -0,0 +1,429 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use deciders;
+use ensemble;
+use game::Decider;
+use heuristic_decider;
+use human;
+use priority_strategy;
+use process_decider;
+use search_decider;
+use strategy_file;
+use util;
+
+// An option a player accepts, e.g. "iters" taking a count, for listing and
+// checking player specs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptionSpec {
+    pub key: &'static str,
+    // What sort of value it takes, e.g. "N", "on/off" or "file".
+    pub value: &'static str,
+    pub help: &'static str,
+    // The value it has when not given, or "" if it's unset or worked out
+    // some other way.
+    pub default: &'static str,
+}
+
+// How wide the column of options is when listing players, so their help
+// lines up.
+const OPTION_WIDTH: usize = 30;
+
+// A player spec, e.g. "bigmoney+smithy:copies=2", split up for a factory.
+pub struct PlayerSpec<'a> {
+    // The player's name, in lower case.
+    pub name: String,
+    // What follows the name of a player that takes an argument, e.g.
+    // "smithy" for "bigmoney+smithy".
+    pub argument: Option<String>,
+    pub options: Vec<(&'a str, &'a str)>,
+    // Whether the player should keep quiet about its thinking.
+    pub silent: bool,
+}
+
+// Builds a player from its spec, or explains what's wrong with it. It's
+// given the registry too, for players made of other players.
+pub type Factory = fn(&PlayerSpec, &Registry) -> Result<Box<Decider>, String>;
+
+pub struct PlayerKind {
+    // The name the player is chosen by, or the start of it for players that
+    // take an argument, e.g. "bigmoney+".
+    pub name: &'static str,
+    // What the player does, for listing players.
+    pub summary: &'static str,
+    // A spec choosing the player with some of its options.
+    pub example: &'static str,
+    // The argument's name, e.g. "action", if the player takes one.
+    pub argument: Option<&'static str>,
+    pub options: &'static [OptionSpec],
+    pub factory: Factory,
+}
+
+impl PlayerKind {
+    fn matches(&self, name: &str) -> bool {
+        match self.argument {
+            Some(_) => name.starts_with(self.name) && name.len() > self.name.len(),
+            None => name == self.name,
+        }
+    }
+}
+
+impl fmt::Display for PlayerKind {
+    // Writes the name as it's used, e.g. "bigmoney+<action>".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.argument {
+            Some(argument) => write!(f, "{}<{}>", self.name, argument),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// The players that can be chosen by name. Each module with a player
+// registers it, so adding one only needs a line in standard().
+pub struct Registry {
+    kinds: Vec<PlayerKind>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry { kinds: vec![] }
+    }
+
+    // Every player that comes with Tactician.
+    pub fn standard() -> Registry {
+        let mut registry = Registry::new();
+        search_decider::register(&mut registry);
+        deciders::register(&mut registry);
+        heuristic_decider::register(&mut registry);
+        priority_strategy::register(&mut registry);
+        strategy_file::register(&mut registry);
+        human::register(&mut registry);
+        process_decider::register(&mut registry);
+        ensemble::register(&mut registry);
+        registry
+    }
+
+    pub fn register(&mut self, kind: PlayerKind) {
+        assert!(
+            self.kinds.iter().all(|k| k.name != kind.name),
+            "Player {} is already registered",
+            kind.name
+        );
+        self.kinds.push(kind);
+    }
+
+    pub fn find(&self, name: &str) -> Option<&PlayerKind> {
+        self.kinds.iter().find(|k| k.matches(name))
+    }
+
+    // Lists each player with what it does, an example spec, and its
+    // options with their values and defaults.
+    pub fn describe(&self) -> String {
+        let mut lines = vec![];
+        for kind in self.kinds.iter() {
+            lines.push(format!("{:<20} {}", kind.to_string(), kind.summary));
+            lines.push(format!("    e.g. {}", kind.example));
+            for o in kind.options.iter() {
+                let default = match o.default {
+                    "" => String::new(),
+                    d => format!(" (default {})", d),
+                };
+                let usage = format!("{}={}", o.key, o.value);
+                lines.push(format!("    {:<w$} {}{}", usage, o.help, default, w = OPTION_WIDTH));
+            }
+            lines.push(String::new());
+        }
+        lines.join("\n")
+    }
+
+    // Checks a spec names a player and only gives it options it takes, with
+    // values of the sort they take, with an error suggesting what may have
+    // been meant if not.
+    pub fn check(&self, spec: &str) -> Result<(), String> {
+        let (kind, _, options) = self.resolve(spec)?;
+        for &(key, value) in options.iter() {
+            match kind.options.iter().find(|o| o.key == key) {
+                Some(o) => check_option_value(o, value)?,
+                None => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    // Whether a spec names a known player and only options it takes,
+    // whatever their values.
+    pub fn knows(&self, spec: &str) -> bool {
+        self.resolve(spec).is_ok()
+    }
+
+    // The kind of player a spec names, its name in lower case and its
+    // options.
+    fn resolve<'a>(
+        &self,
+        spec: &'a str,
+    ) -> Result<(&PlayerKind, String, Vec<(&'a str, &'a str)>), String> {
+        let mut parts = spec.splitn(2, ':');
+        let name = parts.next().unwrap().to_lowercase();
+        let options = parse_player_options(parts.next())?;
+        let kind = match self.find(&name) {
+            Some(k) => k,
+            None => {
+                let names = self.kinds.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+                let suggestion = util::closest_match(&name, self.kinds.iter().map(|k| k.name))
+                    .map_or(String::new(), |n| format!(" Did you mean {}?", n));
+                return Err(format!(
+                    "Unknown player {}.{} Players are {}",
+                    spec,
+                    suggestion,
+                    names.join(", ")
+                ));
+            }
+        };
+        for &(key, _) in options.iter() {
+            if kind.options.iter().all(|o| o.key != key) {
+                let suggestion = util::closest_match(key, kind.options.iter().map(|o| o.key))
+                    .map_or(String::new(), |k| format!(" Did you mean {}?", k));
+                let keys = kind.options.iter().map(|o| o.key).collect::<Vec<_>>();
+                return Err(match keys.is_empty() {
+                    true => format!("Player {} takes no options", name),
+                    false => format!(
+                        "Unknown option {} for player {}.{} Its options are {}",
+                        key,
+                        name,
+                        suggestion,
+                        keys.join(", ")
+                    ),
+                });
+            }
+        }
+        Ok((kind, name, options))
+    }
+
+    // The spec with a seed option added if the player takes one and isn't
+    // given one, e.g. "tactician:iters=500,seed=42", so a seeded match can
+    // seed its players as well as its shuffles.
+    pub fn seeded(&self, spec: &str, seed: u32) -> Result<String, String> {
+        let (kind, _, options) = self.resolve(spec)?;
+        if kind.options.iter().all(|o| o.key != "seed") || options.iter().any(|o| o.0 == "seed") {
+            return Ok(spec.to_string());
+        }
+        let separator = match spec.contains(':') {
+            true if spec.ends_with(':') => "",
+            true => ",",
+            false => ":",
+        };
+        Ok(format!("{}{}seed={}", spec, separator, seed))
+    }
+
+    // Builds a player from a spec such as "random:seed=42". Options follow
+    // the name after a colon, e.g. "tactician:iters=50000,c=0.7".
+    pub fn create(&self, spec: &str, silent: bool) -> Result<Box<Decider>, String> {
+        let (kind, name, options) = self.resolve(spec)?;
+        let argument = match kind.argument {
+            Some(_) => Some(name[kind.name.len()..].to_string()),
+            None => None,
+        };
+        (kind.factory)(
+            &PlayerSpec {
+                name: name,
+                argument: argument,
+                options: options,
+                silent: silent,
+            },
+            self,
+        )
+    }
+}
+
+// Splits a player's options, e.g. "iters=50000,c=0.7", into key/value pairs.
+pub fn parse_player_options(options: Option<&str>) -> Result<Vec<(&str, &str)>, String> {
+    options
+        .into_iter()
+        .flat_map(|o| o.split(','))
+        .filter(|o| !o.is_empty())
+        .map(|o| {
+            let mut kv = o.splitn(2, '=');
+            let key = kv.next().unwrap();
+            match kv.next() {
+                Some(value) => Ok((key, value)),
+                None => Err(format!("Expected key=value, found {}", o)),
+            }
+        })
+        .collect()
+}
+
+// Names for players in a match: their descriptions, except where several
+// share one, such as Tactician with different budgets, which are named by
+// their specs instead.
+pub fn match_names(specs: &[String], descriptions: Vec<String>) -> Vec<String> {
+    descriptions
+        .iter()
+        .zip(specs.iter())
+        .map(|(d, spec)| match descriptions.iter().filter(|&other| other == d).count() {
+            1 => d.clone(),
+            _ => spec.clone(),
+        })
+        .collect()
+}
+
+pub fn parse_option_value<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value {} for option {}", value, key))
+}
+
+pub fn parse_option_flag(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(format!(
+            "Invalid value {} for option {}, expected on or off",
+            value, key
+        )),
+    }
+}
+
+pub fn parse_option_duration(key: &str, value: &str) -> Result<Duration, String> {
+    util::parse_duration(value).ok_or(format!("Invalid duration {} for option {}", value, key))
+}
+
+// Checks a value is of the sort its option takes. Values such as files and
+// card names are left for the player to check as it's built.
+fn check_option_value(o: &OptionSpec, value: &str) -> Result<(), String> {
+    match o.value {
+        "N" => parse_option_value::<u32>(o.key, value).map(|_| ()),
+        "number" => parse_option_value::<f64>(o.key, value).map(|_| ()),
+        "on/off" => parse_option_flag(o.key, value).map(|_| ()),
+        "duration" => parse_option_duration(o.key, value).map(|_| ()),
+        "N or duration" => parse_option_value::<u32>(o.key, value)
+            .map(|_| ())
+            .or_else(|_| parse_option_duration(o.key, value).map(|_| ())),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use registry::*;
+
+    struct Named(String);
+
+    impl Decider for Named {
+        fn description(&self) -> String {
+            self.0.clone()
+        }
+
+        fn make_decision(&mut self, _g: &Game) -> Vec<CardIdentifier> {
+            vec![]
+        }
+    }
+
+    fn named(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+        let options = spec.options
+            .iter()
+            .map(|&(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>();
+        Ok(Box::new(Named(format!(
+            "{} {:?} {}",
+            spec.name,
+            spec.argument,
+            options.join(",")
+        ))))
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::new();
+        registry.register(PlayerKind {
+            name: "plain",
+            summary: "a test player",
+            example: "plain",
+            argument: None,
+            options: &[],
+            factory: named,
+        });
+        registry.register(PlayerKind {
+            name: "with+",
+            summary: "a test player with an argument",
+            example: "with+smithy:n=2",
+            argument: Some("card"),
+            options: &[OptionSpec { key: "n", value: "N", help: "how many", default: "1" }],
+            factory: named,
+        });
+
+        assert_eq!(registry.create("Plain", true).unwrap().description(), "plain None ");
+        assert_eq!(
+            registry.create("with+smithy:n=2", true).unwrap().description(),
+            "with+smithy Some(\"smithy\") n=2"
+        );
+        assert!(registry.find("with+").is_none());
+        assert!(registry.find("plainer").is_none());
+        assert_eq!(registry.find("with+smithy").unwrap().to_string(), "with+<card>");
+        assert!(registry.check("with+smithy:n=1").is_ok());
+        assert_eq!(
+            registry.check("plian"),
+            Err("Unknown player plian. Did you mean plain? Players are plain, with+<card>".into())
+        );
+        assert_eq!(
+            registry.check("with+moat:m=1"),
+            Err("Unknown option m for player with+moat. Did you mean n? Its options are n".into())
+        );
+        assert_eq!(
+            registry.check("plain:n=1"),
+            Err("Player plain takes no options".into())
+        );
+        assert!(registry.check("plain:n").is_err());
+        assert_eq!(
+            registry.check("with+smithy:n=two"),
+            Err("Invalid value two for option n".into())
+        );
+        assert_eq!(
+            registry.describe().lines().nth(5),
+            Some("    n=N                            how many (default 1)")
+        );
+
+        // The standard players all have distinct names, and examples that
+        // check out.
+        let standard = Registry::standard();
+        for kind in standard.kinds.iter() {
+            assert_eq!(standard.check(kind.example), Ok(()));
+            for o in kind.options.iter() {
+                assert!(o.key.len() + 1 + o.value.len() <= OPTION_WIDTH, "{} is too wide", o.key);
+            }
+        }
+        for bad in ["tactician:iters=abc", "random:seed=99999999999", "bigmoney:ppr=maybe"].iter() {
+            assert!(standard.check(bad).is_err(), "{} passed", bad);
+            assert!(standard.create(bad, true).is_err(), "{} was created", bad);
+            assert!(standard.knows(bad));
+        }
+        assert!(!standard.knows("tactician:itres=5") && !standard.knows("nobody"));
+
+        // Only players that take a seed and aren't given one are seeded.
+        let seeded = |spec: &str| standard.seeded(spec, 5).unwrap();
+        assert_eq!(seeded("tactician"), "tactician:seed=5");
+        assert_eq!(seeded("tactician:iters=50"), "tactician:iters=50,seed=5");
+        assert_eq!(seeded("random:seed=1"), "random:seed=1");
+        assert_eq!(seeded("bigmoney"), "bigmoney");
+        assert!(standard.seeded("nobody", 5).is_err());
+    }
+
+    #[test]
+    fn test_match_names() {
+        let specs = ["tactician:iters=100", "tactician:iters=10000", "bigmoney"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let descriptions = ["Tactician", "Tactician", "Big Money"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            match_names(&specs, descriptions),
+            vec!["tactician:iters=100", "tactician:iters=10000", "Big Money"]
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+
+use cards::CardIdentifier;
+use game::{Decider, DecisionError, Game, GameEvent, PlayerIdentifier, Rules};
+use game_serialization::{cards_to_json, event_from_json, event_to_json};
+use json::Json;
+use match_runner::MatchRunner;
+use process_decider::parse_choice;
+use registry::Registry;
+
+// The player spec for a seat filled by a client that connects to the host.
+pub const REMOTE: &str = "remote";
+
+// A connection to a client, carrying whole messages each way.
+pub trait Connection {
+    fn send(&mut self, message: &str) -> io::Result<()>;
+    // The next message, or None once the other end closes the connection.
+    fn receive(&mut self) -> io::Result<Option<String>>;
+    fn close(&mut self);
+}
+
+// A seat played by a client, such as a browser or another tactician that
+// joined, over a connection. Messages are JSON. On joining the client is
+// sent
+//
+//     {"type": "joined", "seat": 0, "players": ["remote", "tactician"]}
+//
+// and then, as its games are played,
+//
+//     {"type": "event", "event": {"type": "Bought", "player": 1, ...}}
+//         for each public event, as save files write them.
+//     {"type": "state", "state": {...}}
+//         while another player decides, with the game as it stands.
+//     {"type": "decision", "state": {...}}
+//         when it's the client's turn to decide, with the pending decision.
+//     {"type": "error", "message": "..."}
+//         after a choice that isn't legal, before the client tries again.
+//     {"type": "game_over", "state": {...}, "scores": [1, 0]}
+//         after each game, showing everything.
+//
+// States only show what the client's seat can see: hidden hands and decks are
+// counts, with their cards listed under unseen in sorted order, and other
+// players' decisions are left out. The client
+// replies to a decision with a list of the card names it chooses, as bots
+// run with process: do. A client that disconnects forfeits.
+struct RemoteDecider<C: Connection> {
+    name: String,
+    seat: PlayerIdentifier,
+    connection: C,
+    connected: bool,
+}
+
+impl<C: Connection> RemoteDecider<C> {
+    fn send(&mut self, message: Json) -> Result<(), DecisionError> {
+        if !self.connected {
+            return Err(DecisionError::Disconnected(format!("{} left", self.name)));
+        }
+        match self.connection.send(&message.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(self.disconnect(e.to_string())),
+        }
+    }
+
+    fn disconnect(&mut self, why: String) -> DecisionError {
+        self.connected = false;
+        DecisionError::Disconnected(format!("{}: {}", self.name, why))
+    }
+
+    // The game as the client's seat sees it.
+    fn view(&self, g: &Game) -> Json {
+        g.view_json(self.seat)
+    }
+
+    fn request_choice(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        let d = g
+            .pending_decision
+            .as_ref()
+            .expect("RemoteDecider::make_decision called without pending decision");
+        let state = self.view(g);
+        self.send(Json::object(vec![
+            ("type", "decision".into()),
+            ("state", state),
+        ]))?;
+        loop {
+            let reply = match self.connection.receive() {
+                Ok(Some(reply)) => reply,
+                Ok(None) => return Err(self.disconnect("closed the connection".into())),
+                Err(e) => return Err(self.disconnect(e.to_string())),
+            };
+            // A person may well misclick, so they get to try again.
+            match parse_choice(&reply, d) {
+                Ok(choice) => return Ok(choice),
+                Err(e) => self.send(Json::object(vec![
+                    ("type", "error".into()),
+                    ("message", e.into()),
+                ]))?,
+            }
+        }
+    }
+}
+
+impl<C: Connection> Decider for RemoteDecider<C> {
+    fn description(&self) -> String {
+        self.name.clone()
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        match self.try_make_decision(g) {
+            Ok(choice) => choice,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        self.request_choice(g)
+    }
+
+    fn ponder(&mut self, g: &Game) {
+        let state = self.view(g);
+        let _ = self.send(Json::object(vec![
+            ("type", "state".into()),
+            ("state", state),
+        ]));
+    }
+
+    fn on_event(&mut self, event: &GameEvent) {
+        let _ = self.send(Json::object(vec![
+            ("type", "event".into()),
+            ("event", event_to_json(event)),
+        ]));
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        let scores = match g.is_game_over() {
+            true => Json::Array(g.player_scores().iter().map(|&(_, s)| s.into()).collect()),
+            false => Json::Null,
+        };
+        let _ = self.send(Json::object(vec![
+            ("type", "game_over".into()),
+            ("state", g.to_json()),
+            ("scores", scores),
+        ]));
+    }
+}
+
+impl<C: Connection> Drop for RemoteDecider<C> {
+    fn drop(&mut self) {
+        self.connection.close();
+    }
+}
+
+// Hosts games between the players given by specs, where each remote seat
+// is filled by the next client accept returns, along with who it is. Once
+// every seat is filled the games are played, and a line for each game's
+// result is printed.
+pub fn host<C: Connection + 'static>(
+    specs: &[String],
+    rules: Rules,
+    games: u32,
+    registry: &Registry,
+    accept: &mut FnMut() -> io::Result<(C, String)>,
+) -> io::Result<()> {
+    let mut players: Vec<Box<Decider>> = vec![];
+    for (i, spec) in specs.iter().enumerate() {
+        if spec != REMOTE {
+            let player = registry
+                .create(spec, true)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            players.push(player);
+            continue;
+        }
+        let (connection, peer) = accept()?;
+        let mut player = RemoteDecider {
+            name: format!("{} {}", REMOTE, peer),
+            seat: PlayerIdentifier(i as u8),
+            connection: connection,
+            connected: true,
+        };
+        let _ = player.send(Json::object(vec![
+            ("type", "joined".into()),
+            ("seat", (i as i32).into()),
+            (
+                "players",
+                Json::Array(specs.iter().map(|s| s.as_str().into()).collect()),
+            ),
+        ]));
+        eprintln!("{} joined in seat {}", peer, i + 1);
+        players.push(Box::new(player));
+    }
+    let mut runner = MatchRunner::new(players);
+    runner.set_games(games);
+    runner.set_rules(rules);
+    runner.run_with(&mut |n, outcome| {
+        let scores = outcome
+            .scores
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        println!(
+            "Game {}: {} in {} turns",
+            n,
+            scores.join(" - "),
+            outcome.turns
+        );
+        true
+    });
+    Ok(())
+}
+
+// The number of remote seats, as a phrase for the host's waiting message.
+pub fn remote_seats(specs: &[String]) -> String {
+    match specs.iter().filter(|s| *s == REMOTE).count() {
+        1 => "1 player".into(),
+        n => format!("{} players", n),
+    }
+}
+
+// A TCP connection carrying a message per line, for play between two
+// tactician programs: one hosts and runs the rules, and the others join.
+pub struct Lines {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+}
+
+impl Lines {
+    pub fn new(stream: TcpStream) -> io::Result<Lines> {
+        // Messages are small and each waits on a reply, so they're sent
+        // right away rather than batched.
+        stream.set_nodelay(true)?;
+        Ok(Lines {
+            reader: BufReader::new(stream.try_clone()?),
+            stream: stream,
+        })
+    }
+}
+
+impl Connection for Lines {
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        writeln!(self.stream, "{}", message)?;
+        self.stream.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line.trim_end().to_string())),
+        }
+    }
+
+    fn close(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+// Hosts games over TCP, where each remote seat is filled by the next
+// tactician to join, in the order they connect.
+pub fn host_tcp(
+    listener: TcpListener,
+    specs: &[String],
+    rules: Rules,
+    games: u32,
+    registry: &Registry,
+) -> io::Result<()> {
+    eprintln!(
+        "Waiting for {} to join on {}",
+        remote_seats(specs),
+        listener.local_addr()?
+    );
+    host(specs, rules, games, registry, &mut || {
+        let (stream, peer) = listener.accept()?;
+        Ok((Lines::new(stream)?, peer.to_string()))
+    })
+}
+
+fn field<'a>(message: &'a Json, key: &str) -> Result<&'a Json, String> {
+    message
+        .get(key)
+        .ok_or(format!("Message without {}: {}", key, message))
+}
+
+// Plays a seat of games hosted elsewhere with decider, until the host
+// closes the connection, and returns how many games were played. The
+// decider sees the games as the host shows them to the seat, and decides
+// again whenever the host turns a choice down.
+pub fn join<C: Connection>(connection: &mut C, decider: &mut Box<Decider>) -> Result<u32, String> {
+    let mut deciding = None;
+    let mut games = 0;
+    loop {
+        let message = match connection.receive().map_err(|e| e.to_string())? {
+            Some(m) => Json::parse(&m)?,
+            None => return Ok(games),
+        };
+        let decide = match field(&message, "type")?.as_str() {
+            Some("joined") => {
+                let seat = field(&message, "seat")?.as_i64().unwrap_or(0);
+                eprintln!("Joined in seat {}", seat + 1);
+                false
+            }
+            Some("event") => {
+                decider.on_event(&event_from_json(field(&message, "event")?)?);
+                false
+            }
+            Some("state") => {
+                decider.ponder(&Game::from_json(field(&message, "state")?)?);
+                false
+            }
+            Some("decision") => {
+                deciding = Some(Game::from_json(field(&message, "state")?)?);
+                true
+            }
+            Some("error") => {
+                eprintln!("{}", field(&message, "message")?.as_str().unwrap_or(""));
+                true
+            }
+            Some("game_over") => {
+                decider.game_over(&Game::from_json(field(&message, "state")?)?);
+                games += 1;
+                false
+            }
+            _ => return Err(format!("Unexpected message {}", message)),
+        };
+        if let (true, Some(g)) = (decide, deciding.as_ref()) {
+            let choice = decider.try_make_decision(g).map_err(|e| e.to_string())?;
+            connection
+                .send(&cards_to_json(&choice).to_string())
+                .map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use registry::Registry;
+    use remote_play::*;
+    use std::thread;
+
+    #[test]
+    fn test_host_and_join() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut connection = Lines::new(TcpStream::connect(addr).unwrap()).unwrap();
+            let mut decider = Registry::standard().create("bigmoney", true).unwrap();
+            join(&mut connection, &mut decider)
+        });
+        let specs = vec!["random".to_string(), REMOTE.to_string()];
+        host_tcp(listener, &specs, Rules::default(), 2, &Registry::standard()).unwrap();
+        assert_eq!(client.join().unwrap(), Ok(2));
+    }
+}
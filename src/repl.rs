@@ -0,0 +1,303 @@
+use itertools::Itertools;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+
+use cards::CardIdentifier;
+use game::{fresh_game, EvalContext, Game};
+use game_encoding;
+use human::describe_decision;
+use json::Json;
+use locale;
+use process_decider::check_choice;
+use registry;
+use search_decider::SearchDecider;
+use spectator::dashboard;
+use tree_search::SearchableState;
+
+// How many of a search's moves to show, most visited first.
+const MOVES_SHOWN: usize = 10;
+
+const HELP: &str = "\
+show              the board and the decision to make
+moves             the legal moves, numbered
+play N            play move N of the list
+play CARD...      play these cards, or nothing with no cards
+undo              take back the last move played
+search [OPTIONS]  search the decision, with tactician options such as iters=5000
+load FILE         load a saved position
+save FILE         save the position, encoded as bytes if FILE ends in .bin
+new               start a new game
+quit              leave";
+
+// A position being explored, with the positions before each move played
+// so they can be taken back.
+struct Session {
+    game: Game,
+    history: Vec<Game>,
+    ctx: EvalContext,
+}
+
+impl Session {
+    // Starts again from a position, played on to its next decision.
+    fn start(&mut self, g: Game) {
+        self.game = g;
+        self.history.clear();
+        self.advance();
+    }
+
+    fn advance(&mut self) {
+        while !self.game.is_game_over() && self.game.pending_decision.is_none() {
+            self.game.advance_game(&mut self.ctx);
+        }
+    }
+
+    fn play(&mut self, choice: Vec<CardIdentifier>) {
+        self.history.push(self.game.clone());
+        self.game.resolve_decision(choice, &mut self.ctx);
+        self.advance();
+    }
+
+    // The moves to choose from, as the search sees them.
+    fn moves(&self) -> Vec<Vec<CardIdentifier>> {
+        match self.game.pending_decision {
+            Some(_) => self.game.all_moves(),
+            None => vec![],
+        }
+    }
+}
+
+// A new two-player game with the first-game kingdom, dealt and ready for
+// its first decision.
+pub fn new_game(ctx: &mut EvalContext) -> Game {
+    let mut g = fresh_game(&vec!["Player 1".into(), "Player 2".into()]);
+    g.initialize_game(ctx);
+    g
+}
+
+pub fn load_position(path: &str) -> Result<Game, String> {
+    let contents = fs::read(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    let game = match game_encoding::is_encoded(&contents) {
+        true => Game::from_bytes(&contents),
+        false => String::from_utf8(contents)
+            .map_err(|_| "Save files should be UTF-8".to_string())
+            .and_then(|c| Json::parse(&c))
+            .and_then(|j| Game::from_json(&j)),
+    };
+    game.map_err(|e| format!("Unable to load game state from {}: {}", path, e))
+}
+
+// Writes a position as JSON, or encoded as bytes if the file ends in .bin.
+fn save_position(path: &str, g: &Game) -> io::Result<()> {
+    match path.ends_with(".bin") {
+        true => fs::write(path, g.to_bytes()),
+        false => fs::write(path, g.to_json().to_string()),
+    }
+}
+
+// Who is to decide what, or the scores once the game is over.
+fn print_status<W: Write>(out: &mut W, g: &Game) -> io::Result<()> {
+    match g.pending_decision {
+        Some(ref d) => writeln!(
+            out,
+            "Turn {}, {}: {}",
+            g.turn,
+            g.players[d.player.0 as usize].colored_name(),
+            describe_decision(g, d)
+        ),
+        None => {
+            let scores = g.player_scores()
+                .iter()
+                .map(|&(p, s)| format!("{} {}", g.players[p.0 as usize].name, s))
+                .join(", ");
+            writeln!(out, "The game is over: {}", scores)
+        }
+    }
+}
+
+fn print_search<W: Write>(out: &mut W, g: &Game, options: &str) -> io::Result<()> {
+    let spec = format!("tactician:{}", options);
+    if let Err(e) = registry::Registry::standard().check(&spec) {
+        return writeln!(out, "{}", e);
+    }
+    let decider = registry::parse_player_options(Some(options))
+        .and_then(|options| SearchDecider::with_options(options, true));
+    let analysis = match decider {
+        Ok(mut decider) => decider.analyze(g),
+        Err(e) => return writeln!(out, "{}", e),
+    };
+    let total: i32 = analysis.iter().map(|a| a.visits).sum();
+    writeln!(out, "{:<40} {:>8} {:>6} {:>9}", "Move", "Visits", "Share", "Win rate")?;
+    for a in analysis.iter().take(MOVES_SHOWN) {
+        writeln!(
+            out,
+            "{:<40} {:>8} {:>5.1}% {:>8.1}%",
+            g.printable_move(&a.choice),
+            a.visits,
+            100.0 * a.visits as f32 / total.max(1) as f32,
+            100.0 * a.win_rate
+        )?;
+    }
+    Ok(())
+}
+
+// Parses the move to play: a number from the list of moves, or the names
+// of the cards to choose, separated by spaces or commas.
+fn parse_move(session: &Session, args: &[&str]) -> Result<Vec<CardIdentifier>, String> {
+    let d = session
+        .game
+        .pending_decision
+        .as_ref()
+        .ok_or("The game is over")?;
+    if let [n] = args {
+        if let Ok(n) = n.parse::<usize>() {
+            let moves = session.moves();
+            if n < 1 || n > moves.len() {
+                return Err(format!("Expected a move from 1 to {}", moves.len()));
+            }
+            return Ok(moves[n - 1].clone());
+        }
+    }
+    let choice = args.iter()
+        .flat_map(|a| a.split(','))
+        .filter(|a| !a.is_empty())
+        .map(|a| match locale::card_named(a) {
+            Some(c) => Ok(c.identifier),
+            None => Err(format!("Unknown card {}", a)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    check_choice(&choice, d)?;
+    Ok(choice)
+}
+
+// Reads commands for exploring a position from input until it ends or
+// says quit: listing and playing moves, taking them back, searching,
+// and loading and saving positions. Random draws come from ctx.
+pub fn repl<R: BufRead, W: Write>(
+    start: Game,
+    ctx: EvalContext,
+    input: &mut R,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut session = Session {
+        game: start,
+        history: vec![],
+        ctx: ctx,
+    };
+    session.advance();
+    print_status(out, &session.game)?;
+    loop {
+        write!(out, "> ")?;
+        out.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (command, args) = match words.split_first() {
+            Some((&command, args)) => (command, args),
+            None => continue,
+        };
+        match (command, args) {
+            ("quit", []) | ("q", []) => return Ok(()),
+            ("help", []) => writeln!(out, "{}", HELP)?,
+            ("show", []) => {
+                let g = &session.game;
+                write!(out, "{}", dashboard(g, g.pending_decision.as_ref(), &VecDeque::new()))?;
+                print_status(out, g)?;
+            }
+            ("moves", []) => {
+                for (i, m) in session.moves().iter().enumerate() {
+                    writeln!(out, "{:>3}. {}", i + 1, session.game.printable_move(m))?;
+                }
+            }
+            ("play", args) => match parse_move(&session, args) {
+                Ok(choice) => {
+                    session.play(choice);
+                    print_status(out, &session.game)?;
+                }
+                Err(e) => writeln!(out, "{}", e)?,
+            },
+            ("undo", []) => match session.history.pop() {
+                Some(g) => {
+                    session.game = g;
+                    print_status(out, &session.game)?;
+                }
+                None => writeln!(out, "There's nothing to undo")?,
+            },
+            ("search", options) => match session.game.pending_decision {
+                Some(_) => print_search(out, &session.game, &options.join(","))?,
+                None => writeln!(out, "The game is over")?,
+            },
+            ("load", [path]) => match load_position(path) {
+                Ok(g) => {
+                    session.start(g);
+                    print_status(out, &session.game)?;
+                }
+                Err(e) => writeln!(out, "{}", e)?,
+            },
+            ("save", [path]) => match save_position(path, &session.game) {
+                Ok(()) => writeln!(out, "Saved to {}", path)?,
+                Err(e) => writeln!(out, "Unable to write {}: {}", path, e)?,
+            },
+            ("new", []) => {
+                let g = new_game(&mut session.ctx);
+                session.start(g);
+                print_status(out, &session.game)?;
+            }
+            _ => writeln!(out, "Unknown command {}. Try help", line.trim())?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use game::*;
+    use repl::*;
+    use std;
+    use util;
+
+    #[test]
+    fn test_repl() {
+        let mut ctx = EvalContext {
+            rng: util::seeded_weak_rng(1),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        let g = new_game(&mut ctx);
+        let path = std::env::temp_dir().join("tactician-test-repl.json");
+        let path = path.to_str().unwrap();
+        let commands = format!(
+            "moves\nplay 1\nplay Gold\nundo\nundo\nundo\nsearch iters=50\nsave {}\nload {}\nfly\nq\n",
+            path, path
+        );
+        let mut out = vec![];
+        repl(g, ctx, &mut commands.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("  1. "));
+        assert!(out.contains("There's nothing to undo"));
+        assert!(out.contains("Win rate"));
+        assert!(out.contains("Saved to"));
+        assert!(out.contains("Unknown command fly"));
+        assert!(load_position(path).is_ok());
+    }
+
+    #[test]
+    fn test_load_encoded_position() {
+        let mut ctx = EvalContext {
+            rng: util::seeded_weak_rng(1),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        let g = new_game(&mut ctx);
+        let path = std::env::temp_dir().join("tactician-test-repl.bin");
+        let path = path.to_str().unwrap();
+        let commands = format!("save {}\nq\n", path);
+        repl(g, ctx, &mut commands.as_bytes(), &mut vec![]).unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        assert!(bytes.starts_with(b"TCTN"));
+        assert_eq!(load_position(path).unwrap().to_bytes(), bytes);
+    }
+}
@@ -0,0 +1,174 @@
+use itertools::Itertools;
+use std::collections::VecDeque;
+use std::io;
+use std::io::{BufRead, Write};
+
+use color;
+use game::Game;
+use game_log::GameLog;
+use locale;
+use spectator::dashboard;
+
+// How many of a search's moves to show, most visited first.
+const MOVES_SHOWN: usize = 8;
+
+fn print_analysis<W: Write>(out: &mut W, log: &GameLog, i: usize) -> io::Result<()> {
+    let d = &log.decisions[i];
+    let player = d.state.pending_decision.as_ref().unwrap().player;
+    let name = d.state.players[player.0 as usize].colored_name();
+    let choice = match d.choice.is_empty() {
+        true => locale::text("nothing", &[]),
+        false => color::card_names(&d.choice),
+    };
+    writeln!(out, "{} chose {}", name, choice)?;
+    if d.analysis.is_empty() {
+        return Ok(());
+    }
+
+    let total: i32 = d.analysis.iter().map(|m| m.visits).sum();
+    let moves = d.analysis
+        .iter()
+        .sorted_by_key(|m| -m.visits)
+        .into_iter()
+        .take(MOVES_SHOWN)
+        .map(|m| {
+            let mut choice = m.choice.clone();
+            choice.sort();
+            let name = match choice.is_empty() {
+                true => format!("({})", locale::text("nothing", &[])),
+                false => locale::card_names(&choice),
+            };
+            (name, m)
+        })
+        .collect::<Vec<_>>();
+    let width = moves.iter().map(|&(ref n, _)| n.chars().count()).max().unwrap_or(0).max(4);
+    writeln!(out, "  {:<w$} {:>8} {:>6} {:>8}", "Move", "Visits", "Share", "Win rate", w = width)?;
+    for (name, m) in moves {
+        writeln!(
+            out,
+            "  {:<w$} {:>8} {:>5.1}% {:>7.1}%",
+            name,
+            m.visits,
+            100.0 * m.visits as f32 / total.max(1) as f32,
+            100.0 * m.win_rate,
+            w = width
+        )?;
+    }
+    Ok(())
+}
+
+// Shows the board at a decision, or after the last one, the final scores.
+fn print_frame<W: Write>(out: &mut W, log: &GameLog, i: usize) -> io::Result<()> {
+    let n = log.decisions.len();
+    writeln!(out, "")?;
+    let state: Option<&Game> = match log.decisions.get(i) {
+        Some(d) => {
+            writeln!(out, "Game {}, decision {} of {}", log.game, i + 1, n)?;
+            Some(&d.state)
+        }
+        None => {
+            writeln!(out, "Game {}, after all {} decisions", log.game, n)?;
+            log.final_state.as_ref()
+        }
+    };
+    if let Some(g) = state {
+        write!(out, "{}", dashboard(g, g.pending_decision.as_ref(), &VecDeque::new()))?;
+    }
+    if i < n {
+        print_analysis(out, log, i)
+    } else {
+        let scores = log.players
+            .iter()
+            .zip(log.scores.iter())
+            .map(|(p, s)| format!("{} {}", p, s))
+            .join(", ");
+        writeln!(out, "Final scores: {}", scores)
+    }
+}
+
+// Steps through a logged game, showing the board at each decision and what
+// the decider weighed, read from commands on input: Enter or "n" for the
+// next decision, "b" for the one before, "g N" to go to decision N, "e" for
+// the end and "q" to quit.
+pub fn replay<R: BufRead, W: Write>(log: &GameLog, input: &mut R, out: &mut W) -> io::Result<()> {
+    let last = log.decisions.len();
+    let mut i = 0;
+    print_frame(out, log, i)?;
+    loop {
+        write!(out, "[Enter] next, b back, g N go to, e end, q quit> ")?;
+        out.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let next = match words.as_slice() {
+            [] | ["n"] => i + 1,
+            ["b"] => i.saturating_sub(1),
+            ["e"] => last,
+            ["q"] => return Ok(()),
+            ["g", n] => match n.parse::<usize>() {
+                Ok(n) if n >= 1 => n - 1,
+                _ => {
+                    writeln!(out, "Expected a decision number, from 1")?;
+                    continue;
+                }
+            },
+            _ => {
+                writeln!(out, "Unknown command {}", line.trim())?;
+                continue;
+            }
+        };
+        if next > last {
+            writeln!(out, "That's past the end of the game")?;
+            continue;
+        }
+        i = next;
+        print_frame(out, log, i)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use deciders::FirstChoice;
+    use game::*;
+    use game_log::*;
+    use json::Json;
+    use replay::*;
+    use util;
+
+    #[test]
+    fn test_replay() {
+        let log = Rc::new(RefCell::new(GameLog::default()));
+        let mut players: Vec<Box<Decider>> = (0..2)
+            .map(|_| Box::new(Logged::new(Box::new(FirstChoice), log.clone())) as Box<Decider>)
+            .collect();
+        let mut ctx = EvalContext {
+            rng: util::seeded_weak_rng(1),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        let scores = run_game_with_context(&mut players, &mut ctx);
+        log.borrow_mut().players = vec!["First".into(), "First".into()];
+        log.borrow_mut().scores = scores;
+
+        // Logs come back from JSON as they were.
+        let text = log.borrow().to_json().to_string();
+        let loaded = GameLog::from_json(&Json::parse(&text).unwrap()).unwrap();
+        let n = loaded.decisions.len();
+        assert_eq!(n, log.borrow().decisions.len());
+        assert!(loaded.final_state.unwrap().is_game_over());
+
+        let mut out = vec![];
+        replay(&log.borrow(), &mut "\ng 3\nb\ne\nq\n".as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for shown in ["decision 1 of", "decision 2 of", "decision 3 of", "after all"].iter() {
+            assert!(out.contains(shown), "{} wasn't shown", shown);
+        }
+        assert!(out.contains("Final scores: First"));
+    }
+}
@@ -0,0 +1,320 @@
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use json;
+use json::Json;
+use rpc_server::{RpcError, Server, GAME_ERROR, INVALID_PARAMS};
+
+// The longest request body read, far more than any request needs.
+const MAX_BODY_LEN: usize = 1 << 20;
+
+// How long a client may take to send its request or read the answer, so a
+// slow one doesn't hold up the others for long.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Runs games for web services and scripts over HTTP, as the JSON-RPC server
+// does over stdio. Bodies are JSON both ways:
+//
+//     POST /games {"players": ["Alice", "Bob"], "kingdom": "...", "seed": 7}
+//         Deals a game and plays to its first decision, answering 201 with
+//         its id and status. The body may be left out.
+//     GET /games
+//         Every game's id, players and status. With ?finished=true, only
+//         the games that are over, with their scores.
+//     GET /games/1
+//         A game's players and status: its turn, whether it's over, the
+//         pending decision and the scores.
+//     GET /games/1/players/0
+//         The game as save files write it, showing only what a seat can see.
+//     GET /games/1/moves
+//         The choices for the pending decision, each a list of card names.
+//     POST /games/1/decisions {"choice": ["Silver"]}
+//         Makes the pending decision and plays on to the next, answering
+//         with the status.
+//
+// Errors are answered with {"error": "..."}: 400 for a bad request, 404 for
+// an unknown game or path, and 409 for a move the game doesn't allow.
+pub struct Response {
+    pub status: u16,
+    pub body: Json,
+}
+
+fn respond(status: u16, body: Json) -> Response {
+    Response {
+        status: status,
+        body: body,
+    }
+}
+
+fn error(status: u16, message: &str) -> Response {
+    respond(status, Json::object(vec![("error", message.into())]))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+fn from_rpc(result: Result<Json, RpcError>) -> Response {
+    match result {
+        Ok(value) => respond(200, value),
+        Err(e) => error(
+            match e.code {
+                INVALID_PARAMS => 400,
+                GAME_ERROR => 409,
+                _ => 500,
+            },
+            &e.message,
+        ),
+    }
+}
+
+// How deeply a body's arrays and objects nest, counting the brackets
+// outside strings.
+fn nesting_depth(body: &str) -> usize {
+    let (mut depth, mut deepest) = (0usize, 0);
+    let (mut in_string, mut escaped) = (false, false);
+    for c in body.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '[' | '{' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            ']' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    deepest
+}
+
+// A request's body as method params, with the game's id added if given.
+fn params(body: &str, game: Option<usize>) -> Result<Json, Response> {
+    let mut fields = match body.trim() {
+        "" => vec![],
+        body if nesting_depth(body) > json::MAX_DEPTH => {
+            return Err(error(400, "Request body nested too deeply"))
+        }
+        body => match Json::parse(body) {
+            Ok(Json::Object(fields)) => fields,
+            Ok(_) => return Err(error(400, "Expected a JSON object")),
+            Err(e) => return Err(error(400, &e)),
+        },
+    };
+    if let Some(id) = game {
+        fields.retain(|&(ref key, _)| key != "game");
+        fields.push(("game".to_string(), (id as i32).into()));
+    }
+    Ok(Json::Object(fields))
+}
+
+// Adds fields to an object.
+fn merge(object: Json, more: Json) -> Json {
+    match (object, more) {
+        (Json::Object(mut fields), Json::Object(more)) => {
+            fields.extend(more);
+            Json::Object(fields)
+        }
+        (object, _) => object,
+    }
+}
+
+// Answers a request for a path, such as /games/1/moves?player=0.
+pub fn route(server: &mut Server, method: &str, path: &str, body: &str) -> Response {
+    let (path, query) = match path.find('?') {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => (path, ""),
+    };
+    let query = query
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            Some((parts.next()?, parts.next().unwrap_or("")))
+        })
+        .collect::<Vec<_>>();
+    let segments = path.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    if segments.first() != Some(&"games") {
+        return error(404, &format!("Nothing at {}", path));
+    }
+    let game = match segments.get(1) {
+        Some(id) => match id.parse::<usize>() {
+            Ok(id) if id >= 1 && id <= server.game_count() => Some(id),
+            _ => return error(404, &format!("There's no game {}", id)),
+        },
+        None => None,
+    };
+    let params = match params(body, game) {
+        Ok(params) => params,
+        Err(response) => return response,
+    };
+    match (method, &segments[1..]) {
+        ("GET", &[]) => {
+            let finished = match query.iter().find(|&&(key, _)| key == "finished") {
+                Some(&(_, "true")) => vec![("finished", true.into())],
+                Some(&(_, "false")) | None => vec![],
+                Some(_) => return error(400, "finished should be true or false"),
+            };
+            from_rpc(server.call("list_games", &Json::object(finished)))
+        }
+        ("POST", &[]) => {
+            let created = match server.call("new_game", &params) {
+                Ok(created) => created,
+                Err(e) => return from_rpc(Err(e)),
+            };
+            let status = server.call("advance", &created);
+            match from_rpc(status.map(|status| merge(created, status))) {
+                Response { status: 200, body } => respond(201, body),
+                response => response,
+            }
+        }
+        ("GET", &[_]) => {
+            let games = server.call("list_games", &Json::object(vec![]));
+            from_rpc(games.map(|games| games.as_array().unwrap()[game.unwrap() - 1].clone()))
+        }
+        ("GET", &[_, "players", seat]) => match seat.parse::<i32>() {
+            Ok(seat) => {
+                let params = merge(params, Json::object(vec![("player", seat.into())]));
+                from_rpc(server.call("get_state", &params))
+            }
+            Err(_) => error(404, &format!("There's no player {}", seat)),
+        },
+        ("GET", &[_, "moves"]) => from_rpc(server.call("legal_moves", &params)),
+        ("POST", &[_, "decisions"]) => match server.call("submit_decision", &params) {
+            Ok(_) => from_rpc(server.call("advance", &params)),
+            Err(e) => from_rpc(Err(e)),
+        },
+        (_, &[]) | (_, &[_]) | (_, &[_, "players", _]) | (_, &[_, "moves"])
+        | (_, &[_, "decisions"]) => error(405, &format!("{} isn't allowed there", method)),
+        _ => error(404, &format!("Nothing at {}", path)),
+    }
+}
+
+// Reads a request and writes the answer. Each connection carries one
+// request, so the answer closes it.
+pub fn answer<R: BufRead, W: Write>(
+    server: &mut Server,
+    input: &mut R,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut request_line = String::new();
+    input.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        let mut parts = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (_, _) if content_length > MAX_BODY_LEN => error(400, "Request body too long"),
+        (Some(method), Some(path)) => {
+            let mut body = vec![0; content_length];
+            input.read_exact(&mut body)?;
+            match String::from_utf8(body) {
+                Ok(body) => route(server, method, path, &body),
+                Err(_) => error(400, "Expected the body to be UTF-8"),
+            }
+        }
+        _ => error(400, "Expected an HTTP request"),
+    };
+    let body = response.body.to_string();
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        body.len(),
+        body
+    )?;
+    out.flush()
+}
+
+// Answers requests one at a time, for as long as the listener lasts.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let mut server = Server::new();
+    eprintln!("Serving games on http://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let mut input = BufReader::new(stream.try_clone()?);
+        // One client giving up shouldn't stop the server.
+        if let Err(e) = answer(&mut server, &mut input, &mut stream) {
+            eprintln!("Unable to answer a request: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use json::Json;
+    use rest_server::*;
+
+    #[test]
+    fn test_route() {
+        let mut server = Server::new();
+        let created = route(&mut server, "POST", "/games", r#"{"seed": 3}"#);
+        assert_eq!(created.status, 201);
+        assert_eq!(created.body.get("game").and_then(|g| g.as_i64()), Some(1));
+        assert!(!created.body.get("pending_decision").unwrap().is_null());
+
+        let moves = route(&mut server, "GET", "/games/1/moves", "");
+        let first = moves.body.as_array().unwrap()[0].clone();
+        let choice = Json::object(vec![("choice", first)]).to_string();
+        assert_eq!(route(&mut server, "POST", "/games/1/decisions", &choice).status, 200);
+        let illegal = r#"{"choice": ["Province"]}"#;
+        assert_eq!(route(&mut server, "POST", "/games/1/decisions", illegal).status, 409);
+
+        let view = route(&mut server, "GET", "/games/1/players/1", "");
+        assert!(view.body.get("players").is_some());
+        let game = route(&mut server, "GET", "/games/1", "");
+        assert_eq!(game.body.get("over"), Some(&Json::Bool(false)));
+        assert_eq!(route(&mut server, "GET", "/games", "").body.as_array().unwrap().len(), 1);
+        let finished = route(&mut server, "GET", "/games?finished=true", "");
+        assert_eq!(finished.body.as_array().unwrap().len(), 0);
+
+        assert_eq!(route(&mut server, "GET", "/games/2", "").status, 404);
+        assert_eq!(route(&mut server, "DELETE", "/games/1", "").status, 405);
+        assert_eq!(route(&mut server, "POST", "/games", "[1]").status, 400);
+        let deep = "[".repeat(1 << 20);
+        assert_eq!(route(&mut server, "POST", "/games", &deep).status, 400);
+        assert_eq!(nesting_depth(r#"{"a": ["[[", {"b": "\\\""}]}"#), 3);
+        assert_eq!(route(&mut server, "GET", "/cards", "").status, 404);
+    }
+
+    #[test]
+    fn test_answer() {
+        let mut server = Server::new();
+        let request = "POST /games HTTP/1.1\r\nHost: localhost\r\nContent-Length: 11\r\n\r\n\
+                       {\"seed\": 1}";
+        let mut out = vec![];
+        answer(&mut server, &mut request.as_bytes(), &mut out).unwrap();
+        let response = String::from_utf8(out).unwrap();
+        assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        assert!(response.contains(&format!("Content-Length: {}\r\n", body.len())));
+        assert_eq!(Json::parse(body).unwrap().get("game"), Some(&Json::from(1)));
+    }
+}
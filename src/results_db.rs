@@ -0,0 +1,347 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use cards;
+use cards::CardIdentifier;
+use game::{Decider, DecisionError, Game, GameEvent, MoveStats};
+use results_output::GameResult;
+#[cfg(feature = "sqlite")]
+use rusqlite::{params, Connection};
+
+// Match results in a SQLite database, so experiments that run for days can
+// be queried with SQL. A database holds any number of runs, each a play
+// command, and is created with this schema if it's new. Seats are numbered
+// from 1, as in CSV results, and turns are each player's own.
+#[cfg(feature = "sqlite")]
+pub const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started TEXT NOT NULL,
+    command TEXT NOT NULL,
+    seed INTEGER,
+    kingdom TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS games (
+    run INTEGER NOT NULL REFERENCES runs (id),
+    game INTEGER NOT NULL,
+    seed INTEGER,
+    turns INTEGER NOT NULL,
+    winner TEXT,
+    PRIMARY KEY (run, game)
+);
+CREATE TABLE IF NOT EXISTS scores (
+    run INTEGER NOT NULL,
+    game INTEGER NOT NULL,
+    seat INTEGER NOT NULL,
+    player TEXT NOT NULL,
+    score REAL NOT NULL,
+    vp INTEGER,
+    PRIMARY KEY (run, game, seat),
+    FOREIGN KEY (run, game) REFERENCES games (run, game)
+);
+CREATE TABLE IF NOT EXISTS turns (
+    run INTEGER NOT NULL,
+    game INTEGER NOT NULL,
+    turn INTEGER NOT NULL,
+    seat INTEGER NOT NULL,
+    vp INTEGER NOT NULL,
+    cards INTEGER NOT NULL,
+    money INTEGER NOT NULL,
+    state BLOB NOT NULL,
+    PRIMARY KEY (run, game, turn, seat),
+    FOREIGN KEY (run, game) REFERENCES games (run, game)
+);
+";
+
+// The player whose turn it is, as their turn began: the VP, cards and
+// total coin value they own, and the whole game as Game::to_bytes encodes
+// it. Only a database reads them, so without sqlite they're taken unread.
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+#[derive(Clone, Debug)]
+pub struct TurnSnapshot {
+    pub turn: i32,
+    pub seat: usize,
+    pub vp: i32,
+    pub cards: usize,
+    pub money: i32,
+    pub state: Vec<u8>,
+}
+
+// A game's turns, and each seat's VP once it ended.
+#[derive(Clone, Debug, Default)]
+pub struct GameSnapshots {
+    pub turns: Vec<TurnSnapshot>,
+    pub final_vp: Vec<i32>,
+}
+
+fn snapshot(g: &Game) -> TurnSnapshot {
+    let p = &g.players[g.active_player.0 as usize];
+    let all_cards = p.all_cards();
+    TurnSnapshot {
+        turn: g.turn,
+        seat: g.active_player.0 as usize,
+        vp: cards::score_cards(&all_cards),
+        cards: all_cards.len(),
+        money: all_cards
+            .iter()
+            .map(|c| cards::lookup_card(c).coin_value.unwrap_or(0))
+            .sum(),
+        state: g.to_bytes(),
+    }
+}
+
+// Snapshots each turn at its first decision, which every player observes,
+// so wrapping one player of a game is enough.
+pub struct Snapshotted {
+    decider: Box<Decider>,
+    snapshots: Rc<RefCell<GameSnapshots>>,
+}
+
+impl Snapshotted {
+    pub fn new(decider: Box<Decider>, snapshots: Rc<RefCell<GameSnapshots>>) -> Snapshotted {
+        Snapshotted {
+            decider: decider,
+            snapshots: snapshots,
+        }
+    }
+}
+
+// Wraps the first of players to snapshot their games, returning where the
+// snapshots of each game go.
+pub fn watch(players: &mut Vec<Box<Decider>>) -> Rc<RefCell<GameSnapshots>> {
+    let snapshots = Rc::new(RefCell::new(GameSnapshots::default()));
+    let first = players.remove(0);
+    players.insert(0, Box::new(Snapshotted::new(first, snapshots.clone())));
+    snapshots
+}
+
+impl Decider for Snapshotted {
+    fn description(&self) -> String {
+        self.decider.description()
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        self.decider.make_decision(g)
+    }
+
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        self.decider.try_make_decision(g)
+    }
+
+    fn observe_decision(&mut self, g: &Game, choice: &Vec<CardIdentifier>) {
+        {
+            let mut snapshots = self.snapshots.borrow_mut();
+            let seen = snapshots.turns.last().map_or(false, |s| {
+                s.turn == g.turn && s.seat == g.active_player.0 as usize
+            });
+            if !seen {
+                snapshots.turns.push(snapshot(g));
+            }
+        }
+        self.decider.observe_decision(g, choice);
+    }
+
+    fn ponder(&mut self, g: &Game) {
+        self.decider.ponder(g);
+    }
+
+    fn confidence(&self) -> Option<f32> {
+        self.decider.confidence()
+    }
+
+    fn analysis(&self) -> Vec<MoveStats> {
+        self.decider.analysis()
+    }
+
+    fn clock(&mut self, remaining: Duration, increment: Duration) {
+        self.decider.clock(remaining, increment);
+    }
+
+    fn on_event(&mut self, event: &GameEvent) {
+        self.decider.on_event(event);
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        self.snapshots.borrow_mut().final_vp = g
+            .players
+            .iter()
+            .map(|p| cards::score_cards(&p.all_cards()))
+            .collect();
+        self.decider.game_over(g);
+    }
+}
+
+// A run being written to a results database.
+pub struct ResultsDb {
+    #[cfg(feature = "sqlite")]
+    conn: Connection,
+    #[cfg(feature = "sqlite")]
+    run: i64,
+    #[cfg(feature = "sqlite")]
+    players: Vec<String>,
+}
+
+#[cfg(feature = "sqlite")]
+impl ResultsDb {
+    // Opens the database at path, creating it if need be, and starts a run
+    // of the players given in it.
+    pub fn open(
+        path: &str,
+        players: Vec<String>,
+        command: &str,
+        seed: Option<u32>,
+        kingdom: &[CardIdentifier],
+    ) -> Result<ResultsDb, String> {
+        let error = |e: ::rusqlite::Error| format!("Unable to open {}: {}", path, e);
+        let conn = Connection::open(path).map_err(&error)?;
+        conn.execute_batch(SCHEMA).map_err(&error)?;
+        conn.execute(
+            "INSERT INTO runs (started, command, seed, kingdom)
+             VALUES (datetime('now'), ?1, ?2, ?3)",
+            params![command, seed, kingdom_names(kingdom)],
+        )
+        .map_err(&error)?;
+        Ok(ResultsDb {
+            run: conn.last_insert_rowid(),
+            conn: conn,
+            players: players,
+        })
+    }
+
+    // Inserts a finished game, its scores and its turns, all at once so an
+    // interrupted run never leaves half a game.
+    pub fn record(&mut self, r: &GameResult, snapshots: &GameSnapshots) -> Result<(), String> {
+        let run = self.run;
+        let players = &self.players;
+        let winner = r.winner().map(|w| players[r.seats[w]].clone());
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO games (run, game, seed, turns, winner) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![run, r.game, r.seed, r.outcome.turns, winner],
+        )
+        .map_err(|e| e.to_string())?;
+        for (seat, &p) in r.seats.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO scores (run, game, seat, player, score, vp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    run,
+                    r.game,
+                    seat as i64 + 1,
+                    players[p],
+                    r.outcome.scores[seat] as f64,
+                    snapshots.final_vp.get(seat)
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        for s in &snapshots.turns {
+            tx.execute(
+                "INSERT INTO turns (run, game, turn, seat, vp, cards, money, state)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    run,
+                    r.game,
+                    s.turn,
+                    s.seat as i64 + 1,
+                    s.vp,
+                    s.cards as i64,
+                    s.money,
+                    s.state
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+impl ResultsDb {
+    pub fn open(
+        path: &str,
+        _players: Vec<String>,
+        _command: &str,
+        _seed: Option<u32>,
+        _kingdom: &[CardIdentifier],
+    ) -> Result<ResultsDb, String> {
+        Err(format!(
+            "Unable to open {}: Tactician was built without the sqlite feature",
+            path
+        ))
+    }
+
+    pub fn record(&mut self, _r: &GameResult, _snapshots: &GameSnapshots) -> Result<(), String> {
+        unreachable!("Results databases can't be opened without the sqlite feature")
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn kingdom_names(kingdom: &[CardIdentifier]) -> String {
+    kingdom
+        .iter()
+        .map(|c| cards::lookup_card(c).name)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+
+    use cards;
+    use game::*;
+    use results_db::*;
+    use results_output::GameResult;
+    use rusqlite::Connection;
+    use std;
+
+    #[test]
+    fn test_record_games() {
+        let path = std::env::temp_dir().join("tactician-test-results.sqlite");
+        let _ = std::fs::remove_file(&path);
+        let path = path.to_str().unwrap();
+        let kingdom = cards::first_game_kingdom();
+        let players = vec!["bigmoney".to_string(), "random".to_string()];
+        for _ in 0..2 {
+            let mut db =
+                ResultsDb::open(path, players.clone(), "tactician play", Some(3), &kingdom)
+                    .unwrap();
+            let names = players.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+            let g = fresh_game_with_kingdom(&names, &kingdom);
+            let snapshots = GameSnapshots {
+                turns: vec![snapshot(&g)],
+                final_vp: vec![30, 4],
+            };
+            let outcome = GameOutcome {
+                scores: vec![0.0, 1.0],
+                turns: 20,
+            };
+            let result = GameResult {
+                game: 1,
+                seed: Some(3),
+                seats: vec![1, 0],
+                outcome: &outcome,
+                kingdom: &kingdom,
+            };
+            db.record(&result, &snapshots).unwrap();
+        }
+
+        let conn = Connection::open(path).unwrap();
+        let count = |sql: &str| conn.query_row(sql, [], |r| r.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM runs"), 2);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM scores WHERE player = 'random'"),
+            2
+        );
+        assert_eq!(count("SELECT vp FROM turns WHERE run = 2 AND seat = 1"), 3);
+        let winner: String = conn
+            .query_row("SELECT winner FROM games WHERE run = 1", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(winner, "bigmoney");
+        let state: Vec<u8> = conn
+            .query_row("SELECT state FROM turns WHERE run = 1", [], |r| r.get(0))
+            .unwrap();
+        assert!(Game::from_bytes(&state).is_ok());
+    }
+}
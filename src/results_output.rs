@@ -0,0 +1,302 @@
+use std::fs::File;
+use std::io;
+use std::io::Write;
+
+use cards;
+use cards::CardIdentifier;
+use game::GameOutcome;
+use json::Json;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResultsFormat {
+    // A line of JSON per game, then one of the totals.
+    Json,
+    // A row per player per game. The totals go to a second file, named
+    // after the first with -summary added.
+    Csv,
+}
+
+impl ResultsFormat {
+    pub fn parse(s: &str) -> Result<ResultsFormat, String> {
+        match s {
+            "json" => Ok(ResultsFormat::Json),
+            "csv" => Ok(ResultsFormat::Csv),
+            _ => Err(format!("Unknown results format {}, expected json or csv", s)),
+        }
+    }
+}
+
+// Parses an --output option such as "csv:results.csv".
+pub fn parse_output(s: &str) -> Result<(ResultsFormat, String), String> {
+    match s.find(':') {
+        Some(i) if i + 1 < s.len() => Ok((ResultsFormat::parse(&s[..i])?, s[i + 1..].to_string())),
+        _ => Err(format!("Expected --output json:FILE or csv:FILE, not {}", s)),
+    }
+}
+
+// A finished game, with who sat in each seat as indices into the players
+// the file was created with.
+pub struct GameResult<'a> {
+    pub game: u32,
+    pub seed: Option<u32>,
+    pub seats: Vec<usize>,
+    pub outcome: &'a GameOutcome,
+    pub kingdom: &'a [CardIdentifier],
+}
+
+impl<'a> GameResult<'a> {
+    // The seat that scored most, unless the game was tied.
+    pub fn winner(&self) -> Option<usize> {
+        let scores = &self.outcome.scores;
+        let best = scores.iter().cloned().fold(0.0, f32::max);
+        match scores.iter().filter(|&&s| s == best).count() {
+            1 => scores.iter().position(|&s| s == best),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct PlayerTotals {
+    games: u32,
+    score: f32,
+    wins: u32,
+    turns: i32,
+}
+
+// Writes each game's result as it finishes, so scripts can read a match's
+// results without scraping its output, and the totals at the end.
+pub struct ResultsFile {
+    format: ResultsFormat,
+    path: String,
+    file: File,
+    players: Vec<String>,
+    totals: Vec<PlayerTotals>,
+    games: u32,
+    turns: i32,
+}
+
+fn csv_field(s: &str) -> String {
+    match s.contains(|c| c == ',' || c == '"' || c == '\n') {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+fn summary_path(path: &str) -> String {
+    match path.rfind('.') {
+        Some(i) if !path[i..].contains('/') => format!("{}-summary{}", &path[..i], &path[i..]),
+        _ => format!("{}-summary", path),
+    }
+}
+
+impl ResultsFile {
+    pub fn create(
+        path: &str,
+        format: ResultsFormat,
+        players: Vec<String>,
+    ) -> io::Result<ResultsFile> {
+        let mut file = File::create(path)?;
+        if format == ResultsFormat::Csv {
+            writeln!(file, "game,seed,turns,kingdom,seat,player,score,winner")?;
+        }
+        Ok(ResultsFile {
+            format: format,
+            path: path.to_string(),
+            file: file,
+            totals: vec![PlayerTotals::default(); players.len()],
+            players: players,
+            games: 0,
+            turns: 0,
+        })
+    }
+
+    // Starts JSON results with a line describing the run: the command that
+    // plays it again, its seed and its kingdom.
+    pub fn record_run(
+        &mut self,
+        command: &str,
+        seed: Option<u32>,
+        kingdom: &[CardIdentifier],
+    ) -> io::Result<()> {
+        if self.format != ResultsFormat::Json {
+            return Ok(());
+        }
+        let line = Json::object(vec![
+            ("type", "run".into()),
+            ("command", command.into()),
+            ("seed", seed.map_or(Json::Null, |s| (s as f64).into())),
+            (
+                "kingdom",
+                Json::Array(kingdom.iter().map(|c| c.to_string().into()).collect()),
+            ),
+            (
+                "players",
+                Json::Array(self.players.iter().map(|p| p.as_str().into()).collect()),
+            ),
+        ]);
+        writeln!(self.file, "{}", line)
+    }
+
+    pub fn record(&mut self, r: &GameResult) -> io::Result<()> {
+        let winner = r.winner();
+        self.games += 1;
+        self.turns += r.outcome.turns;
+        for (seat, &p) in r.seats.iter().enumerate() {
+            let totals = &mut self.totals[p];
+            totals.games += 1;
+            totals.score += r.outcome.scores[seat];
+            totals.turns += r.outcome.turns;
+            if winner == Some(seat) {
+                totals.wins += 1;
+            }
+        }
+
+        match self.format {
+            ResultsFormat::Json => {
+                let names = r.seats
+                    .iter()
+                    .map(|&p| self.players[p].as_str().into())
+                    .collect::<Vec<Json>>();
+                let line = Json::object(vec![
+                    ("type", "game".into()),
+                    ("game", (r.game as i32).into()),
+                    ("seed", r.seed.map_or(Json::Null, |s| (s as f64).into())),
+                    ("players", Json::Array(names)),
+                    (
+                        "scores",
+                        Json::Array(r.outcome.scores.iter().map(|&s| s.into()).collect()),
+                    ),
+                    (
+                        "winner",
+                        winner.map_or(Json::Null, |w| self.players[r.seats[w]].as_str().into()),
+                    ),
+                    ("turns", r.outcome.turns.into()),
+                    (
+                        "kingdom",
+                        Json::Array(r.kingdom.iter().map(|c| c.to_string().into()).collect()),
+                    ),
+                ]);
+                writeln!(self.file, "{}", line)
+            }
+            ResultsFormat::Csv => {
+                let seed = r.seed.map_or(String::new(), |s| s.to_string());
+                let kingdom = cards::card_names(&r.kingdom.to_vec()).replace(", ", ";");
+                for (seat, &p) in r.seats.iter().enumerate() {
+                    writeln!(
+                        self.file,
+                        "{},{},{},{},{},{},{},{}",
+                        r.game,
+                        seed,
+                        r.outcome.turns,
+                        kingdom,
+                        seat + 1,
+                        csv_field(&self.players[p]),
+                        r.outcome.scores[seat],
+                        (winner == Some(seat)) as u8
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Writes each player's games, score, outright wins and win rate, and
+    // the average game length.
+    pub fn finish(&mut self) -> io::Result<()> {
+        let mean_turns = self.turns as f32 / self.games.max(1) as f32;
+        let win_rate = |t: &PlayerTotals| t.score / t.games.max(1) as f32;
+        match self.format {
+            ResultsFormat::Json => {
+                let players = self.players
+                    .iter()
+                    .zip(self.totals.iter())
+                    .map(|(name, t)| {
+                        Json::object(vec![
+                            ("name", name.as_str().into()),
+                            ("games", (t.games as i32).into()),
+                            ("score", t.score.into()),
+                            ("wins", (t.wins as i32).into()),
+                            ("win_rate", win_rate(t).into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                let line = Json::object(vec![
+                    ("type", "summary".into()),
+                    ("games", (self.games as i32).into()),
+                    ("mean_turns", mean_turns.into()),
+                    ("players", Json::Array(players)),
+                ]);
+                writeln!(self.file, "{}", line)?;
+            }
+            ResultsFormat::Csv => {
+                let mut f = File::create(summary_path(&self.path))?;
+                writeln!(f, "player,games,score,wins,win_rate,mean_turns")?;
+                for (name, t) in self.players.iter().zip(self.totals.iter()) {
+                    writeln!(
+                        f,
+                        "{},{},{},{},{:.4},{:.2}",
+                        csv_field(name),
+                        t.games,
+                        t.score,
+                        t.wins,
+                        win_rate(t),
+                        t.turns as f32 / t.games.max(1) as f32
+                    )?;
+                }
+            }
+        }
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards;
+    use game::GameOutcome;
+    use results_output::*;
+    use std;
+
+    #[test]
+    fn test_parse_output() {
+        assert_eq!(
+            parse_output("csv:out/results.csv"),
+            Ok((ResultsFormat::Csv, "out/results.csv".to_string()))
+        );
+        assert!(parse_output("json:").is_err());
+        assert!(parse_output("xml:results.xml").is_err());
+        assert_eq!(summary_path("out/results.csv"), "out/results-summary.csv");
+        assert_eq!(summary_path("out.d/results"), "out.d/results-summary");
+    }
+
+    #[test]
+    fn test_results_file() {
+        let path = std::env::temp_dir().join("tactician-test-results.csv");
+        let path = path.to_str().unwrap();
+        let kingdom = cards::first_game_kingdom();
+        let players = vec!["a".to_string(), "b, c".to_string()];
+        let mut f = ResultsFile::create(path, ResultsFormat::Csv, players).unwrap();
+        for &(seats, scores) in [([0, 1], [1.0, 0.0]), ([1, 0], [0.5, 0.5])].iter() {
+            let outcome = GameOutcome {
+                scores: scores.to_vec(),
+                turns: 20,
+            };
+            f.record(&GameResult {
+                game: 1,
+                seed: Some(7),
+                seats: seats.to_vec(),
+                outcome: &outcome,
+                kingdom: &kingdom,
+            }).unwrap();
+        }
+        f.finish().unwrap();
+
+        let rows = std::fs::read_to_string(path).unwrap();
+        assert_eq!(rows.lines().count(), 5);
+        assert!(rows.lines().nth(1).unwrap().ends_with(",1,a,1,1"));
+        let summary = std::fs::read_to_string(summary_path(path)).unwrap();
+        assert!(summary.contains("a,2,1.5,1,0.7500,20.00"));
+        assert!(summary.contains("\"b, c\",2,0.5,0,0.2500,20.00"));
+    }
+}
@@ -0,0 +1,331 @@
+use std::io;
+use std::io::{BufRead, Write};
+
+use cards;
+use game::{fresh_game_with_kingdom, EvalContext, Game, Verbosity};
+use game_serialization::{cards_from_json, cards_to_json, status_to_json};
+use json::Json;
+use process_decider::check_choice;
+use tree_search::SearchableState;
+use util;
+
+// JSON-RPC 2.0 error codes.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+// A call the game can't make, such as a move that isn't legal.
+pub const GAME_ERROR: i32 = -32000;
+
+const DEFAULT_PLAYERS: [&str; 2] = ["Player 1", "Player 2"];
+
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    RpcError {
+        code: INVALID_PARAMS,
+        message: message.to_string(),
+    }
+}
+
+fn game_error(message: &str) -> RpcError {
+    RpcError {
+        code: GAME_ERROR,
+        message: message.to_string(),
+    }
+}
+
+// A game being played through the server, with the random draws for its
+// shuffles.
+struct ServedGame {
+    game: Game,
+    ctx: EvalContext,
+}
+
+impl ServedGame {
+    // Plays on until someone has a decision to make or the game is over.
+    fn advance(&mut self) {
+        while !self.game.is_game_over() && self.game.pending_decision.is_none() {
+            self.game.advance_game(&mut self.ctx);
+        }
+    }
+
+    fn status(&self) -> Json {
+        status_to_json(&self.game)
+    }
+
+    // The game's id and players, and its status.
+    fn summary(&self, id: usize) -> Json {
+        let players = self.game.players.iter().map(|p| p.name.as_str().into()).collect();
+        let mut fields = vec![
+            ("game".to_string(), (id as i32).into()),
+            ("players".to_string(), Json::Array(players)),
+        ];
+        if let Json::Object(status) = self.status() {
+            fields.extend(status);
+        }
+        Json::Object(fields)
+    }
+}
+
+// Runs games for other programs, such as frontends and bots, which call
+// its methods with JSON-RPC 2.0 requests:
+//
+//     new_game {"players": ["Alice", "Bob"], "kingdom": "preset:attack",
+//               "seed": 7}
+//         Deals a game, returning {"game": 1}. Every parameter is optional.
+//     get_state {"game": 1, "player": 0}
+//         The game as saved by to_json. With a player, only what they can
+//         see: hidden hands and decks are counts instead.
+//     legal_moves {"game": 1}
+//         The choices for the pending decision, each a list of card names.
+//     submit_decision {"game": 1, "choice": ["Silver"]}
+//         Makes the pending decision, returning the game's status.
+//     advance {"game": 1}
+//         Plays on to the next decision, returning the game's status: its
+//         turn, whether it's over, the pending decision and the scores.
+//     list_games {"finished": true}
+//         Every game's id, players and status, or only the finished ones.
+//
+// Cards are named, as in save files. Nothing is played until advance is
+// called, so a client sees each decision before it's made.
+pub struct Server {
+    // Games by id, from 1.
+    games: Vec<ServedGame>,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server { games: vec![] }
+    }
+
+    // Answers a request, or returns None for a notification, which has no
+    // id and gets no answer.
+    pub fn handle(&mut self, request: &Json) -> Option<Json> {
+        let id = request.get("id").cloned();
+        let version = request.get("jsonrpc").and_then(|v| v.as_str());
+        let result = match (version, request.get("method")) {
+            (Some("2.0"), Some(&Json::String(ref method))) => {
+                let params = request.get("params").cloned().unwrap_or(Json::Object(vec![]));
+                self.call(method, &params)
+            }
+            _ => Err(RpcError {
+                code: INVALID_REQUEST,
+                message: "Expected a JSON-RPC 2.0 request with a method".into(),
+            }),
+        };
+        let id = match id {
+            Some(id) => id,
+            None if result.is_ok() => return None,
+            None => Json::Null,
+        };
+        let outcome = match result {
+            Ok(value) => ("result", value),
+            Err(e) => (
+                "error",
+                Json::object(vec![("code", e.code.into()), ("message", e.message.into())]),
+            ),
+        };
+        Some(Json::object(vec![("jsonrpc", "2.0".into()), outcome, ("id", id)]))
+    }
+
+    // Answers a line of JSON, or a line that isn't JSON with an error.
+    pub fn handle_line(&mut self, line: &str) -> Option<Json> {
+        match Json::parse(line) {
+            Ok(request) => self.handle(&request),
+            Err(e) => Some(Json::object(vec![
+                ("jsonrpc", "2.0".into()),
+                (
+                    "error",
+                    Json::object(vec![("code", PARSE_ERROR.into()), ("message", e.into())]),
+                ),
+                ("id", Json::Null),
+            ])),
+        }
+    }
+
+    // The number of games dealt, which are numbered from 1.
+    pub fn game_count(&self) -> usize {
+        self.games.len()
+    }
+
+    // Calls a method with its params, an object.
+    pub fn call(&mut self, method: &str, params: &Json) -> Result<Json, RpcError> {
+        match method {
+            "new_game" => self.new_game(params),
+            "get_state" => {
+                let served = self.game(params)?;
+                match params.get("player") {
+                    None => Ok(served.game.to_json()),
+                    Some(p) => {
+                        let p = p.as_i64()
+                            .filter(|&p| p >= 0 && (p as usize) < served.game.players.len())
+                            .ok_or(invalid_params("player should be a seat number"))?;
+                        let player = served.game.players[p as usize].identifier;
+                        Ok(served.game.view_json(player))
+                    }
+                }
+            }
+            "legal_moves" => {
+                let served = self.game(params)?;
+                if served.game.pending_decision.is_none() {
+                    return Err(game_error("There's no decision to make"));
+                }
+                let moves = served.game.all_moves();
+                Ok(Json::Array(moves.iter().map(cards_to_json).collect()))
+            }
+            "submit_decision" => {
+                let served = self.game(params)?;
+                let choice = match params.get("choice") {
+                    Some(c) => cards_from_json(c).map_err(|e| invalid_params(&e))?,
+                    None => return Err(invalid_params("Missing choice")),
+                };
+                match served.game.pending_decision {
+                    Some(ref d) => check_choice(&choice, d).map_err(|e| game_error(&e))?,
+                    None => return Err(game_error("There's no decision to make")),
+                }
+                served.game.resolve_decision(choice, &mut served.ctx);
+                Ok(served.status())
+            }
+            "advance" => {
+                let served = self.game(params)?;
+                served.advance();
+                Ok(served.status())
+            }
+            "list_games" => {
+                let finished = match params.get("finished") {
+                    Some(&Json::Bool(finished)) => finished,
+                    Some(_) => return Err(invalid_params("finished should be true or false")),
+                    None => false,
+                };
+                let games = self.games
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, served)| !finished || served.game.is_game_over())
+                    .map(|(i, served)| served.summary(i + 1))
+                    .collect();
+                Ok(Json::Array(games))
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method {}", method),
+            }),
+        }
+    }
+
+    fn new_game(&mut self, params: &Json) -> Result<Json, RpcError> {
+        let players = match params.get("players") {
+            Some(names) => names
+                .as_array()
+                .and_then(|names| {
+                    names
+                        .iter()
+                        .map(|n| n.as_str().map(String::from))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or(invalid_params("players should be a list of names"))?,
+            None => DEFAULT_PLAYERS.iter().map(|&n| n.to_string()).collect::<Vec<_>>(),
+        };
+        if players.len() < 2 || players.len() > 4 {
+            return Err(invalid_params("Games need 2 to 4 players"));
+        }
+        let kingdom = match params.get("kingdom").map(|k| k.as_str()) {
+            Some(Some(k)) => cards::parse_kingdom(k).map_err(|e| invalid_params(&e))?,
+            Some(None) => return Err(invalid_params("kingdom should be a string")),
+            None => cards::first_game_kingdom(),
+        };
+        let rng = match params.get("seed").map(|s| s.as_i64()) {
+            Some(Some(seed)) if seed >= 0 && seed <= u32::max_value() as i64 => {
+                util::seeded_weak_rng(seed as u32)
+            }
+            Some(_) => return Err(invalid_params("seed should be a whole number")),
+            None => util::randomly_seeded_weak_rng(),
+        };
+        let mut served = ServedGame {
+            game: fresh_game_with_kingdom(&players, &kingdom),
+            ctx: EvalContext {
+                rng: rng,
+                verbosity: Verbosity::Quiet,
+                events: None,
+            },
+        };
+        served.game.initialize_game(&mut served.ctx);
+        self.games.push(served);
+        Ok(Json::object(vec![("game", (self.games.len() as i32).into())]))
+    }
+
+    fn game(&mut self, params: &Json) -> Result<&mut ServedGame, RpcError> {
+        let count = self.games.len();
+        match params.get("game").and_then(|g| g.as_i64()) {
+            Some(id) if id >= 1 && id as usize <= count => Ok(&mut self.games[id as usize - 1]),
+            Some(id) => Err(invalid_params(&format!("There's no game {}", id))),
+            None => Err(invalid_params("Missing game")),
+        }
+    }
+}
+
+// Serves requests read a line at a time from input until it ends, writing
+// each answer on a line of its own.
+pub fn serve<R: BufRead, W: Write>(input: &mut R, out: &mut W) -> io::Result<()> {
+    let mut server = Server::new();
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_line(&line) {
+            writeln!(out, "{}", response)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use json::Json;
+    use rpc_server::*;
+
+    #[test]
+    fn test_serve() {
+        let requests = [
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "new_game", "params": {"seed": 3}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "advance", "params": {"game": 1}}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "legal_moves", "params": {"game": 1}}"#,
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "submit_decision", "params": {"game": 1, "choice": ["Province"]}}"#,
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "get_state", "params": {"game": 1, "player": 1}}"#,
+            r#"{"jsonrpc": "2.0", "method": "advance", "params": {"game": 1}}"#,
+            r#"{"jsonrpc": "2.0", "id": 6, "method": "resign", "params": {}}"#,
+            r#"{"jsonrpc": "2.0", "id": 7, "method": "advance", "params": {"game": 2}}"#,
+            "not json",
+        ];
+        let input = requests.join("\n");
+        let mut out = vec![];
+        serve(&mut input.as_bytes(), &mut out).unwrap();
+        let responses = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| Json::parse(l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(responses.len(), 8);
+
+        let result = |i: usize| responses[i].get("result").unwrap().clone();
+        let error = |i: usize| {
+            let e = responses[i].get("error").unwrap();
+            e.get("code").and_then(|c| c.as_i64())
+        };
+        assert_eq!(result(0).get("game").and_then(|g| g.as_i64()), Some(1));
+        assert!(!result(1).get("pending_decision").unwrap().is_null());
+        assert!(result(2).as_array().unwrap().len() > 0);
+        assert_eq!(error(3), Some(-32000));
+        assert!(result(4).get("players").is_some());
+        assert_eq!(error(5), Some(-32601));
+        assert_eq!(error(6), Some(-32602));
+        assert_eq!(error(7), Some(-32700));
+        assert_eq!(responses[5].get("id"), Some(&Json::from(6)));
+    }
+}
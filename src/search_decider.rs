@@ -1,13 +1,44 @@
 use itertools::Itertools;
+use rand::XorShiftRng;
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
 
+use cards;
 use cards::CardIdentifier;
+use deciders;
 
-use game::{Decider, Decision, DecisionType, EvalContext, Game, PlayerIdentifier};
-use tree_search::{find_best_move, SearchableState, Winners};
+use endgame_search;
+use endgame_search::EndgameLimits;
+use game::{Decider, Decision, DecisionType, EvalContext, Game, MoveStats, PlayerIdentifier,
+           Verbosity, EMPTY_PILES_FOR_GAME_END};
+use game_features::player_features;
+use heuristic_decider;
+use interrupt;
+use linear_evaluator;
+use locale;
+use match_history::{MatchHistory, MatchPriorPolicy};
+use opening_book::OpeningBook;
+use registry::{parse_option_duration, parse_option_flag, parse_option_value,
+               parse_player_options, OptionSpec, PlayerKind, PlayerSpec, Registry};
+use tree_search;
+use tree_search::{grow_tree, EpsilonGreedyRollout, Evaluator, MovePruner, OpponentModelRollout,
+                  Prior, PriorPolicy, RandomRollout, Rewards, RolloutPolicy, SearchBudget,
+                  SearchOptions, SearchPolicies, SearchStats, SearchTree, SearchableState,
+                  Winners, ROOT};
+use tree_search_logging::TreeFormat;
+use util;
 
+// Moves are sorted, matching the canonical form of searched moves.
 fn hard_coded_decision(d: &Decision) -> Option<Vec<CardIdentifier>> {
     match d.decision_type {
-        DecisionType::PlayTreasures => Some(d.choices.clone()),
+        DecisionType::PlayTreasures => {
+            let mut choice = d.choices.clone();
+            choice.sort();
+            Some(choice)
+        }
         _ => None,
     }
 }
@@ -76,18 +107,16 @@ impl SearchableState for Game {
                 ret.push(v);
             }
         }
-        ret
-    }
-
-    fn make_move(&self, choice: Self::M, ctx: &mut Self::C) -> Self {
-        let mut game_copy = self.clone();
-        game_copy.resolve_decision(choice, ctx);
 
-        while !game_copy.is_game_over() && game_copy.pending_decision.is_none() {
-            game_copy.advance_game(ctx);
+        // Choices often include copies of the same card, which would make
+        // identical moves (discarding one Copper or another) separate
+        // branches. Keep one sorted move per multiset of cards.
+        for m in ret.iter_mut() {
+            m.sort();
         }
-
-        game_copy
+        ret.sort();
+        ret.dedup();
+        ret
     }
 
     fn make_move_mut(&mut self, choice: Self::M, ctx: &mut Self::C) {
@@ -97,15 +126,567 @@ impl SearchableState for Game {
         }
     }
 
+    // Opponents' hands and every deck order are hidden from the searching
+    // player, so the search only ever sees them reshuffled.
+    fn determinize(&self, observer: &Self::P, ctx: &mut Self::C) -> Self {
+        Game::determinize(self, *observer, ctx)
+    }
+
+    fn scores(&self) -> Option<Vec<(Self::P, f32)>> {
+        Some(
+            self.players
+                .iter()
+                .map(|p| (p.identifier, cards::score_cards(&p.all_cards()) as f32))
+                .collect(),
+        )
+    }
+
+    // Draws are the only randomness, and the observer sees their own.
+    fn chance_outcome(&self, observer: &Self::P) -> Option<u64> {
+        Some(self.hand_hash(*observer))
+    }
+
+    fn transposition_key(&self) -> Option<u64> {
+        Some(self.public_zobrist_hash())
+    }
+
+    fn turn(&self) -> i32 {
+        self.turn
+    }
+
     fn printable_player_identifier(&self, p: &Self::P) -> String {
         self.players[p.0 as usize].name.clone()
     }
+
+    fn printable_move(&self, m: &Self::M) -> String {
+        if m.is_empty() {
+            locale::text("nothing", &[])
+        } else {
+            locale::card_names(m)
+        }
+    }
+}
+
+// How many VP a point of money density is worth per Province left to buy.
+const MONEY_DENSITY_WEIGHT: f32 = 3.0;
+// A lead of this many points makes a player e (~2.7) times as likely to win.
+const EVALUATION_SCALE: f32 = 6.0;
+
+// Scores each player by VP plus how well their deck can buy the remaining
+// Provinces, then converts the scores to win probabilities with a softmax.
+pub struct HeuristicEvaluator;
+
+impl HeuristicEvaluator {
+    fn player_score(&self, g: &Game, pid: PlayerIdentifier) -> f32 {
+        let all_cards = g.players[pid.0 as usize].all_cards();
+        let vp = cards::score_cards(&all_cards) as f32;
+        let money: i32 = all_cards
+            .iter()
+            .map(|c| cards::lookup_card(c).coin_value.unwrap_or(0))
+            .sum();
+        let money_density = money as f32 / all_cards.len().max(1) as f32;
+        let provinces_left = g.piles[&cards::PROVINCE.identifier] as f32;
+        vp + MONEY_DENSITY_WEIGHT * money_density * provinces_left
+    }
+}
+
+impl Evaluator<Game> for HeuristicEvaluator {
+    fn evaluate(&self, g: &Game) -> Rewards<PlayerIdentifier> {
+        let scores = g.all_players()
+            .into_iter()
+            .map(|pid| (pid, self.player_score(g, pid)))
+            .collect::<Vec<_>>();
+        Rewards::from_scores(scores, EVALUATION_SCALE)
+    }
+}
+
+// Rolls out games by letting an ordinary decider make every player's choices.
+pub struct DeciderRollout(pub Box<Decider>);
+
+impl RolloutPolicy<Game> for DeciderRollout {
+    fn choose_move(&mut self, state: &Game, _: &mut XorShiftRng) -> Option<Vec<CardIdentifier>> {
+        if state.is_game_over() {
+            None
+        } else {
+            Some(self.0.make_decision(state))
+        }
+    }
+}
+
+// Pseudo-visits given to buys the heuristic has an opinion about.
+const BUY_PRIOR_VISITS: i32 = 10;
+
+// Seeds buy decisions with the usual money-first ordering: Province over
+// Gold over Silver, and a pessimistic start for buying Curses.
+pub struct BuyPriorPolicy;
+
+impl PriorPolicy<Game> for BuyPriorPolicy {
+    fn prior(&self, g: &Game, m: &Vec<CardIdentifier>) -> Option<Prior> {
+        match g.pending_decision {
+            Some(ref d) if d.decision_type == DecisionType::BuyCard => {}
+            _ => return None,
+        }
+
+        let value = match m.first() {
+            Some(c) if *c == cards::PROVINCE.identifier => 0.7,
+            Some(c) if *c == cards::GOLD.identifier => 0.65,
+            Some(c) if *c == cards::SILVER.identifier => 0.6,
+            Some(c) if *c == cards::CURSE.identifier => 0.0,
+            _ => return None,
+        };
+        Some(Prior {
+            value: value,
+            visits: BUY_PRIOR_VISITS,
+        })
+    }
+}
+
+// The endgame search takes over once this few Provinces are left, or this
+// few cards are left across the piles closest to emptying.
+const ENDGAME_PROVINCES: i32 = 2;
+const ENDGAME_PILE_CARDS: i32 = 3;
+
+// Under a clock, each search gets this share of the time left, plus most
+// of the increment.
+const CLOCK_MOVES_LEFT: u32 = 30;
+
+fn is_near_endgame(g: &Game) -> bool {
+    if g.piles[&cards::PROVINCE.identifier] <= ENDGAME_PROVINCES {
+        return true;
+    }
+    let mut counts = g.piles
+        .iter()
+        .filter(|&(c, _)| *c != cards::PROVINCE.identifier)
+        .map(|(_, &n)| n)
+        .collect::<Vec<_>>();
+    counts.sort();
+    let closest = counts.iter().take(EMPTY_PILES_FOR_GAME_END as usize);
+    closest.sum::<i32>() <= ENDGAME_PILE_CARDS
+}
+
+// Dominated moves the search can leave out, each optional.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PruningRules {
+    // Never buy or gain a Curse.
+    pub curses: bool,
+    // Always discard every Victory card and Curse to Cellar, since keeping
+    // them in hand does nothing while discarding them draws a card.
+    pub dead_cards: bool,
+}
+
+impl PruningRules {
+    // Parses rule names separated by slashes, e.g. "curses/deadcards".
+    pub fn parse(s: &str) -> Result<PruningRules, String> {
+        let mut rules = PruningRules::default();
+        for name in s.split('/').filter(|n| !n.is_empty()) {
+            match name {
+                "curses" => rules.curses = true,
+                "deadcards" => rules.dead_cards = true,
+                _ => return Err(format!("Unknown pruning rule {}", name)),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+fn is_dead_card(c: &CardIdentifier) -> bool {
+    let card = cards::lookup_card(c);
+    card.is_victory() || card.is_curse()
+}
+
+impl MovePruner<Game> for PruningRules {
+    fn is_pruned(&self, g: &Game, m: &Vec<CardIdentifier>) -> bool {
+        let d = match g.pending_decision {
+            Some(ref d) => d,
+            None => return false,
+        };
+        match d.decision_type {
+            DecisionType::BuyCard | DecisionType::GainCard(_) if self.curses => {
+                m.first() == Some(&cards::CURSE.identifier)
+            }
+            DecisionType::DiscardCards(Some(_)) if self.dead_cards => {
+                let dead = |cs: &Vec<CardIdentifier>| cs.iter().filter(|c| is_dead_card(c)).count();
+                dead(m) < dead(&d.choices)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Search budgets for particular kinds of decision, overriding the
+// search's own budget. Reaction reveals and discards rarely need the
+// compute that buys deserve.
+#[derive(Clone, Debug, Default)]
+pub struct PhaseBudgets {
+    pub play_action: Option<SearchBudget>,
+    pub buy_card: Option<SearchBudget>,
+    pub gain_card: Option<SearchBudget>,
+    pub discard_cards: Option<SearchBudget>,
+    pub trash_cards: Option<SearchBudget>,
+    pub reveal_reaction: Option<SearchBudget>,
+}
+
+impl PhaseBudgets {
+    // The budget for a kind of decision, named as for the random player's
+    // pass option: action, buy, gain, discard, trash or reveal.
+    fn budget_named(&mut self, kind: &str) -> Option<&mut Option<SearchBudget>> {
+        match kind {
+            "action" => Some(&mut self.play_action),
+            "buy" => Some(&mut self.buy_card),
+            "gain" => Some(&mut self.gain_card),
+            "discard" => Some(&mut self.discard_cards),
+            "trash" => Some(&mut self.trash_cards),
+            "reveal" => Some(&mut self.reveal_reaction),
+            _ => None,
+        }
+    }
+
+    pub fn budget_for(&self, decision_type: &DecisionType) -> Option<SearchBudget> {
+        match *decision_type {
+            DecisionType::PlayAction => self.play_action,
+            DecisionType::PlayTreasures => None,
+            DecisionType::BuyCard => self.buy_card,
+            DecisionType::GainCard(_) => self.gain_card,
+            DecisionType::DiscardCards(_) => self.discard_cards,
+            DecisionType::TrashCards(_) => self.trash_cards,
+            DecisionType::RevealReaction(_) => self.reveal_reaction,
+        }
+    }
+}
+
+// How the search rated one move of an analyzed decision.
+#[derive(Clone, Debug)]
+pub struct MoveAnalysis {
+    pub choice: Vec<CardIdentifier>,
+    pub visits: i32,
+    pub win_rate: f32,
+}
+
+// A searched decision and how the search spread its visits over the moves,
+// kept as training data.
+#[derive(Clone)]
+pub struct SearchRecord {
+    pub decision: Decision,
+    pub turn: i32,
+    // Each player's features, in player order.
+    pub features: Vec<Vec<f32>>,
+    pub choice: Vec<CardIdentifier>,
+    pub visits: Vec<(Vec<CardIdentifier>, i32)>,
+}
+
+// Where and how a decider writes the top of its tree after each search.
+#[derive(Clone, Debug)]
+pub struct TreeExport {
+    pub format: TreeFormat,
+    pub directory: PathBuf,
+    pub depth: usize,
 }
 
 pub struct SearchDecider {
     pub ctx: EvalContext,
-    pub debug: bool,
-    pub iterations: i32,
+    // Drives the search's own choices, separately from ctx, which samples
+    // hidden information and plays out the game's randomness.
+    rng: XorShiftRng,
+    pub options: SearchOptions,
+    pub phase_budgets: PhaseBudgets,
+    pub rollout_policy: Box<RolloutPolicy<Game>>,
+    // Chance of a random move in place of each of our rollout_policy moves.
+    pub rollout_epsilon: Option<f32>,
+    // Plays the opponents' moves in rollouts when set, while ours still
+    // follow rollout_policy.
+    pub opponent_rollout: Option<Box<RolloutPolicy<Game>>>,
+    pub evaluator: Option<Box<Evaluator<Game>>>,
+    pub prior: Option<Box<PriorPolicy<Game>>>,
+    // Our buys in earlier games of the match and how they turned out, which
+    // seed buys ahead of the prior policy when set.
+    pub match_history: Option<MatchHistory>,
+    pub opening_book: Option<OpeningBook>,
+    pub pruning: PruningRules,
+    // Near the end of the game, decides by searching every line of play
+    // through the next turns, when the position is small enough.
+    pub endgame_search: Option<EndgameLimits>,
+    pub tree_export: Option<TreeExport>,
+    // Collects a record of each searched decision when set. It can be shared
+    // between deciders playing the same game.
+    pub recorder: Option<Rc<RefCell<Vec<SearchRecord>>>>,
+    // Prints how each search spent its budget, and the totals at the end
+    // of each game.
+    pub report_stats: bool,
+    // Every search of the current game, including pondering.
+    game_stats: SearchStats,
+    trees_exported: u32,
+    // Coins in our first hand, which determines our opening split.
+    first_turn_coins: Option<i32>,
+    // Search tree for a decision point, and the decision count at which
+    // it's valid to search from. It follows each observed move downwards.
+    subtree: Option<(SearchTree<Game>, i32)>,
+    // Which player we are, learned at our first decision.
+    player: Option<PlayerIdentifier>,
+    // The share of root visits given to the last move searched for.
+    last_confidence: Option<f32>,
+    // The root's moves in the last search, with their visits and win rates.
+    last_analysis: Vec<MoveStats>,
+    // Time left on our clock and its increment, in games with a time
+    // control.
+    clock: Option<(Duration, Duration)>,
+}
+
+impl SearchDecider {
+    pub fn new(options: SearchOptions) -> SearchDecider {
+        SearchDecider {
+            ctx: EvalContext {
+                verbosity: Verbosity::Quiet,
+                rng: util::randomly_seeded_weak_rng(),
+                events: None,
+            },
+            rng: util::randomly_seeded_weak_rng(),
+            options: options,
+            phase_budgets: PhaseBudgets::default(),
+            rollout_policy: Box::new(RandomRollout),
+            rollout_epsilon: None,
+            opponent_rollout: None,
+            evaluator: Some(Box::new(HeuristicEvaluator)),
+            prior: None,
+            match_history: None,
+            opening_book: Some(OpeningBook::standard()),
+            pruning: PruningRules::default(),
+            endgame_search: None,
+            tree_export: None,
+            recorder: None,
+            report_stats: false,
+            game_stats: SearchStats::default(),
+            trees_exported: 0,
+            first_turn_coins: None,
+            subtree: None,
+            player: None,
+            last_confidence: None,
+            last_analysis: vec![],
+            clock: None,
+        }
+    }
+
+    // Makes searches reproducible. Only iteration budgets are, since how far
+    // a timed search gets depends on the machine.
+    pub fn seed(&mut self, seed: u32) {
+        self.ctx.rng = util::seeded_weak_rng(seed);
+        self.rng = util::seeded_weak_rng(seed.wrapping_add(1));
+    }
+
+    fn book_buy(&mut self, g: &Game) -> Option<Vec<CardIdentifier>> {
+        let book = self.opening_book.as_ref()?;
+        let is_buy = g.pending_decision
+            .as_ref()
+            .map_or(false, |d| d.decision_type == DecisionType::BuyCard);
+        if is_buy && g.turn == 1 {
+            self.first_turn_coins = Some(g.coins);
+        }
+        book.book_buy(g, self.first_turn_coins?)
+    }
+
+    fn search_endgame(&mut self, g: &Game) -> Option<Vec<CardIdentifier>> {
+        let limits = self.endgame_search?;
+        if !is_near_endgame(g) {
+            return None;
+        }
+        let evaluator: &Evaluator<Game> = match self.evaluator {
+            Some(ref e) => &**e,
+            None => &HeuristicEvaluator,
+        };
+        let values = endgame_search::search(g, evaluator, limits, &mut self.ctx)?;
+
+        if self.options.debug {
+            println!("Endgame search:");
+            for &(ref m, value) in values.iter() {
+                println!("  {}: {:.3}", g.printable_move(m), value);
+            }
+        }
+        values
+            .into_iter()
+            .fold(None, |best: Option<(Vec<CardIdentifier>, f32)>, (m, v)| match best {
+                Some((_, best_v)) if best_v >= v => best,
+                _ => Some((m, v)),
+            })
+            .map(|(m, _)| m)
+    }
+
+    // A subtree can be searched from when it's rooted at the current
+    // decision, having followed every move observed since it was built.
+    fn take_search_tree(&mut self, g: &Game) -> SearchTree<Game> {
+        match self.subtree.take() {
+            Some((tree, valid_at)) => {
+                if self.options.reuse_tree && valid_at == g.decisions_resolved {
+                    tree
+                } else {
+                    SearchTree::new(g)
+                }
+            }
+            None => SearchTree::new(g),
+        }
+    }
+
+    fn budget_for(&self, d: &Decision) -> SearchBudget {
+        let budget = self.phase_budgets
+            .budget_for(&d.decision_type)
+            .unwrap_or(self.options.budget);
+        match self.clock {
+            // Searches by time instead of iterations under a clock, and
+            // never for more than half the time left.
+            Some((remaining, increment)) => {
+                let allowance = (remaining / CLOCK_MOVES_LEFT + increment * 3 / 4).min(remaining / 2);
+                match budget {
+                    SearchBudget::Time(t) if t < allowance => budget,
+                    _ => SearchBudget::Time(allowance),
+                }
+            }
+            None => budget,
+        }
+    }
+
+    // Searches the pending decision from scratch, without the opening book
+    // or hard-coded moves, and returns every legal move most visited first.
+    pub fn analyze(&mut self, g: &Game) -> Vec<MoveAnalysis> {
+        let (player, budget) = {
+            let d = g.pending_decision
+                .as_ref()
+                .expect("SearchDecider::analyze called without pending decision");
+            (d.player, self.budget_for(&d))
+        };
+
+        let mut tree = SearchTree::new(g);
+        self.grow_search_tree(&mut tree, g, player, budget);
+
+        let mut analysis = g.all_moves()
+            .into_iter()
+            .map(|m| {
+                let (visits, wins) = match tree.child_with_move(ROOT, &m) {
+                    Some(c) => (tree.nodes[c].visits, tree.nodes[c].wins),
+                    None => (0, 0.0),
+                };
+                MoveAnalysis {
+                    choice: m,
+                    visits: visits,
+                    win_rate: if visits > 0 { wins / visits as f32 } else { 0.0 },
+                }
+            })
+            .collect::<Vec<_>>();
+        analysis.sort_by(|a, b| b.visits.cmp(&a.visits));
+        analysis
+    }
+
+    // Trees are numbered by search, so several games can share a directory.
+    // Each is written with the position it was searched from, which can be
+    // analyzed again later.
+    fn export_tree(&mut self, tree: &SearchTree<Game>, g: &Game) {
+        let export = match self.tree_export.clone() {
+            Some(export) => export,
+            None => return,
+        };
+
+        self.trees_exported += 1;
+        let path = export.directory.join(format!(
+            "tree-{:05}.{}",
+            self.trees_exported,
+            export.format.extension()
+        ));
+        let contents = tree.export_tree(export.format, export.depth);
+        if let Err(e) = fs::write(&path, contents) {
+            eprintln!("Unable to write search tree to {}: {}", path.display(), e);
+        }
+
+        let position_path = export
+            .directory
+            .join(format!("position-{:05}.json", self.trees_exported));
+        if let Err(e) = fs::write(&position_path, g.to_json().to_string()) {
+            eprintln!("Unable to write position to {}: {}", position_path.display(), e);
+        }
+    }
+
+    fn record_search(&self, tree: &SearchTree<Game>, g: &Game, choice: &Vec<CardIdentifier>) {
+        let recorder = match self.recorder {
+            Some(ref r) => r,
+            None => return,
+        };
+
+        let visits = tree.root()
+            .children
+            .iter()
+            .map(|&c| {
+                let child = &tree.nodes[c];
+                (child.last_move.clone().unwrap(), child.visits)
+            })
+            .collect();
+        recorder.borrow_mut().push(SearchRecord {
+            decision: g.pending_decision.clone().unwrap(),
+            turn: g.turn,
+            features: g.all_players()
+                .into_iter()
+                .map(|pid| player_features(g, pid))
+                .collect(),
+            choice: choice.clone(),
+            visits: visits,
+        });
+    }
+
+    fn grow_search_tree(
+        &mut self,
+        tree: &mut SearchTree<Game>,
+        g: &Game,
+        player: PlayerIdentifier,
+        budget: SearchBudget,
+    ) -> SearchStats {
+        let fallback = self.prior.as_ref().map(|p| &**p);
+        let match_prior = self.match_history.as_ref().map(|h| MatchPriorPolicy {
+            history: h,
+            fallback: fallback,
+        });
+        let mut epsilon_greedy;
+        let own: &mut RolloutPolicy<Game> = match self.rollout_epsilon {
+            Some(epsilon) => {
+                epsilon_greedy = EpsilonGreedyRollout {
+                    policy: &mut *self.rollout_policy,
+                    epsilon: epsilon,
+                };
+                &mut epsilon_greedy
+            }
+            None => &mut *self.rollout_policy,
+        };
+        let mut opponent_model;
+        let rollout: &mut RolloutPolicy<Game> = match self.opponent_rollout {
+            Some(ref mut opponents) => {
+                opponent_model = OpponentModelRollout {
+                    player: player,
+                    own: own,
+                    opponents: &mut **opponents,
+                };
+                &mut opponent_model
+            }
+            None => own,
+        };
+        let mut policies = SearchPolicies {
+            rollout: rollout,
+            evaluator: self.evaluator.as_ref().map(|e| &**e),
+            prior: match match_prior {
+                Some(ref p) => Some(p),
+                None => fallback,
+            },
+            pruner: match self.pruning == PruningRules::default() {
+                true => None,
+                false => Some(&self.pruning),
+            },
+        };
+        let stats = grow_tree(
+            tree,
+            g,
+            &player,
+            budget,
+            &self.options,
+            &mut policies,
+            &mut self.rng,
+            &mut self.ctx,
+        );
+        self.game_stats.add(&stats);
+        stats
+    }
 }
 
 impl Decider for SearchDecider {
@@ -114,15 +695,594 @@ impl Decider for SearchDecider {
     }
 
     fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
-        {
+        self.last_confidence = None;
+        self.last_analysis.clear();
+        let (player, budget) = {
             let d = g.pending_decision
                 .as_ref()
                 .expect("SearchDecider::make_decision called without pending decision");
             if let Some(choice) = hard_coded_decision(&d) {
                 return choice;
             }
+            (d.player, self.budget_for(&d))
+        };
+        self.player = Some(player);
+
+        if let Some(buy) = self.book_buy(g) {
+            return buy;
+        }
+        if let Some(choice) = self.search_endgame(g) {
+            self.subtree = None;
+            return choice;
+        }
+
+        let mut tree = self.take_search_tree(g);
+        let stats = self.grow_search_tree(&mut tree, g, player, budget);
+
+        if self.options.debug {
+            tree.print_debug_move_tree();
+        }
+        if stats.interrupted > 0 {
+            println!("Search interrupted after {} iterations", stats.iterations);
+        }
+        if self.report_stats {
+            println!("Search: {}", stats);
+        }
+        self.export_tree(&tree, g);
+        let best_child = tree.best_child(ROOT);
+        let best_move = tree.nodes[best_child].last_move.clone().unwrap();
+        let root_visits: i32 = tree.nodes[ROOT]
+            .children
+            .iter()
+            .map(|&c| tree.nodes[c].visits)
+            .sum();
+        if root_visits > 0 {
+            self.last_confidence = Some(tree.nodes[best_child].visits as f32 / root_visits as f32);
+        }
+        self.last_analysis = tree.nodes[ROOT]
+            .children
+            .iter()
+            .map(|&c| {
+                let child = &tree.nodes[c];
+                MoveStats {
+                    choice: child.last_move.clone().unwrap(),
+                    visits: child.visits,
+                    win_rate: child.wins / child.visits.max(1) as f32,
+                }
+            })
+            .collect();
+        self.record_search(&tree, g, &best_move);
+        self.subtree = Some((tree, g.decisions_resolved));
+        best_move
+    }
+
+    // Every move is public, so the tree can follow it down to the subtree
+    // for the next decision, whoever made it.
+    fn observe_decision(&mut self, g: &Game, choice: &Vec<CardIdentifier>) {
+        if let (Some(history), Some(player)) = (self.match_history.as_mut(), self.player) {
+            if g.pending_decision.as_ref().map(|d| d.player) == Some(player) {
+                history.record_decision(g, choice);
+            }
         }
 
-        find_best_move(g.clone(), self.iterations, &mut self.ctx, self.debug)
+        let tree = match self.subtree.take() {
+            Some((tree, valid_at)) if valid_at == g.decisions_resolved => tree,
+            _ => return,
+        };
+        // With chance nodes, the children below a move are outcomes rather
+        // than moves, so the subtree can't serve as a root.
+        if self.options.chance_nodes {
+            return;
+        }
+
+        let mut canonical = choice.clone();
+        canonical.sort();
+        self.subtree = tree.child_with_move(ROOT, &canonical)
+            .map(|c| (tree.into_subtree(c), g.decisions_resolved + 1));
+    }
+
+    fn ponder(&mut self, g: &Game) {
+        let (budget, player) = match (self.options.ponder_budget, self.player) {
+            (Some(budget), Some(player)) => (budget, player),
+            _ => return,
+        };
+
+        let mut tree = self.take_search_tree(g);
+        self.grow_search_tree(&mut tree, g, player, budget);
+        self.subtree = Some((tree, g.decisions_resolved));
+    }
+
+    fn confidence(&self) -> Option<f32> {
+        self.last_confidence
+    }
+
+    fn analysis(&self) -> Vec<MoveStats> {
+        self.last_analysis.clone()
+    }
+
+    fn clock(&mut self, remaining: Duration, increment: Duration) {
+        self.clock = Some((remaining, increment));
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        self.clock = None;
+        if let (Some(history), Some(player)) = (self.match_history.as_mut(), self.player) {
+            // A forfeited game says nothing about how our buys played out.
+            if g.is_game_over() {
+                let scores = g.player_scores();
+                history.finish_game(scores[player.0 as usize].1);
+            } else {
+                history.abandon_game();
+            }
+        }
+        // Decision counts start over each game, so a tree kept from this one
+        // could otherwise look valid in the next. Seats can change too, as
+        // in paired games.
+        self.subtree = None;
+        self.player = None;
+
+        if self.report_stats {
+            println!(
+                "Search totals: {} searches, {}",
+                self.game_stats.searches, self.game_stats
+            );
+        }
+        self.game_stats = SearchStats::default();
+    }
+}
+
+pub fn rollout_policy_named(name: &str) -> Result<Box<RolloutPolicy<Game>>, String> {
+    match name {
+        "random" => Ok(Box::new(RandomRollout)),
+        "bigmoney" => Ok(Box::new(DeciderRollout(Box::new(deciders::BigMoney)))),
+        "heuristic" => Ok(Box::new(DeciderRollout(Box::new(
+            heuristic_decider::HeuristicDecider,
+        )))),
+        _ => Err(format!("Unknown rollout policy {}", name)),
+    }
+}
+
+impl SearchDecider {
+    // A decider with options from a player spec, e.g. [("iters", "5000")].
+    // Its searches stop early on the command line's Ctrl-C, whose handler
+    // only the command line installs, so programs using the library can
+    // point options.interrupt at a flag of their own.
+    pub fn with_options(options: Vec<(&str, &str)>, silent: bool) -> Result<SearchDecider, String> {
+        let mut decider = SearchDecider::new(SearchOptions {
+            interrupt: Some(&interrupt::INTERRUPTED),
+            debug: !silent,
+            ..Default::default()
+        });
+        for (key, value) in options {
+            decider.set_option(key, value)?;
+        }
+        Ok(decider)
+    }
+
+    pub fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "iters" => match parse_option_value(key, value)? {
+                n if n >= 1 => self.options.budget = SearchBudget::Iterations(n),
+                _ => return Err(format!("Option iters must be at least 1, not {}", value)),
+            },
+            "time" => match parse_option_duration(key, value)? {
+                d if d > Duration::from_secs(0) => self.options.budget = SearchBudget::Time(d),
+                _ => return Err(format!("Option time must be longer than {}", value)),
+            },
+            "report" => {
+                self.options.report_interval = Some(match value.ends_with('s') {
+                    true => SearchBudget::Time(parse_option_duration(key, value)?),
+                    false => SearchBudget::Iterations(parse_option_value(key, value)?),
+                })
+            }
+            "ponder" => {
+                self.options.ponder_budget = Some(SearchBudget::Time(
+                    parse_option_duration(key, value)?,
+                ))
+            }
+            "action" | "buy" | "gain" | "discard" | "trash" | "reveal" => {
+                let budget = match value.ends_with('s') {
+                    true => match parse_option_duration(key, value)? {
+                        d if d > Duration::from_secs(0) => SearchBudget::Time(d),
+                        _ => return Err(format!("Option {} must be longer than {}", key, value)),
+                    },
+                    false => match parse_option_value(key, value)? {
+                        n if n >= 1 => SearchBudget::Iterations(n),
+                        _ => {
+                            return Err(format!("Option {} must be at least 1, not {}", key, value))
+                        }
+                    },
+                };
+                *self.phase_budgets.budget_named(key).unwrap() = Some(budget);
+            }
+            "c" => match parse_option_value::<f32>(key, value)? {
+                c if c.is_finite() && c >= 0.0 => self.options.exploration = c,
+                _ => return Err(format!("Option c must be a number from 0, not {}", value)),
+            },
+            "select" => {
+                self.options.selection = match value {
+                    "ucb1" => tree_search::SelectionPolicy::Ucb1,
+                    "tuned" => tree_search::SelectionPolicy::Ucb1Tuned,
+                    "thompson" => tree_search::SelectionPolicy::Thompson,
+                    _ => return Err(format!("Unknown selection policy {}", value)),
+                }
+            }
+            "rollouts" => match parse_option_value(key, value)? {
+                n if n >= 1 => self.options.rollouts_per_leaf = n,
+                _ => return Err(format!("Option rollouts must be at least 1, not {}", value)),
+            },
+            "depth" => match parse_option_value(key, value)? {
+                n if n >= 1 => self.options.rollout_turn_limit = Some(n),
+                _ => return Err(format!("Option depth must be at least 1, not {}", value)),
+            },
+            "fpu" => match parse_option_value::<f32>(key, value)? {
+                u if u.is_finite() => self.options.first_play_urgency = Some(u),
+                _ => return Err(format!("Option fpu must be a number, not {}", value)),
+            },
+            "rave" => match parse_option_value::<f32>(key, value)? {
+                k if k.is_finite() && k > 0.0 => self.options.rave_equivalence = Some(k),
+                _ => return Err(format!("Option rave must be a number above 0, not {}", value)),
+            },
+            "solver" => self.options.solver = parse_option_flag(key, value)?,
+            "transpositions" => self.options.use_transpositions = parse_option_flag(key, value)?,
+            "reuse" => self.options.reuse_tree = parse_option_flag(key, value)?,
+            "chance" => self.options.chance_nodes = parse_option_flag(key, value)?,
+            "nodes" => match parse_option_value(key, value)? {
+                n if n >= 1 => self.options.max_nodes = Some(n),
+                _ => return Err(format!("Option nodes must be at least 1, not {}", value)),
+            },
+            "discount" => match parse_option_value::<f32>(key, value)? {
+                d if d > 0.0 && d <= 1.0 => self.options.turn_discount = Some(d),
+                _ => {
+                    return Err(format!(
+                        "Option discount must be above 0 and at most 1, not {}",
+                        value
+                    ))
+                }
+            },
+            "margin" => match parse_option_value::<f32>(key, value)? {
+                w if (0.0..=1.0).contains(&w) => {
+                    self.options.reward_shaping = tree_search::RewardShaping::ScoreMargin {
+                        weight: w,
+                        scale: 10.0,
+                    }
+                }
+                _ => return Err(format!("Option margin must be from 0 to 1, not {}", value)),
+            },
+            "rollout" => self.rollout_policy = rollout_policy_named(value)?,
+            "epsilon" => match parse_option_value::<f32>(key, value)? {
+                e if (0.0..=1.0).contains(&e) => self.rollout_epsilon = Some(e),
+                _ => return Err(format!("Option epsilon must be from 0 to 1, not {}", value)),
+            },
+            "opponent" => self.opponent_rollout = Some(rollout_policy_named(value)?),
+            "eval" => {
+                self.evaluator = Some(Box::new(linear_evaluator::LinearEvaluator::load(value)?))
+            }
+            "priors" => {
+                self.prior = match parse_option_flag(key, value)? {
+                    true => Some(Box::new(BuyPriorPolicy)),
+                    false => None,
+                }
+            }
+            "dot" | "json" => {
+                self.tree_export = Some(TreeExport {
+                    format: match key {
+                        "dot" => TreeFormat::Dot,
+                        _ => TreeFormat::Json,
+                    },
+                    directory: PathBuf::from(value),
+                    depth: 3,
+                })
+            }
+            "book" => {
+                self.opening_book = match parse_option_flag(key, value)? {
+                    true => Some(OpeningBook::standard()),
+                    false => None,
+                }
+            }
+            "config" => self.load_config(value)?,
+            "seed" => self.seed(parse_option_value(key, value)?),
+            "stats" => self.report_stats = parse_option_flag(key, value)?,
+            "endgame" => {
+                self.endgame_search = match parse_option_flag(key, value)? {
+                    true => Some(EndgameLimits::default()),
+                    false => None,
+                }
+            }
+            "prune" => self.pruning = PruningRules::parse(value)?,
+            "history" => {
+                self.match_history = match parse_option_flag(key, value)? {
+                    true => Some(MatchHistory::new()),
+                    false => None,
+                }
+            }
+            _ => return Err(format!("Unknown option {} for player tactician", key)),
+        }
+        Ok(())
+    }
+
+    // Reads options saved one per line as key=value, such as those written by
+    // the tune command, and applies them to the decider.
+    pub fn load_config(&mut self, path: &str) -> Result<(), String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Unable to read {}: {}", path, e)),
+        };
+        let lines = contents
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect::<Vec<_>>();
+        for (key, value) in parse_player_options(Some(&lines.join(",")))? {
+            self.set_option(key, value)?;
+        }
+        Ok(())
+    }
+}
+
+const TACTICIAN_OPTIONS: &[OptionSpec] = &[
+    OptionSpec { key: "iters", value: "N", help: "iterations per search", default: "10000" },
+    OptionSpec { key: "time", value: "duration", help: "time per search, e.g. 500ms", default: "" },
+    OptionSpec { key: "ponder", value: "duration", help: "thinking time while the opponents decide", default: "" },
+    OptionSpec { key: "report", value: "N or duration", help: "print the best move so far this often", default: "" },
+    OptionSpec { key: "action", value: "N or duration", help: "budget for playing actions", default: "" },
+    OptionSpec { key: "buy", value: "N or duration", help: "budget for buys", default: "" },
+    OptionSpec { key: "gain", value: "N or duration", help: "budget for gains", default: "" },
+    OptionSpec { key: "discard", value: "N or duration", help: "budget for discards", default: "" },
+    OptionSpec { key: "trash", value: "N or duration", help: "budget for trashing", default: "" },
+    OptionSpec { key: "reveal", value: "N or duration", help: "budget for revealing reactions", default: "" },
+    OptionSpec { key: "c", value: "number", help: "exploration constant", default: "1.414" },
+    OptionSpec { key: "select", value: "ucb1/tuned/thompson", help: "selection rule", default: "ucb1" },
+    OptionSpec { key: "rollouts", value: "N", help: "rollouts per leaf", default: "1" },
+    OptionSpec { key: "depth", value: "N", help: "rollout turn limit", default: "" },
+    OptionSpec { key: "fpu", value: "number", help: "first play urgency", default: "" },
+    OptionSpec { key: "rave", value: "number", help: "RAVE equivalence", default: "" },
+    OptionSpec { key: "margin", value: "number", help: "score margin reward weight", default: "" },
+    OptionSpec { key: "discount", value: "number", help: "per-turn reward discount", default: "" },
+    OptionSpec { key: "nodes", value: "N", help: "cap on the search tree's size", default: "" },
+    OptionSpec { key: "rollout", value: "policy", help: "random, bigmoney or heuristic rollouts", default: "random" },
+    OptionSpec { key: "epsilon", value: "number", help: "chance of a random rollout move", default: "" },
+    OptionSpec { key: "opponent", value: "policy", help: "opponents' rollout policy, as for rollout", default: "" },
+    OptionSpec { key: "eval", value: "file", help: "linear evaluator weights", default: "" },
+    OptionSpec { key: "prune", value: "curses/deadcards", help: "moves the search leaves out", default: "" },
+    OptionSpec { key: "dot", value: "directory", help: "export search trees as Graphviz", default: "" },
+    OptionSpec { key: "json", value: "directory", help: "export search trees as JSON", default: "" },
+    OptionSpec { key: "config", value: "file", help: "saved options", default: "" },
+    OptionSpec { key: "seed", value: "N", help: "random seed", default: "" },
+    OptionSpec { key: "solver", value: "on/off", help: "prove won and lost positions", default: "off" },
+    OptionSpec { key: "transpositions", value: "on/off", help: "share nodes between move orders", default: "off" },
+    OptionSpec { key: "reuse", value: "on/off", help: "keep the tree between decisions", default: "on" },
+    OptionSpec { key: "priors", value: "on/off", help: "start buys from money-first priors", default: "off" },
+    OptionSpec { key: "history", value: "on/off", help: "learn from earlier games of the match", default: "off" },
+    OptionSpec { key: "book", value: "on/off", help: "opening book", default: "on" },
+    OptionSpec { key: "chance", value: "on/off", help: "branch on draws", default: "off" },
+    OptionSpec { key: "endgame", value: "on/off", help: "depth-limited endgame search", default: "off" },
+    OptionSpec { key: "stats", value: "on/off", help: "print search statistics", default: "off" },
+];
+
+fn tactician(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    Ok(Box::new(SearchDecider::with_options(spec.options.clone(), spec.silent)?))
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "tactician",
+        summary: "Monte Carlo tree search, thinking about each decision",
+        example: "tactician:iters=5000,c=0.7",
+        argument: None,
+        options: TACTICIAN_OPTIONS,
+        factory: tactician,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use search_decider::*;
+
+    #[test]
+    fn test_duplicate_cards_give_one_move() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(1),
+            decision_type: DecisionType::DiscardCards(None),
+            choices: vec![
+                COPPER.identifier,
+                ESTATE.identifier,
+                COPPER.identifier,
+                COPPER.identifier,
+            ],
+            range: (2, 2),
+        });
+
+        let moves = g.all_moves();
+        assert_eq!(moves.len(), 2);
+        assert!(moves.contains(&vec![COPPER.identifier, COPPER.identifier]));
+    }
+
+    #[test]
+    fn test_buy_priors() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::BuyCard,
+            choices: vec![PROVINCE.identifier, GOLD.identifier, CURSE.identifier],
+            range: (0, 1),
+        });
+
+        let value = |c: &Card| BuyPriorPolicy.prior(&g, &vec![c.identifier]).unwrap().value;
+        assert!(value(&PROVINCE) > value(&GOLD));
+        assert!(value(&GOLD) > value(&CURSE));
+        assert!(BuyPriorPolicy.prior(&g, &vec![]).is_none());
+    }
+
+    #[test]
+    fn test_is_near_endgame() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        assert!(!is_near_endgame(&g));
+
+        g.piles.insert(VILLAGE.identifier, 0);
+        g.piles.insert(SMITHY.identifier, 1);
+        assert!(!is_near_endgame(&g));
+        g.piles.insert(MOAT.identifier, 2);
+        assert!(is_near_endgame(&g));
+
+        let mut g = fresh_game(&names);
+        g.piles.insert(PROVINCE.identifier, 2);
+        assert!(is_near_endgame(&g));
+    }
+
+    #[test]
+    fn test_pruning_rules() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        let rules = PruningRules::parse("curses/deadcards").unwrap();
+        assert!(PruningRules::parse("curses/nonsense").is_err());
+
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::BuyCard,
+            choices: vec![SILVER.identifier, CURSE.identifier],
+            range: (0, 1),
+        });
+        assert!(rules.is_pruned(&g, &vec![CURSE.identifier]));
+        assert!(!rules.is_pruned(&g, &vec![SILVER.identifier]));
+        assert!(!PruningRules::default().is_pruned(&g, &vec![CURSE.identifier]));
+
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::DiscardCards(Some(DiscardEffect::DrawPerDiscard)),
+            choices: vec![ESTATE.identifier, PROVINCE.identifier, COPPER.identifier],
+            range: (0, 3),
+        });
+        assert!(rules.is_pruned(&g, &vec![ESTATE.identifier]));
+        assert!(!rules.is_pruned(&g, &vec![ESTATE.identifier, PROVINCE.identifier]));
+        assert!(!rules.is_pruned(&g, &g.pending_decision.as_ref().unwrap().choices.clone()));
+    }
+
+    #[test]
+    fn test_heuristic_evaluator() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        let even = HeuristicEvaluator.evaluate(&g);
+        assert!((even.for_player(&PlayerIdentifier(0)) - 0.5).abs() < 0.001);
+
+        g.players[0].discard.push(PROVINCE.identifier);
+        let ahead = HeuristicEvaluator.evaluate(&g);
+        assert!(ahead.for_player(&PlayerIdentifier(0)) > 0.5);
+        assert!(
+            (ahead.for_player(&PlayerIdentifier(0)) + ahead.for_player(&PlayerIdentifier(1))
+                - 1.0)
+                .abs() < 0.001
+        );
+    }
+
+    #[test]
+    fn test_multiplayer_tree() {
+        let names = vec!["Player 1".into(), "Player 2".into(), "Player 3".into()];
+        let mut g = fresh_game(&names);
+        g.pending_decision = Some(Decision {
+            player: PlayerIdentifier(0),
+            decision_type: DecisionType::BuyCard,
+            choices: vec![SILVER.identifier, COPPER.identifier],
+            range: (0, 1),
+        });
+        assert_eq!(SearchTree::new(&g).root().player_just_moved, PlayerIdentifier(2));
+
+        g.pending_decision.as_mut().unwrap().player = PlayerIdentifier(1);
+        assert_eq!(SearchTree::new(&g).root().player_just_moved, PlayerIdentifier(0));
+
+        let even = HeuristicEvaluator.evaluate(&g);
+        for p in g.all_players() {
+            assert!((even.for_player(&p) - 1.0 / 3.0).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn test_chance_nodes() {
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut ctx = EvalContext {
+            rng: util::seeded_weak_rng(1),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        let mut g = fresh_game(&names);
+        g.initialize_game(&mut ctx);
+        while g.pending_decision.is_none() {
+            g.advance_game(&mut ctx);
+        }
+
+        let mut decider = SearchDecider::new(SearchOptions {
+            chance_nodes: true,
+            ..Default::default()
+        });
+        decider.seed(1);
+        let mut tree = SearchTree::new(&g);
+        let player = g.active_player().unwrap();
+        decider.grow_search_tree(&mut tree, &g, player, SearchBudget::Iterations(300));
+
+        // Moves alternate with the outcomes they lead to, below the root.
+        let is_move = |id: usize| id != ROOT && tree.nodes[id].chance_outcome.is_none();
+        assert!(tree.nodes.iter().any(|n| n.chance_outcome.is_some()));
+        for node in tree.nodes.iter().skip(1) {
+            let parent = node.parent.unwrap();
+            assert_eq!(node.chance_outcome.is_some(), is_move(parent));
+        }
+    }
+
+    #[test]
+    fn test_phase_budgets() {
+        let options = vec![("buy", "5000"), ("reveal", "50"), ("trash", "20ms")];
+        let budgets = SearchDecider::with_options(options, true).unwrap().phase_budgets;
+
+        let budget_iterations = |t: &DecisionType| match budgets.budget_for(t) {
+            Some(SearchBudget::Iterations(n)) => Some(n),
+            _ => None,
+        };
+        assert_eq!(budget_iterations(&DecisionType::BuyCard), Some(5000));
+        assert_eq!(
+            budget_iterations(&DecisionType::RevealReaction(ActionIdentifier(0))),
+            Some(50)
+        );
+        assert_eq!(budget_iterations(&DecisionType::DiscardCards(None)), None);
+        assert!(match budgets.budget_for(&DecisionType::TrashCards(None)) {
+            Some(SearchBudget::Time(t)) => t == Duration::from_millis(20),
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_empty_budgets() {
+        let empty = [("iters", "0"), ("time", "0ms"), ("iters", "-5"), ("buy", "0"), ("reveal", "0s")];
+        for &(key, value) in empty.iter() {
+            assert!(SearchDecider::with_options(vec![(key, value)], true).is_err());
+        }
+        for &(key, value) in [("c", "nan"), ("c", "inf"), ("c", "-1"), ("rollouts", "0")].iter() {
+            assert!(SearchDecider::with_options(vec![(key, value)], true).is_err());
+        }
+        let out_of_range = [
+            ("rave", "-1"),
+            ("rave", "0"),
+            ("discount", "nan"),
+            ("discount", "0"),
+            ("discount", "1.5"),
+            ("epsilon", "-0.1"),
+            ("margin", "inf"),
+            ("fpu", "nan"),
+            ("nodes", "0"),
+            ("depth", "0"),
+        ];
+        for &(key, value) in out_of_range.iter() {
+            let options = vec![(key, value)];
+            assert!(SearchDecider::with_options(options, true).is_err(), "{}={}", key, value);
+        }
+        let options = vec![("discount", "1"), ("margin", "0.5")];
+        assert!(SearchDecider::with_options(options, true).is_ok());
+        assert!(SearchDecider::with_options(vec![("iters", "1"), ("time", "1ms")], true).is_ok());
     }
 }
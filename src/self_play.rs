@@ -0,0 +1,288 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
+
+use cards;
+use cards::CardIdentifier;
+use game;
+use game_features::FEATURE_NAMES;
+use game_serialization::{cards_to_json, decision_to_json};
+use json::Json;
+use search_decider::{SearchDecider, SearchRecord};
+
+// Self-play always seats two copies of Tactician.
+const PLAYERS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DatasetFormat {
+    // A line of JSON per record.
+    Json,
+    // A row per record, with a column for each of every player's features,
+    // so datasets load straight into data frames.
+    Csv,
+}
+
+impl DatasetFormat {
+    pub fn parse(s: &str) -> Result<DatasetFormat, String> {
+        match s {
+            "json" => Ok(DatasetFormat::Json),
+            "csv" => Ok(DatasetFormat::Csv),
+            _ => Err(format!(
+                "Unknown dataset format {}, expected json or csv",
+                s
+            )),
+        }
+    }
+}
+
+// Converts a record to JSON, with the outcome of its game for the player
+// who made the decision: 1 for a win, 0 for a loss, and a share for ties.
+fn record_to_json(game_number: u32, record: &SearchRecord, outcome: f32) -> Json {
+    Json::object(vec![
+        ("game", (game_number as i32).into()),
+        ("turn", record.turn.into()),
+        ("decision", decision_to_json(&record.decision)),
+        (
+            "features",
+            Json::Array(
+                record
+                    .features
+                    .iter()
+                    .map(|fs| Json::Array(fs.iter().map(|&f| f.into()).collect()))
+                    .collect(),
+            ),
+        ),
+        ("move", cards_to_json(&record.choice)),
+        (
+            "visits",
+            Json::Array(
+                record
+                    .visits
+                    .iter()
+                    .map(|&(ref m, n)| {
+                        Json::object(vec![("move", cards_to_json(m)), ("visits", n.into())])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("outcome", outcome.into()),
+    ])
+}
+
+// A move as CSV columns name it: its cards joined by +, or none.
+fn move_name(m: &[CardIdentifier]) -> String {
+    match m.is_empty() {
+        true => "none".into(),
+        false => m
+            .iter()
+            .map(|c| cards::lookup_card(c).name)
+            .collect::<Vec<_>>()
+            .join("+"),
+    }
+}
+
+fn csv_header() -> String {
+    let mut columns = vec!["game", "turn", "player", "decision"]
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    for p in 0..PLAYERS {
+        columns.extend(FEATURE_NAMES.iter().map(|f| format!("p{}_{}", p, f)));
+    }
+    columns.extend(vec!["move".into(), "visits".into(), "outcome".into()]);
+    columns.join(",")
+}
+
+// Converts a record to a CSV row under csv_header. The visit distribution
+// is one column of move:visits pairs separated by semicolons, such as
+// "Gold:120;Silver:30;none:2". No card name needs quoting.
+fn record_to_csv(game_number: u32, record: &SearchRecord, outcome: f32) -> String {
+    let kind = format!("{:?}", record.decision.decision_type);
+    let mut row = vec![
+        game_number.to_string(),
+        record.turn.to_string(),
+        record.decision.player.0.to_string(),
+        kind.split('(').next().unwrap().to_string(),
+    ];
+    for fs in &record.features {
+        row.extend(fs.iter().map(|f| f.to_string()));
+    }
+    row.push(move_name(&record.choice));
+    row.push(
+        record
+            .visits
+            .iter()
+            .map(|&(ref m, n)| format!("{}:{}", move_name(m), n))
+            .collect::<Vec<_>>()
+            .join(";"),
+    );
+    row.push(outcome.to_string());
+    row.join(",")
+}
+
+// The file for the nth batch of records, numbered before the extension,
+// such as selfplay-00002.csv.
+fn batch_path(path: &str, n: usize) -> String {
+    match path.rfind('.') {
+        Some(i) if !path[i..].contains('/') => format!("{}-{:05}{}", &path[..i], n, &path[i..]),
+        _ => format!("{}-{:05}", path, n),
+    }
+}
+
+// Writes records to one file, or with a batch size to numbered files of
+// that many records each, so large datasets can be loaded a file at a time.
+struct DatasetWriter {
+    format: DatasetFormat,
+    path: String,
+    batch: Option<usize>,
+    files: Vec<String>,
+    in_file: usize,
+    out: BufWriter<File>,
+}
+
+impl DatasetWriter {
+    fn create(
+        path: &str,
+        format: DatasetFormat,
+        batch: Option<usize>,
+    ) -> Result<DatasetWriter, String> {
+        let file_path = match batch {
+            Some(_) => batch_path(path, 1),
+            None => path.to_string(),
+        };
+        let mut writer = DatasetWriter {
+            format: format,
+            path: path.to_string(),
+            batch: batch,
+            files: vec![],
+            in_file: 0,
+            out: DatasetWriter::open(&file_path, format)?,
+        };
+        writer.files.push(file_path);
+        Ok(writer)
+    }
+
+    fn open(path: &str, format: DatasetFormat) -> Result<BufWriter<File>, String> {
+        let file = File::create(path).map_err(|e| format!("Unable to create {}: {}", path, e))?;
+        let mut out = BufWriter::new(file);
+        if format == DatasetFormat::Csv {
+            writeln!(out, "{}", csv_header())
+                .map_err(|e| format!("Unable to write to {}: {}", path, e))?;
+        }
+        Ok(out)
+    }
+
+    fn write(
+        &mut self,
+        game_number: u32,
+        record: &SearchRecord,
+        outcome: f32,
+    ) -> Result<(), String> {
+        if self.batch == Some(self.in_file) {
+            self.finish()?;
+            let path = batch_path(&self.path, self.files.len() + 1);
+            self.out = DatasetWriter::open(&path, self.format)?;
+            self.files.push(path);
+            self.in_file = 0;
+        }
+        let line = match self.format {
+            DatasetFormat::Json => record_to_json(game_number, record, outcome).to_string(),
+            DatasetFormat::Csv => record_to_csv(game_number, record, outcome),
+        };
+        self.in_file += 1;
+        writeln!(self.out, "{}", line).map_err(|e| self.write_error(e))
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.out.flush().map_err(|e| self.write_error(e))
+    }
+
+    fn write_error(&self, e: io::Error) -> String {
+        format!("Unable to write to {}: {}", self.files.last().unwrap(), e)
+    }
+}
+
+// Plays games between deciders built by new_decider, writing every searched
+// decision to out_path, or to numbered files of batch records each. Returns
+// how many records were written, and the files written.
+pub fn run_self_play(
+    num_games: u32,
+    new_decider: &Fn() -> SearchDecider,
+    out_path: &str,
+    format: DatasetFormat,
+    batch: Option<usize>,
+    silent: bool,
+) -> Result<(usize, Vec<String>), String> {
+    let mut out = DatasetWriter::create(out_path, format, batch)?;
+    let mut written = 0;
+
+    for i in 0..num_games {
+        let records = Rc::new(RefCell::new(vec![]));
+        let mut players: Vec<Box<game::Decider>> = (0..PLAYERS)
+            .map(|_| {
+                let mut decider = new_decider();
+                decider.recorder = Some(records.clone());
+                Box::new(decider) as Box<game::Decider>
+            })
+            .collect();
+
+        let outcomes = game::run_game(&mut players, game::Verbosity::Quiet);
+        for record in records.borrow().iter() {
+            let outcome = outcomes[record.decision.player.0 as usize];
+            out.write(i + 1, record, outcome)?;
+            written += 1;
+        }
+
+        if !silent {
+            println!(
+                "Game {} of {}: {} decisions recorded",
+                i + 1,
+                num_games,
+                records.borrow().len()
+            );
+        }
+    }
+
+    out.finish()?;
+    Ok((written, out.files))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use self_play::*;
+    use std;
+
+    #[test]
+    fn test_batch_path() {
+        assert_eq!(batch_path("out/selfplay.csv", 2), "out/selfplay-00002.csv");
+        assert_eq!(batch_path("out.d/selfplay", 12), "out.d/selfplay-00012");
+    }
+
+    #[test]
+    fn test_csv_batches() {
+        let path = std::env::temp_dir().join("tactician-test-selfplay.csv");
+        let path = path.to_str().unwrap();
+        let new_decider = || SearchDecider::with_options(vec![("iters", "5")], true).unwrap();
+        let (written, files) =
+            run_self_play(1, &new_decider, path, DatasetFormat::Csv, Some(20), true).unwrap();
+        assert_eq!(files.len(), written.div_ceil(20));
+        assert_eq!(files[0], batch_path(path, 1));
+
+        let header = csv_header();
+        let columns = header.split(',').count();
+        let mut rows = 0;
+        for f in &files {
+            let contents = std::fs::read_to_string(f).unwrap();
+            let mut lines = contents.lines();
+            assert_eq!(lines.next(), Some(&header[..]));
+            for line in lines {
+                assert_eq!(line.split(',').count(), columns);
+                rows += 1;
+            }
+        }
+        assert_eq!(rows, written);
+    }
+}
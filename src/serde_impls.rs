@@ -0,0 +1,177 @@
+use serde::de;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::fmt;
+
+use cards::CardIdentifier;
+use game::{Decision, Game, GameEvent, Player, PlayerIdentifier};
+use game_serialization::{card_from_json, card_to_json, decision_from_json, decision_to_json,
+                         event_from_json, event_to_json, player_to_json, seated_player_from_json};
+use json::Json;
+
+// Serde support, with the serde feature. Every type is written the way save
+// files write it, through its JSON form, so there's one format to keep
+// stable whatever serde format carries it. Cards are written by name, not
+// by identifier, since identifiers depend on the order cards are created.
+
+impl Serialize for Json {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Json::Null => s.serialize_unit(),
+            Json::Bool(b) => s.serialize_bool(b),
+            // Whole numbers stay whole in formats that tell them apart.
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 => {
+                s.serialize_i64(n as i64)
+            }
+            Json::Number(n) => s.serialize_f64(n),
+            Json::String(ref text) => s.serialize_str(text),
+            Json::Array(ref values) => {
+                let mut seq = s.serialize_seq(Some(values.len()))?;
+                for v in values {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
+            Json::Object(ref fields) => {
+                let mut map = s.serialize_map(Some(fields.len()))?;
+                for &(ref key, ref value) in fields {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct JsonVisitor;
+
+impl<'de> Visitor<'de> for JsonVisitor {
+    type Value = Json;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Json, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Json, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Json, D::Error> {
+        Json::deserialize(d)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Json, E> {
+        Ok(Json::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Json, E> {
+        Ok(Json::Number(n as f64))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Json, E> {
+        Ok(Json::Number(n as f64))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Json, E> {
+        Ok(Json::Number(n))
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<Json, E> {
+        Ok(Json::String(text.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, text: String) -> Result<Json, E> {
+        Ok(Json::String(text))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+        let mut values = vec![];
+        while let Some(v) = seq.next_element()? {
+            values.push(v);
+        }
+        Ok(Json::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+        let mut fields = vec![];
+        while let Some(field) = map.next_entry::<String, Json>()? {
+            fields.push(field);
+        }
+        Ok(Json::Object(fields))
+    }
+}
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Json, D::Error> {
+        d.deserialize_any(JsonVisitor)
+    }
+}
+
+// Implements Serialize and Deserialize for a type with functions to and
+// from its JSON form.
+macro_rules! serde_via_json {
+    ($t:ty, $to_json:expr, $from_json:expr) => {
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                ($to_json)(self).serialize(s)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<$t, D::Error> {
+                let j = Json::deserialize(d)?;
+                ($from_json)(&j).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+serde_via_json!(Game, Game::to_json, Game::from_json);
+serde_via_json!(Player, player_to_json, seated_player_from_json);
+serde_via_json!(Decision, decision_to_json, decision_from_json);
+serde_via_json!(GameEvent, event_to_json, event_from_json);
+serde_via_json!(CardIdentifier, card_to_json, card_from_json);
+serde_via_json!(
+    PlayerIdentifier,
+    |p: &PlayerIdentifier| Json::from(p.0 as i32),
+    |j: &Json| match j.as_i64() {
+        Some(n) if n >= 0 && n < 256 => Ok(PlayerIdentifier(n as u8)),
+        _ => Err("Players should be given by seat number"),
+    }
+);
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use serde_json;
+
+    #[test]
+    fn test_serde() {
+        let mut g = fresh_game(&vec!["Alice".into(), "Bob".into()]);
+        g.players[1].hand = vec![GOLD.identifier, MOAT.identifier];
+        let text = serde_json::to_string(&g).unwrap();
+        let loaded: Game = serde_json::from_str(&text).unwrap();
+        assert_eq!(loaded.to_json(), g.to_json());
+
+        assert_eq!(serde_json::to_string(&MOAT.identifier).unwrap(), "\"Moat\"");
+        let card: CardIdentifier = serde_json::from_str("\"Smithy\"").unwrap();
+        assert_eq!(card, SMITHY.identifier);
+        assert!(serde_json::from_str::<CardIdentifier>("\"Bridge\"").is_err());
+
+        let e = GameEvent::Played(PlayerIdentifier(1), vec![COPPER.identifier, SILVER.identifier]);
+        let text = serde_json::to_string(&e).unwrap();
+        assert_eq!(text, r#"{"type":"Played","player":1,"cards":["Copper","Silver"]}"#);
+        assert_eq!(serde_json::from_str::<GameEvent>(&text).unwrap(), e);
+
+        let text = serde_json::to_string(&g.players[1]).unwrap();
+        let bob: Player = serde_json::from_str(&text).unwrap();
+        assert_eq!(bob.identifier, PlayerIdentifier(1));
+        assert_eq!(bob.hand, g.players[1].hand);
+    }
+}
@@ -0,0 +1,307 @@
+use itertools::Itertools;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use cards;
+use cards::CardIdentifier;
+use color;
+use game::{Decider, Decision, DecisionError, Game, GameEvent, MoveStats, PlayerIdentifier};
+use human::describe_decision;
+use locale;
+
+// How long each decision stays on screen by default, so bots can be
+// followed.
+pub const FRAME_DELAY: Duration = Duration::from_millis(400);
+const LOG_LINES: usize = 8;
+const SUPPLY_COLUMNS: usize = 5;
+
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+
+// How a Spectator shows a game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum View {
+    // A dashboard redrawn in place before every decision.
+    Dashboard,
+    // What happened since the last decision, printed as lines that scroll
+    // by, each batch followed by a summary of the board.
+    Scrolling,
+}
+
+// Shows a game as it's played, pausing after each decision so people can
+// follow it. The dashboard shows the supply, each player's card counts, the
+// play area, the decision being made and the latest public events. It
+// watches over one player's shoulder, since every player is shown each
+// decision and event, and passes everything on to that player.
+pub struct Spectator {
+    decider: Box<Decider>,
+    view: View,
+    delay: Duration,
+    // The latest public events, oldest first.
+    log: VecDeque<GameEvent>,
+    // Events not yet printed, when scrolling.
+    unshown: Vec<GameEvent>,
+}
+
+impl Spectator {
+    pub fn new(decider: Box<Decider>, view: View, delay: Duration) -> Spectator {
+        Spectator {
+            decider: decider,
+            view: view,
+            delay: delay,
+            log: VecDeque::new(),
+            unshown: vec![],
+        }
+    }
+
+    // Prints the events since the last batch and a summary of the board,
+    // if anything happened.
+    fn scroll(&mut self, g: &Game) -> bool {
+        let lines = self.unshown
+            .drain(..)
+            .filter_map(|e| event_line(g, &e))
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return false;
+        }
+        println!("{}", lines.join("\n"));
+        println!("{}", summary_line(g));
+        true
+    }
+
+    fn show(&self, g: &Game, d: Option<&Decision>) {
+        if self.view != View::Dashboard {
+            return;
+        }
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let _ = write!(out, "{}{}{}", HIDE_CURSOR, CLEAR_SCREEN, dashboard(g, d, &self.log));
+        let _ = out.flush();
+    }
+}
+
+fn event_line(g: &Game, e: &GameEvent) -> Option<String> {
+    let name = |p: &PlayerIdentifier| g.players[p.0 as usize].colored_name();
+    let (player, key, cards) = match *e {
+        GameEvent::TurnStarted(ref p, turn) => {
+            let turn = turn.to_string();
+            return Some(locale::text("turn_started", &[("turn", &turn), ("player", &name(p))]));
+        }
+        GameEvent::Played(ref p, ref cs) => (p, "played", cs.clone()),
+        GameEvent::Bought(ref p, c) => (p, "bought", vec![c]),
+        GameEvent::Gained(ref p, c) => (p, "gained", vec![c]),
+        GameEvent::Discarded(ref p, ref cs) => (p, "discarded", cs.clone()),
+        GameEvent::Trashed(ref p, ref cs) => (p, "trashed", cs.clone()),
+        GameEvent::Revealed(ref p, c) => (p, "revealed", vec![c]),
+        _ => return None,
+    };
+    match cards.is_empty() {
+        true => None,
+        false => {
+            let cards = color::card_names(&cards);
+            Some(locale::text(key, &[("player", &name(player)), ("cards", &cards)]))
+        }
+    }
+}
+
+fn supply_lines(g: &Game) -> Vec<String> {
+    let piles = g.piles
+        .iter()
+        .sorted_by_key(|&(c, _)| (cards::lookup_card(c).cost, *c))
+        .into_iter()
+        .map(|(c, n)| {
+            let name = format!("{:<10}", locale::card_name(c));
+            format!("{} ${} {:>2}", color::paint_as(c, &name), cards::lookup_card(c).cost, n)
+        })
+        .collect::<Vec<_>>();
+    piles
+        .chunks(SUPPLY_COLUMNS)
+        .map(|row| format!("  {}", row.join("   ")))
+        .collect()
+}
+
+fn player_lines(g: &Game) -> Vec<String> {
+    let width = g.players
+        .iter()
+        .map(|p| p.name.chars().count())
+        .chain(Some("Player".len()))
+        .max()
+        .unwrap();
+    let mut lines = vec![format!(
+        "    {:<w$} {:>3} {:>5} {:>5} {:>8} {:>6}",
+        "Player",
+        "VP",
+        "Deck",
+        "Hand",
+        "Discard",
+        "Cards",
+        w = width
+    )];
+    for (p, (vp, _)) in g.players.iter().zip(g.player_vp_and_turns()) {
+        let mut total = p.all_cards().len();
+        if p.identifier == g.active_player {
+            total += g.play_area.len();
+        }
+        let marker = if p.identifier == g.active_player { ">" } else { " " };
+        let name = color::player(p.identifier.0 as usize, &format!("{:<w$}", p.name, w = width));
+        lines.push(format!(
+            "  {} {} {:>3} {:>5} {:>5} {:>8} {:>6}",
+            marker,
+            name,
+            vp,
+            p.deck.len(),
+            p.hand.len(),
+            p.discard.len(),
+            total
+        ));
+    }
+    lines
+}
+
+// Each player's victory points and the Provinces left, e.g. "  Alice 5 VP,
+// Bob 3 VP | 7 Provinces left".
+fn summary_line(g: &Game) -> String {
+    let players = g.players
+        .iter()
+        .zip(g.player_vp_and_turns())
+        .map(|(p, (vp, _))| format!("{} {} VP", p.colored_name(), vp))
+        .join(", ");
+    let provinces = g.piles.get(&cards::PROVINCE.identifier).cloned().unwrap_or(0);
+    format!("  {} | {} Provinces left", players, provinces)
+}
+
+// The whole dashboard, as text to print on a cleared screen.
+pub fn dashboard(g: &Game, d: Option<&Decision>, log: &VecDeque<GameEvent>) -> String {
+    let names = g.players.iter().map(|p| p.colored_name()).join(" vs ");
+    let status = match g.is_game_over() {
+        true => "game over".to_string(),
+        false => {
+            let active = &g.players[g.active_player.0 as usize];
+            format!("turn {}, {} to play", g.turn, active.colored_name())
+        }
+    };
+    let mut lines = vec![
+        format!("{}: {}", names, status),
+        String::new(),
+        "Supply".to_string(),
+    ];
+    lines.extend(supply_lines(g));
+    lines.push(String::new());
+    lines.extend(player_lines(g));
+    lines.push(String::new());
+    let in_play = match g.play_area.is_empty() {
+        true => "nothing".to_string(),
+        false => color::card_names(&g.play_area),
+    };
+    lines.push(format!("In play: {}", in_play));
+    lines.push(format!("{} actions, {} buys, ${}", g.actions, g.buys, g.coins));
+    if let Some(d) = d {
+        let decider = g.players[d.player.0 as usize].colored_name();
+        lines.push(format!("{} to decide: {}", decider, describe_decision(g, d)));
+        let mut choices = d.choices.clone();
+        choices.sort();
+        choices.dedup();
+        lines.push(format!("  from {}", color::card_names(&choices)));
+    }
+    lines.push(String::new());
+    lines.extend(log.iter().filter_map(|e| event_line(g, e)));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+impl Decider for Spectator {
+    fn description(&self) -> String {
+        self.decider.description()
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        self.show(g, g.pending_decision.as_ref());
+        self.decider.make_decision(g)
+    }
+
+    fn try_make_decision(&mut self, g: &Game) -> Result<Vec<CardIdentifier>, DecisionError> {
+        self.show(g, g.pending_decision.as_ref());
+        self.decider.try_make_decision(g)
+    }
+
+    fn observe_decision(&mut self, g: &Game, choice: &Vec<CardIdentifier>) {
+        let shown = match self.view {
+            View::Dashboard => true,
+            View::Scrolling => self.scroll(g),
+        };
+        if shown {
+            thread::sleep(self.delay);
+        }
+        self.decider.observe_decision(g, choice);
+    }
+
+    fn ponder(&mut self, g: &Game) {
+        self.show(g, g.pending_decision.as_ref());
+        self.decider.ponder(g);
+    }
+
+    fn confidence(&self) -> Option<f32> {
+        self.decider.confidence()
+    }
+
+    fn analysis(&self) -> Vec<MoveStats> {
+        self.decider.analysis()
+    }
+
+    fn clock(&mut self, remaining: Duration, increment: Duration) {
+        self.decider.clock(remaining, increment);
+    }
+
+    fn on_event(&mut self, event: &GameEvent) {
+        // Cleanup and drawing aren't worth a line.
+        match *event {
+            GameEvent::Shuffled(_) | GameEvent::Drew(..) | GameEvent::CleanedUp(_) => {}
+            _ => {
+                self.log.push_back(event.clone());
+                while self.log.len() > LOG_LINES {
+                    self.log.pop_front();
+                }
+                if self.view == View::Scrolling {
+                    self.unshown.push(event.clone());
+                }
+            }
+        }
+        self.decider.on_event(event);
+    }
+
+    fn game_over(&mut self, g: &Game) {
+        match self.view {
+            View::Dashboard => {
+                self.show(g, None);
+                print!("{}", SHOW_CURSOR);
+            }
+            View::Scrolling => {
+                self.scroll(g);
+            }
+        }
+        self.decider.game_over(g);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use game::*;
+    use spectator::*;
+
+    #[test]
+    fn test_dashboard() {
+        let names = vec!["Alice".into(), "Bob".into()];
+        let g = fresh_game(&names);
+        let text = dashboard(&g, None, &VecDeque::new());
+        assert!(text.starts_with("Alice vs Bob: turn 1, Alice to play"));
+        assert!(text.contains("Province   $8  8"));
+        assert!(text.contains("    Player  VP  Deck  Hand  Discard  Cards"));
+        assert!(text.contains("  > Alice    3     0     0       10     10"));
+        assert!(text.contains("    Bob      3     0     0       10     10"));
+        assert_eq!(summary_line(&g), "  Alice 3 VP, Bob 3 VP | 8 Provinces left");
+    }
+}
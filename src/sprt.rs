@@ -0,0 +1,152 @@
+// A sequential probability ratio test for deciding whether one player is
+// stronger than another, so matches can stop as soon as the result is
+// clear. H0 is that the first player's Elo advantage is elo0, H1 that it's
+// elo1; the log-likelihood ratio between them uses the normal approximation
+// of the generalized SPRT, which handles ties.
+#[derive(Clone)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    // The chances of accepting H1 when H0 holds, and H0 when H1 holds.
+    pub alpha: f64,
+    pub beta: f64,
+    wins: u32,
+    ties: u32,
+    losses: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SprtResult {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+// How many games of each outcome are assumed on top of those played.
+const PSEUDO_GAMES: f64 = 0.5;
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt {
+            elo0: elo0,
+            elo1: elo1,
+            alpha: 0.05,
+            beta: 0.05,
+            wins: 0,
+            ties: 0,
+            losses: 0,
+        }
+    }
+
+    // Parses an SPRT's Elo bounds, given as "elo0,elo1".
+    pub fn parse(s: &str) -> Result<Sprt, String> {
+        let bounds = s.split(',')
+            .map(|b| b.trim().parse::<f64>())
+            .collect::<Vec<_>>();
+        match bounds.as_slice() {
+            [Ok(elo0), Ok(elo1)] if elo0 < elo1 => Ok(Sprt::new(*elo0, *elo1)),
+            _ => Err(format!(
+                "Invalid SPRT bounds {}, expected elo0,elo1 with elo0 < elo1",
+                s
+            )),
+        }
+    }
+
+    // Records a game, given the first player's share of it.
+    pub fn record(&mut self, score: f32) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.ties += 1;
+        }
+    }
+
+    pub fn llr(&self) -> f64 {
+        let n = (self.wins + self.ties + self.losses) as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        // PSEUDO_GAMES of each outcome are added, so a one-sided result such as
+        // 300 wins to none still has a variance and can end the test.
+        let pseudo = 3.0 * PSEUDO_GAMES;
+        let (w, t, l) = (
+            (self.wins as f64 + PSEUDO_GAMES) / (n + pseudo),
+            (self.ties as f64 + PSEUDO_GAMES) / (n + pseudo),
+            (self.losses as f64 + PSEUDO_GAMES) / (n + pseudo),
+        );
+        let score = w + t / 2.0;
+        let variance =
+            w * (1.0 - score).powi(2) + t * (0.5 - score).powi(2) + l * score.powi(2);
+
+        let s0 = expected_score(self.elo0);
+        let s1 = expected_score(self.elo1);
+        n * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    pub fn result(&self) -> SprtResult {
+        let llr = self.llr();
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            SprtResult::AcceptH0
+        } else if llr >= upper {
+            SprtResult::AcceptH1
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use sprt::*;
+
+    #[test]
+    fn test_sprt() {
+        let mut sprt = Sprt::new(0.0, 100.0);
+        for score in [1.0, 0.0, 1.0].iter() {
+            sprt.record(*score);
+        }
+        assert_eq!(sprt.result(), SprtResult::Continue);
+
+        // A player winning three games in four is far stronger than 100 Elo.
+        for i in 0..200 {
+            sprt.record(if i % 4 == 0 { 0.0 } else { 1.0 });
+            if sprt.result() != SprtResult::Continue {
+                break;
+            }
+        }
+        assert_eq!(sprt.result(), SprtResult::AcceptH1);
+
+        let mut even = Sprt::new(0.0, 100.0);
+        for i in 0..1000 {
+            even.record(if i % 2 == 0 { 0.0 } else { 1.0 });
+        }
+        assert_eq!(even.result(), SprtResult::AcceptH0);
+    }
+
+    #[test]
+    fn test_one_sided_sprt() {
+        let mut sweep = Sprt::new(0.0, 5.0);
+        let mut swept = Sprt::new(0.0, 5.0);
+        for _ in 0..300 {
+            sweep.record(1.0);
+            swept.record(0.0);
+        }
+        assert_eq!(sweep.result(), SprtResult::AcceptH1);
+        assert_eq!(swept.result(), SprtResult::AcceptH0);
+    }
+}
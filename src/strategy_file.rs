@@ -0,0 +1,379 @@
+use std::fmt;
+use std::fs;
+
+use cards;
+use cards::CardIdentifier;
+use deciders::{plus_actions, BigMoney};
+use game::{Decider, DecisionType, Game, PlayerIdentifier};
+use registry::{OptionSpec, PlayerKind, PlayerSpec, Registry};
+
+// Something a rule's condition can count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quantity {
+    // Copies of a card in the player's deck, including cards in play.
+    Owned(CardIdentifier),
+    // Cards left in a supply pile.
+    Supply(CardIdentifier),
+    Coins,
+    Turn,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    AtMost,
+    Equal,
+    NotEqual,
+    AtLeast,
+    Greater,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Condition {
+    pub quantity: Quantity,
+    pub comparison: Comparison,
+    pub value: i32,
+}
+
+// A card to buy, discard or trash while every condition holds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub card: CardIdentifier,
+    pub conditions: Vec<Condition>,
+}
+
+// A strategy read from a file of rules, one per line, tried in order:
+//
+//     # Smithy Big Money
+//     buy Province if owned Gold >= 1
+//     buy Duchy if supply Province <= 4
+//     buy Gold
+//     buy Smithy if owned Smithy < 1
+//     buy Silver
+//     discard Estate
+//     trash Curse
+//
+// Conditions compare "owned <card>", "supply <card>", "coins" or "turn"
+// with a number, and several can be joined with "and". Cards offered to
+// gain are picked like buys. Discards and trashes forced past the rules
+// take the cheapest cards, and everything else is played like Big Money,
+// except that actions are played, villages first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StrategyFile {
+    pub buys: Vec<Rule>,
+    pub discards: Vec<Rule>,
+    pub trashes: Vec<Rule>,
+}
+
+fn parse_card(name: &str) -> Result<CardIdentifier, String> {
+    cards::card_named(name)
+        .map(|c| c.identifier)
+        .ok_or(format!("Unknown card {}", name))
+}
+
+fn parse_comparison(s: &str) -> Result<Comparison, String> {
+    match s {
+        "<" => Ok(Comparison::Less),
+        "<=" | "≤" => Ok(Comparison::AtMost),
+        "=" | "==" => Ok(Comparison::Equal),
+        "!=" => Ok(Comparison::NotEqual),
+        ">=" | "≥" => Ok(Comparison::AtLeast),
+        ">" => Ok(Comparison::Greater),
+        _ => Err(format!("Unknown comparison {}", s)),
+    }
+}
+
+fn parse_condition(words: &[&str]) -> Result<Condition, String> {
+    let (quantity, rest) = match words.first().map(|w| w.to_lowercase()) {
+        Some(ref w) if w == "owned" && words.len() > 1 => {
+            (Quantity::Owned(parse_card(words[1])?), &words[2..])
+        }
+        Some(ref w) if w == "supply" && words.len() > 1 => {
+            (Quantity::Supply(parse_card(words[1])?), &words[2..])
+        }
+        Some(ref w) if w == "coins" => (Quantity::Coins, &words[1..]),
+        Some(ref w) if w == "turn" => (Quantity::Turn, &words[1..]),
+        _ => return Err(format!("Invalid condition {}", words.join(" "))),
+    };
+    if rest.len() != 2 {
+        return Err(format!(
+            "Expected a comparison and a number in {}",
+            words.join(" ")
+        ));
+    }
+    Ok(Condition {
+        quantity: quantity,
+        comparison: parse_comparison(rest[0])?,
+        value: rest[1]
+            .parse::<i32>()
+            .map_err(|_| format!("Invalid number {}", rest[1]))?,
+    })
+}
+
+fn parse_rule(words: &[&str]) -> Result<Rule, String> {
+    let card = match words.first() {
+        Some(name) => parse_card(name)?,
+        None => return Err("Expected a card".into()),
+    };
+    let mut conditions = vec![];
+    match words.get(1) {
+        Some(w) if w.eq_ignore_ascii_case("if") => {
+            for part in words[2..].split(|w| w.eq_ignore_ascii_case("and")) {
+                conditions.push(parse_condition(part)?);
+            }
+        }
+        Some(w) => return Err(format!("Expected if, found {}", w)),
+        None => {}
+    }
+    Ok(Rule {
+        card: card,
+        conditions: conditions,
+    })
+}
+
+fn owned_count(g: &Game, player: PlayerIdentifier, card: CardIdentifier) -> usize {
+    let mut owned = g.players[player.0 as usize].all_cards();
+    if player == g.active_player {
+        owned.extend(&g.play_area);
+    }
+    owned.iter().filter(|&&c| c == card).count()
+}
+
+impl Condition {
+    fn holds(&self, g: &Game, player: PlayerIdentifier) -> bool {
+        let n = match self.quantity {
+            Quantity::Owned(c) => owned_count(g, player, c) as i32,
+            Quantity::Supply(c) => g.piles.get(&c).cloned().unwrap_or(0),
+            Quantity::Coins => g.coins,
+            Quantity::Turn => g.turn,
+        };
+        match self.comparison {
+            Comparison::Less => n < self.value,
+            Comparison::AtMost => n <= self.value,
+            Comparison::Equal => n == self.value,
+            Comparison::NotEqual => n != self.value,
+            Comparison::AtLeast => n >= self.value,
+            Comparison::Greater => n > self.value,
+        }
+    }
+}
+
+impl Rule {
+    fn applies(&self, g: &Game, player: PlayerIdentifier) -> bool {
+        self.conditions.iter().all(|c| c.holds(g, player))
+    }
+}
+
+impl StrategyFile {
+    pub fn parse(contents: &str) -> Result<StrategyFile, String> {
+        let mut strategy = StrategyFile::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let rules = match words[0].to_lowercase().as_str() {
+                "buy" => &mut strategy.buys,
+                "discard" => &mut strategy.discards,
+                "trash" => &mut strategy.trashes,
+                w => return Err(format!("Line {}: unknown rule {}", i + 1, w)),
+            };
+            rules.push(parse_rule(&words[1..]).map_err(|e| format!("Line {}: {}", i + 1, e))?);
+        }
+        Ok(strategy)
+    }
+
+    pub fn load(path: &str) -> Result<StrategyFile, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        StrategyFile::parse(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn card_to_buy(&self, g: &Game, player: PlayerIdentifier, choices: &Vec<CardIdentifier>) -> Option<CardIdentifier> {
+        self.buys
+            .iter()
+            .find(|r| choices.contains(&r.card) && r.applies(g, player))
+            .map(|r| r.card)
+    }
+
+    // Picks cards in the order of the rules that apply, then the cheapest
+    // others if more are required.
+    fn cards_to_remove(
+        rules: &Vec<Rule>,
+        g: &Game,
+        player: PlayerIdentifier,
+        choices: &Vec<CardIdentifier>,
+        range: (usize, usize),
+    ) -> Vec<CardIdentifier> {
+        let mut remaining = choices.clone();
+        let mut removed = vec![];
+        for r in rules.iter().filter(|r| r.applies(g, player)) {
+            while removed.len() < range.1 {
+                match remaining.iter().position(|&c| c == r.card) {
+                    Some(i) => removed.push(remaining.remove(i)),
+                    None => break,
+                }
+            }
+        }
+
+        remaining.sort_by_key(|c| cards::lookup_card(c).cost);
+        let missing = range.0.saturating_sub(removed.len());
+        removed.extend(remaining.into_iter().take(missing));
+        removed
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.quantity {
+            Quantity::Owned(c) => write!(f, "owned {}", c)?,
+            Quantity::Supply(c) => write!(f, "supply {}", c)?,
+            Quantity::Coins => write!(f, "coins")?,
+            Quantity::Turn => write!(f, "turn")?,
+        }
+        let comparison = match self.comparison {
+            Comparison::Less => "<",
+            Comparison::AtMost => "<=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::AtLeast => ">=",
+            Comparison::Greater => ">",
+        };
+        write!(f, " {} {}", comparison, self.value)
+    }
+}
+
+impl fmt::Display for StrategyFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sections = [
+            ("buy", &self.buys),
+            ("discard", &self.discards),
+            ("trash", &self.trashes),
+        ];
+        for &(kind, rules) in sections.iter() {
+            for r in rules.iter() {
+                write!(f, "{} {}", kind, r.card)?;
+                for (i, c) in r.conditions.iter().enumerate() {
+                    write!(f, " {} {}", if i == 0 { "if" } else { "and" }, c)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct StrategyFileDecider {
+    pub strategy: StrategyFile,
+}
+
+impl Decider for StrategyFileDecider {
+    fn description(&self) -> String {
+        return "Strategy".into();
+    }
+
+    fn make_decision(&mut self, g: &Game) -> Vec<CardIdentifier> {
+        let d = g.pending_decision
+            .as_ref()
+            .expect("StrategyFileDecider::make_decision called without pending decision");
+        let s = &self.strategy;
+        match d.decision_type {
+            DecisionType::PlayAction => {
+                let mut actions = d.choices.clone();
+                actions.sort_by_key(|c| -plus_actions(c));
+                actions.into_iter().take(1).collect()
+            }
+            DecisionType::BuyCard => s.card_to_buy(g, d.player, &d.choices).into_iter().collect(),
+            DecisionType::GainCard(_) => match s.card_to_buy(g, d.player, &d.choices) {
+                Some(c) => vec![c],
+                None => BigMoney.make_decision(g),
+            },
+            DecisionType::DiscardCards(_) => {
+                StrategyFile::cards_to_remove(&s.discards, g, d.player, &d.choices, d.range)
+            }
+            DecisionType::TrashCards(_) => {
+                StrategyFile::cards_to_remove(&s.trashes, g, d.player, &d.choices, d.range)
+            }
+            _ => BigMoney.make_decision(g),
+        }
+    }
+}
+
+fn strategy(spec: &PlayerSpec, _registry: &Registry) -> Result<Box<Decider>, String> {
+    match spec.options.first().map(|&(_, path)| StrategyFile::load(path)) {
+        Some(Ok(s)) => Ok(Box::new(StrategyFileDecider { strategy: s })),
+        Some(Err(e)) => Err(e),
+        None => Err("Player strategy needs a file, e.g. strategy:file=smithy.txt".into()),
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.register(PlayerKind {
+        name: "strategy",
+        summary: "follows a strategy file of conditional rules",
+        example: "strategy:file=strategies/smithy.txt",
+        argument: None,
+        options: &[OptionSpec { key: "file", value: "file", help: "the strategy", default: "" }],
+        factory: strategy,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards::*;
+    use game::*;
+    use strategy_file::*;
+
+    const SMITHY_BM: &str = "
+        # Smithy Big Money
+        buy Province if owned Gold >= 1
+        buy Duchy if supply Province <= 4
+        buy Gold
+        buy Smithy if owned Smithy < 1 and turn <= 10
+        buy Silver
+        discard Estate
+        trash Curse
+    ";
+
+    #[test]
+    fn test_strategy_file() {
+        let strategy = StrategyFile::parse(SMITHY_BM).unwrap();
+        assert_eq!(strategy.buys.len(), 5);
+        assert_eq!(strategy.buys[3].conditions.len(), 2);
+        assert_eq!(StrategyFile::parse(&strategy.to_string()), Ok(strategy.clone()));
+
+        let names = vec!["Player 1".into(), "Player 2".into()];
+        let mut g = fresh_game(&names);
+        let me = PlayerIdentifier(0);
+        let offered = vec![
+            PROVINCE.identifier,
+            DUCHY.identifier,
+            SMITHY.identifier,
+            SILVER.identifier,
+        ];
+        assert_eq!(strategy.card_to_buy(&g, me, &offered), Some(SMITHY.identifier));
+
+        g.players[0].discard.push(GOLD.identifier);
+        assert_eq!(strategy.card_to_buy(&g, me, &offered), Some(PROVINCE.identifier));
+
+        g.players[0].discard.clear();
+        g.players[0].discard.push(SMITHY.identifier);
+        *g.piles.get_mut(&PROVINCE.identifier).unwrap() = 4;
+        assert_eq!(strategy.card_to_buy(&g, me, &offered), Some(DUCHY.identifier));
+
+        let hand = vec![COPPER.identifier, SILVER.identifier, ESTATE.identifier];
+        assert_eq!(
+            StrategyFile::cards_to_remove(&strategy.discards, &g, me, &hand, (2, 2)),
+            vec![ESTATE.identifier, COPPER.identifier]
+        );
+        assert!(StrategyFile::cards_to_remove(&strategy.trashes, &g, me, &hand, (0, 1)).is_empty());
+
+        assert!(StrategyFile::parse("buy Platinum").is_err());
+        assert!(StrategyFile::parse("buy Gold when coins >= 6").is_err());
+        assert!(StrategyFile::parse("buy Gold if coins").is_err());
+        assert!(StrategyFile::parse("gain Gold").is_err());
+    }
+}
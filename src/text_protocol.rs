@@ -0,0 +1,290 @@
+use std::io;
+use std::io::{BufRead, Write};
+
+use cards;
+use cards::CardIdentifier;
+use game::{fresh_game_with_kingdom, Decider, EvalContext, Game};
+use process_decider::check_choice;
+use search_decider::SearchDecider;
+
+// The commands the protocol understands, as list_commands answers.
+const COMMANDS: &[&str] = &[
+    "name",
+    "version",
+    "protocol_version",
+    "list_commands",
+    "new",
+    "state",
+    "genmove",
+    "play",
+    "undo",
+    "scores",
+    "quit",
+];
+
+// Tactician as an engine for match managers, the way Go engines speak GTP.
+// Each line of input is a command, optionally after a number to identify
+// it, and each is answered with "=" and its result, or "?" and an error,
+// followed by the number if it had one and then a blank line:
+//
+//     3 play Copper Copper Silver
+//     =3
+//
+//     genmove
+//     = Silver
+//
+// state answers with the game as saved by to_json, on one line. genmove
+// makes the pending decision with Tactician's search, and play with the
+// cards given, separated by spaces or commas, for whichever player is to
+// decide. Both name the cards chosen in English, or none to choose
+// nothing. undo takes back the last decision made, new deals a new game
+// and scores lists each player's outcome, from 0 to 1, once the game is
+// over.
+pub struct Engine {
+    game: Game,
+    // The positions before each decision made, so they can be taken back.
+    history: Vec<Game>,
+    names: Vec<String>,
+    kingdom: Vec<CardIdentifier>,
+    decider: SearchDecider,
+    ctx: EvalContext,
+}
+
+impl Engine {
+    pub fn new(
+        names: Vec<String>,
+        kingdom: Vec<CardIdentifier>,
+        decider: SearchDecider,
+        ctx: EvalContext,
+    ) -> Engine {
+        let game = fresh_game_with_kingdom(&names, &kingdom);
+        let mut engine = Engine {
+            game: game,
+            history: vec![],
+            names: names,
+            kingdom: kingdom,
+            decider: decider,
+            ctx: ctx,
+        };
+        engine.start();
+        engine
+    }
+
+    // Deals a new game, played on to its first decision.
+    fn start(&mut self) {
+        self.game = fresh_game_with_kingdom(&self.names, &self.kingdom);
+        self.game.initialize_game(&mut self.ctx);
+        self.history.clear();
+        self.advance();
+    }
+
+    fn advance(&mut self) {
+        while !self.game.is_game_over() && self.game.pending_decision.is_none() {
+            self.game.advance_game(&mut self.ctx);
+        }
+    }
+
+    fn play(&mut self, choice: Vec<CardIdentifier>) {
+        self.history.push(self.game.clone());
+        self.game.resolve_decision(choice, &mut self.ctx);
+        self.advance();
+    }
+
+    // Answers a command and its arguments, or says what's wrong with them.
+    fn run(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        match (command, args) {
+            ("name", []) => Ok("Tactician".into()),
+            ("version", []) => Ok(env!("CARGO_PKG_VERSION").into()),
+            ("protocol_version", []) => Ok("1".into()),
+            ("list_commands", []) => Ok(COMMANDS.join("\n")),
+            ("new", []) => {
+                self.start();
+                Ok("".into())
+            }
+            ("state", []) => Ok(self.game.to_json().to_string()),
+            ("genmove", []) => {
+                if self.game.pending_decision.is_none() {
+                    return Err("The game is over".into());
+                }
+                let choice = self.decider.make_decision(&self.game);
+                let answer = choice_names(&choice);
+                self.play(choice);
+                Ok(answer)
+            }
+            ("play", args) => {
+                let choice = {
+                    let d = self
+                        .game
+                        .pending_decision
+                        .as_ref()
+                        .ok_or("The game is over")?;
+                    let choice = parse_cards(args)?;
+                    check_choice(&choice, d)?;
+                    choice
+                };
+                self.play(choice);
+                Ok("".into())
+            }
+            ("undo", []) => match self.history.pop() {
+                Some(g) => {
+                    self.game = g;
+                    Ok("".into())
+                }
+                None => Err("There's nothing to undo".into()),
+            },
+            ("quit", []) => Ok("".into()),
+            ("scores", []) => match self.game.is_game_over() {
+                true => {
+                    let scores = self.game.player_scores();
+                    Ok(scores
+                        .iter()
+                        .map(|&(_, s)| s.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "))
+                }
+                false => Err("The game isn't over".into()),
+            },
+            _ if COMMANDS.contains(&command) => Err(format!("Wrong arguments for {}", command)),
+            _ => Err(format!("Unknown command {}", command)),
+        }
+    }
+}
+
+// The cards of a choice, as the protocol names them.
+fn choice_names(choice: &[CardIdentifier]) -> String {
+    match choice.is_empty() {
+        true => "none".into(),
+        false => choice
+            .iter()
+            .map(|c| cards::lookup_card(c).name)
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+// Parses the English names of the cards chosen, separated by spaces or
+// commas, or none.
+fn parse_cards(args: &[&str]) -> Result<Vec<CardIdentifier>, String> {
+    if let [none] = args {
+        if none.eq_ignore_ascii_case("none") {
+            return Ok(vec![]);
+        }
+    }
+    args.iter()
+        .flat_map(|a| a.split(','))
+        .filter(|a| !a.is_empty())
+        .map(|a| match cards::card_named(a) {
+            Some(c) => Ok(c.identifier),
+            None => Err(format!("Unknown card {}", a)),
+        })
+        .collect()
+}
+
+// Answers commands from input until it ends or says quit.
+pub fn serve<R: BufRead, W: Write>(
+    engine: &mut Engine,
+    input: &mut R,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        // Comments start with #, as in GTP.
+        let words = line
+            .split('#')
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        let (id, words) = match words.split_first() {
+            Some((first, rest)) if first.parse::<u32>().is_ok() => (*first, rest),
+            _ => ("", &words[..]),
+        };
+        let (command, args) = match words.split_first() {
+            Some((&command, args)) => (command, args),
+            None => continue,
+        };
+        let (status, answer) = match engine.run(command, args) {
+            Ok(answer) => ('=', answer),
+            Err(e) => ('?', e),
+        };
+        match answer.is_empty() {
+            true => write!(out, "{}{}\n\n", status, id)?,
+            false => write!(out, "{}{} {}\n\n", status, id, answer)?,
+        }
+        out.flush()?;
+        if command == "quit" && args.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards;
+    use game::*;
+    use search_decider::SearchDecider;
+    use text_protocol::*;
+    use tree_search::{SearchBudget, SearchOptions};
+    use util;
+
+    fn engine(iterations: i32) -> Engine {
+        let decider = SearchDecider::new(SearchOptions {
+            budget: SearchBudget::Iterations(iterations),
+            ..Default::default()
+        });
+        let ctx = EvalContext {
+            rng: util::seeded_weak_rng(1),
+            verbosity: Verbosity::Quiet,
+            events: None,
+        };
+        let names = vec!["Player 1".to_string(), "Player 2".to_string()];
+        Engine::new(names, cards::first_game_kingdom(), decider, ctx)
+    }
+
+    fn answers(engine: &mut Engine, commands: &str) -> Vec<String> {
+        let mut out = vec![];
+        serve(engine, &mut commands.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        out.split("\n\n")
+            .filter(|a| !a.is_empty())
+            .map(|a| a.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_serve() {
+        let mut engine = engine(50);
+        let commands = "1 name\n# a comment\nplay Gold\n2 undo\nfly\nplay none\nundo\ngenmove\n";
+        let out = answers(&mut engine, &format!("{}scores\nquit\nname\n", commands));
+        assert_eq!(out[0], "=1 Tactician");
+        assert_eq!(out[1], "? Gold wasn't offered");
+        assert_eq!(out[2], "?2 There's nothing to undo");
+        assert_eq!(out[3], "? Unknown command fly");
+        assert_eq!(out[4], "=");
+        assert_eq!(out[5], "=");
+        assert!(out[6].starts_with("= "));
+        assert_eq!(out[7], "? The game isn't over");
+        assert_eq!(out[8], "=");
+        assert_eq!(out.len(), 9);
+    }
+
+    #[test]
+    fn test_play_to_the_end() {
+        let mut engine = engine(5);
+        let mut commands = String::new();
+        for _ in 0..2000 {
+            commands.push_str("genmove\n");
+        }
+        commands.push_str("scores\nstate\n");
+        let out = answers(&mut engine, &commands);
+        assert!(engine.game.is_game_over());
+        assert_eq!(out[out.len() - 3], "? The game is over");
+        assert!(out[out.len() - 2].starts_with("= "));
+        assert!(out[out.len() - 1].starts_with("= {"));
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use util;
+
+// What happens when a player's clock runs out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnTimeout {
+    // They lose the game.
+    Forfeit,
+    // Their decision is replaced with the least they can choose, and they
+    // play on with only the increment for each later decision.
+    DefaultMove,
+}
+
+// A chess clock for each player: base time for the whole game, and an
+// increment added after each of their decisions. Only time spent deciding
+// counts, not pondering on the other players' time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+    pub on_timeout: OnTimeout,
+}
+
+impl TimeControl {
+    // Parses time controls like "60s+1s", or "5s" for no increment.
+    pub fn parse(s: &str) -> Result<TimeControl, String> {
+        let mut parts = s.splitn(2, '+');
+        let duration = |d: &str| util::parse_duration(d).ok_or(format!("Invalid time {}", d));
+        let base = duration(parts.next().unwrap())?;
+        let increment = match parts.next() {
+            Some(i) => duration(i)?,
+            None => Duration::from_millis(0),
+        };
+        Ok(TimeControl {
+            base: base,
+            increment: increment,
+            on_timeout: OnTimeout::Forfeit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+    use time_control::*;
+
+    #[test]
+    fn test_parse_time_control() {
+        let tc = TimeControl::parse("60s+500ms").unwrap();
+        assert_eq!(tc.base, Duration::from_millis(60000));
+        assert_eq!(tc.increment, Duration::from_millis(500));
+        assert_eq!(TimeControl::parse("5s").unwrap().increment, Duration::from_millis(0));
+        assert!(TimeControl::parse("5s+soon").is_err());
+    }
+}
@@ -0,0 +1,398 @@
+use std;
+
+use json::Json;
+
+// Parses the subset of TOML that config files need: tables, arrays of
+// tables, dotted keys, strings, numbers, booleans, arrays and inline tables.
+// Dates and multi-line strings aren't supported. The document is returned as
+// JSON, with each table an object, so it's read the same way as JSON files.
+pub fn parse(s: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: s.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    let mut root = Json::Object(vec![]);
+    // The path of the table the following keys belong in.
+    let mut current: Vec<String> = vec![];
+    loop {
+        parser.skip_blank_lines();
+        match parser.peek() {
+            None => return Ok(root),
+            Some('[') => {
+                parser.pos += 1;
+                let array = parser.peek() == Some('[');
+                if array {
+                    parser.pos += 1;
+                }
+                let path = parser.parse_key()?;
+                parser.expect(']')?;
+                if array {
+                    parser.expect(']')?;
+                    let (last, parent) = path.split_last().unwrap();
+                    let fields = table_at(&mut root, parent).map_err(|e| parser.error(&e))?;
+                    if !fields.iter().any(|f| f.0 == *last) {
+                        fields.push((last.clone(), Json::Array(vec![])));
+                    }
+                    match fields.iter_mut().find(|f| f.0 == *last) {
+                        Some(&mut (_, Json::Array(ref mut tables))) => {
+                            tables.push(Json::Object(vec![]))
+                        }
+                        _ => return Err(parser.error(&format!("{} isn't an array of tables", last))),
+                    }
+                } else {
+                    table_at(&mut root, &path).map_err(|e| parser.error(&e))?;
+                }
+                current = path;
+            }
+            Some(_) => {
+                let key = parser.parse_key()?;
+                parser.expect('=')?;
+                let value = parser.parse_value()?;
+                let (last, parent) = key.split_last().unwrap();
+                let mut path = current.clone();
+                path.extend(parent.iter().cloned());
+                let fields = table_at(&mut root, &path).map_err(|e| parser.error(&e))?;
+                if fields.iter().any(|f| f.0 == *last) {
+                    return Err(parser.error(&format!("{} is defined twice", last)));
+                }
+                fields.push((last.clone(), value));
+            }
+        }
+        parser.end_line()?;
+    }
+}
+
+// Finds the table at a path, creating any tables missing along the way. A
+// path through an array of tables leads into its latest table.
+fn table_at<'a>(root: &'a mut Json, path: &[String]) -> Result<&'a mut Vec<(String, Json)>, String> {
+    let mut table = root;
+    for key in path.iter() {
+        let fields = match *{ table } {
+            Json::Object(ref mut fields) => fields,
+            _ => unreachable!(),
+        };
+        if !fields.iter().any(|f| f.0 == *key) {
+            fields.push((key.clone(), Json::Object(vec![])));
+        }
+        let value = &mut fields.iter_mut().find(|f| f.0 == *key).unwrap().1;
+        table = match *value {
+            Json::Object(_) => value,
+            Json::Array(ref mut values) => match values.last_mut() {
+                Some(last @ &mut Json::Object(_)) => last,
+                _ => return Err(format!("{} isn't a table", key)),
+            },
+            _ => return Err(format!("{} isn't a table", key)),
+        };
+    }
+    match *table {
+        Json::Object(ref mut fields) => Ok(fields),
+        _ => unreachable!(),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid TOML on line {}: {}", self.line, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    // Skips spaces and tabs, but not newlines, which end keys and values.
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(' ') || self.peek() == Some('\t') {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while self.peek().map_or(false, |c| c != '\n') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    // Skips whitespace, comments and newlines, e.g. between array values.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => self.line += 1,
+                Some('\r') => {}
+                _ => return,
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn end_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.skip_comment();
+        if self.peek() == Some('\r') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.pos += 1;
+                self.line += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.error("expected the end of the line")),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_spaces();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    // Parses a key, which may be dotted, e.g. players.options.
+    fn parse_key(&mut self) -> Result<Vec<String>, String> {
+        let mut path = vec![];
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.parse_basic_string()?,
+                Some('\'') => self.parse_literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek()
+                        .map_or(false, |c| c.is_alphanumeric() || c == '_' || c == '-')
+                    {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.chars[start..self.pos].iter().collect()
+                }
+            };
+            path.push(part);
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(path);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('"') => self.parse_basic_string().map(Json::String),
+            Some('\'') => self.parse_literal_string().map(Json::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_inline_table(),
+            Some(c) if c.is_alphanumeric() || c == '-' || c == '+' => self.parse_bare_value(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    // Parses a boolean or number.
+    fn parse_bare_value(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while self.peek().map_or(false, |c| {
+            c.is_alphanumeric() || c == '-' || c == '+' || c == '.' || c == '_'
+        }) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match text.as_str() {
+            "true" => return Ok(Json::Bool(true)),
+            "false" => return Ok(Json::Bool(false)),
+            _ => {}
+        }
+        let digits = text.replace('_', "");
+        match digits.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Json::Number(n)),
+            _ => Err(self.error(&format!("invalid value {}", text))),
+        }
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let c = match self.peek() {
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => c,
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some(e) => e,
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        '"' => s.push('"'),
+                        '\\' => s.push('\\'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' | 'U' => {
+                            let len = if escaped == 'u' { 4 } else { 8 };
+                            if self.pos + len > self.chars.len() {
+                                return Err(self.error("truncated unicode escape"));
+                            }
+                            let hex: String = self.chars[self.pos..self.pos + len].iter().collect();
+                            self.pos += len;
+                            let code = u32::from_str_radix(&hex, 16)
+                                .map_err(|_| self.error("invalid unicode escape"))?;
+                            s.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    // Parses a string in single quotes, which has no escapes.
+    fn parse_literal_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => s.push(c),
+            }
+            self.pos += 1;
+        }
+    }
+
+    // Parses an array, whose values may span lines.
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.pos += 1;
+        let mut values = vec![];
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Json::Array(values));
+            }
+            values.push(self.parse_value()?);
+            self.skip_blank_lines();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    // Parses an inline table, e.g. { name = "random", seed = 42 }, which
+    // must be on one line.
+    fn parse_inline_table(&mut self) -> Result<Json, String> {
+        self.pos += 1;
+        let mut table = Json::Object(vec![]);
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(table);
+        }
+        loop {
+            let key = self.parse_key()?;
+            self.expect('=')?;
+            let value = self.parse_value()?;
+            let (last, parent) = key.split_last().unwrap();
+            let fields = table_at(&mut table, parent).map_err(|e| self.error(&e))?;
+            if fields.iter().any(|f| f.0 == *last) {
+                return Err(self.error(&format!("{} is defined twice", last)));
+            }
+            fields.push((last.clone(), value));
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(table);
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use json::Json;
+    use toml::*;
+
+    #[test]
+    fn test_toml() {
+        let doc = parse(
+            r#"
+# A match.
+games = 1_000
+seed = -3
+name = "first \"game\""
+
+[rules]
+kingdom = [
+    "Smithy",  # the draw
+    'Village',
+]
+clock.base = 1.5e1
+
+[[players]]
+name = "tactician"
+iters = 500
+
+[[players]]
+name = "random"
+options = { seed = 42, pass = false }
+"#,
+        ).unwrap();
+        assert_eq!(doc.get("games").and_then(|g| g.as_i64()), Some(1000));
+        assert_eq!(doc.get("seed").and_then(|g| g.as_i64()), Some(-3));
+        assert_eq!(doc.get("name").and_then(|n| n.as_str()), Some("first \"game\""));
+
+        let rules = doc.get("rules").unwrap();
+        assert_eq!(
+            rules.get("kingdom"),
+            Some(&Json::Array(vec!["Smithy".into(), "Village".into()]))
+        );
+        assert_eq!(rules.get("clock").and_then(|c| c.get("base")), Some(&Json::Number(15.0)));
+
+        let players = doc.get("players").and_then(|p| p.as_array()).unwrap();
+        assert_eq!(players.len(), 2);
+        assert_eq!(players[0].get("iters").and_then(|i| i.as_i64()), Some(500));
+        assert_eq!(
+            players[1].get("options").and_then(|o| o.get("pass")),
+            Some(&Json::Bool(false))
+        );
+
+        assert!(parse("a = 1\na = 2").is_err());
+        assert!(parse("a = 1 b = 2").is_err());
+        assert!(parse("a = \"open").is_err());
+        assert_eq!(parse("a = [1,\n2").unwrap_err(), "Invalid TOML on line 2: expected ',' or ']'");
+    }
+}
@@ -0,0 +1,391 @@
+use std::f64::consts::LN_10;
+
+use game;
+use match_runner::game_context;
+use match_stats;
+
+// Builds a fresh decider for each game, so no state carries between them.
+pub type DeciderFactory<'a> = &'a Fn() -> Box<game::Decider>;
+
+// Results from the first player's point of view.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub ties: u32,
+    pub losses: u32,
+}
+
+impl MatchResult {
+    pub fn games(&self) -> u32 {
+        self.wins + self.ties + self.losses
+    }
+
+    pub fn score(&self) -> f32 {
+        self.wins as f32 + 0.5 * self.ties as f32
+    }
+
+    pub fn record(&mut self, score: f32) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.ties += 1;
+        }
+    }
+
+    pub fn add(&mut self, other: &MatchResult) {
+        self.wins += other.wins;
+        self.ties += other.ties;
+        self.losses += other.losses;
+    }
+
+    pub fn reversed(&self) -> MatchResult {
+        MatchResult {
+            wins: self.losses,
+            ties: self.ties,
+            losses: self.wins,
+        }
+    }
+}
+
+// How every game of a tournament is played: by the same rules and, with a
+// seed, with the i-th game of each match seeded from seed + i, so every
+// pairing plays the same shuffles and the tournament can be replayed.
+#[derive(Default)]
+pub struct Settings {
+    pub rules: game::Rules,
+    pub seed: Option<u32>,
+}
+
+// Called with each game's outcome, its seed, and which of a match's two
+// players sat in each seat, as indices into the players of a tournament.
+pub type GameObserver<'a> = &'a mut FnMut(&[usize], Option<u32>, &game::GameOutcome);
+
+// Plays games between a and b, alternating who goes first. Each game is
+// passed to on_game with a's seat first.
+pub fn play_match(
+    a: DeciderFactory,
+    b: DeciderFactory,
+    games: u32,
+    settings: &Settings,
+    on_game: &mut FnMut(usize, Option<u32>, &game::GameOutcome),
+) -> MatchResult {
+    let mut result = MatchResult::default();
+    for i in 0..games {
+        let seat = (i % 2) as usize;
+        let mut players = match seat {
+            0 => vec![a(), b()],
+            _ => vec![b(), a()],
+        };
+        let seed = settings.seed.map(|s| s.wrapping_add(i));
+        let mut ctx = game_context(seed, game::Verbosity::Quiet);
+        let outcome = game::play_game(&mut players, &mut ctx, &settings.rules);
+        result.record(outcome.scores[seat]);
+        on_game(seat, seed, &outcome);
+    }
+    result
+}
+
+// Plays a match between players i and j, passing its games on with the
+// seats they sat in.
+fn play_pairing(
+    players: &[DeciderFactory],
+    i: usize,
+    j: usize,
+    games: u32,
+    settings: &Settings,
+    on_game: GameObserver,
+) -> MatchResult {
+    play_match(players[i], players[j], games, settings, &mut |seat, seed, outcome| {
+        let seats = match seat {
+            0 => [i, j],
+            _ => [j, i],
+        };
+        on_game(&seats, seed, outcome)
+    })
+}
+
+// Plays every pairing of the players, returning each player's results
+// against each other player.
+pub fn round_robin(
+    players: &[DeciderFactory],
+    games: u32,
+    settings: &Settings,
+    on_match: &mut FnMut(usize, usize, &MatchResult),
+    on_game: GameObserver,
+) -> Vec<Vec<MatchResult>> {
+    let n = players.len();
+    let mut table = vec![vec![MatchResult::default(); n]; n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let result = play_pairing(players, i, j, games, settings, on_game);
+            on_match(i, j, &result);
+            table[i][j] = result;
+            table[j][i] = result.reversed();
+        }
+    }
+    table
+}
+
+// Pairs players for a Swiss round: ranked by points, each takes the
+// highest-ranked player below them they haven't met yet, or the next one
+// if they've met everyone. With an odd number of players, the lowest-ranked
+// player without a bye sits out, or the lowest-ranked player once everyone
+// has had one, and is returned with the pairs.
+fn swiss_pairings(
+    points: &[f32],
+    table: &Vec<Vec<MatchResult>>,
+    byes: &mut Vec<bool>,
+) -> (Vec<(usize, usize)>, Option<usize>) {
+    let mut ranked = (0..points.len()).collect::<Vec<_>>();
+    ranked.sort_by(|&a, &b| points[b].partial_cmp(&points[a]).unwrap());
+
+    let mut bye = None;
+    if ranked.len() % 2 == 1 {
+        let i = ranked
+            .iter()
+            .rposition(|&p| !byes[p])
+            .unwrap_or(ranked.len() - 1);
+        let p = ranked.remove(i);
+        byes[p] = true;
+        bye = Some(p);
+    }
+
+    let mut pairs = vec![];
+    while !ranked.is_empty() {
+        let a = ranked.remove(0);
+        let i = ranked
+            .iter()
+            .position(|&b| table[a][b].games() == 0)
+            .unwrap_or(0);
+        pairs.push((a, ranked.remove(i)));
+    }
+    (pairs, bye)
+}
+
+// Plays a Swiss tournament, which ranks many players in far fewer games
+// than a round robin by pairing players with similar scores each round.
+// Each bye is worth as many points as winning every game of a match. Returns
+// the results table and each player's points.
+pub fn swiss(
+    players: &[DeciderFactory],
+    rounds: u32,
+    games: u32,
+    settings: &Settings,
+    on_match: &mut FnMut(usize, usize, &MatchResult),
+    on_game: GameObserver,
+) -> (Vec<Vec<MatchResult>>, Vec<f32>) {
+    let n = players.len();
+    let mut table = vec![vec![MatchResult::default(); n]; n];
+    let mut points = vec![0.0; n];
+    let mut byes = vec![false; n];
+
+    for _ in 0..rounds {
+        let (pairs, bye) = swiss_pairings(&points, &table, &mut byes);
+        for (a, b) in pairs {
+            let result = play_pairing(players, a, b, games, settings, on_game);
+            on_match(a, b, &result);
+            points[a] += result.score();
+            points[b] += result.reversed().score();
+
+            table[a][b].add(&result);
+            table[b][a] = table[a][b].reversed();
+        }
+        if let Some(p) = bye {
+            points[p] += games as f32;
+        }
+    }
+    (table, points)
+}
+
+fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+// Fits Elo ratings to a results table by maximum likelihood, centered on
+// zero, with the 95% confidence margin of each. As in BayesElo, each pairing
+// that was played counts one extra tie, which keeps the ratings of players
+// who won or lost every game finite.
+pub fn elo_ratings(table: &Vec<Vec<MatchResult>>) -> Vec<(f64, f64)> {
+    let n = table.len();
+    let games = |i: usize, j: usize| match table[i][j].games() {
+        0 => 0.0,
+        g => g as f64 + 1.0,
+    };
+    let score = |i: usize, j: usize| match table[i][j].games() {
+        0 => 0.0,
+        _ => table[i][j].score() as f64 + 0.5,
+    };
+    let information = |ratings: &Vec<f64>, i: usize| {
+        (0..n)
+            .map(|j| {
+                let e = expected_score(ratings[i], ratings[j]);
+                games(i, j) * e * (1.0 - e)
+            })
+            .sum::<f64>()
+    };
+
+    let mut ratings = vec![0.0; n];
+    for _ in 0..200 {
+        for i in 0..n {
+            let expected: f64 = (0..n)
+                .map(|j| games(i, j) * expected_score(ratings[i], ratings[j]))
+                .sum();
+            let actual: f64 = (0..n).map(|j| score(i, j)).sum();
+            let info = information(&ratings, i);
+            if info > 0.0 {
+                ratings[i] += (actual - expected) / info * 400.0 / LN_10;
+            }
+        }
+        let mean = ratings.iter().sum::<f64>() / n.max(1) as f64;
+        for r in ratings.iter_mut() {
+            *r -= mean;
+        }
+    }
+
+    (0..n)
+        .map(|i| {
+            let info = information(&ratings, i);
+            let margin = match info > 0.0 {
+                true => 1.96 * 400.0 / LN_10 / info.sqrt(),
+                false => std::f64::INFINITY,
+            };
+            (ratings[i], margin)
+        })
+        .collect()
+}
+
+// A head-to-head table of the players in the given order: each row's win
+// rate against each column, with its 95% confidence margin and the number
+// of games they played.
+pub fn matchup_matrix(table: &Vec<Vec<MatchResult>>, names: &[String], order: &[usize]) -> String {
+    const CELL_WIDTH: usize = 26;
+    let width = order.iter().map(|&i| names[i].chars().count()).max().unwrap_or(0).max(6);
+    let mut header = format!("{:>3} {:<w$}", "#", "Player", w = width);
+    for rank in 0..order.len() {
+        header += &format!(" {:>cw$}", rank + 1, cw = CELL_WIDTH);
+    }
+    let mut lines = vec![header];
+    for (rank, &i) in order.iter().enumerate() {
+        let mut line = format!("{:>3} {:<w$}", rank + 1, names[i], w = width);
+        for &j in order.iter() {
+            let result = &table[i][j];
+            let cell = match result.games() {
+                _ if i == j => "-".to_string(),
+                0 => String::new(),
+                n => {
+                    let p = result.score() / n as f32;
+                    let (low, high) = match_stats::wilson_interval(p, n);
+                    format!("{:.1}% {:.1}-{:.1}% ({})", 100.0 * p, 100.0 * low, 100.0 * high, n)
+                }
+            };
+            line += &format!(" {:>cw$}", cell, cw = CELL_WIDTH);
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use cards;
+    use deciders::*;
+    use game;
+    use tournament::*;
+
+    #[test]
+    fn test_play_match() {
+        let bigmoney = || Box::new(BigMoney) as Box<game::Decider>;
+        let settings = Settings {
+            rules: game::Rules {
+                kingdom: cards::parse_kingdom("preset:big-money").unwrap(),
+                ..Default::default()
+            },
+            seed: Some(7),
+        };
+        let mut seats = vec![];
+        let mut seeds = vec![];
+        let mut turns = vec![];
+        let result = play_match(&bigmoney, &bigmoney, 4, &settings, &mut |seat, seed, outcome| {
+            assert_eq!(outcome.scores.len(), 2);
+            seats.push(seat);
+            seeds.push(seed);
+            turns.push(outcome.turns);
+        });
+        assert_eq!(result.games(), 4);
+        assert_eq!(seats, vec![0, 1, 0, 1]);
+        assert_eq!(seeds, vec![Some(7), Some(8), Some(9), Some(10)]);
+        assert_eq!(result.reversed().reversed(), result);
+
+        // The same seeds play the same games again.
+        let mut replayed = vec![];
+        play_match(&bigmoney, &bigmoney, 4, &settings, &mut |_, _, outcome| {
+            replayed.push(outcome.turns)
+        });
+        assert_eq!(replayed, turns);
+    }
+
+    #[test]
+    fn test_swiss_pairings() {
+        let mut table = vec![vec![MatchResult::default(); 5]; 5];
+        table[0][1].wins = 1;
+        table[1][0].losses = 1;
+        let mut byes = vec![false, false, false, false, true];
+
+        // Player 4 has had a bye, so the lowest-ranked player without one
+        // sits out, and players 0 and 1 don't meet again.
+        let points = [3.0, 2.0, 1.0, 0.5, 0.0];
+        let (pairs, bye) = swiss_pairings(&points, &table, &mut byes);
+        assert_eq!(pairs, vec![(0, 2), (1, 4)]);
+        assert_eq!(bye, Some(3));
+        assert!(byes[3]);
+    }
+
+    #[test]
+    fn test_swiss_byes() {
+        // Three players in four rounds means someone gets a second bye, and
+        // every bye, like every match of two games, hands out two points.
+        let bigmoney = || Box::new(BigMoney) as Box<game::Decider>;
+        let players: Vec<DeciderFactory> = vec![&bigmoney, &bigmoney, &bigmoney];
+        let settings = Settings {
+            seed: Some(3),
+            ..Default::default()
+        };
+        let (_, points) = swiss(&players, 4, 2, &settings, &mut |_, _, _| {}, &mut |_, _, _| {});
+        assert_eq!(points.iter().sum::<f32>(), 16.0);
+    }
+
+    #[test]
+    fn test_elo_ratings() {
+        let result = |wins, losses| MatchResult {
+            wins: wins,
+            ties: 0,
+            losses: losses,
+        };
+        let mut table = vec![vec![MatchResult::default(); 3]; 3];
+        table[0][1] = result(15, 5);
+        table[1][2] = result(15, 5);
+        table[0][2] = result(20, 0);
+        for i in 0..3 {
+            for j in 0..i {
+                table[i][j] = table[j][i].reversed();
+            }
+        }
+
+        let names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let matrix = matchup_matrix(&table, &names, &[2, 0, 1]);
+        let rows = matrix.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(
+            rows[2].split_whitespace().collect::<Vec<_>>(),
+            vec!["2", "a", "100.0%", "83.9-100.0%", "(20)", "-", "75.0%", "53.1-88.8%", "(20)"]
+        );
+
+        let ratings = elo_ratings(&table);
+        assert!(ratings[0].0 > ratings[1].0 && ratings[1].0 > ratings[2].0);
+        assert!(ratings.iter().map(|r| r.0).sum::<f64>().abs() < 1e-6);
+        assert!(ratings.iter().all(|r| r.1 > 0.0 && r.1.is_finite()));
+    }
+}
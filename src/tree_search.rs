@@ -1,46 +1,360 @@
+use rand::distributions::{Gamma, IndependentSample};
 use rand::{Rng, XorShiftRng};
+use std;
+use std::fmt;
 use std::fmt::Debug;
-use std::rc::{Rc, Weak};
+use std::rc::Rc;
 use std::cell::RefCell;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use util::Stopwatch;
 
-use util;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Winners<P>(pub Vec<P>);
 
-pub type WeakNodeRef<T> = Weak<RefCell<SearchNode<T>>>;
-pub type NodeRef<T> = Rc<RefCell<SearchNode<T>>>;
+// Each player's share of the outcome of a playout, from 0.0 to 1.0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rewards<P>(pub Vec<(P, f32)>);
+
+impl<P: Clone + PartialEq> Rewards<P> {
+    pub fn from_winners(winners: &Winners<P>, players: &Vec<P>) -> Rewards<P> {
+        Rewards(
+            players
+                .iter()
+                .map(|p| {
+                    if winners.0.contains(p) {
+                        (p.clone(), 1.0 / winners.0.len() as f32)
+                    } else {
+                        (p.clone(), 0.0)
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    // Converts scores to win probabilities with a softmax, where a lead of
+    // `scale` points makes a player e (~2.7) times as likely to win.
+    pub fn from_scores(scores: Vec<(P, f32)>, scale: f32) -> Rewards<P> {
+        let best = scores
+            .iter()
+            .map(|&(_, s)| s)
+            .fold(std::f32::MIN, f32::max);
+        let weights = scores
+            .into_iter()
+            .map(|(p, s)| (p, ((s - best) / scale).exp()))
+            .collect::<Vec<_>>();
+        let total: f32 = weights.iter().map(|&(_, w)| w).sum();
+        Rewards(weights.into_iter().map(|(p, w)| (p, w / total)).collect())
+    }
+
+    // Scales every reward by factor once per turn, so the same result is
+    // worth less the longer it took to reach.
+    pub fn discounted(self, factor: f32, turns: i32) -> Rewards<P> {
+        let scale = factor.powi(turns.max(0));
+        Rewards(self.0.into_iter().map(|(p, r)| (p, r * scale)).collect())
+    }
+
+    pub fn for_player(&self, player: &P) -> f32 {
+        self.0
+            .iter()
+            .find(|&&(ref p, _)| p == player)
+            .map(|&(_, r)| r)
+            .unwrap_or(0.0)
+    }
+}
+
+// How long find_best_move searches before committing to a move.
+#[derive(Clone, Copy, Debug)]
+pub enum SearchBudget {
+    Iterations(i32),
+    Time(Duration),
+}
+
+impl SearchBudget {
+    fn is_exhausted(&self, iterations: i32, started: Stopwatch) -> bool {
+        match *self {
+            SearchBudget::Iterations(n) => iterations >= n,
+            SearchBudget::Time(d) => started.elapsed() >= d,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum RewardShaping {
+    WinLoss,
+    // Blends the win/loss outcome with a logistic function of each player's
+    // score margin over their best opponent, so convincing wins count for
+    // more than narrow ones. A margin of `scale` is worth ~0.73.
+    ScoreMargin { weight: f32, scale: f32 },
+}
+
+impl RewardShaping {
+    fn shape<T: SearchableState>(&self, end_state: &T, rewards: Rewards<T::P>) -> Rewards<T::P> {
+        let (weight, scale) = match *self {
+            RewardShaping::WinLoss => return rewards,
+            RewardShaping::ScoreMargin { weight, scale } => (weight, scale),
+        };
+        let scores = match end_state.scores() {
+            Some(scores) => scores,
+            None => return rewards,
+        };
+
+        Rewards(
+            rewards
+                .0
+                .into_iter()
+                .map(|(p, r)| {
+                    let own = scores
+                        .iter()
+                        .find(|&&(ref sp, _)| *sp == p)
+                        .map_or(0.0, |&(_, s)| s);
+                    let best_other = scores
+                        .iter()
+                        .filter(|&&(ref sp, _)| *sp != p)
+                        .map(|&(_, s)| s)
+                        .fold(std::f32::MIN, f32::max);
+                    let margin = own - best_other;
+                    let margin_reward = 1.0 / (1.0 + (-margin / scale).exp());
+                    (p, (1.0 - weight) * r + weight * margin_reward)
+                })
+                .collect(),
+        )
+    }
+}
+
+// How selection weighs a child's value against exploring it further.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionPolicy {
+    // UCB1: the mean reward plus `exploration` times sqrt(ln(n) / visits).
+    Ucb1,
+    // UCB1-Tuned: bounds the exploration term by each child's observed
+    // reward variance, so children with consistent results are explored
+    // less. It ignores `exploration`.
+    Ucb1Tuned,
+    // Thompson sampling: draws each child's value from a Beta distribution
+    // over its mean reward, and picks the highest draw.
+    Thompson,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchOptions {
+    pub budget: SearchBudget,
+    // Scales the exploration term of UCT selection. Lower values spend more
+    // of the search on the moves that already look best.
+    pub exploration: f32,
+    pub selection: SelectionPolicy,
+    // Independent rollouts run from each newly expanded node. Their average
+    // is backpropagated as a single visit, reducing variance per visit.
+    pub rollouts_per_leaf: i32,
+    // Stops rollouts after this many turns and scores the position with the
+    // search's evaluator instead of playing to the end. A limit of 0 scores
+    // each new leaf directly, without any rollout.
+    pub rollout_turn_limit: Option<i32>,
+    pub reward_shaping: RewardShaping,
+    // Discounts playout rewards by this factor for each turn between the
+    // searched position and the end of the playout, so the search prefers
+    // closing out a win to reaching the same win later. Solved nodes keep
+    // their exact result.
+    pub turn_discount: Option<f32>,
+    // Blends each child's all-moves-as-first (AMAF) statistics into its
+    // value, weighted by sqrt(k / (3 * visits + k)) for this k, so moves
+    // that recur throughout playouts are credited before they're explored.
+    pub rave_equivalence: Option<f32>,
+    // Value given to moves not yet in the tree when selecting among a node's
+    // children. Without one, every legal move is tried before any is
+    // revisited.
+    pub first_play_urgency: Option<f32>,
+    // Search a decider may spend growing its tree while another player is
+    // deciding, to be reused once it's the decider's turn again.
+    pub ponder_budget: Option<SearchBudget>,
+    // Marks nodes whose move ends the game as solved, with their exact
+    // result. Solved wins are always chosen and solved losses avoided, both
+    // during selection and when picking the final move.
+    pub solver: bool,
+    // Pools the value estimates of nodes whose states share a transposition
+    // key, however the moves leading to them were ordered.
+    pub use_transpositions: bool,
+    // Whether a decider keeps the subtree below its chosen move for its next
+    // decision, when nothing unobserved happened in between.
+    pub reuse_tree: bool,
+    // Caps the number of nodes in the tree. When the cap is reached, the
+    // least visited subtrees are discarded down to three quarters of it,
+    // and their moves can be expanded again later.
+    pub max_nodes: Option<usize>,
+    // Branches on each chance outcome after a move, so nodes below it only
+    // collect statistics from iterations with the same draws.
+    pub chance_nodes: bool,
+    // Prints the best move so far each time this much more of the search
+    // has run.
+    pub report_interval: Option<SearchBudget>,
+    // Stops the search early, keeping what it has found so far, once the
+    // flag is set. The search clears it.
+    pub interrupt: Option<&'static AtomicBool>,
+    pub debug: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> SearchOptions {
+        SearchOptions {
+            budget: SearchBudget::Iterations(10000),
+            exploration: std::f32::consts::SQRT_2,
+            selection: SelectionPolicy::Ucb1,
+            rollouts_per_leaf: 1,
+            rollout_turn_limit: None,
+            reward_shaping: RewardShaping::WinLoss,
+            turn_discount: None,
+            rave_equivalence: None,
+            first_play_urgency: None,
+            ponder_budget: None,
+            solver: false,
+            use_transpositions: false,
+            reuse_tree: true,
+            max_nodes: None,
+            chance_nodes: false,
+            report_interval: None,
+            interrupt: None,
+            debug: false,
+        }
+    }
+}
+
+// Statistics shared by every node with the same transposition key. Rewards
+// are summed per player, since the same position can be reached after a move
+// by different players.
+#[derive(Debug)]
+pub struct TranspositionEntry<P> {
+    pub visits: i32,
+    pub reward_sums: Vec<(P, f32)>,
+}
+
+impl<P: Clone + PartialEq> TranspositionEntry<P> {
+    fn add_results(&mut self, results: &Vec<Rewards<P>>) {
+        self.visits += 1;
+        for result in results.iter() {
+            for &(ref p, r) in result.0.iter() {
+                let share = r / results.len() as f32;
+                match self.reward_sums.iter_mut().find(|&&mut (ref sp, _)| sp == p) {
+                    Some(entry) => entry.1 += share,
+                    None => self.reward_sums.push((p.clone(), share)),
+                }
+            }
+        }
+    }
+
+    fn mean_reward(&self, player: &P) -> f32 {
+        let total = self.reward_sums
+            .iter()
+            .find(|&&(ref p, _)| p == player)
+            .map_or(0.0, |&(_, r)| r);
+        total / self.visits as f32
+    }
+}
+
+pub type TranspositionRef<P> = Rc<RefCell<TranspositionEntry<P>>>;
+type TranspositionTable<P> = HashMap<u64, TranspositionRef<P>>;
+
+// Index of a node within its tree's arena.
+pub type NodeId = usize;
 
 pub trait SearchableState: Clone + Debug {
     type P: Clone + PartialEq + Debug;
-    type M: Clone + Debug;
+    type M: Clone + Debug + PartialEq;
     type C;
 
     fn game_result(&self) -> Option<Winners<Self::P>>;
     fn all_players(&self) -> Vec<Self::P>;
     fn active_player(&self) -> Option<Self::P>;
     fn all_moves(&self) -> Vec<Self::M>;
-    fn make_move(&self, Self::M, &mut Self::C) -> Self;
     fn make_move_mut(&mut self, Self::M, &mut Self::C);
 
+    // Samples a state consistent with what the observer knows. Games with
+    // hidden information should reshuffle whatever the observer can't see.
+    fn determinize(&self, _observer: &Self::P, _ctx: &mut Self::C) -> Self {
+        self.clone()
+    }
+
+    // Each player's score, for games where the margin of victory matters.
+    fn scores(&self) -> Option<Vec<(Self::P, f32)>> {
+        None
+    }
+
+    // Identifies states that can be reached by different move orders, so
+    // their statistics can be shared. Hidden information should be left out,
+    // since every determinization of a state must produce the same key.
+    fn transposition_key(&self) -> Option<u64> {
+        None
+    }
+
+    // Identifies what the observer has seen of the game's randomness, such
+    // as the cards in their hand. Searches with chance nodes branch on it
+    // after each move, so the statistics below a move aren't shared between
+    // different draws. None for states without hidden randomness.
+    fn chance_outcome(&self, _observer: &Self::P) -> Option<u64> {
+        None
+    }
+
+    // Used to cut rollouts short after a number of turns. States that don't
+    // track turns are always played out to the end.
+    fn turn(&self) -> i32 {
+        0
+    }
+
     fn printable_player_identifier(&self, p: &Self::P) -> String;
+
+    fn printable_move(&self, m: &Self::M) -> String {
+        format!("{:?}", m)
+    }
+}
+
+// Estimates each player's chance of winning from a state that isn't over.
+pub trait Evaluator<T: SearchableState> {
+    fn evaluate(&self, state: &T) -> Rewards<T::P>;
 }
 
+// Nodes represent information sets rather than concrete states: each search
+// iteration walks the tree with a freshly determinized state, so nodes keep
+// no state of their own. Nodes refer to their parent and children by index
+// into their tree.
 #[derive(Debug)]
 pub struct SearchNode<T: SearchableState> {
-    pub state: T,
+    pub player_to_move: Option<T::P>,
     pub wins: f32,
+    // Sum of each visit's reward squared, for the variance of UCB1-Tuned.
+    pub squared_rewards: f32,
     pub visits: i32,
+    pub availability: i32,
+    pub amaf_wins: f32,
+    pub amaf_visits: i32,
+    pub transposition: Option<(u64, TranspositionRef<T::P>)>,
+    // The result of the game from here once it's solved, as plain wins,
+    // losses and draws, since shaping and discounts would hide which it is.
+    pub proven: Option<Rewards<T::P>>,
+    // Set on chance nodes, which stand for an outcome of the randomness
+    // after their parent's move rather than for a move.
+    pub chance_outcome: Option<u64>,
     pub last_move: Option<T::M>,
-    pub untried_moves: Vec<T::M>,
     pub player_just_moved: T::P,
-    pub parent: Option<WeakNodeRef<T>>,
-    pub children: Vec<NodeRef<T>>,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
 }
 
+// Nodes are stored in a flat arena with the root at index 0, so selection and
+// backpropagation are loops over indices rather than chains of pointers.
+// `state` is the state the tree was first built from, which is only used to
+// describe players and moves.
+#[derive(Debug)]
+pub struct SearchTree<T: SearchableState> {
+    pub state: T,
+    pub nodes: Vec<SearchNode<T>>,
+}
+
+pub const ROOT: NodeId = 0;
+
 #[derive(Clone, Debug)]
 pub struct NodeStats<T: SearchableState> {
-    pub state: T,
     pub wins: f32,
     pub visits: i32,
     pub percent_won: f32,
@@ -53,7 +367,6 @@ where
 {
     pub fn stats(&self) -> NodeStats<T> {
         NodeStats {
-            state: self.state.clone(),
             wins: self.wins,
             visits: self.visits,
             percent_won: self.wins / self.visits as f32,
@@ -63,181 +376,978 @@ where
 }
 
 impl<T: SearchableState> SearchNode<T> {
-    fn expectation(&self, parent_visits: f32) -> f32 {
+    // The exact reward for the player who just moved, if this node is solved.
+    fn proven_reward(&self) -> Option<f32> {
+        self.proven
+            .as_ref()
+            .map(|r| r.for_player(&self.player_just_moved))
+    }
+
+    fn expectation(&self, options: &SearchOptions, rng: &mut XorShiftRng) -> f32 {
+        match self.proven_reward() {
+            Some(r) if r >= 1.0 => return std::f32::INFINITY,
+            Some(r) if r <= 0.0 => return std::f32::NEG_INFINITY,
+            _ => {}
+        }
+
         let f_visits = self.visits as f32;
-        let payout = self.wins / f_visits;
-        let confidence = (2.0 * parent_visits.ln() / f_visits).sqrt();
-        payout + confidence
+        let mut payout = match self.transposition {
+            Some((_, ref entry)) => entry.borrow().mean_reward(&self.player_just_moved),
+            None => self.wins / f_visits,
+        };
+        if let Some(k) = options.rave_equivalence {
+            if self.amaf_visits > 0 {
+                let beta = (k / (3.0 * f_visits + k)).sqrt();
+                let amaf_payout = self.amaf_wins / self.amaf_visits as f32;
+                payout = (1.0 - beta) * payout + beta * amaf_payout;
+            }
+        }
+        // Child moves are only sometimes legal under a determinization, so the
+        // exploration term uses how often this node was available to be picked
+        // rather than the parent's visit count.
+        let log_availability = (self.availability as f32).ln();
+        match options.selection {
+            SelectionPolicy::Ucb1 => {
+                payout + options.exploration * (log_availability / f_visits).sqrt()
+            }
+            SelectionPolicy::Ucb1Tuned => {
+                let mean = self.wins / f_visits;
+                let variance = self.squared_rewards / f_visits - mean * mean
+                    + (2.0 * log_availability / f_visits).sqrt();
+                payout + (log_availability / f_visits * variance.min(0.25)).sqrt()
+            }
+            SelectionPolicy::Thompson => {
+                let p = payout.max(0.0).min(1.0) as f64;
+                let n = self.visits as f64;
+                let alpha = Gamma::new(p * n + 1.0, 1.0).ind_sample(rng);
+                let beta = Gamma::new((1.0 - p) * n + 1.0, 1.0).ind_sample(rng);
+                (alpha / (alpha + beta)) as f32
+            }
+        }
+    }
+
+    fn update_with_results(&mut self, results: &Vec<Rewards<T::P>>) {
+        self.visits += 1;
+        let reward = results
+            .iter()
+            .map(|r| r.for_player(&self.player_just_moved))
+            .sum::<f32>() / results.len() as f32;
+        self.wins += reward;
+        self.squared_rewards += reward * reward;
+        if let Some((_, ref entry)) = self.transposition {
+            entry.borrow_mut().add_results(results);
+        }
+    }
+}
+
+impl<T: SearchableState> SearchTree<T> {
+    pub fn new(root_state: &T) -> SearchTree<T> {
+        // No move led to the root, so credit it to the player before the one
+        // to move, in turn order. Every other node records whoever actually
+        // moved, and each node's rewards are for that player alone, so with
+        // more than two players every player maximizes their own reward.
+        let players = root_state.all_players();
+        let just_moved = match root_state
+            .active_player()
+            .and_then(|p| players.iter().position(|q| *q == p))
+        {
+            Some(i) => players[(i + players.len() - 1) % players.len()].clone(),
+            None => players.last().cloned().expect("Players must not be empty"),
+        };
+        SearchTree {
+            state: root_state.clone(),
+            nodes: vec![SearchNode {
+                player_to_move: root_state.active_player(),
+                wins: 0.0,
+                squared_rewards: 0.0,
+                visits: 0,
+                availability: 0,
+                amaf_wins: 0.0,
+                amaf_visits: 0,
+                transposition: None,
+                proven: None,
+                chance_outcome: None,
+                last_move: None,
+                player_just_moved: just_moved,
+                parent: None,
+                children: vec![],
+            }],
+        }
+    }
+
+    pub fn root(&self) -> &SearchNode<T> {
+        &self.nodes[ROOT]
+    }
+
+    pub fn child_with_move(&self, id: NodeId, m: &T::M) -> Option<NodeId> {
+        self.nodes[id]
+            .children
+            .iter()
+            .find(|&&c| self.nodes[c].last_move.as_ref() == Some(m))
+            .cloned()
+    }
+
+    // Solves a node from its children once they're solved: it's won if the
+    // player to move has a legal move that's a proven win, and lost if every
+    // legal move is a proven loss.
+    fn prove_from_children(&mut self, id: NodeId, legal_moves: &Vec<T::M>) {
+        if self.nodes[id].proven.is_some() {
+            return;
+        }
+        let mut lost = None;
+        let mut all_lost = true;
+        for m in legal_moves.iter() {
+            let child = match self.child_with_move(id, m) {
+                Some(c) => &self.nodes[c],
+                None => {
+                    all_lost = false;
+                    continue;
+                }
+            };
+            match child.proven_reward() {
+                Some(r) if r >= 1.0 => {
+                    self.nodes[id].proven = child.proven.clone();
+                    return;
+                }
+                Some(r) if r <= 0.0 => lost = child.proven.clone(),
+                _ => all_lost = false,
+            }
+        }
+        if all_lost {
+            self.nodes[id].proven = lost;
+        }
+    }
+
+    fn untried_moves(&self, id: NodeId, legal_moves: &Vec<T::M>) -> Vec<T::M> {
+        legal_moves
+            .iter()
+            .filter(|m| self.child_with_move(id, m).is_none())
+            .cloned()
+            .collect()
     }
 
-    pub fn most_visited_child(&self) -> NodeRef<T> {
-        self.children
+    pub fn most_visited_child(&self, id: NodeId) -> NodeId {
+        *self.nodes[id]
+            .children
             .iter()
-            .max_by_key(|&c| c.borrow().visits)
+            .max_by_key(|&&c| self.nodes[c].visits)
             .expect("most_visited_child() called on terminal node")
-            .clone()
     }
 
-    pub fn select_most_promising_child(&mut self) -> NodeRef<T> {
-        let parent_visits = self.visits as f32;
-        self.children.sort_by(|a, b| {
-            let a_exp = a.borrow().expectation(parent_visits);
-            let b_exp = b.borrow().expectation(parent_visits);
-            match a_exp.partial_cmp(&b_exp) {
-                Some(o) => o.reverse(), // Sort most promising first
-                None => panic!("SearchNode::select_most_promising_child failed with non-total comparison of {} vs {}", a_exp, b_exp)
+    // Prefers a solved win, then the most visited child that isn't a solved
+    // loss, and otherwise the most visited child.
+    pub fn best_child(&self, id: NodeId) -> NodeId {
+        let children = &self.nodes[id].children;
+        let proven_win = children
+            .iter()
+            .find(|&&c| self.nodes[c].proven_reward().map_or(false, |r| r >= 1.0));
+        if let Some(&c) = proven_win {
+            return c;
+        }
+
+        let not_lost = children
+            .iter()
+            .filter(|&&c| self.nodes[c].proven_reward().map_or(true, |r| r > 0.0))
+            .max_by_key(|&&c| self.nodes[c].visits);
+        match not_lost {
+            Some(&c) => c,
+            None => self.most_visited_child(id),
+        }
+    }
+
+    fn is_legal_child(&self, child: NodeId, legal_moves: &Vec<T::M>) -> bool {
+        match self.nodes[child].last_move {
+            Some(ref m) => legal_moves.contains(m),
+            None => false,
+        }
+    }
+
+    // Counts this as an opportunity to be picked for each child whose move
+    // is legal in the current determinization.
+    fn mark_children_available(&mut self, id: NodeId, legal_moves: &Vec<T::M>) {
+        for i in 0..self.nodes[id].children.len() {
+            let c = self.nodes[id].children[i];
+            if self.is_legal_child(c, legal_moves) {
+                self.nodes[c].availability += 1;
             }
-        });
-        self.children
-            .first()
-            .expect("SearchNode::select_most_promising_child failed: no children")
-            .clone()
+        }
     }
 
-    fn update_with_result(&mut self, result: &Winners<T::P>) {
-        self.visits += 1;
-        if result.0.contains(&self.player_just_moved) {
-            self.wins += 1.0 / result.0.len() as f32;
+    pub fn select_most_promising_child(
+        &mut self,
+        id: NodeId,
+        legal_moves: &Vec<T::M>,
+        options: &SearchOptions,
+        rng: &mut XorShiftRng,
+    ) -> Option<NodeId> {
+        self.mark_children_available(id, legal_moves);
+
+        let mut best: Option<(NodeId, f32)> = None;
+        for &c in self.nodes[id].children.iter() {
+            if !self.is_legal_child(c, legal_moves) {
+                continue;
+            }
+            let exp = self.nodes[c].expectation(options, rng);
+            if exp.is_nan() {
+                panic!(
+                    "SearchTree::select_most_promising_child failed with non-total expectation {}",
+                    exp
+                );
+            }
+            // Ties go to the earliest child.
+            if best.map_or(true, |(_, best_exp)| exp > best_exp) {
+                best = Some((c, exp));
+            }
         }
+        best.map(|(c, _)| c)
     }
 
-    fn ancestors(&self) -> Vec<NodeRef<T>> {
-        let mut vector = vec![];
-        fn walk<T: SearchableState>(parent_ref: &Option<WeakNodeRef<T>>, v: &mut Vec<NodeRef<T>>) {
-            match parent_ref {
-                &Some(ref p) => match p.upgrade() {
-                    Some(n) => {
-                        v.push(n.clone());
-                        walk(&n.borrow().parent, v);
-                    }
-                    None => {}
-                },
-                &None => {}
+    fn add_child(&mut self, parent: NodeId, mut node: SearchNode<T>) -> NodeId {
+        let id = self.nodes.len();
+        node.parent = Some(parent);
+        self.nodes.push(node);
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    fn collect_transpositions(&self, table: &mut TranspositionTable<T::P>) {
+        for node in self.nodes.iter() {
+            if let Some((key, ref entry)) = node.transposition {
+                table.entry(key).or_insert_with(|| entry.clone());
+            }
+        }
+    }
+
+    // The chance node below a move for an outcome, added if it's new.
+    fn outcome_child(&mut self, id: NodeId, outcome: u64, state: &T) -> NodeId {
+        let existing = self.nodes[id]
+            .children
+            .iter()
+            .find(|&&c| self.nodes[c].chance_outcome == Some(outcome))
+            .cloned();
+        if let Some(c) = existing {
+            return c;
+        }
+
+        let node = SearchNode {
+            player_to_move: state.active_player(),
+            wins: 0.0,
+            squared_rewards: 0.0,
+            visits: 0,
+            availability: 0,
+            amaf_wins: 0.0,
+            amaf_visits: 0,
+            transposition: None,
+            proven: None,
+            chance_outcome: Some(outcome),
+            last_move: None,
+            player_just_moved: self.nodes[id].player_just_moved.clone(),
+            parent: None,
+            children: vec![],
+        };
+        self.add_child(id, node)
+    }
+
+    // Credits each child whose move was made by the same player at any point
+    // after this node, not just as the immediate next move.
+    fn update_amaf(
+        &mut self,
+        id: NodeId,
+        later_moves: &[(T::P, T::M)],
+        results: &Vec<Rewards<T::P>>,
+    ) {
+        for i in 0..self.nodes[id].children.len() {
+            let child = self.nodes[id].children[i];
+            let c = &mut self.nodes[child];
+            let was_played = match c.last_move {
+                Some(ref m) => later_moves
+                    .iter()
+                    .any(|&(ref p, ref lm)| *p == c.player_just_moved && lm == m),
+                None => false,
+            };
+
+            if was_played {
+                c.amaf_visits += 1;
+                for result in results.iter() {
+                    c.amaf_wins += result.for_player(&c.player_just_moved) / results.len() as f32;
+                }
+            }
+        }
+    }
+
+    // Keeps the `keep` nodes reached by repeatedly following the most visited
+    // edge out of the nodes kept so far, starting from the root. Every kept
+    // node's parent is kept, so the result is still a tree.
+    pub fn prune(&mut self, keep: usize) {
+        if self.nodes.len() <= keep {
+            return;
+        }
+
+        let mut kept = vec![false; self.nodes.len()];
+        let mut frontier = BinaryHeap::new();
+        frontier.push((self.nodes[ROOT].visits, ROOT));
+        let mut kept_count = 0;
+        while kept_count < keep {
+            let id = match frontier.pop() {
+                Some((_, id)) => id,
+                None => break,
             };
+            kept[id] = true;
+            kept_count += 1;
+            for &c in self.nodes[id].children.iter() {
+                frontier.push((self.nodes[c].visits, c));
+            }
         }
 
-        walk(&self.parent, &mut vector);
-        return vector;
+        // Children are always added after their parents, so renumbering in
+        // order sees each parent before its children.
+        let old_nodes = std::mem::replace(&mut self.nodes, vec![]);
+        let mut new_ids = vec![None; old_nodes.len()];
+        for (old_id, mut node) in old_nodes.into_iter().enumerate() {
+            if !kept[old_id] {
+                continue;
+            }
+            let new_id = self.nodes.len();
+            new_ids[old_id] = Some(new_id);
+            node.parent = node.parent.and_then(|p| new_ids[p]);
+            node.children = vec![];
+            if let Some(p) = node.parent {
+                self.nodes[p].children.push(new_id);
+            }
+            self.nodes.push(node);
+        }
+    }
+
+    // Consumes the tree, keeping only the subtree below `id` so it can serve
+    // as the root of a later search.
+    pub fn into_subtree(self, id: NodeId) -> SearchTree<T> {
+        let mut old_nodes = self.nodes.into_iter().map(Some).collect::<Vec<_>>();
+        let mut nodes: Vec<SearchNode<T>> = vec![];
+        let mut queue: Vec<(NodeId, Option<NodeId>)> = vec![(id, None)];
+        let mut next = 0;
+        while next < queue.len() {
+            let (old_id, parent) = queue[next];
+            next += 1;
+
+            let mut node = old_nodes[old_id]
+                .take()
+                .expect("Search tree nodes should have one parent");
+            let new_id = nodes.len();
+            if let Some(p) = parent {
+                nodes[p].children.push(new_id);
+            }
+            for &c in node.children.iter() {
+                queue.push((c, Some(new_id)));
+            }
+            node.parent = parent;
+            node.children = vec![];
+            nodes.push(node);
+        }
+        SearchTree {
+            state: self.state,
+            nodes: nodes,
+        }
     }
 }
 
 fn expand_node_by_move<T: SearchableState>(
-    node_ref: NodeRef<T>,
-    move_idx: usize,
+    tree: &mut SearchTree<T>,
+    parent: NodeId,
+    picked_move: T::M,
+    state: &mut T,
+    played: &mut Vec<(T::P, T::M)>,
+    table: Option<&mut TranspositionTable<T::P>>,
+    prior_policy: Option<&PriorPolicy<T>>,
+    options: &SearchOptions,
     ctx: &mut T::C,
-) -> NodeRef<T> {
-    let mut node = node_ref.borrow_mut();
-    let picked_move = node.untried_moves[move_idx].clone();
-    let new_state = node.state.make_move(picked_move.clone(), ctx);
-    let all_moves = new_state.all_moves();
+) -> NodeId {
+    let prior = prior_policy.and_then(|p| p.prior(state, &picked_move));
+    let player_just_moved = state
+        .active_player()
+        .expect("State with move must have active player");
+    played.push((player_just_moved.clone(), picked_move.clone()));
+    state.make_move_mut(picked_move.clone(), ctx);
+
+    // Whether the game is over can't depend on hidden information, so a
+    // move that ends it has the same result in every determinization.
+    let proven = if options.solver {
+        state
+            .game_result()
+            .map(|winners| Rewards::from_winners(&winners, &state.all_players()))
+    } else {
+        None
+    };
+
+    let transposition = match (table, state.transposition_key()) {
+        (Some(table), Some(key)) => {
+            let entry = table.entry(key).or_insert_with(|| {
+                Rc::new(RefCell::new(TranspositionEntry {
+                    visits: 0,
+                    reward_sums: vec![],
+                }))
+            });
+            Some((key, entry.clone()))
+        }
+        _ => None,
+    };
 
     let new_node = SearchNode {
-        state: new_state,
-        wins: 0.0,
-        visits: 0,
+        player_to_move: state.active_player(),
+        wins: prior.map_or(0.0, |p| p.value * p.visits as f32),
+        squared_rewards: prior.map_or(0.0, |p| p.value * p.value * p.visits as f32),
+        visits: prior.map_or(0, |p| p.visits),
+        availability: 1,
+        amaf_wins: 0.0,
+        amaf_visits: 0,
+        transposition: transposition,
+        proven: proven,
+        chance_outcome: None,
         last_move: Some(picked_move),
-        untried_moves: all_moves,
-        player_just_moved: node.state
-            .active_player()
-            .expect("State with move must have active player"),
-        parent: Some(Rc::downgrade(&node_ref)),
+        player_just_moved: player_just_moved,
+        parent: None,
         children: vec![],
     };
+    tree.add_child(parent, new_node)
+}
+
+// Descends from the root, applying the selected moves to the determinized
+// state, until reaching a node with moves that are legal in this
+// determinization but not yet in the tree (or a terminal state). The nodes
+// visited, starting with the root, are left in `path`.
+fn select_node<T: SearchableState>(
+    tree: &mut SearchTree<T>,
+    state: &mut T,
+    observer: &T::P,
+    options: &SearchOptions,
+    path: &mut Vec<NodeId>,
+    played: &mut Vec<(T::P, T::M)>,
+    pruner: Option<&MovePruner<T>>,
+    rng: &mut XorShiftRng,
+    ctx: &mut T::C,
+) -> Vec<T::M> {
+    // Proofs reach past the terminal nodes only in games without hidden
+    // randomness, where the moves legal at a node are the same in every
+    // determinization. They're checked on the way down, with the moves
+    // legal here, so each visit carries them a level further up.
+    let solvable = options.solver && state.chance_outcome(observer).is_none();
+    let mut id = ROOT;
+    path.push(id);
+    loop {
+        let legal_moves = unpruned_moves(state, pruner);
+        if legal_moves.is_empty() {
+            return vec![];
+        }
+
+        if options.chance_nodes && id != ROOT && tree.nodes[id].chance_outcome.is_none() {
+            if let Some(outcome) = state.chance_outcome(observer) {
+                id = tree.outcome_child(id, outcome, state);
+                path.push(id);
+            }
+        }
+
+        if solvable {
+            tree.prove_from_children(id, &legal_moves);
+        }
+
+        let untried = tree.untried_moves(id, &legal_moves);
+        if !untried.is_empty() && options.first_play_urgency.is_none() {
+            tree.mark_children_available(id, &legal_moves);
+            return untried;
+        }
 
-    let new_node_cell = Rc::new(RefCell::new(new_node));
-    node.untried_moves.remove(move_idx);
-    node.children.push(new_node_cell.clone());
-    new_node_cell
+        // With first play urgency, untried moves are valued at the urgency
+        // and only expanded once no existing child looks better.
+        let child = match tree.select_most_promising_child(id, &legal_moves, options, rng) {
+            Some(c) => {
+                let urgency = options.first_play_urgency.unwrap_or(std::f32::MIN);
+                if untried.is_empty() || tree.nodes[c].expectation(options, rng) >= urgency {
+                    c
+                } else {
+                    return untried;
+                }
+            }
+            None => return untried,
+        };
+        let m = tree.nodes[child]
+            .last_move
+            .clone()
+            .expect("children should have last move");
+        played.push((tree.nodes[child].player_just_moved.clone(), m.clone()));
+        state.make_move_mut(m, ctx);
+        id = child;
+        path.push(id);
+    }
 }
 
-fn best_unexplored_node<T: SearchableState>(node_ref: &NodeRef<T>) -> NodeRef<T> {
-    let mut node = node_ref.borrow_mut();
-    if node.untried_moves.is_empty() && !node.children.is_empty() {
-        let child_ref = node.select_most_promising_child();
-        best_unexplored_node(&child_ref)
-    } else {
-        node_ref.clone()
+// Picks moves during the rollout phase of a search, from a newly expanded
+// node until the game ends. Returning None ends the rollout, and should only
+// happen when the state has no moves.
+pub trait RolloutPolicy<T: SearchableState> {
+    fn choose_move(&mut self, state: &T, rng: &mut XorShiftRng) -> Option<T::M>;
+}
+
+pub struct RandomRollout;
+
+// Follows a policy, but makes a random legal move instead with probability
+// epsilon, in between the policy's biased playouts and uniform ones.
+pub struct EpsilonGreedyRollout<'a, T: SearchableState + 'a> {
+    pub policy: &'a mut RolloutPolicy<T>,
+    pub epsilon: f32,
+}
+
+impl<'a, T: SearchableState> RolloutPolicy<T> for EpsilonGreedyRollout<'a, T> {
+    fn choose_move(&mut self, state: &T, rng: &mut XorShiftRng) -> Option<T::M> {
+        if rng.gen::<f32>() < self.epsilon {
+            RandomRollout.choose_move(state, rng)
+        } else {
+            self.policy.choose_move(state, rng)
+        }
     }
 }
 
-fn choose_random_move<T: SearchableState>(state: &T, rng: &mut XorShiftRng) -> Option<T::M> {
-    let possible_moves = state.all_moves();
-    if possible_moves.is_empty() {
-        None
+// Plays the searching player's moves with their own rollout policy and
+// everyone else's with a model of the opponents, e.g. a known baseline.
+pub struct OpponentModelRollout<'a, T: SearchableState + 'a> {
+    pub player: T::P,
+    pub own: &'a mut RolloutPolicy<T>,
+    pub opponents: &'a mut RolloutPolicy<T>,
+}
+
+impl<'a, T: SearchableState> RolloutPolicy<T> for OpponentModelRollout<'a, T> {
+    fn choose_move(&mut self, state: &T, rng: &mut XorShiftRng) -> Option<T::M> {
+        if state.active_player().as_ref() == Some(&self.player) {
+            self.own.choose_move(state, rng)
+        } else {
+            self.opponents.choose_move(state, rng)
+        }
+    }
+}
+
+// Pseudo-counts a new node starts with, as if it had already been visited
+// `visits` times with an average reward of `value` for the player moving.
+#[derive(Clone, Copy, Debug)]
+pub struct Prior {
+    pub value: f32,
+    pub visits: i32,
+}
+
+// Supplies domain knowledge about moves before they've been searched, so
+// promising moves get explored first without pruning the others.
+pub trait PriorPolicy<T: SearchableState> {
+    fn prior(&self, state: &T, m: &T::M) -> Option<Prior>;
+}
+
+// Rules out moves known to be no better than another legal move, trading
+// the search's completeness for spending its budget elsewhere.
+pub trait MovePruner<T: SearchableState> {
+    fn is_pruned(&self, state: &T, m: &T::M) -> bool;
+}
+
+// The moves a search considers. If every legal move is pruned, none are.
+fn unpruned_moves<T: SearchableState>(state: &T, pruner: Option<&MovePruner<T>>) -> Vec<T::M> {
+    let moves = state.all_moves();
+    let pruner = match pruner {
+        Some(p) => p,
+        None => return moves,
+    };
+    let kept = moves
+        .iter()
+        .filter(|m| !pruner.is_pruned(state, m))
+        .cloned()
+        .collect::<Vec<_>>();
+    if kept.is_empty() {
+        moves
     } else {
-        rng.choose(&possible_moves).cloned()
+        kept
+    }
+}
+
+// Replaces a policy's pruned moves with a random unpruned one.
+struct PrunedRollout<'a, T: SearchableState + 'a> {
+    policy: &'a mut RolloutPolicy<T>,
+    pruner: &'a MovePruner<T>,
+}
+
+impl<'a, T: SearchableState> RolloutPolicy<T> for PrunedRollout<'a, T> {
+    fn choose_move(&mut self, state: &T, rng: &mut XorShiftRng) -> Option<T::M> {
+        let m = self.policy.choose_move(state, rng)?;
+        if !self.pruner.is_pruned(state, &m) {
+            return Some(m);
+        }
+        rng.choose(&unpruned_moves(state, Some(self.pruner)))
+            .cloned()
+            .or(Some(m))
+    }
+}
+
+// The pluggable parts of a search: how rollouts pick moves, how positions
+// are scored when rollouts stop early, how new nodes are seeded, and which
+// moves are left out altogether.
+pub struct SearchPolicies<'a, T: SearchableState + 'a> {
+    pub rollout: &'a mut RolloutPolicy<T>,
+    pub evaluator: Option<&'a Evaluator<T>>,
+    pub prior: Option<&'a PriorPolicy<T>>,
+    pub pruner: Option<&'a MovePruner<T>>,
+}
+
+impl<T: SearchableState> RolloutPolicy<T> for RandomRollout {
+    fn choose_move(&mut self, state: &T, rng: &mut XorShiftRng) -> Option<T::M> {
+        let possible_moves = state.all_moves();
+        if possible_moves.is_empty() {
+            None
+        } else {
+            rng.choose(&possible_moves).cloned()
+        }
     }
 }
 
 fn simulate_until_terminal<T: SearchableState>(
     state: T,
+    policy: &mut RolloutPolicy<T>,
+    turn_limit: Option<i32>,
+    mut played: Option<&mut Vec<(T::P, T::M)>>,
     rng: &mut XorShiftRng,
     ctx: &mut T::C,
 ) -> T {
+    let last_turn = turn_limit.map(|n| state.turn() + n);
     let mut mut_state = state;
-    while let Some(m) = choose_random_move(&mut_state, rng) {
-        mut_state.make_move_mut(m, ctx);
+    while last_turn.map_or(true, |t| mut_state.turn() < t) {
+        match policy.choose_move(&mut_state, rng) {
+            Some(m) => {
+                if let Some(ref mut played) = played {
+                    if let Some(p) = mut_state.active_player() {
+                        played.push((p, m.clone()));
+                    }
+                }
+                mut_state.make_move_mut(m, ctx)
+            }
+            None => break,
+        }
     }
     mut_state
 }
 
-pub fn find_best_move<T: SearchableState>(
-    root_state: T,
-    max_iters: i32,
+// Prints the most visited move so far and how it's doing.
+fn report_progress<T: SearchableState>(tree: &SearchTree<T>, iterations: i32, started: Stopwatch) {
+    if tree.root().children.is_empty() {
+        return;
+    }
+    let best = &tree.nodes[tree.best_child(ROOT)];
+    let elapsed = started.elapsed();
+    println!(
+        "  {} iterations, {}.{:03}s: best {} ({} visits, {:.1}%)",
+        iterations,
+        elapsed.as_secs(),
+        elapsed.subsec_millis(),
+        tree.state.printable_move(best.last_move.as_ref().unwrap()),
+        best.visits,
+        100.0 * best.wins / best.visits as f32
+    );
+}
+
+// Whether the search should stop before its budget runs out. Every search
+// runs at least one iteration, so it always has a move to return.
+fn is_interrupted(options: &SearchOptions, iterations: i32) -> bool {
+    match options.interrupt {
+        Some(flag) if iterations > 0 => flag.swap(false, Ordering::SeqCst),
+        _ => false,
+    }
+}
+
+fn rollout_rewards<T: SearchableState>(
+    end_state: &T,
+    evaluator: Option<&Evaluator<T>>,
+) -> Rewards<T::P> {
+    match end_state.game_result() {
+        Some(winners) => Rewards::from_winners(&winners, &end_state.all_players()),
+        None => evaluator
+            .expect("Rollout stopped early without an evaluator")
+            .evaluate(end_state),
+    }
+}
+
+// Where a search's budget went. Counts from several searches can be added
+// together, such as every decision of one game.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchStats {
+    pub searches: u32,
+    pub iterations: u64,
+    pub nodes_expanded: u64,
+    pub rollouts: u64,
+    // Deepest node reached below the root by selection and expansion.
+    pub max_depth: usize,
+    pub elapsed: Duration,
+    // Children and nodes with children in the finished tree, from which
+    // the effective branching factor is measured.
+    pub tree_children: u64,
+    pub interior_nodes: u64,
+    // Searches stopped by an interrupt before their budget ran out.
+    pub interrupted: u32,
+}
+
+impl SearchStats {
+    pub fn effective_branching_factor(&self) -> f32 {
+        match self.interior_nodes {
+            0 => 0.0,
+            n => self.tree_children as f32 / n as f32,
+        }
+    }
+
+    pub fn add(&mut self, other: &SearchStats) {
+        self.searches += other.searches;
+        self.iterations += other.iterations;
+        self.nodes_expanded += other.nodes_expanded;
+        self.rollouts += other.rollouts;
+        self.max_depth = std::cmp::max(self.max_depth, other.max_depth);
+        self.elapsed += other.elapsed;
+        self.tree_children += other.tree_children;
+        self.interior_nodes += other.interior_nodes;
+        self.interrupted += other.interrupted;
+    }
+
+    fn record_tree<T: SearchableState>(&mut self, tree: &SearchTree<T>) {
+        for node in tree.nodes.iter().filter(|n| !n.children.is_empty()) {
+            self.tree_children += node.children.len() as u64;
+            self.interior_nodes += 1;
+        }
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let millis = self.elapsed.as_secs() * 1000 + self.elapsed.subsec_millis() as u64;
+        write!(
+            f,
+            "{} iterations, {} nodes expanded, {} rollouts, max depth {}, branching {:.1}, {}ms",
+            self.iterations,
+            self.nodes_expanded,
+            self.rollouts,
+            self.max_depth,
+            self.effective_branching_factor(),
+            millis
+        )?;
+        match self.interrupted {
+            0 => Ok(()),
+            n => write!(f, ", {} interrupted", n),
+        }
+    }
+}
+
+// Runs search iterations on the tree, which may hold statistics from an
+// earlier search, until the budget runs out. Hidden information is sampled
+// from the observer's point of view, who needn't be the player to move.
+//
+// The search's own choices come from rng, so with an iteration budget, a
+// seeded rng and a seeded context, searches are reproducible.
+pub fn grow_tree<T: SearchableState>(
+    tree: &mut SearchTree<T>,
+    root_state: &T,
+    observer: &T::P,
+    budget: SearchBudget,
+    options: &SearchOptions,
+    policies: &mut SearchPolicies<T>,
+    rng: &mut XorShiftRng,
     ctx: &mut T::C,
-    debug: bool,
-) -> T::M {
-    let mut rng = util::randomly_seeded_weak_rng();
-    let untried = root_state.all_moves();
+) -> SearchStats {
+    let mut table = HashMap::new();
+    if options.use_transpositions {
+        tree.collect_transpositions(&mut table);
+    }
 
-    // Start with last player as having moved. Not meaningful for >2P games.
-    let just_moved: T::P = root_state
-        .all_players()
-        .last()
-        .cloned()
-        .expect("Players must not be empty");
-    let root_node = Rc::new(RefCell::new(SearchNode {
-        state: root_state,
-        wins: 0.0,
-        visits: 0,
-        last_move: None,
-        untried_moves: untried,
-        player_just_moved: just_moved,
-        parent: None,
-        children: vec![],
-    }));
+    let evaluator = policies.evaluator;
+    let mut pruned_rollout;
+    let rollout_policy: &mut RolloutPolicy<T> = match policies.pruner {
+        Some(pruner) => {
+            pruned_rollout = PrunedRollout {
+                policy: &mut *policies.rollout,
+                pruner: pruner,
+            };
+            &mut pruned_rollout
+        }
+        None => &mut *policies.rollout,
+    };
+
+    // Reused across iterations to avoid reallocating.
+    let mut path = vec![];
+    let mut played = vec![];
+
+    let started = Stopwatch::start();
+    let mut iterations = 0;
+    let mut stats = SearchStats {
+        searches: 1,
+        ..Default::default()
+    };
+    let mut last_report = (0, started);
+    // The budget is only checked after the first iteration, so even a search
+    // with none has a move to return.
+    while iterations == 0 || !budget.is_exhausted(iterations, started) {
+        if is_interrupted(options, iterations) {
+            stats.interrupted = 1;
+            break;
+        }
+        if let Some(interval) = options.report_interval {
+            let (report_iterations, report_time) = last_report;
+            if interval.is_exhausted(iterations - report_iterations, report_time) {
+                report_progress(tree, iterations, started);
+                last_report = (iterations, Stopwatch::start());
+            }
+        }
+
+        iterations += 1;
+        path.clear();
+        played.clear();
+
+        if let Some(max_nodes) = options.max_nodes {
+            if tree.nodes.len() >= max_nodes {
+                tree.prune(std::cmp::max(1, max_nodes * 3 / 4));
+            }
+        }
+
+        // Determinize, so each iteration samples hidden information afresh
+        // while sharing statistics across every state the player can't
+        // distinguish from the real one.
+        let mut state = root_state.determinize(observer, ctx);
 
-    for _ in 0..max_iters {
         // Select
-        let mut node_ref = best_unexplored_node(&root_node);
+        let untried = select_node(
+            tree,
+            &mut state,
+            observer,
+            options,
+            &mut path,
+            &mut played,
+            policies.pruner,
+            rng,
+            ctx,
+        );
 
         // Expand
-        if !node_ref.borrow().untried_moves.is_empty() {
-            let move_idx = rng.gen_range(0, node_ref.borrow().untried_moves.len());
-            let child_ref = expand_node_by_move(node_ref, move_idx, ctx);
-            node_ref = child_ref;
+        if !untried.is_empty() {
+            let picked_move = untried[rng.gen_range(0, untried.len())].clone();
+            let table = if options.use_transpositions {
+                Some(&mut table)
+            } else {
+                None
+            };
+            let parent = *path.last().unwrap();
+            let child = expand_node_by_move(
+                tree,
+                parent,
+                picked_move,
+                &mut state,
+                &mut played,
+                table,
+                policies.prior,
+                options,
+                ctx,
+            );
+            path.push(child);
+            stats.nodes_expanded += 1;
         }
+        stats.max_depth = std::cmp::max(stats.max_depth, path.len() - 1);
 
-        // Rollout
-        let start_state = node_ref.borrow().state.clone();
-        let end_state = simulate_until_terminal(start_state, &mut rng, ctx);
-        let result = end_state
-            .game_result()
-            .expect("Terminal game state is missing a result");
+        // Rollout. The last rollout plays out the iteration's own state, so
+        // with one rollout per leaf the state is never copied.
+        let results = {
+            let mut rollout = |start: T| {
+                let rollout_moves = if options.rave_equivalence.is_some() {
+                    Some(&mut played)
+                } else {
+                    None
+                };
+                let end_state = simulate_until_terminal(
+                    start,
+                    rollout_policy,
+                    options.rollout_turn_limit,
+                    rollout_moves,
+                    rng,
+                    ctx,
+                );
+                let rewards = rollout_rewards(&end_state, evaluator);
+                let rewards = options.reward_shaping.shape(&end_state, rewards);
+                match options.turn_discount {
+                    Some(d) => rewards.discounted(d, end_state.turn() - root_state.turn()),
+                    None => rewards,
+                }
+            };
+            let mut results = (1..options.rollouts_per_leaf)
+                .map(|_| rollout(state.clone()))
+                .collect::<Vec<_>>();
+            results.push(rollout(state));
+            results
+        };
+        stats.rollouts += results.len() as u64;
 
         // Backpropagate
-        node_ref.borrow_mut().update_with_result(&result);
-        for n_ref in node_ref.borrow().ancestors() {
-            n_ref.borrow_mut().update_with_result(&result);
+        for &id in path.iter() {
+            tree.nodes[id].update_with_results(&results);
+        }
+
+        if options.rave_equivalence.is_some() {
+            // A node d moves below the root saw every move from played[d]
+            // onwards. Chance nodes are as deep as the move above them.
+            let mut depth = 0;
+            for &id in path.iter() {
+                if id != ROOT && tree.nodes[id].chance_outcome.is_none() {
+                    depth += 1;
+                }
+                tree.update_amaf(id, &played[depth..], &results);
+            }
         }
     }
 
-    let borrowed_root = root_node.borrow();
-    if debug {
-        borrowed_root.print_debug_move_tree();
+    stats.iterations = iterations as u64;
+    stats.elapsed = started.elapsed();
+    stats.record_tree(tree);
+    stats
+}
+
+// Grows the tree from root_state for the player to move, and returns the
+// child for the best move.
+pub fn search_tree<T: SearchableState>(
+    tree: &mut SearchTree<T>,
+    root_state: T,
+    options: &SearchOptions,
+    policies: &mut SearchPolicies<T>,
+    rng: &mut XorShiftRng,
+    ctx: &mut T::C,
+) -> NodeId {
+    let observer = root_state
+        .active_player()
+        .expect("search_tree called on a finished game");
+    grow_tree(
+        tree,
+        &root_state,
+        &observer,
+        options.budget,
+        options,
+        policies,
+        rng,
+        ctx,
+    );
+
+    if options.debug {
+        tree.print_debug_move_tree();
     }
 
-    let best_child = borrowed_root.most_visited_child();
-    let best_move = best_child.borrow().last_move.as_ref().unwrap().clone();
+    tree.best_child(ROOT)
+}
+
+#[allow(dead_code)]
+pub fn find_best_move<T: SearchableState>(
+    root_state: T,
+    options: &SearchOptions,
+    rng: &mut XorShiftRng,
+    ctx: &mut T::C,
+) -> T::M {
+    let mut tree = SearchTree::new(&root_state);
+    let mut policies = SearchPolicies {
+        rollout: &mut RandomRollout,
+        evaluator: None,
+        prior: None,
+        pruner: None,
+    };
+    let best_child = search_tree(&mut tree, root_state, options, &mut policies, rng, ctx);
+    let best_move = tree.nodes[best_child].last_move.as_ref().unwrap().clone();
     best_move
 }
@@ -1,44 +1,243 @@
-use tree_search::{NodeStats, SearchNode, SearchableState};
+use tree_search::{NodeId, NodeStats, SearchNode, SearchTree, SearchableState, ROOT};
 
-impl<T: SearchableState> SearchNode<T> {
+// Formats the top of a search tree can be exported in, for inspecting a
+// decision after the fact.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TreeFormat {
+    Dot,
+    Json,
+}
+
+impl TreeFormat {
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            TreeFormat::Dot => "dot",
+            TreeFormat::Json => "json",
+        }
+    }
+}
+
+// Quotes a string for use in a DOT label or a JSON document, which escape
+// the same characters.
+fn quoted(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl<T: SearchableState> SearchTree<T> {
+    // The move that led to a node, or for a chance node, its outcome.
+    fn describe_edge(&self, node: &SearchNode<T>) -> String {
+        match (node.last_move.as_ref(), node.chance_outcome) {
+            (Some(m), _) => self.state.printable_move(m),
+            (None, Some(outcome)) => format!("Outcome {:016x}", outcome),
+            (None, None) => "Root".into(),
+        }
+    }
+
+    // Prints each node along the principal variation with its children's
+    // statistics.
     pub fn print_debug_move_tree(&self) {
-        println!("  {:?} --", self.state);
-        if let Some(p) = self.state.active_player() {
-            println!(
-                "    Moves for {}: ",
-                self.state.printable_player_identifier(&p)
-            );
+        let mut id = ROOT;
+        loop {
+            let node = &self.nodes[id];
+            match id {
+                ROOT => println!("  {:?} --", self.state),
+                _ => println!("  After {} --", self.describe_edge(node)),
+            }
+            match node.player_to_move {
+                Some(ref p) => println!(
+                    "    Moves for {}: ",
+                    self.state.printable_player_identifier(p)
+                ),
+                None => {
+                    println!("    ...game is over");
+                    return;
+                }
+            }
 
-            self.print_child_move_stats();
+            self.print_child_move_stats(id);
 
-            if !self.children.is_empty() {
-                let child = self.most_visited_child();
-                child.borrow().print_debug_move_tree();
-            } else {
+            if node.children.is_empty() {
                 println!("    ...tree is exhausted");
+                return;
             }
-        } else {
-            println!("    ...game is over");
+            id = self.most_visited_child(id);
         }
     }
 
-    pub fn print_child_move_stats(&self) {
-        let mut child_stats: Vec<NodeStats<T>> =
-            self.children.iter().map(|c| c.borrow().stats()).collect();
+    pub fn print_child_move_stats(&self, id: NodeId) {
+        let node = &self.nodes[id];
+        let mut child_stats: Vec<(NodeId, NodeStats<T>)> = node.children
+            .iter()
+            .map(|&c| (c, self.nodes[c].stats()))
+            .collect();
 
         // Reverse so in descending order
-        child_stats.sort_by(|a, b| (b.percent_won).partial_cmp(&a.percent_won).unwrap());
+        child_stats.sort_by(|a, b| (b.1.percent_won).partial_cmp(&a.1.percent_won).unwrap());
 
-        for stat in child_stats.iter() {
+        for &(c, ref stat) in child_stats.iter() {
             println!(
-                "    {:?}: won {} / {} ({:.2}%) visits",
-                stat.last_move
-                    .as_ref()
-                    .expect("children should have last move"),
+                "    {}: won {} / {} ({:.2}%) visits",
+                match stat.last_move {
+                    Some(ref m) => self.state.printable_move(m),
+                    None => self.describe_edge(&self.nodes[c]),
+                },
                 stat.wins,
                 stat.visits,
                 100.0 * stat.percent_won as f32
             );
         }
     }
+
+    // Nodes along the most visited path from the root, the line the search
+    // expects to be played.
+    pub fn principal_variation(&self) -> Vec<NodeId> {
+        let mut line = vec![];
+        let mut id = ROOT;
+        while !self.nodes[id].children.is_empty() {
+            id = self.most_visited_child(id);
+            line.push(id);
+        }
+        line
+    }
+
+    // Renders the root and its descendants down to max_depth moves, with
+    // the principal variation highlighted.
+    pub fn export_tree(&self, format: TreeFormat, max_depth: usize) -> String {
+        let root = self.root();
+        let mut out = String::new();
+        match format {
+            TreeFormat::Dot => {
+                out.push_str("digraph search_tree {\n");
+                out.push_str("  node [shape=box];\n");
+                let label = format!(
+                    "{} to move\n{} visits",
+                    self.player_to_move(root),
+                    root.visits
+                );
+                out.push_str(&format!("  n0 [label={}];\n", quoted(&label)));
+                let mut next_id = 1;
+                self.write_dot_children(ROOT, 0, &mut next_id, true, max_depth, &mut out);
+                out.push_str("}\n");
+            }
+            TreeFormat::Json => {
+                let mut pv = vec![];
+                for &id in self.principal_variation().iter() {
+                    pv.push(quoted(&self.describe_edge(&self.nodes[id])));
+                }
+                out.push_str(&format!(
+                    "{{\"player\": {}, \"visits\": {}, \"principal_variation\": [{}], \
+                     \"children\": ",
+                    quoted(&self.player_to_move(root)),
+                    root.visits,
+                    pv.join(", ")
+                ));
+                self.write_json_children(ROOT, max_depth, &mut out);
+                out.push_str("}\n");
+            }
+        }
+        out
+    }
+
+    fn player_to_move(&self, node: &SearchNode<T>) -> String {
+        match node.player_to_move {
+            Some(ref p) => self.state.printable_player_identifier(p),
+            None => "Nobody".into(),
+        }
+    }
+
+    fn children_by_visits(&self, id: NodeId) -> Vec<NodeId> {
+        let mut children = self.nodes[id].children.clone();
+        children.sort_by(|&a, &b| self.nodes[b].visits.cmp(&self.nodes[a].visits));
+        children
+    }
+
+    fn write_dot_children(
+        &self,
+        id: NodeId,
+        dot_id: usize,
+        next_dot_id: &mut usize,
+        on_pv: bool,
+        depth_left: usize,
+        out: &mut String,
+    ) {
+        if depth_left == 0 {
+            return;
+        }
+
+        for (i, &c) in self.children_by_visits(id).iter().enumerate() {
+            let child = &self.nodes[c];
+            let child_dot_id = *next_dot_id;
+            *next_dot_id += 1;
+
+            let label = format!(
+                "{}\n{} visits, {:.1}%",
+                self.describe_edge(child),
+                child.visits,
+                100.0 * win_rate(child)
+            );
+            out.push_str(&format!("  n{} [label={}];\n", child_dot_id, quoted(&label)));
+
+            let child_on_pv = on_pv && i == 0;
+            let edge_style = if child_on_pv {
+                " [penwidth=3, color=red]"
+            } else {
+                ""
+            };
+            out.push_str(&format!("  n{} -> n{}{};\n", dot_id, child_dot_id, edge_style));
+
+            self.write_dot_children(
+                c,
+                child_dot_id,
+                next_dot_id,
+                child_on_pv,
+                depth_left - 1,
+                out,
+            );
+        }
+    }
+
+    fn write_json_children(&self, id: NodeId, depth_left: usize, out: &mut String) {
+        let node = &self.nodes[id];
+        out.push('[');
+        if depth_left > 0 {
+            for (i, &c) in self.children_by_visits(id).iter().enumerate() {
+                let child = &self.nodes[c];
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(&format!(
+                    "{{\"move\": {}, \"player\": {}, \"visits\": {}, \"win_rate\": {}, \
+                     \"children\": ",
+                    quoted(&self.describe_edge(child)),
+                    quoted(&self.player_to_move(node)),
+                    child.visits,
+                    win_rate(child)
+                ));
+                self.write_json_children(c, depth_left - 1, out);
+                out.push('}');
+            }
+        }
+        out.push(']');
+    }
+}
+
+
+// Children are scored as soon as they're expanded, but guard against
+// unvisited ones so exports never contain NaN.
+fn win_rate<T: SearchableState>(node: &SearchNode<T>) -> f32 {
+    if node.visits > 0 {
+        node.wins / node.visits as f32
+    } else {
+        0.0
+    }
 }
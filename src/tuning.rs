@@ -0,0 +1,174 @@
+use rand::{Rng, XorShiftRng};
+
+// A search option to tune, named as on the command line, within the range
+// of values worth trying.
+#[derive(Clone, Debug)]
+pub struct TunedParameter {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub initial: f32,
+}
+
+impl TunedParameter {
+    fn normalize(&self, value: f32) -> f32 {
+        (value - self.min) / (self.max - self.min)
+    }
+
+    fn denormalize(&self, x: f32) -> f32 {
+        self.min + x * (self.max - self.min)
+    }
+}
+
+// The SearchDecider options worth tuning by default.
+pub fn search_parameters() -> Vec<TunedParameter> {
+    vec![
+        TunedParameter {
+            name: "c",
+            min: 0.1,
+            max: 3.0,
+            initial: std::f32::consts::SQRT_2,
+        },
+        TunedParameter {
+            name: "fpu",
+            min: 0.0,
+            max: 1.0,
+            initial: 0.5,
+        },
+        TunedParameter {
+            name: "margin",
+            min: 0.0,
+            max: 0.5,
+            initial: 0.0,
+        },
+        TunedParameter {
+            name: "epsilon",
+            min: 0.0,
+            max: 1.0,
+            initial: 0.1,
+        },
+    ]
+}
+
+// Gain sequences from Spall's guidelines for SPSA, with values tuned for
+// parameters normalized to [0, 1] and noisy single-match comparisons.
+const SPSA_A: f32 = 0.1;
+const SPSA_C: f32 = 0.2;
+const SPSA_STABILITY: f32 = 10.0;
+
+// Tunes parameters with simultaneous perturbation stochastic approximation.
+// Each iteration perturbs every parameter at once in a random direction,
+// plays the two resulting configurations against each other, and steps
+// towards the winner. play_match is given the values of both configurations
+// and returns the first's share of the points.
+//
+// Returns the tuned values, in the order of params.
+pub fn spsa(
+    params: &[TunedParameter],
+    iterations: usize,
+    rng: &mut XorShiftRng,
+    play_match: &mut FnMut(&[f32], &[f32]) -> f32,
+    silent: bool,
+) -> Vec<f32> {
+    let mut theta = params
+        .iter()
+        .map(|p| p.normalize(p.initial))
+        .collect::<Vec<_>>();
+    let values = |xs: &[f32]| {
+        params
+            .iter()
+            .zip(xs.iter())
+            .map(|(p, x)| p.denormalize(x.max(0.0).min(1.0)))
+            .collect::<Vec<_>>()
+    };
+
+    for k in 0..iterations {
+        let a = SPSA_A / (k as f32 + 1.0 + SPSA_STABILITY).powf(0.602);
+        let c = SPSA_C / (k as f32 + 1.0).powf(0.101);
+        let delta = params
+            .iter()
+            .map(|_| if rng.gen() { 1.0 } else { -1.0 })
+            .collect::<Vec<f32>>();
+
+        let plus = theta
+            .iter()
+            .zip(delta.iter())
+            .map(|(t, d)| t + c * d)
+            .collect::<Vec<_>>();
+        let minus = theta
+            .iter()
+            .zip(delta.iter())
+            .map(|(t, d)| t - c * d)
+            .collect::<Vec<_>>();
+
+        // The minus configuration scores 1 - share, so the difference between
+        // the two is 2 * share - 1.
+        let share = play_match(&values(&plus), &values(&minus));
+        for (t, d) in theta.iter_mut().zip(delta.iter()) {
+            *t = (*t + a * (2.0 * share - 1.0) / (2.0 * c * d))
+                .max(0.0)
+                .min(1.0);
+        }
+
+        if !silent {
+            let current = values(&theta)
+                .iter()
+                .zip(params.iter())
+                .map(|(v, p)| format!("{}={:.3}", p.name, v))
+                .collect::<Vec<_>>();
+            println!(
+                "Iteration {}: plus scored {:.2}, now {}",
+                k + 1,
+                share,
+                current.join(",")
+            );
+        }
+    }
+
+    values(&theta)
+}
+
+// Writes values as a config file for the tactician player's config option.
+pub fn config_file_contents(params: &[TunedParameter], values: &[f32]) -> String {
+    let mut out = String::from("# Search options tuned by `tactician tune`\n");
+    for (p, v) in params.iter().zip(values.iter()) {
+        out.push_str(&format!("{}={}\n", p.name, v));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use tuning::*;
+    use util::seeded_weak_rng;
+
+    #[test]
+    fn test_spsa() {
+        let params = vec![
+            TunedParameter {
+                name: "x",
+                min: 0.0,
+                max: 10.0,
+                initial: 2.0,
+            },
+        ];
+
+        // Configurations closer to 7 always win.
+        let mut play_match = |a: &[f32], b: &[f32]| {
+            if (a[0] - 7.0).abs() < (b[0] - 7.0).abs() {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        let mut rng = seeded_weak_rng(3);
+        let tuned = spsa(&params, 300, &mut rng, &mut play_match, true);
+        assert!((tuned[0] - 7.0).abs() < 1.0, "tuned to {}", tuned[0]);
+
+        assert_eq!(
+            config_file_contents(&params, &tuned[..]).lines().count(),
+            2
+        );
+    }
+}
@@ -1,4 +1,11 @@
-use rand::{thread_rng, Rng, SeedableRng, XorShiftRng};
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+use js_sys;
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+use rand::{thread_rng, Rng};
+use rand::{SeedableRng, XorShiftRng};
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+use std::time::Instant;
+use std::time::Duration;
 
 pub fn subtract_vector<T: Eq>(vs: &mut Vec<T>, s: &Vec<T>) {
     for x in s.iter() {
@@ -9,6 +16,7 @@ pub fn subtract_vector<T: Eq>(vs: &mut Vec<T>, s: &Vec<T>) {
     }
 }
 
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
 pub fn randomly_seeded_weak_rng() -> XorShiftRng {
     let mut base_rng = thread_rng();
     let seed = &[
@@ -19,3 +27,147 @@ pub fn randomly_seeded_weak_rng() -> XorShiftRng {
     ];
     XorShiftRng::from_seed(*seed)
 }
+
+// In a browser the operating system's randomness, which thread_rng needs,
+// is out of reach, so the seed comes from JavaScript.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub fn randomly_seeded_weak_rng() -> XorShiftRng {
+    let word = || (js_sys::Math::random() * 4294967296.0) as u32;
+    XorShiftRng::from_seed([word(), word(), word(), word() | 1])
+}
+
+// Times searches. Instant panics in a browser, so there the time comes from
+// JavaScript's clock instead.
+#[derive(Clone, Copy)]
+pub struct Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    started: Instant,
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    started_millis: f64,
+}
+
+impl Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    pub fn start() -> Stopwatch {
+        Stopwatch {
+            started: Instant::now(),
+        }
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    pub fn start() -> Stopwatch {
+        Stopwatch {
+            started_millis: js_sys::Date::now(),
+        }
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    pub fn elapsed(&self) -> Duration {
+        let millis = (js_sys::Date::now() - self.started_millis).max(0.0);
+        Duration::from_millis(millis as u64)
+    }
+}
+
+// Deterministic generator for reproducible runs. The constant words keep
+// the state from ever being all zeros, which XorShift can't leave.
+pub fn seeded_weak_rng(seed: u32) -> XorShiftRng {
+    XorShiftRng::from_seed([0x193a_6754, seed, 0xa8a7_d469, 0x9783_0e05])
+}
+
+// Parses durations like "500ms" or "2s". Bare numbers are milliseconds.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let (digits, millis_per_unit) = if s.ends_with("ms") {
+        (&s[..s.len() - 2], 1)
+    } else if s.ends_with("s") {
+        (&s[..s.len() - 1], 1000)
+    } else {
+        (s, 1)
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .map(|n| Duration::from_millis(n * millis_per_unit))
+}
+
+// How many single-character insertions, deletions and substitutions, and
+// swaps of neighbouring characters, it takes to turn one string into the
+// other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..a.len() + 1 {
+        d[i][0] = i;
+    }
+    for j in 0..b.len() + 1 {
+        d[0][j] = j;
+    }
+    for i in 1..a.len() + 1 {
+        for j in 1..b.len() + 1 {
+            let substituted = d[i - 1][j - 1] + (a[i - 1] != b[j - 1]) as usize;
+            d[i][j] = substituted.min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// The candidate a mistyped name was most likely meant to be, if any is
+// close enough, for suggesting in error messages.
+pub fn closest_match<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let name = name.to_lowercase();
+    let allowed = (name.len() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|c| (edit_distance(&name, &c.to_lowercase()), c))
+        .filter(|&(d, _)| d <= allowed)
+        .min_by_key(|&(d, _)| d)
+        .map(|(_, c)| c)
+}
+
+// Quotes an argument for a POSIX shell, if it needs it, so printed
+// commands can be pasted back in.
+pub fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_+-=:,./@%".contains(c);
+    match !arg.is_empty() && arg.chars().all(plain) {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
+    }
+}
+
+#[test]
+fn test_closest_match() {
+    let names = ["tactician", "bigmoney", "random"];
+    assert_eq!(closest_match("tactitian", names.iter().cloned()), Some("tactician"));
+    assert_eq!(closest_match("BigMony", names.iter().cloned()), Some("bigmoney"));
+    assert_eq!(closest_match("human", names.iter().cloned()), None);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("plian", "plain"), 1);
+}
+
+#[test]
+fn test_shell_quote() {
+    assert_eq!(shell_quote("tactician:iters=500,c=0.7"), "tactician:iters=500,c=0.7");
+    assert_eq!(shell_quote("process:cmd=python3 bot.py"), "'process:cmd=python3 bot.py'");
+    assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    assert_eq!(shell_quote(""), "''");
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+    assert_eq!(parse_duration("2s"), Some(Duration::from_millis(2000)));
+    assert_eq!(parse_duration("250"), Some(Duration::from_millis(250)));
+    assert_eq!(parse_duration("fast"), None);
+}
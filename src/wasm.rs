@@ -0,0 +1,61 @@
+use wasm_bindgen::prelude::*;
+
+use embedded_game::EmbeddedGame;
+
+// The engine and its search for JavaScript, with the wasm feature. Players,
+// moves and states are JSON strings, as the JSON-RPC server takes them:
+//
+//     const game = new Game('["You", "Tactician"]', "", seed);
+//     JSON.parse(game.legalMoves());
+//     game.applyMove(game.aiChoose(5000));
+//
+// Games are always waiting on a decision or over. Errors are thrown as
+// strings.
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    inner: EmbeddedGame,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    // Deals a game between players named by a JSON list, with a kingdom as
+    // --kingdom takes it. Empty strings give two players and the first-game
+    // kingdom.
+    #[wasm_bindgen(constructor)]
+    pub fn new(players: &str, kingdom: &str, seed: u32) -> Result<WasmGame, JsValue> {
+        let inner = EmbeddedGame::new(players, kingdom, seed).map_err(JsValue::from)?;
+        Ok(WasmGame { inner: inner })
+    }
+
+    // Picks up a game saved by state().
+    pub fn load(state: &str, seed: u32) -> Result<WasmGame, JsValue> {
+        let inner = EmbeddedGame::load(state, seed).map_err(JsValue::from)?;
+        Ok(WasmGame { inner: inner })
+    }
+
+    // The whole game, or with a seat, only what that seat can see.
+    pub fn state(&mut self, seat: Option<u32>) -> Result<String, JsValue> {
+        self.inner.state(seat.map(|s| s as usize)).map_err(JsValue::from)
+    }
+
+    pub fn status(&self) -> String {
+        self.inner.status()
+    }
+
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> String {
+        self.inner.legal_moves()
+    }
+
+    #[wasm_bindgen(js_name = applyMove)]
+    pub fn apply_move(&mut self, choice: &str) -> Result<(), JsValue> {
+        self.inner.apply_move(choice).map_err(JsValue::from)
+    }
+
+    // The move Tactician picks with a search of so many iterations. Searches
+    // run on the calling thread, so pages should call this from a worker.
+    #[wasm_bindgen(js_name = aiChoose)]
+    pub fn ai_choose(&mut self, iterations: u32) -> Result<String, JsValue> {
+        self.inner.ai_choose(iterations).map_err(JsValue::from)
+    }
+}
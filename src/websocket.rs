@@ -0,0 +1,232 @@
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+// Just enough of WebSocket (RFC 6455) for a server to swap text messages
+// with browsers and other clients: the opening handshake, and text, ping
+// and close frames. Extensions and binary messages aren't supported.
+
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Messages are lines of JSON, so anything bigger is a mistake or an attack.
+const MAX_MESSAGE_LEN: u64 = 1 << 24;
+
+pub const CONTINUATION: u8 = 0x0;
+pub const TEXT: u8 = 0x1;
+pub const CLOSE: u8 = 0x8;
+pub const PING: u8 = 0x9;
+pub const PONG: u8 = 0xa;
+
+// The Sec-WebSocket-Accept header answering a client's key.
+fn accept_key(key: &str) -> String {
+    let digest = Sha1::digest(format!("{}{}", key.trim(), HANDSHAKE_GUID).as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+pub fn read_frame<R: Read>(r: &mut R) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0u8; 2];
+    r.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0f;
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut n = [0u8; 2];
+            r.read_exact(&mut n)?;
+            (n[0] as u64) << 8 | n[1] as u64
+        }
+        127 => {
+            let mut n = [0u8; 8];
+            r.read_exact(&mut n)?;
+            n.iter().fold(0, |len, &b| len << 8 | b as u64)
+        }
+        n => n as u64,
+    };
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+    }
+    let mut mask = [0u8; 4];
+    if masked {
+        r.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    r.read_exact(&mut payload)?;
+    for (i, b) in payload.iter_mut().enumerate() {
+        *b ^= mask[i % 4];
+    }
+    Ok((fin, opcode, payload))
+}
+
+// Writes a whole message as one frame. Servers don't mask their frames.
+pub fn write_frame<W: Write>(w: &mut W, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    let len = payload.len();
+    if len < 126 {
+        frame.push(len as u8);
+    } else if len < 1 << 16 {
+        frame.push(126);
+        frame.extend(&[(len >> 8) as u8, len as u8]);
+    } else {
+        frame.push(127);
+        frame.extend((0..8).rev().map(|i| ((len as u64) >> (i * 8)) as u8));
+    }
+    frame.extend(payload);
+    w.write_all(&frame)?;
+    w.flush()
+}
+
+// Reads frames up to the end of the next text message, answering pings on
+// the writer. A message's frames together are held to the same limit as
+// one frame, so a client can't build up a huge one in pieces.
+fn read_message<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<Option<String>> {
+    let mut message = vec![];
+    loop {
+        let (fin, opcode, payload) = read_frame(reader)?;
+        match opcode {
+            TEXT | CONTINUATION => {
+                if (message.len() + payload.len()) as u64 > MAX_MESSAGE_LEN {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+                }
+                message.extend(payload)
+            }
+            PING => {
+                write_frame(writer, PONG, &payload)?;
+                continue;
+            }
+            PONG => continue,
+            CLOSE => {
+                let _ = write_frame(writer, CLOSE, &[]);
+                return Ok(None);
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "expected text")),
+        }
+        if fin {
+            return String::from_utf8(message)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+    }
+}
+
+// Frames are read through the buffer the handshake was read with, so any
+// the client sent straight after it aren't lost.
+pub struct WebSocket {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+}
+
+impl WebSocket {
+    // Answers a client's opening handshake, upgrading its connection.
+    pub fn accept(stream: TcpStream) -> io::Result<WebSocket> {
+        let mut key = None;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "closed in handshake"));
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+            let mut parts = line.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) if name.eq_ignore_ascii_case("sec-websocket-key") => {
+                    key = Some(value.trim().to_string())
+                }
+                _ => {}
+            }
+        }
+        let mut stream = stream;
+        let key = match key {
+            Some(key) => key,
+            None => {
+                write!(stream, "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket request"));
+            }
+        };
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        )?;
+        Ok(WebSocket {
+            reader: reader,
+            stream: stream,
+        })
+    }
+
+    pub fn send(&mut self, text: &str) -> io::Result<()> {
+        write_frame(&mut self.stream, TEXT, text.as_bytes())
+    }
+
+    // The next text message, answering pings along the way, or None once
+    // the client closes the connection.
+    pub fn receive(&mut self) -> io::Result<Option<String>> {
+        read_message(&mut self.reader, &mut self.stream)
+    }
+
+    pub fn close(&mut self) {
+        let _ = write_frame(&mut self.stream, CLOSE, &[]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+    use std::net::TcpListener;
+    use websocket::*;
+
+    #[test]
+    fn test_handshake() {
+        // The example from RFC 6455.
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_frames_sent_with_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut request = b"GET / HTTP/1.1\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n"
+            .to_vec();
+        write_frame(&mut request, TEXT, b"Hello").unwrap();
+        client.write_all(&request).unwrap();
+
+        let mut socket = WebSocket::accept(listener.accept().unwrap().0).unwrap();
+        assert_eq!(socket.receive().unwrap(), Some("Hello".into()));
+    }
+
+    #[test]
+    fn test_frames() {
+        // A masked "Hello" from the RFC, as a client sends it.
+        let frame = [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58];
+        let (fin, opcode, payload) = read_frame(&mut &frame[..]).unwrap();
+        assert_eq!((fin, opcode, payload), (true, TEXT, b"Hello".to_vec()));
+
+        let text = "x".repeat(300);
+        let mut out = vec![];
+        write_frame(&mut out, TEXT, text.as_bytes()).unwrap();
+        assert_eq!(&out[..4], &[0x81, 126, 1, 44]);
+        assert_eq!(read_frame(&mut &out[..]).unwrap().2, text.as_bytes());
+    }
+
+    #[test]
+    fn test_message_limit() {
+        let mut frames = vec![];
+        write_frame(&mut frames, TEXT, b"Hel").unwrap();
+        frames[0] &= 0x7f;
+        write_frame(&mut frames, CONTINUATION, b"lo").unwrap();
+        let hello = read_message(&mut Cursor::new(frames), &mut vec![]).unwrap();
+        assert_eq!(hello, Some("Hello".into()));
+
+        // Continuations that are each short enough but too long together.
+        let piece = vec![b'x'; MAX_MESSAGE_LEN as usize / 2 + 1];
+        let mut frames = vec![];
+        write_frame(&mut frames, TEXT, &piece).unwrap();
+        frames[0] &= 0x7f;
+        write_frame(&mut frames, CONTINUATION, &piece).unwrap();
+        assert!(read_message(&mut Cursor::new(frames), &mut vec![]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use std::io;
+use std::net::TcpListener;
+
+use game::Rules;
+use registry::Registry;
+use remote_play;
+use remote_play::Connection;
+use websocket::WebSocket;
+
+// Clients such as browsers connect over WebSocket, with each message of
+// remote_play's protocol in a text message of its own.
+impl Connection for WebSocket {
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        WebSocket::send(self, message)
+    }
+
+    fn receive(&mut self) -> io::Result<Option<String>> {
+        WebSocket::receive(self)
+    }
+
+    fn close(&mut self) {
+        WebSocket::close(self);
+    }
+}
+
+// Hosts games between the players given by specs, where each remote seat
+// is filled by the next client to connect, in the order they connect. Once
+// every seat is filled the games are played, and a line for each game's
+// result is printed.
+pub fn host(
+    listener: TcpListener,
+    specs: &[String],
+    rules: Rules,
+    games: u32,
+    registry: &Registry,
+) -> io::Result<()> {
+    eprintln!(
+        "Waiting for {} to connect on ws://{}",
+        remote_play::remote_seats(specs),
+        listener.local_addr()?
+    );
+    remote_play::host(specs, rules, games, registry, &mut || loop {
+        let (stream, peer) = listener.accept()?;
+        match WebSocket::accept(stream) {
+            Ok(socket) => return Ok((socket, peer.to_string())),
+            Err(e) => eprintln!("Turned away {}: {}", peer, e),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use json::Json;
+    use registry::Registry;
+    use remote_play::REMOTE;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use websocket;
+    use websocket_server::*;
+
+    // A client that takes the fewest cards it may, in the order offered,
+    // returning the messages it was sent.
+    fn play(port: u16) -> Vec<Json> {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        ).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("HTTP/1.1 101"));
+        while line.trim() != "" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+        let mut messages = vec![];
+        loop {
+            let (_, opcode, payload) = websocket::read_frame(&mut reader).unwrap();
+            if opcode == websocket::CLOSE {
+                return messages;
+            }
+            let message = Json::parse(&String::from_utf8(payload).unwrap()).unwrap();
+            if message.get("type").and_then(|t| t.as_str()) == Some("decision") {
+                let d = message.get("state").and_then(|s| s.get("pending_decision")).unwrap();
+                let min = d.get("range").and_then(|r| r.as_array()).unwrap()[0].as_i64().unwrap();
+                let choices = d.get("choices").and_then(|c| c.as_array()).unwrap();
+                let choice = Json::Array(choices[..min as usize].to_vec()).to_string();
+                websocket::write_frame(&mut stream, websocket::TEXT, choice.as_bytes()).unwrap();
+            }
+            messages.push(message);
+        }
+    }
+
+    #[test]
+    fn test_host() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = thread::spawn(move || play(port));
+        let specs = vec![REMOTE.to_string(), "bigmoney".to_string()];
+        host(listener, &specs, Rules::default(), 1, &Registry::standard()).unwrap();
+
+        let messages = client.join().unwrap();
+        let kind = |m: &Json| m.get("type").and_then(|t| t.as_str()).unwrap().to_string();
+        assert_eq!(kind(&messages[0]), "joined");
+        assert_eq!(kind(messages.last().unwrap()), "game_over");
+        assert!(messages.iter().any(|m| kind(m) == "event"));
+        assert!(messages.iter().any(|m| kind(m) == "decision"));
+        // Big Money's decisions are never shown.
+        for m in messages.iter().filter(|m| kind(m) == "state") {
+            assert!(m.get("state").unwrap().get("pending_decision").unwrap().is_null());
+        }
+    }
+}